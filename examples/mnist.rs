@@ -98,9 +98,9 @@ fn validation(g: &GraphDef) -> Result<Box<FnMut(&[ArrayD<f32>])>>{
 		let mut err = 0.0;
 		for _ in 0..epoch/batch_size {
 			let mut inputs = data_stream.next();
-			inputs.extend(parameters.iter().cloned());
-			let storage = subgraph.execute(inputs).expect("Could not execute validation");
-			let err_vec = storage.get(&prediction_loss.value_id()).unwrap();
+			inputs.extend(parameters.iter().cloned().map(|param| param.into()));
+			let storage = subgraph.execute_typed(inputs).expect("Could not execute validation");
+			let err_vec = storage.get::<f32>(&prediction_loss.value_id()).unwrap();
 			err += err_vec.scalar_sum();
 		}
 
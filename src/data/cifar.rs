@@ -1,4 +1,5 @@
 use ndarray::{ArrayD, IxDyn};
+use dtype::TypedArrayD;
 use std::fs::File;
 use std::path::Path;
 use std::io::Read;
@@ -48,7 +49,7 @@ impl Cifar10 {
 }
 
 impl DataSet for Cifar10 {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>> {
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD> {
 		let bytes = &self.images[i];
 		let mut image_vec = vec![0.0; 3072];
 		for i in 0..1024{
@@ -57,9 +58,9 @@ impl DataSet for Cifar10 {
 			image_vec[i*3 + 2] = bytes[i + 2*1024] as f32/255.0;
 		}
 		let image = ArrayD::from_shape_vec(&[32, 32, 3][..], image_vec).unwrap();
-		let mut one_hot_label = ArrayD::zeros(IxDyn(&[10]));
+		let mut one_hot_label: ArrayD<f32> = ArrayD::zeros(IxDyn(&[10]));
 		one_hot_label[self.labels[i] as usize] = 1.0;
-		vec![image, one_hot_label]
+		vec![image.into(), one_hot_label.into()]
 	}
 
 	fn length(&self) -> usize {
@@ -140,7 +141,7 @@ impl Cifar100 {
 }
 
 impl DataSet for Cifar100 {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>>{
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD>{
 		let bytes = &self.images[i];
 		let mut image_vec = vec![0.0; 3072];
 		for i in 0..1024{
@@ -149,11 +150,11 @@ impl DataSet for Cifar100 {
 			image_vec[i*3 + 2] = bytes[i + 2*1024] as f32/255.0;
 		}
 		let image = ArrayD::from_shape_vec(&[32, 32, 3][..], image_vec).unwrap();
-		let mut coarse_one_hot_label = ArrayD::zeros(IxDyn(&[20]));
+		let mut coarse_one_hot_label: ArrayD<f32> = ArrayD::zeros(IxDyn(&[20]));
 		coarse_one_hot_label[self.coarse_labels[i] as usize] = 1.0;
-		let mut fine_one_hot_label = ArrayD::zeros(IxDyn(&[100]));
+		let mut fine_one_hot_label: ArrayD<f32> = ArrayD::zeros(IxDyn(&[100]));
 		fine_one_hot_label[self.fine_labels[i] as usize] = 1.0;
-		vec![image, coarse_one_hot_label, fine_one_hot_label]
+		vec![image.into(), coarse_one_hot_label.into(), fine_one_hot_label.into()]
 	}
 
 	fn length(&self) -> usize{
@@ -1,6 +1,7 @@
 use indexmap::IndexMap;
 use rand::{thread_rng, Rng};
 use ndarray::{ArrayD, IxDyn, SliceOrIndex, SliceInfo, Slice};
+use dtype::{DType, Element, TypedArrayD};
 use smallvec::SmallVec;
 use data::DataSet;
 
@@ -43,6 +44,8 @@ impl<S: DataSet> Crop<S> {
 
 	/// Set what should be used to fill areas where the crop dimension is larger than the input dimension.
 	///
+	/// The value is converted to the element type of the component, see `TypedArrayD::cast()`.
+	///
 	/// Default: 0.0
 	pub fn fill(mut self, component: usize, fill: f32) -> Self {
 		self.fill.insert(component, fill);
@@ -62,13 +65,19 @@ impl<S: DataSet> Crop<S> {
 }
 
 impl<S: DataSet> DataSet for Crop<S> {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>> {
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD> {
 		let mut data = self.set.get(i);
 
 		for (&component, &(ref shape, ref cropping)) in self.crops.iter() {
-			let arr = mem::replace(&mut data[component], ArrayD::zeros(IxDyn(&[])));
+			let arr = mem::replace(&mut data[component], TypedArrayD::zeros(DType::F32, IxDyn(&[])));
 			let fill = self.fill.get(&component).cloned().unwrap_or(0.0);
-			mem::replace(&mut data[component], crop(arr, shape, cropping, fill)) ;
+			let cropped = match arr {
+				TypedArrayD::F32(arr) => crop(arr, shape, cropping, fill).into(),
+				TypedArrayD::I64(arr) => crop(arr, shape, cropping, fill).into(),
+				TypedArrayD::U32(arr) => crop(arr, shape, cropping, fill).into(),
+				TypedArrayD::Bool(arr) => crop(arr, shape, cropping, fill).into(),
+			};
+			mem::replace(&mut data[component], cropped) ;
 		}
 
 		data
@@ -88,11 +97,11 @@ impl<S: DataSet> DataSet for Crop<S> {
 }
 
 
-fn crop(arr: ArrayD<f32>, crop_shape: &[usize], cropping: &Cropping, fill: f32) -> ArrayD<f32> {
+fn crop<T: Element>(arr: ArrayD<T>, crop_shape: &[usize], cropping: &Cropping, fill: f32) -> ArrayD<T> {
 
 	assert_eq!(crop_shape.len(), arr.ndim());
 
-	let mut out_arr = ArrayD::from_elem(IxDyn(crop_shape), fill);
+	let mut out_arr = ArrayD::from_elem(IxDyn(crop_shape), T::from_f64(fill as f64));
 
 	let mut input_slice_arg: SmallVec<[SliceOrIndex; 6]> = SmallVec::new();
 	let mut output_slice_arg: SmallVec<[SliceOrIndex; 6]> = SmallVec::new();
@@ -3,6 +3,7 @@ use walkdir::WalkDir;
 use image;
 use image::{GenericImage, DynamicImage, Pixel};
 use ndarray::{ArrayD, ArrayViewD, IxDyn};
+use dtype::TypedArrayD;
 use std::path::{PathBuf, Path};
 use data::DataSet;
 use std::usize;
@@ -50,7 +51,7 @@ impl ImageFolder {
 }
 
 impl DataSet for ImageFolder {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>> {
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD> {
 
 		let image = match image::open(&self.paths[i]) {
 			Ok(ref dyn_image) => image_to_data(dyn_image),
@@ -60,7 +61,7 @@ impl DataSet for ImageFolder {
 				},
 		};
 
-		vec![image]
+		vec![image.into()]
 	}
 
 	fn length(&self) -> usize {
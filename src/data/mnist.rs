@@ -1,4 +1,5 @@
 use ndarray::{ArrayD, IxDyn};
+use dtype::TypedArrayD;
 use std::fs::File;
 use std::path::Path;
 use std::io::Read;
@@ -38,7 +39,7 @@ impl Mnist {
 }
 
 impl DataSet for Mnist {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>>{
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD>{
 		self.data.get(i)
 	}
 
@@ -74,9 +75,9 @@ impl MnistImages {
 }
 
 impl DataSet for MnistImages {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>>{
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD>{
 		let image = ArrayD::from_shape_vec(self.shape.as_slice(), self.data[i].iter().map(|&v| v as f32/255.0).collect()).unwrap();
-		vec![image]
+		vec![image.into()]
 	}
 
 	fn length(&self) -> usize{
@@ -131,10 +132,10 @@ impl MnistLabels {
 }
 
 impl DataSet for MnistLabels {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>>{
-		let mut one_hot_label = ArrayD::zeros(IxDyn(&[10]));
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD>{
+		let mut one_hot_label: ArrayD<f32> = ArrayD::zeros(IxDyn(&[10]));
 		one_hot_label[self.labels[i] as usize] = 1.0;
-		vec![one_hot_label]
+		vec![one_hot_label.into()]
 	}
 
 	fn length(&self) -> usize{
@@ -6,7 +6,8 @@ pub mod crop;
 pub use data::crop::{Crop, Cropping};
 
 use rand::{thread_rng, Isaac64Rng, Rng, RngCore, SeedableRng};
-use ndarray::{ArrayD, IxDyn, Axis};
+use ndarray::{IxDyn, Axis};
+use dtype::{DType, Element, TypedArrayD};
 use smallvec::SmallVec;
 
 use std::mem;
@@ -17,11 +18,14 @@ use std::iter;
 use std::time::Duration;
 
 /// An indexable data set.
-/// To use tensorflow terminology a dataset is made up of elements(`Vec<ArrayD>>`s), each of which can contain multiple components (`ArrayD`s)
+/// To use tensorflow terminology a dataset is made up of elements(`Vec<TypedArrayD>>`s), each of which can contain multiple components (`TypedArrayD`s)
+///
+/// Components can have any element type, e.g. `F32` images alongside `I64` class labels,
+/// and are converted to the element type of the corresponding subgraph inputs by `opt::cast_inputs()`.
 pub trait DataSet {
 
 	/// Returns the `i`th element of the `Dataset`
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>>;
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD>;
 
 	/// Returns the number of elements in the `Dataset`
 	fn length(&self) -> usize;
@@ -32,7 +36,7 @@ pub trait DataSet {
 	/// Returns the names of components
 	fn components(&self) -> Vec<String>;
 
-	fn iter<'a>(&'a mut self) -> Box<Iterator<Item=Vec<ArrayD<f32>>> + 'a>{
+	fn iter<'a>(&'a mut self) -> Box<Iterator<Item=Vec<TypedArrayD>> + 'a>{
 		let iter = (0..self.length()).map(move|i| self.get(i));
 		Box::new(iter)
 	}
@@ -58,11 +62,11 @@ pub trait DataSet {
 		ConcatElements::new(self, set)
 	}
 
-	fn map_all<F: FnMut(usize, Vec<ArrayD<f32>>) -> Vec<ArrayD<f32>>>(self, func: F, names: Option<Vec<String>>) -> MapAll<Self, F> where Self: Sized {
+	fn map_all<F: FnMut(usize, Vec<TypedArrayD>) -> Vec<TypedArrayD>>(self, func: F, names: Option<Vec<String>>) -> MapAll<Self, F> where Self: Sized {
 		MapAll::new(self, func, names)
 	}
 
-	fn map_one<F: FnMut(usize, ArrayD<f32>) -> ArrayD<f32>>(self, func: F, component: usize) -> MapOne<Self, F> where Self: Sized {
+	fn map_one<F: FnMut(usize, TypedArrayD) -> TypedArrayD>(self, func: F, component: usize) -> MapOne<Self, F> where Self: Sized {
 		MapOne::new(self, func, component)
 	}
 
@@ -90,7 +94,7 @@ pub struct BoxedSet {
 }
 
 impl DataSet for BoxedSet {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>>{
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD>{
 		self.inner.get(i)
 	}
 
@@ -139,14 +143,14 @@ impl<S: DataSet> ReorderComponents<S> {
 }
 
 impl<S: DataSet> DataSet for ReorderComponents<S> {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>> {
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD> {
 		enum Comp{
-			Present(ArrayD<f32>),
+			Present(TypedArrayD),
 			Moved(usize),
 		}
 		let mut components: Vec<_> = self.set.get(i).into_iter().map(|e| Comp::Present(e)).collect();
 
-		let mut out: Vec<ArrayD<f32>> = Vec::with_capacity(self.order.len());
+		let mut out: Vec<TypedArrayD> = Vec::with_capacity(self.order.len());
 		for (new, &old) in self.order.iter().enumerate() {
 			match &mut components[old] {
 				x @ &mut Comp::Present(_) => {
@@ -212,7 +216,7 @@ impl<S: DataSet> ReorderElements<S> {
 }
 
 impl<S: DataSet> DataSet for ReorderElements<S> {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>> {
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD> {
 		let j = self.order[i];
 		self.set.get(j)
 	}
@@ -266,7 +270,7 @@ impl<S1: DataSet, S2: DataSet> ConcatComponents<S1, S2> {
 }
 
 impl<S1: DataSet, S2: DataSet> DataSet for ConcatComponents<S1, S2> {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>> {
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD> {
 		let mut data1 = self.set1.get(i);
 		let mut data2 = self.set2.get(i);
 		data1.append(&mut data2);
@@ -325,7 +329,7 @@ impl<S1: DataSet, S2: DataSet> ConcatElements<S1, S2> {
 }
 
 impl<S1: DataSet, S2: DataSet> DataSet for ConcatElements<S1, S2> {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>> {
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD> {
 		if i < self.set1.length() {
 			self.set1.get(i)
 		} else {
@@ -350,13 +354,13 @@ impl<S1: DataSet, S2: DataSet> DataSet for ConcatElements<S1, S2> {
 ///
 /// If names for the new components arent provided the old names will be used.
 /// If the map changes the number of components, new names must be provided.
-pub struct MapAll<S: DataSet, F: FnMut(usize, Vec<ArrayD<f32>>) -> Vec<ArrayD<f32>>> {
+pub struct MapAll<S: DataSet, F: FnMut(usize, Vec<TypedArrayD>) -> Vec<TypedArrayD>> {
 	func: F,
 	set: S,
 	names: Option<Vec<String>>
 }
 
-impl<S: DataSet, F: FnMut(usize, Vec<ArrayD<f32>>) -> Vec<ArrayD<f32>>> MapAll<S, F> {
+impl<S: DataSet, F: FnMut(usize, Vec<TypedArrayD>) -> Vec<TypedArrayD>> MapAll<S, F> {
 	pub fn new(set: S, func: F, names: Option<Vec<String>>) -> Self {
 		MapAll{
 			func,
@@ -377,8 +381,8 @@ impl<S: DataSet, F: FnMut(usize, Vec<ArrayD<f32>>) -> Vec<ArrayD<f32>>> MapAll<S
 	}
 }
 
-impl<S: DataSet, F: FnMut(usize, Vec<ArrayD<f32>>) -> Vec<ArrayD<f32>>> DataSet for MapAll<S, F> {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>> {
+impl<S: DataSet, F: FnMut(usize, Vec<TypedArrayD>) -> Vec<TypedArrayD>> DataSet for MapAll<S, F> {
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD> {
 		let data = self.set.get(i);
 		let new_data = (self.func)(i, data); // that is some weird syntax
 
@@ -412,14 +416,14 @@ impl<S: DataSet, F: FnMut(usize, Vec<ArrayD<f32>>) -> Vec<ArrayD<f32>>> DataSet
 /// For one component in each element of the dataset: apply a function.
 ///
 /// Renaming the component is optional.
-pub struct MapOne<S: DataSet, F: FnMut(usize, ArrayD<f32>) -> ArrayD<f32>> {
+pub struct MapOne<S: DataSet, F: FnMut(usize, TypedArrayD) -> TypedArrayD> {
 	func: F,
 	set: S,
 	component: usize,
 	component_name: Option<String>,
 }
 
-impl<S: DataSet, F: FnMut(usize, ArrayD<f32>) -> ArrayD<f32>> MapOne<S, F> {
+impl<S: DataSet, F: FnMut(usize, TypedArrayD) -> TypedArrayD> MapOne<S, F> {
 	pub fn new(set: S, func: F, component: usize) -> Self {
 		MapOne{
 			func,
@@ -446,10 +450,10 @@ impl<S: DataSet, F: FnMut(usize, ArrayD<f32>) -> ArrayD<f32>> MapOne<S, F> {
 	}
 }
 
-impl<S: DataSet, F: FnMut(usize, ArrayD<f32>) -> ArrayD<f32>> DataSet for MapOne<S, F> {
-	fn get(&mut self, i: usize) -> Vec<ArrayD<f32>> {
+impl<S: DataSet, F: FnMut(usize, TypedArrayD) -> TypedArrayD> DataSet for MapOne<S, F> {
+	fn get(&mut self, i: usize) -> Vec<TypedArrayD> {
 		let mut data = self.set.get(i);
-		let arr = mem::replace(&mut data[self.component], ArrayD::zeros(IxDyn(&[])));
+		let arr = mem::replace(&mut data[self.component], TypedArrayD::zeros(DType::F32, IxDyn(&[])));
 		mem::replace(&mut data[self.component], (self.func)(i, arr)) ;
 		data
 	}
@@ -499,7 +503,7 @@ impl<S: DataSet> Sequential<S> {
 }
 
 impl<S: DataSet> DataStream for Sequential<S> {
-	fn next(&mut self) -> Vec<ArrayD<f32>>{
+	fn next(&mut self) -> Vec<TypedArrayD>{
 		let out = self.set.get(self.next_i);
 		self.next_i = (self.next_i + 1)%self.set.length();
 		out
@@ -537,7 +541,7 @@ impl<S: DataSet> Random<S> {
 }
 
 impl<S: DataSet> DataStream for Random<S> {
-	fn next(&mut self) -> Vec<ArrayD<f32>>{
+	fn next(&mut self) -> Vec<TypedArrayD>{
 		let set_len = self.set.length();
 		self.set.get(thread_rng().gen_range(0, set_len))
 	}
@@ -579,7 +583,7 @@ impl<S: DataSet> ShuffleRandom<S> {
 }
 
 impl<S: DataSet> DataStream for ShuffleRandom<S> {
-	fn next(&mut self) -> Vec<ArrayD<f32>>{
+	fn next(&mut self) -> Vec<TypedArrayD>{
 		if self.next_i >= self.order.len() {
 			self.rng.shuffle(&mut self.order);
 			self.next_i = 0;
@@ -594,7 +598,7 @@ impl<S: DataSet> DataStream for ShuffleRandom<S> {
 
 
 pub trait DataStream {
-	fn next(&mut self) -> Vec<ArrayD<f32>>;
+	fn next(&mut self) -> Vec<TypedArrayD>;
 
 	fn boxed(self) -> Box<Self> where Self: Sized {
 		Box::new(self)
@@ -624,7 +628,7 @@ pub trait DataStream {
 /// Augment a stream with a fixed sized buffer fed by a new thread.
 pub struct Buffered<S: DataStream + Send + 'static> {
 	stream: Arc<Mutex<S>>,
-	rx: Receiver<Vec<ArrayD<f32>>>,
+	rx: Receiver<Vec<TypedArrayD>>,
 }
 
 impl<S: DataStream + Send + 'static> Buffered<S> {
@@ -684,7 +688,7 @@ impl<S: DataStream + Send + 'static> Buffered<S> {
 }
 
 impl<S: DataStream + Send + 'static> DataStream for Buffered<S> {
-	fn next(&mut self) -> Vec<ArrayD<f32>>{
+	fn next(&mut self) -> Vec<TypedArrayD>{
 		self.rx.recv().expect("Buffer internal thread has died")
 	}
 }
@@ -722,7 +726,7 @@ impl<S1: DataStream, S2: DataStream> Zip<S1, S2> {
 }
 
 impl<S1: DataStream, S2: DataStream> DataStream for Zip<S1, S2> {
-	fn next(&mut self) -> Vec<ArrayD<f32>>{
+	fn next(&mut self) -> Vec<TypedArrayD>{
 		let mut data = self.stream1.next();
 		data.append(&mut self.stream2.next());
 		data
@@ -762,7 +766,7 @@ impl Interleave {
 }
 
 impl DataStream for Interleave {
-	fn next(&mut self) -> Vec<ArrayD<f32>>{
+	fn next(&mut self) -> Vec<TypedArrayD>{
 		let data = self.streams[self.next].next();
 		self.next = (self.next + 1) % self.streams.len();
 		data
@@ -802,7 +806,7 @@ impl<S: DataStream> Count<S> {
 }
 
 impl<S: DataStream> DataStream for Count<S> {
-	fn next(&mut self) -> Vec<ArrayD<f32>>{
+	fn next(&mut self) -> Vec<TypedArrayD>{
 		self.count += 1;
 		self.stream.next()
 	}
@@ -812,6 +816,7 @@ impl<S: DataStream> DataStream for Count<S> {
 /// Adds an outer batch dimension to each component by combining multiple elements.
 ///
 /// Batch size must be greater than 0.
+/// Each component must have the same shape and element type in every element.
 pub struct Batch<S: DataStream> {
 	stream: S,
 	batch_size: usize,
@@ -839,14 +844,12 @@ impl<S: DataStream> Batch<S> {
 }
 
 impl<S: DataStream> DataStream for Batch<S> {
-	fn next(&mut self) -> Vec<ArrayD<f32>>{
+	fn next(&mut self) -> Vec<TypedArrayD>{
 
 		let mut batch_data: Vec<_> = self.stream.next().into_iter().map(|arr|{
 			let batch_shape = iter::once(&self.batch_size).chain(arr.shape()).map(|&i|i).collect::<SmallVec<[usize;6]>>();
-			let mut batch_arr = unsafe{
-				ArrayD::uninitialized(IxDyn(&batch_shape))
-			};
-			batch_arr.subview_mut(Axis(0), 0).as_slice_mut().unwrap().copy_from_slice(arr.as_slice().unwrap());
+			let mut batch_arr = TypedArrayD::zeros(arr.dtype(), IxDyn(&batch_shape));
+			assign_batch_element(&mut batch_arr, 0, arr);
 			batch_arr
 		}).collect();
 
@@ -854,12 +857,59 @@ impl<S: DataStream> DataStream for Batch<S> {
 			let input_vec = self.stream.next();
 			assert_eq!(input_vec.len(), batch_data.len());
 			for (input_arr, batch_arr) in input_vec.into_iter().zip(&mut batch_data) {
-				let mut batch_view = (batch_arr as &mut ArrayD<f32>).subview_mut(Axis(0), i);
-				assert_eq!(input_arr.shape(), batch_view.shape(), "Cannot batch arrays of different shapes.");
-				batch_view.as_slice_mut().unwrap().copy_from_slice(input_arr.as_slice().unwrap());
+				assign_batch_element(batch_arr, i, input_arr);
 			}
 		}
 
 		batch_data
 	}
-}
\ No newline at end of file
+}
+
+/// Copies `arr` into index `i` of the outermost axis of `batch_arr`.
+fn assign_batch_element(batch_arr: &mut TypedArrayD, i: usize, arr: TypedArrayD) {
+	fn assign<T: Element>(batch_arr: &mut TypedArrayD, i: usize, arr: TypedArrayD) {
+		let arr = arr.into_array::<T>().unwrap_or_else(|arr| panic!("Cannot batch arrays of different element types: {:?} and {:?}.", T::dtype(), arr.dtype()));
+		let mut batch_view = batch_arr.as_array_mut::<T>().expect("dtype was checked").subview_mut(Axis(0), i);
+		assert_eq!(arr.shape(), batch_view.shape(), "Cannot batch arrays of different shapes.");
+		batch_view.assign(&arr);
+	}
+
+	match batch_arr.dtype() {
+		DType::F32 => assign::<f32>(batch_arr, i, arr),
+		DType::I64 => assign::<i64>(batch_arr, i, arr),
+		DType::U32 => assign::<u32>(batch_arr, i, arr),
+		DType::Bool => assign::<bool>(batch_arr, i, arr),
+	}
+}
+
+
+#[test]
+fn test_batch_typed(){
+	_batch_typed();
+}
+
+fn _batch_typed(){
+	use ndarray::ArrayD;
+
+	struct Labelled;
+
+	impl DataSet for Labelled {
+		fn get(&mut self, i: usize) -> Vec<TypedArrayD> {
+			vec![ArrayD::from_elem(IxDyn(&[2]), i as f32).into(), ArrayD::from_elem(IxDyn(&[]), (i as i64) << 40).into()]
+		}
+
+		fn length(&self) -> usize {3}
+
+		fn width(&self) -> usize {2}
+
+		fn components(&self) -> Vec<String> {
+			vec!["Values".to_string(), "Labels".to_string()]
+		}
+	}
+
+	let mut stream = Labelled.crop(0, &[3], Cropping::Centre).sequential().batch(3);
+	let batch = stream.next();
+
+	assert_eq!(batch[0].as_array::<f32>().map(|arr| arr.shape().to_vec()), Some(vec![3, 3]));
+	assert_eq!(batch[1].as_array::<i64>().map(|arr| arr.iter().cloned().collect::<Vec<_>>()), Some(vec![0, 1 << 40, 2 << 40]));
+}
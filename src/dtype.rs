@@ -0,0 +1,166 @@
+use ndarray::{ArrayD, IxDyn};
+use std::fmt::Debug;
+
+/// The element type of the values of a node.
+///
+/// Gradients are always `F32`, regardless of the element type of the node values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DType {
+	F32,
+	I64,
+	U32,
+	Bool,
+}
+
+/// Implemented for each type which can be used as the elements of node values.
+pub trait Element: Copy + Default + PartialEq + Debug + Send + Sync + 'static {
+	fn dtype() -> DType;
+
+	fn from_typed(arr: &TypedArrayD) -> Option<&ArrayD<Self>>;
+
+	fn from_typed_mut(arr: &mut TypedArrayD) -> Option<&mut ArrayD<Self>>;
+
+	fn into_typed(arr: ArrayD<Self>) -> TypedArrayD;
+
+	/// Lossy conversion used by `TypedArrayD::cast()`
+	fn to_f64(self) -> f64;
+
+	/// Lossy conversion used by `TypedArrayD::cast()`
+	fn from_f64(x: f64) -> Self;
+}
+
+macro_rules! impl_element {
+	($t:ty, $variant:ident, $to:expr, $from:expr) => {
+		impl Element for $t {
+			fn dtype() -> DType {DType::$variant}
+
+			fn from_typed(arr: &TypedArrayD) -> Option<&ArrayD<Self>> {
+				match *arr {
+					TypedArrayD::$variant(ref arr) => Some(arr),
+					_ => None,
+				}
+			}
+
+			fn from_typed_mut(arr: &mut TypedArrayD) -> Option<&mut ArrayD<Self>> {
+				match *arr {
+					TypedArrayD::$variant(ref mut arr) => Some(arr),
+					_ => None,
+				}
+			}
+
+			fn into_typed(arr: ArrayD<Self>) -> TypedArrayD {
+				TypedArrayD::$variant(arr)
+			}
+
+			fn to_f64(self) -> f64 {
+				$to(self)
+			}
+
+			fn from_f64(x: f64) -> Self {
+				$from(x)
+			}
+		}
+	}
+}
+
+impl_element!(f32, F32, |x: f32| x as f64, |x: f64| x as f32);
+impl_element!(i64, I64, |x: i64| x as f64, |x: f64| x.round() as i64);
+impl_element!(u32, U32, |x: u32| x as f64, |x: f64| x.round().max(0.0) as u32);
+impl_element!(bool, Bool, |x: bool| if x {1.0} else {0.0}, |x: f64| x != 0.0);
+
+/// Applies an expression to the array contained in any variant of a `TypedArrayD`
+macro_rules! typed_apply {
+	($typed:expr, $arr:pat => $e:expr) => {
+		match $typed {
+			TypedArrayD::F32($arr) => $e,
+			TypedArrayD::I64($arr) => $e,
+			TypedArrayD::U32($arr) => $e,
+			TypedArrayD::Bool($arr) => $e,
+		}
+	}
+}
+
+/// An n-dimensional array with any of the supported element types
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypedArrayD {
+	F32(ArrayD<f32>),
+	I64(ArrayD<i64>),
+	U32(ArrayD<u32>),
+	Bool(ArrayD<bool>),
+}
+
+impl TypedArrayD {
+	/// Creates an array filled with the default value (zero or false) of the element type.
+	pub fn zeros(dtype: DType, shape: IxDyn) -> TypedArrayD {
+		match dtype {
+			DType::F32 => TypedArrayD::F32(ArrayD::default(shape)),
+			DType::I64 => TypedArrayD::I64(ArrayD::default(shape)),
+			DType::U32 => TypedArrayD::U32(ArrayD::default(shape)),
+			DType::Bool => TypedArrayD::Bool(ArrayD::default(shape)),
+		}
+	}
+
+	pub fn dtype(&self) -> DType {
+		match *self {
+			TypedArrayD::F32(_) => DType::F32,
+			TypedArrayD::I64(_) => DType::I64,
+			TypedArrayD::U32(_) => DType::U32,
+			TypedArrayD::Bool(_) => DType::Bool,
+		}
+	}
+
+	pub fn shape(&self) -> &[usize] {
+		typed_apply!(*self, ref arr => arr.shape())
+	}
+
+	/// Returns a reference to the inner array if the element type is `T`.
+	pub fn as_array<T: Element>(&self) -> Option<&ArrayD<T>> {
+		T::from_typed(self)
+	}
+
+	/// Returns a mutable reference to the inner array if the element type is `T`.
+	pub fn as_array_mut<T: Element>(&mut self) -> Option<&mut ArrayD<T>> {
+		T::from_typed_mut(self)
+	}
+
+	/// Returns the inner array if the element type is `T`, otherwise returns `self` unchanged as the error.
+	pub fn into_array<T: Element>(self) -> ::std::result::Result<ArrayD<T>, TypedArrayD> {
+		if self.dtype() == T::dtype() {
+			let mut typed = self;
+			let arr = T::from_typed_mut(&mut typed).map(|arr| ::std::mem::replace(arr, ArrayD::default(IxDyn(&[]))));
+			Ok(arr.expect("dtype was checked"))
+		} else {
+			Err(self)
+		}
+	}
+
+	/// Returns an owned copy of the array broadcast to the supplied shape, or `None` if the broadcast is not possible.
+	pub fn broadcast_to_owned(&self, shape: IxDyn) -> Option<TypedArrayD> {
+		typed_apply!(*self, ref arr => arr.broadcast(shape).map(|view| view.to_owned().into()))
+	}
+
+	/// Converts the elements to another element type.
+	///
+	/// Conversions to integer types round to the nearest integer, and conversions to `Bool` are true for non-zero values.
+	pub fn cast(self, dtype: DType) -> TypedArrayD {
+		if self.dtype() == dtype {
+			return self;
+		}
+		match dtype {
+			DType::F32 => TypedArrayD::F32(cast_array(&self)),
+			DType::I64 => TypedArrayD::I64(cast_array(&self)),
+			DType::U32 => TypedArrayD::U32(cast_array(&self)),
+			DType::Bool => TypedArrayD::Bool(cast_array(&self)),
+		}
+	}
+}
+
+fn cast_array<T: Element>(arr: &TypedArrayD) -> ArrayD<T> {
+	typed_apply!(*arr, ref arr => arr.mapv(|x| T::from_f64(x.to_f64())))
+}
+
+impl<T: Element> From<ArrayD<T>> for TypedArrayD {
+	fn from(arr: ArrayD<T>) -> TypedArrayD {
+		T::into_typed(arr)
+	}
+}
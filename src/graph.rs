@@ -11,6 +11,7 @@ use indexmap::{IndexMap, IndexSet};
 use ops::*;
 use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 use id::*;
+use dtype::{DType, TypedArrayD};
 use storage::Storage;
 
 error_chain!{
@@ -51,6 +52,10 @@ error_chain!{
 		ParameterNodesMustHaveKnownSize(name: String, shape: NodeShape){
 			display("Parameter node shapes cannot contain any `Unknown` or `Interval` dimensions: {} shape: {:?}", name, shape)
 		}
+		/// This errorkind indicates that a node was marked as a `Parameter` but did not have an `F32` element type.
+		ParameterNodesMustBeF32(name: String, dtype: DType){
+			display("Parameter nodes must have the element type F32: {} dtype: {:?}", name, dtype)
+		}
 		/// Could not find any nodes matching the tag supplied
 		ZeroNodesMatchTag(tag: NodeTag){
 			display("Could not find any nodes matching the tag supplied: {:?}", tag)
@@ -87,6 +92,14 @@ error_chain!{
 		StaticInputBroadcastFailure(id: NodeID, s1: Vec<Ix>, s2: Vec<Ix>){
 			display("Broadcast of initial value failed for node {:?} as shape {:?} could not be broadcast to shape: {:?}", id, s1, s2)
 		}
+		/// Occurs when data supplied to, or requested from, a node does not have the element type of the node
+		DataTypeMismatch(data_name: String, expected: DType, found: DType){
+			display("Element type mismatch for '{}', expected {:?} but found {:?}", data_name, expected, found)
+		}
+		/// Occurs when `f32` data supplied for an integer node cannot be converted to the element type of the node without loss
+		InexactInputConversion(data_name: String, dtype: DType, value: f32){
+			display("The f32 value {} supplied for '{}' cannot be converted exactly to {:?}, supply typed data directly instead", value, data_name, dtype)
+		}
 
 		/// Occurs when a pass immutably accesses data at a data_ID not listed as an input or output dependency
 		StorageImmutableBorrowError(pass_name: String, data_name: String){
@@ -130,7 +143,7 @@ pub struct GraphDef {
 	pass_ids: Vec<PassID>,
 	
 	// Extra information pertaining to nodes
-	static_inputs: IndexMap<DataID, ArrayD<f32>>,
+	static_inputs: IndexMap<DataID, TypedArrayD>,
 	initialisers: IndexMap<NodeID, Initialiser>,
 
 	// These are used to quickly look op names and tags
@@ -214,8 +227,10 @@ impl GraphDef {
 	/// Node values are initialised to be zero filled by default.
 	/// The ArrayD value supplied to this method will be used to set the initial value of the node, and this data must be able to broadcast to this node.
 	/// This can be used to supply fixed inputs to Ops in place of parameters
-	pub fn set_static_input(&mut self, id: DataID, value: ArrayD<f32>){
-		self.static_inputs.insert(id, value);
+	///
+	/// The element type of the value must match that of the `DataID`, which is checked when the subgraph is executed.
+	pub fn set_static_input<A: Into<TypedArrayD>>(&mut self, id: DataID, value: A){
+		self.static_inputs.insert(id, value.into());
 	}
 
 	pub fn clear_static_input(&mut self, id: DataID){
@@ -252,7 +267,7 @@ impl GraphDef {
 		Ok(vec)
 	}
 
-	fn new_node_checks(&self, name: &str, tags: &[NodeTag], shape: &NodeShape) -> Result<()> {
+	fn new_node_checks(&self, name: &str, tags: &[NodeTag], shape: &NodeShape, dtype: DType) -> Result<()> {
 		// ensure names are unique w.r.t other names and tags
 		ensure!(!self.node_names.contains_key(name), ErrorKind::NodeNameConflict(name.to_string()));
 		ensure!(!self.node_tags.contains_key(&NodeTag::from(name)), ErrorKind::NodeTagNameConflict(name.to_string()));
@@ -271,20 +286,28 @@ impl GraphDef {
 		for tag in tags{
 			if matches!(tag, &NodeTag::Parameter){
 				ensure!(shape.is_known(), ErrorKind::ParameterNodesMustHaveKnownSize(name.to_string(), shape.clone()));
+				ensure!(dtype == DType::F32, ErrorKind::ParameterNodesMustBeF32(name.to_string(), dtype));
 			}
 		}
 		Ok(())
 	}
 
-	/// Create a new node in the graph
+	/// Create a new node in the graph, with the element type `F32`
 	pub fn new_node<I: Into<String>>(&mut self, shape: NodeShape, name: I, tags: Vec<NodeTag>) -> Result<NodeID>{
+		self.new_typed_node(shape, DType::F32, name, tags)
+	}
+
+	/// Create a new node in the graph with the supplied element type.
+	///
+	/// Non-`F32` nodes are intended for labels, indices and masks, and cannot be `Parameter`s.
+	pub fn new_typed_node<I: Into<String>>(&mut self, shape: NodeShape, dtype: DType, name: I, tags: Vec<NodeTag>) -> Result<NodeID>{
 		
 		let name = name.into();
 
-		self.new_node_checks(&name, &tags, &shape)?;
+		self.new_node_checks(&name, &tags, &shape, dtype)?;
 
 		// all good, so add node
-		let node_id = NodeID::new(self.next_node_id(), name.clone(), shape, tags.iter().cloned().collect());
+		let node_id = NodeID::new(self.next_node_id(), name.clone(), shape, dtype, tags.iter().cloned().collect());
 		self.node_ids.push(node_id.clone());
 		
 		// update lookup maps
@@ -686,7 +709,7 @@ pub struct Subgraph {
 	subgraph_outputs: Vec<DataID>,
 
 	// Only keep static inputs that dont conflict with a subgraph input
-	filtered_static_inputs: IndexMap<DataID, ArrayD<f32>>,
+	filtered_static_inputs: IndexMap<DataID, TypedArrayD>,
 
 	// Ops and nodes included in the subgraph, used to perform shape inference
 	included_nodes: IndexMap<NodeID, NodeStatus>,
@@ -702,6 +725,9 @@ pub struct Subgraph {
 
 	// To what degree should ops drag in upstream ops
 	strict_op_inclusion: bool,
+
+	// Whether ops with distinct training and inference behaviour should operate in training mode
+	training: bool,
 }
 
 impl Subgraph {
@@ -721,6 +747,7 @@ impl Subgraph {
 		assert!(outputs.iter().all(|id| dependencies.contains_data(id)), "Outputs contained DataIDs from another graph");
 
 		let strict_op_inclusion = true;
+		let training = outputs.iter().any(|id| !id.is_value());
 		// Find the minimum set of data, passes, nodes and ops required to perform shape inference and calculate the `outputs` of the subgraph
 		let (included_data, included_passes, included_nodes, included_ops) = find_included(&graph, inputs, &graph.static_inputs, outputs, &dependencies, strict_op_inclusion);
 
@@ -757,6 +784,7 @@ impl Subgraph {
 			subgraph_outputs: outputs.to_vec(),
 
 			strict_op_inclusion: strict_op_inclusion,
+			training: training,
 		};
 
 		Ok(graph)
@@ -764,11 +792,21 @@ impl Subgraph {
 
 	/// Calling this executes the subgraph and returns a Storage which contains the outputs of the subgraph.
 	///
-	/// todo
+	/// All inputs must be `F32`, use `execute_typed()` if any inputs have other element types.
 	pub fn execute(&mut self, inputs: Vec<ArrayD<f32>>) -> Result<Storage>{
+		self.execute_typed(inputs.into_iter().map(TypedArrayD::from).collect())
+	}
+
+	/// Executes the subgraph with inputs of any element type.
+	///
+	/// Each input must match the element type of the corresponding `DataID` in `inputs()`.
+	pub fn execute_typed(&mut self, inputs: Vec<TypedArrayD>) -> Result<Storage>{
 		ensure!(inputs.len() == self.subgraph_inputs.len(), "The number of inputs provided ({}) did not match the number of expected inputs ({})", inputs.len(), self.subgraph_inputs.len());
 
-		let input_data: IndexMap<DataID, ArrayD<f32>> = self.subgraph_inputs.iter().cloned().zip(inputs).collect();
+		let input_data: IndexMap<DataID, TypedArrayD> = self.subgraph_inputs.iter().cloned().zip(inputs).collect();
+		for (id, input) in &input_data {
+			ensure!(id.dtype() == input.dtype(), ErrorKind::DataTypeMismatch(id.name(), id.dtype(), input.dtype()));
+		}
 
 		// if shapes is empty, or doesnt match the new inputs, recalculate all shapes.
 		if self.shapes.len() != self.included_nodes.len()
@@ -776,7 +814,7 @@ impl Subgraph {
 			self.shapes = find_shapes(&self, &self.op_order, &input_data, &self.filtered_static_inputs)?;
 		}
 
-		let mut storage = Storage::new(&self.included_data, &self.dependencies, &self.filtered_static_inputs, input_data, &self.shapes);
+		let mut storage = Storage::new(&self.included_data, &self.dependencies, &self.filtered_static_inputs, input_data, &self.shapes, self.training);
 
 		let mut passes_before_dealloc = self.passes_before_dealloc.clone();
 
@@ -814,6 +852,19 @@ impl Subgraph {
 		Ok(())
 	}
 
+	/// Determines whether ops which behave differently during training and inference (e.g. `BatchNorm`, `Dropout`) operate in training mode.
+	///
+	/// By default a subgraph is in training mode if any of its outputs are gradients,
+	/// so a subgraph which only produces values (e.g. for validation) will operate in inference mode.
+	pub fn training(&mut self, training: bool) {
+		self.training = training;
+	}
+
+	/// Returns true if this subgraph is executed in training mode. See `training()`.
+	pub fn is_training(&self) -> bool {
+		self.training
+	}
+
 	/// Returns a slice containings all the inputs required to execute this subgraph.
 	pub fn inputs(&self) -> &[DataID]{
 		&self.subgraph_inputs
@@ -827,7 +878,7 @@ impl Subgraph {
 
 
 /// Work backwards from the requested output data marking data, passes, nodes, and ops as required.
-fn find_included(graph: &GraphDef, inputs: &[DataID], static_inputs: &IndexMap<DataID, ArrayD<f32>>, outputs: &[DataID], dependencies: &Dependencies, strict_op_inclusion: bool) -> (IndexMap<DataID, DataStatus>, IndexSet<PassID>, IndexMap<NodeID, NodeStatus>, IndexSet<OpID>){
+fn find_included(graph: &GraphDef, inputs: &[DataID], static_inputs: &IndexMap<DataID, TypedArrayD>, outputs: &[DataID], dependencies: &Dependencies, strict_op_inclusion: bool) -> (IndexMap<DataID, DataStatus>, IndexSet<PassID>, IndexMap<NodeID, NodeStatus>, IndexSet<OpID>){
 		
 	let mut included_data: IndexMap<DataID, DataStatus> = indexmap![];
 	let mut included_passes = indexset![];
@@ -1233,7 +1284,7 @@ fn find_op_order(included_nodes: &IndexMap<NodeID, NodeStatus>, included_ops: &I
 }
 
 
-fn find_shapes(subgraph: &Subgraph, op_order: &[OpID], inputs: &IndexMap<DataID, ArrayD<f32>>, static_inputs: &IndexMap<DataID, ArrayD<f32>>) -> Result<IndexMap<NodeID, IxDyn>> {
+fn find_shapes(subgraph: &Subgraph, op_order: &[OpID], inputs: &IndexMap<DataID, TypedArrayD>, static_inputs: &IndexMap<DataID, TypedArrayD>) -> Result<IndexMap<NodeID, IxDyn>> {
 	// if inputs are present along with static_inputs the inputs should add
 
 	let mut shapes = GraphShapes::new(subgraph);
@@ -1249,6 +1300,7 @@ fn find_shapes(subgraph: &Subgraph, op_order: &[OpID], inputs: &IndexMap<DataID,
 	// iterate from the lowest dimension up, if the static_input dimension is not 1 then enforce it in the shape
 	for (static_input_id, static_input_data) in static_inputs.iter() {
 		if !inputs.contains_key(static_input_id) {
+			ensure!(static_input_id.dtype() == static_input_data.dtype(), ErrorKind::DataTypeMismatch(static_input_id.name(), static_input_id.dtype(), static_input_data.dtype()));
 			shapes.merge_static_input(&static_input_id, static_input_data.shape()).chain_err(|| format!("Could not merge static input for {}", static_input_id))?;
 		}
 	}
@@ -1256,6 +1308,7 @@ fn find_shapes(subgraph: &Subgraph, op_order: &[OpID], inputs: &IndexMap<DataID,
 	// for all ops that are scheduled, call the relevant shape propagation
 	for op_id in op_order {
 		shapes.set_current_op(Some(op_id.clone()));
+		op_id.instance().check_dtypes().chain_err(|| format!("Element type check failed for {}", op_id))?;
 		op_id.instance().propagate_shape_constraints(&mut shapes).chain_err(|| format!("Could not complete shape inference for {}", op_id))?;
 	}
 	shapes.set_current_op(None);
@@ -1452,14 +1505,14 @@ fn _test_execute_deallocation() -> Result<()>{
 
 	// Make sure we get the right errors when accessing nodes that should be deallocated or never have been allocated
 	let s1 = sg1.execute(vec![ArrayBase::zeros(&[4, 5, 16][..])])?;
-	s1.get(&prev_node.value_id()).unwrap();
-	assert!(matches!(s1.get(&node2.value_id()), Err(Error(ErrorKind::StorageDataDeallocated, _))));
-	assert!(matches!(s1.get(&node2.gradient_id()), Err(Error(ErrorKind::StorageDataMarkedNotRequired, _))));
+	s1.get::<f32>(&prev_node.value_id()).unwrap();
+	assert!(matches!(s1.get::<f32>(&node2.value_id()), Err(Error(ErrorKind::StorageDataDeallocated, _))));
+	assert!(matches!(s1.get::<f32>(&node2.gradient_id()), Err(Error(ErrorKind::StorageDataMarkedNotRequired, _))));
 
 	let s2 = sg2.execute(vec![ArrayBase::zeros(&[9, 5, 16][..])])?;
-	s2.get(&node2.gradient_id()).unwrap();
-	assert!(matches!(s2.get(&prev_node.value_id()), Err(Error(ErrorKind::StorageDataDeallocated, _))));
-	assert!(matches!(s2.get(&node1.gradient_id()), Err(Error(ErrorKind::StorageDataMarkedNotRequired, _))));
+	s2.get::<f32>(&node2.gradient_id()).unwrap();
+	assert!(matches!(s2.get::<f32>(&prev_node.value_id()), Err(Error(ErrorKind::StorageDataDeallocated, _))));
+	assert!(matches!(s2.get::<f32>(&node1.gradient_id()), Err(Error(ErrorKind::StorageDataMarkedNotRequired, _))));
 
 	Ok(())
 }
@@ -1587,7 +1640,53 @@ fn _test_insufficient_input_detection() -> Result<()>{
 	Ok(())
 }
 
+#[test]
+fn test_execute_typed(){
+	_test_execute_typed().unwrap();
+}
+
+fn _test_execute_typed() -> Result<()>{
+	use ops::dummy::Dummy;
+	use graph::GraphDef;
+
+	let mut g = GraphDef::new();
 
+	let labels = g.new_typed_node(shape![Unknown, 3], DType::I64, "labels", tag![])?;
+	let node1 = g.new_node(shape![Unknown, 3], "node1", tag![])?;
+	let node2 = g.new_node(shape![Unknown, 3], "node2", tag![])?;
+	g.new_op(Dummy::new().input(&node1).output(&node2), tag![])?;
+
+	assert!(matches!(g.new_typed_node(shape![3], DType::U32, "param", tag![Parameter]), Err(Error(ErrorKind::ParameterNodesMustBeF32(_, DType::U32), _))));
+
+	// typed data can be supplied and retrieved
+	let mut sg1 = g.subgraph(&[labels.value_id()], &[labels.value_id()])?;
+	let s1 = sg1.execute_typed(vec![ArrayD::from_elem(IxDyn(&[2, 3]), 7i64).into()])?;
+	assert_eq!(s1.get::<i64>(&labels.value_id())?[[1, 2]], 7);
+	assert!(matches!(s1.get::<f32>(&labels.value_id()), Err(Error(ErrorKind::DataTypeMismatch(_, DType::I64, DType::F32), _))));
+	assert_eq!(s1.into_typed_map().get(&labels.value_id()).map(|arr| arr.dtype()), Some(DType::I64));
+
+	// inputs must match the element type of the node
+	let mut sg2 = g.subgraph(&[labels.value_id()], &[labels.value_id()])?;
+	assert!(matches!(sg2.execute(vec![ArrayD::zeros(IxDyn(&[2, 3]))]), Err(Error(ErrorKind::DataTypeMismatch(_, DType::I64, DType::F32), _))));
+
+	// f32 ops reject typed nodes
+	let mut g2 = g.clone();
+	let node3 = g2.new_node(shape![Unknown, 3], "node3", tag![])?;
+	g2.new_op(Dummy::new().input(&labels).output(&node3), tag![])?;
+	let mut sg3 = g2.subgraph(&[labels.value_id()], &[node3.value_id()])?;
+	assert!(sg3.execute_typed(vec![ArrayD::<i64>::zeros(IxDyn(&[2, 3])).into()]).is_err());
+
+	// typed inputs from a DataStream are passed through, and f32 inputs are only converted to integer types if the conversion is exact
+	use opt::cast_inputs;
+	let typed = cast_inputs(vec![ArrayD::from_elem(IxDyn(&[2, 3]), 1i64 << 40).into()], &[labels.value_id()])?;
+	assert_eq!(typed[0].as_array::<i64>().map(|arr| arr[[0, 0]]), Some(1 << 40));
+	let exact = cast_inputs(vec![ArrayD::from_elem(IxDyn(&[2, 3]), 16777215.0f32).into()], &[labels.value_id()])?;
+	assert_eq!(exact[0].as_array::<i64>().map(|arr| arr[[0, 0]]), Some(16777215));
+	assert!(matches!(cast_inputs(vec![ArrayD::from_elem(IxDyn(&[2, 3]), 16777216.0f32).into()], &[labels.value_id()]), Err(Error(ErrorKind::InexactInputConversion(_, DType::I64, _), _))));
+	assert!(matches!(cast_inputs(vec![ArrayD::from_elem(IxDyn(&[2, 3]), 1.5f32).into()], &[labels.value_id()]), Err(Error(ErrorKind::InexactInputConversion(_, DType::I64, _), _))));
+
+	Ok(())
+}
 
 // TODO detect required ops which want to write to input data
 
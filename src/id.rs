@@ -1,4 +1,5 @@
 use shape::NodeShape;
+use dtype::DType;
 use indexmap::IndexSet;
 use ops::*;
 use std::borrow::Borrow;
@@ -11,6 +12,7 @@ use std::fmt;
 struct NodeDesc {
 	name: String,
 	shape: NodeShape,
+	dtype: DType,
 	tags: IndexSet<NodeTag>,
 }
 
@@ -45,12 +47,13 @@ pub struct NodeID {
 }
 
 impl NodeID {
-	pub fn new(id: usize, name: String, shape: NodeShape, tags: IndexSet<NodeTag>) -> Self {
+	pub fn new(id: usize, name: String, shape: NodeShape, dtype: DType, tags: IndexSet<NodeTag>) -> Self {
 		NodeID {
 			id,
 			desc: Arc::new(NodeDesc{
 				name,
 				shape,
+				dtype,
 				tags,
 			}),
 		}
@@ -73,6 +76,11 @@ impl NodeID {
 		&self.desc.shape
 	}
 
+	/// Returns the element type of the node values
+	pub fn dtype(&self) -> DType {
+		self.desc.dtype
+	}
+
 	/// Returns the tags of the associated node
 	pub fn tags(&self) -> &IndexSet<NodeTag> {
 		&self.desc.tags
@@ -131,6 +139,17 @@ impl DataID {
 		&self.desc.shape
 	}
 
+	/// Returns the element type of the data.
+	///
+	/// Gradients are always `F32`, values have the element type of the associated node.
+	pub fn dtype(&self) -> DType {
+		if self.is_value() {
+			self.desc.dtype
+		} else {
+			DType::F32
+		}
+	}
+
 	/// Returns the tags of the associated node
 	pub fn tags(&self) -> &IndexSet<NodeTag> {
 		&self.desc.tags
@@ -127,6 +127,7 @@ macro_rules! shape(
 
 
 pub mod shape;
+pub mod dtype;
 pub mod graph;
 pub mod ops;
 pub mod opt;
@@ -0,0 +1,256 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use storage::Storage;
+use id::{NodeID, DataID, OpID, PassID};
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use shape::NodeDim;
+use std::any::Any;
+use std::fmt::Debug;
+use rayon::prelude::*;
+
+
+pub fn colour_build<O: Op, F: ColourFunc>(graph: &mut GraphDef, op: &O, name: &Option<String>, input: &NodeID, output: &NodeID, func: F) -> Result<ColourInstance<F>> {
+	let name = standard_op_name(op, name, graph, &[input.clone()], &[output.clone()]);
+
+	Ok(ColourInstance{
+		name: name,
+		input_id: input.clone(),
+		output_id: output.clone(),
+		func: func.clone(),
+		forward_id: graph.add_pass(ColourForward::new(
+				input.clone(),
+				output.clone(),
+				func.clone())),
+		backward_id: graph.add_pass(ColourBackward::new(
+				input.clone(),
+				output.clone(),
+				func.clone())),
+	})
+}
+
+
+/// Used to define a graph op which converts each pixel between three channel colour spaces.
+///
+/// The channels are the innermost axis of the input and output, which must have a size of 3.
+pub trait ColourFunc: Send + Sync + Clone + Debug + 'static {
+	/// For a given input pixel, what is the output pixel
+	fn value(&self, input: [f32; 3]) -> [f32; 3];
+
+	/// For a given input pixel, and the gradient of the output pixel, what is the gradient of the input pixel
+	fn gradient(&self, input: [f32; 3], output_grad: [f32; 3]) -> [f32; 3];
+}
+
+/// A conversion which multiplies each pixel by a matrix then adds an offset
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AffineColour {
+	matrix: [[f32; 3]; 3],
+	offset: [f32; 3],
+}
+
+impl AffineColour {
+	pub fn new(matrix: [[f32; 3]; 3], offset: [f32; 3]) -> Self {
+		AffineColour {
+			matrix,
+			offset,
+		}
+	}
+
+	/// Returns the conversion which undoes this conversion
+	pub fn inverse(&self) -> Self {
+		let m = self.matrix;
+		let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] as f64 * m[r1][c1] as f64 - m[r0][c1] as f64 * m[r1][c0] as f64;
+		let det = m[0][0] as f64 * cofactor(1, 2, 1, 2) - m[0][1] as f64 * cofactor(1, 2, 0, 2) + m[0][2] as f64 * cofactor(1, 2, 0, 1);
+
+		let mut matrix = [[0.0; 3]; 3];
+		for i in 0..3 {
+			for j in 0..3 {
+				// transposed cofactor, with the sign given by the cyclic ordering of the remaining rows and columns
+				let (r0, r1) = ((j + 1) % 3, (j + 2) % 3);
+				let (c0, c1) = ((i + 1) % 3, (i + 2) % 3);
+				matrix[i][j] = (cofactor(r0, r1, c0, c1)/det) as f32;
+			}
+		}
+
+		let mut offset = [0.0; 3];
+		for i in 0..3 {
+			offset[i] = -(0..3).map(|j| matrix[i][j] * self.offset[j]).sum::<f32>();
+		}
+
+		AffineColour::new(matrix, offset)
+	}
+}
+
+impl ColourFunc for AffineColour {
+	fn value(&self, input: [f32; 3]) -> [f32; 3] {
+		let mut output = self.offset;
+		for i in 0..3 {
+			for j in 0..3 {
+				output[i] += self.matrix[i][j] * input[j];
+			}
+		}
+		output
+	}
+
+	fn gradient(&self, _input: [f32; 3], output_grad: [f32; 3]) -> [f32; 3] {
+		let mut input_grad = [0.0; 3];
+		for i in 0..3 {
+			for j in 0..3 {
+				input_grad[j] += self.matrix[i][j] * output_grad[i];
+			}
+		}
+		input_grad
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct ColourInstance<F: ColourFunc> {
+	name: String,
+	input_id: NodeID,
+	output_id: NodeID,
+	func: F,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl<F: ColourFunc> OpInstance for ColourInstance<F> {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){(vec![self.input_id.clone()], vec![self.output_id.clone()])}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).clone();
+		ensure!(
+			input_shape.ndim() > 0 && input_shape.dimensions()[input_shape.ndim() - 1] == NodeDim::Known(3),
+			ErrorKind::ShapePropagationError(self.name.clone(), format!("the innermost (channel) dimension of input shape {:?} must be 3", input_shape.dimensions()))
+		);
+		shapes.merge_with(&self.output_id, &input_shape)
+	}
+}
+
+fn check_shapes(pass_name: String, input_shape: &[usize], output_shape: &[usize]) -> Result<()> {
+	ensure!(
+		input_shape == output_shape,
+		ErrorKind::PassError(pass_name.clone(), format!("input shape: {:?} did not match output shape: {:?}", input_shape, output_shape))
+	);
+	ensure!(
+		input_shape.last() == Some(&3),
+		ErrorKind::PassError(pass_name, format!("the innermost (channel) dimension of input shape: {:?} must be 3", input_shape))
+	);
+	Ok(())
+}
+
+
+#[derive(Clone, Debug)]
+pub struct ColourForward<F: ColourFunc> {
+	input_id: NodeID,
+	output_id: NodeID,
+	func: F,
+}
+
+impl<F: ColourFunc> ColourForward<F> {
+	pub fn new(input_id: NodeID, output_id: NodeID, func: F) -> Self {
+		ColourForward {
+			input_id,
+			output_id,
+			func,
+		}
+	}
+}
+
+impl<F: ColourFunc> Pass for ColourForward<F> {
+	fn type_name(&self) -> &'static str {"ColourForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.input_id.value_id()],
+			vec![self.output_id.value_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		check_shapes(self.name(), input.shape(), output.shape())?;
+
+		let input = input.as_slice().unwrap();
+		let output = output.as_slice_mut().unwrap();
+
+		input.par_chunks(3).zip(output.par_chunks_mut(3)).for_each(|(inp, out)|{
+			let value = self.func.value([inp[0], inp[1], inp[2]]);
+			for (o, v) in out.iter_mut().zip(&value) {
+				*o += v;
+			}
+		});
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct ColourBackward<F: ColourFunc> {
+	input_id: NodeID,
+	output_id: NodeID,
+	func: F,
+}
+
+impl<F: ColourFunc> ColourBackward<F> {
+	pub fn new(input_id: NodeID, output_id: NodeID, func: F) -> Self {
+		ColourBackward {
+			input_id,
+			output_id,
+			func,
+		}
+	}
+}
+
+impl<F: ColourFunc> Pass for ColourBackward<F> {
+	fn type_name(&self) -> &'static str {"ColourBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.input_id.value_id(), self.output_id.gradient_id()],
+			vec![self.input_id.gradient_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+
+		check_shapes(self.name(), input.shape(), output_grad.shape())?;
+
+		let input = input.as_slice().unwrap();
+		let output_grad = output_grad.as_slice().unwrap();
+		let input_grad = input_grad.as_slice_mut().unwrap();
+
+		input.par_chunks(3).zip(output_grad.par_chunks(3)).zip(input_grad.par_chunks_mut(3)).for_each(|((inp, og), ig)|{
+			let grad = self.func.gradient([inp[0], inp[1], inp[2]], [og[0], og[1], og[2]]);
+			for (i, g) in ig.iter_mut().zip(&grad) {
+				*i += g;
+			}
+		});
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_affine_inverse(){
+	let affine = AffineColour::new([[0.5, 0.2, -0.1], [0.3, 1.5, 0.0], [-0.4, 0.1, 2.0]], [0.1, -0.2, 0.3]);
+	let inverse = affine.inverse();
+	let x = [0.2, 0.7, -0.4];
+	let y = inverse.value(affine.value(x));
+	for i in 0..3 {
+		assert!((x[i] - y[i]).abs() < 1e-5, "{:?} {:?}", x, y);
+	}
+}
@@ -1,7 +1,11 @@
 use graph::{GraphDef, GraphShapes, ErrorKind, Result};
 use storage::Storage;
 use id::{NodeID, DataID, OpID, PassID};
-use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::{standard_op_name, standard_inner_parameter_name, Op, OpInstance, Pass};
+use shape::NodeDim;
+use init::Initialiser;
+use ndarray::{ArrayD, Axis, Zip};
+use ndarray_parallel::prelude::*;
 use std::any::Any;
 use std::fmt::Debug;
 use rayon::prelude::*;
@@ -95,8 +99,8 @@ impl<F: ActivationFunc> Pass for ElementwiseForward<F> where F: Send {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input = data.get(&self.input_id.value_id())?;
-		let mut output = data.get_mut(&self.output_id.value_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		ensure!(
 			input.shape() == output.shape(),
@@ -160,8 +164,8 @@ impl<F: ActivationFunc> Pass for ElementwiseBackward<F> {
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
 		
-		let output_grad = data.get(&self.output_id.gradient_id())?;
-		let mut input_grad = data.get_mut(&self.input_id.gradient_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
 		
 		ensure!(
 			input_grad.shape() == output_grad.shape(),
@@ -174,7 +178,7 @@ impl<F: ActivationFunc> Pass for ElementwiseBackward<F> {
 		let len = input_grad.len();
 
 		if F::backprop_requires_input_value() {
-			let input = data.get(&self.input_id.value_id())?;
+			let input = data.get::<f32>(&self.input_id.value_id())?;
 			let input = input.as_slice().unwrap();
 
 			let inp = &input[..len];
@@ -204,4 +208,259 @@ impl<F: ActivationFunc> Pass for ElementwiseBackward<F> {
 
 		Ok(Box::new(()))
 	}
-}
\ No newline at end of file
+}
+
+pub fn param_elementwise_build<O: Op, F: ParamActivationFunc>(graph: &mut GraphDef, op: &O, name: &Option<String>, input: &NodeID, weights: &Option<NodeID>, output: &NodeID, shared_axes: &[isize], initialiser: Option<Initialiser>, func: F) -> Result<ParamElementwiseInstance<F>> {
+	let (name, weights_are_inner) = if let Some(ref weights) = *weights {
+		(standard_op_name(op, name, graph, &[input.clone(), weights.clone()], &[output.clone()]), false)
+	} else {
+		(standard_op_name(op, name, graph, &[input.clone()], &[output.clone()]), true)
+	};
+
+	let weights = if let Some(ref weights) = *weights {
+		weights.clone()
+	} else {
+		let weights_shape = {
+			let input_shape = input.shape();
+			let mut weights_shape = vec![1; input_shape.ndim()];
+			for axis in 0..input_shape.ndim() {
+				if let NodeDim::Known(dim) = input_shape.dimensions()[axis] {
+					weights_shape[axis] = dim;
+				}
+			}
+
+			for shared_axis in shared_axes {
+				let shared_axis = (shared_axis + input_shape.ndim() as isize) as usize % input_shape.ndim();
+				weights_shape[shared_axis] = 1;
+			}
+
+			weights_shape
+		};
+
+		let weights_name = standard_inner_parameter_name(&name, graph);
+		graph.new_node(weights_shape.into(), weights_name, tag![Parameter])?
+	};
+
+	if let Some(initialiser) = initialiser {
+		graph.set_initialiser(&weights, initialiser);
+	}
+
+	Ok(ParamElementwiseInstance{
+		name: name,
+		input_id: input.clone(),
+		weights_id: weights.clone(),
+		output_id: output.clone(),
+		weights_are_inner: weights_are_inner,
+		func: func.clone(),
+		forward_id: graph.add_pass(ParamElementwiseForward::new(
+				input.clone(),
+				weights.clone(),
+				output.clone(),
+				func.clone())),
+		backward_id: graph.add_pass(ParamElementwiseBackward::new(
+				input.clone(),
+				weights.clone(),
+				output.clone(),
+				func.clone())),
+	})
+}
+
+
+/// Used to define graph op with one learnable parameter per element, where the parameters can be shared by broadcasting.
+pub trait ParamActivationFunc: Send + Sync + Clone + Debug + 'static {
+	/// For a given input x and parameter a, what is the output y
+	fn value(&self, input: f32, param: f32) -> f32;
+
+	/// For a given input x and parameter a, what are the gradients of the input and parameter
+	fn gradient(&self, input: f32, param: f32, output_grad: f32) -> (f32, f32);
+}
+
+#[derive(Clone, Debug)]
+pub struct ParamElementwiseInstance<F: ParamActivationFunc> {
+	name: String,
+	input_id: NodeID,
+	weights_id: NodeID,
+	output_id: NodeID,
+	weights_are_inner: bool,
+	func: F,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl<F: ParamActivationFunc> ParamElementwiseInstance<F> {
+	pub fn weights(&self) -> &NodeID {
+		&self.weights_id
+	}
+}
+
+impl<F: ParamActivationFunc> OpInstance for ParamElementwiseInstance<F> {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			if self.weights_are_inner {
+				vec![self.input_id.clone()]
+			} else {
+				vec![self.input_id.clone(), self.weights_id.clone()]
+			},
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{
+		if self.weights_are_inner {
+			vec![self.weights_id.clone()]
+		} else {
+			vec![]
+		}
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).clone();
+		shapes.merge_with(&self.output_id, &input_shape)
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct ParamElementwiseForward<F: ParamActivationFunc> {
+	input_id: NodeID,
+	weights_id: NodeID,
+	output_id: NodeID,
+	func: F,
+}
+
+impl<F: ParamActivationFunc> ParamElementwiseForward<F> {
+	pub fn new(input_id: NodeID, weights_id: NodeID, output_id: NodeID, func: F) -> Self {
+		ParamElementwiseForward {
+			input_id,
+			weights_id,
+			output_id,
+			func,
+		}
+	}
+}
+
+impl<F: ParamActivationFunc> Pass for ParamElementwiseForward<F> {
+	fn type_name(&self) -> &'static str {"ParamElementwiseForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.input_id.value_id(), self.weights_id.value_id()],
+			vec![self.output_id.value_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let weights = data.get::<f32>(&self.weights_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		ensure!(
+			input.shape() == output.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input.shape(), output.shape()))
+		);
+		let weights = weights.broadcast(input.shape()).ok_or_else(|| {
+			ErrorKind::PassError(self.name(), format!("Could not broadcast weights shape: {:?} to input/output shape: {:?}", weights.shape(), input.shape()))
+		})?;
+
+		Zip::from(output)
+			.and(&input)
+			.and(&weights)
+			.par_apply(|output, &input, &param| {
+				*output += self.func.value(input, param);
+			});
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct ParamElementwiseBackward<F: ParamActivationFunc> {
+	input_id: NodeID,
+	weights_id: NodeID,
+	output_id: NodeID,
+	func: F,
+}
+
+impl<F: ParamActivationFunc> ParamElementwiseBackward<F> {
+	pub fn new(input_id: NodeID, weights_id: NodeID, output_id: NodeID, func: F) -> Self {
+		ParamElementwiseBackward {
+			input_id,
+			weights_id,
+			output_id,
+			func,
+		}
+	}
+}
+
+impl<F: ParamActivationFunc> Pass for ParamElementwiseBackward<F> {
+	fn type_name(&self) -> &'static str {"ParamElementwiseBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.input_id.value_id(), self.weights_id.value_id(), self.output_id.gradient_id()],
+			vec![self.input_id.gradient_id(), self.weights_id.gradient_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let weights = data.get::<f32>(&self.weights_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+
+		ensure!(
+			input.shape() == output_grad.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input.shape(), output_grad.shape()))
+		);
+		let weights_shape = weights.shape().to_vec();
+		let weights = weights.broadcast(input.shape()).ok_or_else(|| {
+			ErrorKind::PassError(self.name(), format!("Could not broadcast weights shape: {:?} to input/output shape: {:?}", weights_shape, input.shape()))
+		})?;
+
+		if data.is_required(&self.input_id.gradient_id()) {
+			let input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+			Zip::from(input_grad)
+				.and(&input)
+				.and(&weights)
+				.and(&output_grad)
+				.par_apply(|input_grad, &input, &param, &output_grad| {
+					*input_grad += self.func.gradient(input, param, output_grad).0;
+				});
+		}
+
+		if data.is_required(&self.weights_id.gradient_id()) {
+			let mut weights_grad = data.get_mut::<f32>(&self.weights_id.gradient_id())?;
+
+			// Calculate the parameter gradient for each element, then sum over the broadcast axes
+			let mut param_grad = ArrayD::zeros(input.shape());
+			Zip::from(&mut param_grad)
+				.and(&input)
+				.and(&weights)
+				.and(&output_grad)
+				.par_apply(|param_grad, &input, &param, &output_grad| {
+					*param_grad = self.func.gradient(input, param, output_grad).1;
+				});
+
+			let ndim_diff = param_grad.ndim() - weights_shape.len();
+			for _ in 0..ndim_diff {
+				param_grad = param_grad.sum_axis(Axis(0));
+			}
+			for (axis, &dim) in weights_shape.iter().enumerate() {
+				if dim == 1 && param_grad.shape()[axis] != 1 {
+					param_grad = param_grad.sum_axis(Axis(axis)).insert_axis(Axis(axis));
+				}
+			}
+
+			weights_grad += &param_grad;
+		}
+
+		Ok(Box::new(()))
+	}
+}
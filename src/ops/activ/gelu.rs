@@ -0,0 +1,95 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::activ::elementwise::{ActivationFunc, ElementwiseInstance, elementwise_build};
+
+/// sqrt(2/pi)
+const SQRT_2_PI: f32 = 0.797_884_560_802_865_4;
+const COEFF: f32 = 0.044_715;
+
+#[derive(Clone, Debug)] 
+pub struct GELUFunc{}
+
+impl ActivationFunc for GELUFunc {
+	fn value(&self, input: f32) -> f32{
+		let x = input;
+		0.5*x*(1.0 + (SQRT_2_PI*(x + COEFF*x*x*x)).tanh())
+	}
+
+	fn gradient(&self, input: f32, output_grad: f32) -> f32{
+		let x = input;
+		let tanh = (SQRT_2_PI*(x + COEFF*x*x*x)).tanh();
+		output_grad*(0.5*(1.0 + tanh) + 0.5*x*(1.0 - tanh*tanh)*SQRT_2_PI*(1.0 + 3.0*COEFF*x*x))
+	}
+
+	fn backprop_requires_input_value() -> bool {true}
+}
+
+/// `GELU` Gaussian Error Linear Unit, `x * Phi(x)` where Phi is the standard normal CDF.
+///
+/// Uses the common tanh approximation: `0.5 * x * (1 + tanh(sqrt(2/pi) * (x + 0.044715 * x^3)))`.
+#[must_use]
+#[derive(Clone, Debug)] 
+pub struct GELU {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+}
+
+impl GELU {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		GELU {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for GELU {
+	type InstanceType = ElementwiseInstance<GELUFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"GELU"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		elementwise_build(graph, &self, &self.name, &self.input, &self.output, GELUFunc{})
+	}
+}
+
+
+#[test]
+fn test_gelu_backprop(){
+	_gelu_backprop().unwrap();
+}
+
+fn _gelu_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 16], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+
+	let _o1 = g.new_op(GELU::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
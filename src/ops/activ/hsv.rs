@@ -0,0 +1,299 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::activ::colour::{ColourFunc, ColourInstance, colour_build};
+
+/// Returns the indices of the largest and smallest channels, preferring the earliest channel in a tie
+fn max_min_channels(input: [f32; 3]) -> (usize, usize) {
+	let mut max = 0;
+	let mut min = 0;
+	for i in 1..3 {
+		if input[i] > input[max] {max = i;}
+		if input[i] < input[min] {min = i;}
+	}
+	(max, min)
+}
+
+#[derive(Clone, Debug)]
+pub struct RgbToHsvFunc{}
+
+impl ColourFunc for RgbToHsvFunc {
+	fn value(&self, input: [f32; 3]) -> [f32; 3] {
+		let (max, min) = max_min_channels(input);
+		let v = input[max];
+		let c = v - input[min];
+		let s = if v != 0.0 {c/v} else {0.0};
+		let h = if c > 0.0 {
+			// each channel is at the centre of a third of the hue circle, offset by the difference of the other two
+			let h = (2.0*max as f32 + (input[(max + 1) % 3] - input[(max + 2) % 3])/c)/6.0;
+			if h < 0.0 {h + 1.0} else {h}
+		} else {
+			0.0
+		};
+		[h, s, v]
+	}
+
+	fn gradient(&self, input: [f32; 3], output_grad: [f32; 3]) -> [f32; 3] {
+		let (max, min) = max_min_channels(input);
+		let v = input[max];
+		let c = v - input[min];
+		let mut input_grad = [0.0; 3];
+
+		// value
+		input_grad[max] += output_grad[2];
+
+		// saturation = c/v
+		if v != 0.0 {
+			input_grad[max] += output_grad[1]*(1.0/v - c/(v*v));
+			input_grad[min] -= output_grad[1]/v;
+		}
+
+		// hue = (2*max + n/c)/6
+		if c > 0.0 {
+			let n = input[(max + 1) % 3] - input[(max + 2) % 3];
+			let dh_dn = output_grad[0]/(6.0*c);
+			let dh_dc = -output_grad[0]*n/(6.0*c*c);
+			input_grad[(max + 1) % 3] += dh_dn;
+			input_grad[(max + 2) % 3] -= dh_dn;
+			input_grad[max] += dh_dc;
+			input_grad[min] -= dh_dc;
+		}
+
+		input_grad
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct HsvToRgbFunc{}
+
+impl HsvToRgbFunc {
+	/// The position of each output channel around the hue circle, in sixths
+	const OFFSETS: [f32; 3] = [5.0, 3.0, 1.0];
+
+	/// Returns the fraction of the chroma removed from a channel, and its derivative with respect to hue in sixths
+	fn chroma_fraction(h: f32, offset: f32) -> (f32, f32) {
+		let k = ((offset + 6.0*h) % 6.0 + 6.0) % 6.0;
+		if k < 1.0 {
+			(k, 1.0)
+		} else if k < 3.0 {
+			(1.0, 0.0)
+		} else if k < 4.0 {
+			(4.0 - k, -1.0)
+		} else {
+			(0.0, 0.0)
+		}
+	}
+}
+
+impl ColourFunc for HsvToRgbFunc {
+	fn value(&self, input: [f32; 3]) -> [f32; 3] {
+		let [h, s, v] = input;
+		let mut output = [0.0; 3];
+		for i in 0..3 {
+			let (m, _) = HsvToRgbFunc::chroma_fraction(h, HsvToRgbFunc::OFFSETS[i]);
+			output[i] = v*(1.0 - s*m);
+		}
+		output
+	}
+
+	fn gradient(&self, input: [f32; 3], output_grad: [f32; 3]) -> [f32; 3] {
+		let [h, s, v] = input;
+		let mut input_grad = [0.0; 3];
+		for i in 0..3 {
+			let (m, dm) = HsvToRgbFunc::chroma_fraction(h, HsvToRgbFunc::OFFSETS[i]);
+			input_grad[0] -= output_grad[i]*v*s*dm*6.0;
+			input_grad[1] -= output_grad[i]*v*m;
+			input_grad[2] += output_grad[i]*(1.0 - s*m);
+		}
+		input_grad
+	}
+}
+
+
+/// Converts RGB to HSV over the innermost (channel) axis
+///
+/// Hue is in the range [0, 1), as a fraction of a full turn starting from red. Saturation and value are in the range [0, 1] for RGB in [0, 1].
+/// Hue wraps around from 1 to 0, and is discontinuous there as well as where channels are equal.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct RgbToHsv {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+}
+
+impl RgbToHsv {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		RgbToHsv {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for RgbToHsv {
+	type InstanceType = ColourInstance<RgbToHsvFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"RgbToHsv"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		colour_build(graph, &self, &self.name, &self.input, &self.output, RgbToHsvFunc{})
+	}
+}
+
+
+/// Converts HSV to RGB over the innermost (channel) axis
+///
+/// Hue is a fraction of a full turn, and values outside of [0, 1) wrap around.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct HsvToRgb {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+}
+
+impl HsvToRgb {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		HsvToRgb {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for HsvToRgb {
+	type InstanceType = ColourInstance<HsvToRgbFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"HsvToRgb"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		colour_build(graph, &self, &self.name, &self.input, &self.output, HsvToRgbFunc{})
+	}
+}
+
+
+#[test]
+fn test_hsv_values(){
+	let to_hsv = RgbToHsvFunc{};
+	let to_rgb = HsvToRgbFunc{};
+	for &(rgb, hsv) in &[
+			([1.0, 0.0, 0.0], [0.0, 1.0, 1.0]),
+			([0.0, 0.5, 0.0], [1.0/3.0, 1.0, 0.5]),
+			([0.5, 0.5, 1.0], [2.0/3.0, 0.5, 1.0]),
+			([1.0, 0.0, 0.5], [11.0/12.0, 1.0, 1.0]),
+			([0.3, 0.3, 0.3], [0.0, 0.0, 0.3]),
+		] {
+		let value = to_hsv.value(rgb);
+		let round_trip = to_rgb.value(hsv);
+		for i in 0..3 {
+			assert!((value[i] - hsv[i]).abs() < 1e-6, "{:?} {:?}", value, hsv);
+			assert!((round_trip[i] - rgb[i]).abs() < 1e-6, "{:?} {:?}", round_trip, rgb);
+		}
+	}
+}
+
+#[test]
+fn test_rgb_to_hsv_backprop(){
+	_rgb_to_hsv_backprop().unwrap();
+}
+
+fn _rgb_to_hsv_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+	use id::NodeID;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 3], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 3], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 3], "target", tag![])?;
+
+	let _o1 = g.new_op(RgbToHsv::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	// the channels of each pixel are drawn from separate bands in a random order,
+	// so that no step changes which channel is largest or smallest
+	let mut bands = [0.1, 0.4, 0.7];
+	let mut count = 0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(move || {
+			if count % 3 == 0 {
+				thread_rng().shuffle(&mut bands);
+			}
+			count += 1;
+			bands[(count - 1) % 3] + thread_rng().gen_range(0.0, 0.2)
+		}) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+#[test]
+fn test_hsv_to_rgb_backprop(){
+	_hsv_to_rgb_backprop().unwrap();
+}
+
+fn _hsv_to_rgb_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+	use id::NodeID;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 3], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 3], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 3], "target", tag![])?;
+
+	let _o1 = g.new_op(HsvToRgb::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	// keep the hue of each pixel away from the boundaries between sixths of the hue circle, where the gradient is discontinuous
+	let mut count = 0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(move || {
+			count += 1;
+			if (count - 1) % 3 == 0 {
+				(thread_rng().gen_range(-6, 12) as f64 + thread_rng().gen_range(0.1, 0.9))/6.0
+			} else {
+				thread_rng().gen_range(0.1, 1.0)
+			}
+		}) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
@@ -0,0 +1,467 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::activ::colour::{ColourFunc, AffineColour, ColourInstance, colour_build};
+
+/// CIE XYZ of the D65 white point, normalised to Y = 1
+const WHITE: [f32; 3] = [0.95047, 1.0, 1.08883];
+
+/// 6/29, the value of `lab_f()` below which it is linear
+const DELTA: f32 = 6.0/29.0;
+
+/// Returns the conversion from linear RGB (sRGB primaries) to CIE XYZ (D65)
+fn rgb_to_xyz() -> AffineColour {
+	AffineColour::new([
+			[0.4124564, 0.3575761, 0.1804375],
+			[0.2126729, 0.7151522, 0.0721750],
+			[0.0193339, 0.1191920, 0.9503041],
+		],
+		[0.0, 0.0, 0.0])
+}
+
+fn lab_f(t: f32) -> f32 {
+	if t > DELTA*DELTA*DELTA {
+		t.cbrt()
+	} else {
+		t/(3.0*DELTA*DELTA) + 4.0/29.0
+	}
+}
+
+fn lab_f_grad(t: f32) -> f32 {
+	if t > DELTA*DELTA*DELTA {
+		1.0/(3.0*t.cbrt()*t.cbrt())
+	} else {
+		1.0/(3.0*DELTA*DELTA)
+	}
+}
+
+fn lab_f_inv(t: f32) -> f32 {
+	if t > DELTA {
+		t*t*t
+	} else {
+		3.0*DELTA*DELTA*(t - 4.0/29.0)
+	}
+}
+
+fn lab_f_inv_grad(t: f32) -> f32 {
+	if t > DELTA {
+		3.0*t*t
+	} else {
+		3.0*DELTA*DELTA
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct XyzToLabFunc{}
+
+impl ColourFunc for XyzToLabFunc {
+	fn value(&self, input: [f32; 3]) -> [f32; 3] {
+		let fx = lab_f(input[0]/WHITE[0]);
+		let fy = lab_f(input[1]/WHITE[1]);
+		let fz = lab_f(input[2]/WHITE[2]);
+		[116.0*fy - 16.0, 500.0*(fx - fy), 200.0*(fy - fz)]
+	}
+
+	fn gradient(&self, input: [f32; 3], output_grad: [f32; 3]) -> [f32; 3] {
+		let dx = lab_f_grad(input[0]/WHITE[0])/WHITE[0];
+		let dy = lab_f_grad(input[1]/WHITE[1])/WHITE[1];
+		let dz = lab_f_grad(input[2]/WHITE[2])/WHITE[2];
+		[
+			500.0*output_grad[1]*dx,
+			(116.0*output_grad[0] - 500.0*output_grad[1] + 200.0*output_grad[2])*dy,
+			-200.0*output_grad[2]*dz,
+		]
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct LabToXyzFunc{}
+
+impl LabToXyzFunc {
+	fn f_values(input: [f32; 3]) -> [f32; 3] {
+		let fy = (input[0] + 16.0)/116.0;
+		[fy + input[1]/500.0, fy, fy - input[2]/200.0]
+	}
+}
+
+impl ColourFunc for LabToXyzFunc {
+	fn value(&self, input: [f32; 3]) -> [f32; 3] {
+		let f = LabToXyzFunc::f_values(input);
+		[WHITE[0]*lab_f_inv(f[0]), WHITE[1]*lab_f_inv(f[1]), WHITE[2]*lab_f_inv(f[2])]
+	}
+
+	fn gradient(&self, input: [f32; 3], output_grad: [f32; 3]) -> [f32; 3] {
+		let f = LabToXyzFunc::f_values(input);
+		let hx = WHITE[0]*lab_f_inv_grad(f[0])*output_grad[0];
+		let hy = WHITE[1]*lab_f_inv_grad(f[1])*output_grad[1];
+		let hz = WHITE[2]*lab_f_inv_grad(f[2])*output_grad[2];
+		[(hx + hy + hz)/116.0, hx/500.0, -hz/200.0]
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct RgbToLabFunc{
+	xyz: AffineColour,
+}
+
+impl ColourFunc for RgbToLabFunc {
+	fn value(&self, input: [f32; 3]) -> [f32; 3] {
+		XyzToLabFunc{}.value(self.xyz.value(input))
+	}
+
+	fn gradient(&self, input: [f32; 3], output_grad: [f32; 3]) -> [f32; 3] {
+		let xyz = self.xyz.value(input);
+		self.xyz.gradient(input, XyzToLabFunc{}.gradient(xyz, output_grad))
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct LabToRgbFunc{
+	rgb: AffineColour,
+}
+
+impl ColourFunc for LabToRgbFunc {
+	fn value(&self, input: [f32; 3]) -> [f32; 3] {
+		self.rgb.value(LabToXyzFunc{}.value(input))
+	}
+
+	fn gradient(&self, input: [f32; 3], output_grad: [f32; 3]) -> [f32; 3] {
+		let xyz = LabToXyzFunc{}.value(input);
+		LabToXyzFunc{}.gradient(input, self.rgb.gradient(xyz, output_grad))
+	}
+}
+
+
+/// Converts linear RGB (sRGB primaries) to CIE XYZ (D65) over the innermost (channel) axis
+///
+/// Gamma compressed sRGB values should first be converted using `ops::activ::srgb::SrgbToLinear`.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct RgbToXyz {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+}
+
+impl RgbToXyz {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		RgbToXyz {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for RgbToXyz {
+	type InstanceType = ColourInstance<AffineColour>;
+
+	fn type_name(&self) -> &'static str {
+		"RgbToXyz"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		colour_build(graph, &self, &self.name, &self.input, &self.output, rgb_to_xyz())
+	}
+}
+
+
+/// Converts CIE XYZ (D65) to linear RGB (sRGB primaries) over the innermost (channel) axis
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct XyzToRgb {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+}
+
+impl XyzToRgb {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		XyzToRgb {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for XyzToRgb {
+	type InstanceType = ColourInstance<AffineColour>;
+
+	fn type_name(&self) -> &'static str {
+		"XyzToRgb"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		colour_build(graph, &self, &self.name, &self.input, &self.output, rgb_to_xyz().inverse())
+	}
+}
+
+
+/// Converts CIE XYZ (D65) to CIELAB over the innermost (channel) axis
+///
+/// L is in the range [0, 100] for Y in [0, 1].
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct XyzToLab {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+}
+
+impl XyzToLab {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		XyzToLab {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for XyzToLab {
+	type InstanceType = ColourInstance<XyzToLabFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"XyzToLab"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		colour_build(graph, &self, &self.name, &self.input, &self.output, XyzToLabFunc{})
+	}
+}
+
+
+/// Converts CIELAB to CIE XYZ (D65) over the innermost (channel) axis
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct LabToXyz {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+}
+
+impl LabToXyz {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		LabToXyz {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for LabToXyz {
+	type InstanceType = ColourInstance<LabToXyzFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"LabToXyz"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		colour_build(graph, &self, &self.name, &self.input, &self.output, LabToXyzFunc{})
+	}
+}
+
+
+/// Converts linear RGB (sRGB primaries) to CIELAB (D65) over the innermost (channel) axis
+///
+/// Equivalent to `RgbToXyz` followed by `XyzToLab`, without the intermediate node.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct RgbToLab {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+}
+
+impl RgbToLab {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		RgbToLab {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for RgbToLab {
+	type InstanceType = ColourInstance<RgbToLabFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"RgbToLab"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		colour_build(graph, &self, &self.name, &self.input, &self.output, RgbToLabFunc{xyz: rgb_to_xyz()})
+	}
+}
+
+
+/// Converts CIELAB (D65) to linear RGB (sRGB primaries) over the innermost (channel) axis
+///
+/// Equivalent to `LabToXyz` followed by `XyzToRgb`, without the intermediate node.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct LabToRgb {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+}
+
+impl LabToRgb {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		LabToRgb {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for LabToRgb {
+	type InstanceType = ColourInstance<LabToRgbFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"LabToRgb"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		colour_build(graph, &self, &self.name, &self.input, &self.output, LabToRgbFunc{rgb: rgb_to_xyz().inverse()})
+	}
+}
+
+
+#[test]
+fn test_lab_values(){
+	let func = RgbToLabFunc{xyz: rgb_to_xyz()};
+	for &(rgb, lab) in &[([1.0, 1.0, 1.0], [100.0, 0.0, 0.0]), ([1.0, 0.0, 0.0], [53.24, 80.09, 67.20]), ([0.0, 0.0, 1.0], [32.30, 79.19, -107.86])] {
+		let value = func.value(rgb);
+		for i in 0..3 {
+			assert!((value[i] - lab[i]).abs() < 0.02, "{:?} {:?}", value, lab);
+		}
+	}
+
+	let inverse = LabToRgbFunc{rgb: rgb_to_xyz().inverse()};
+	for &rgb in &[[0.2, 0.5, 0.9], [0.001, 0.002, 0.0005]] {
+		let round_trip = inverse.value(func.value(rgb));
+		for i in 0..3 {
+			assert!((rgb[i] - round_trip[i]).abs() < 1e-4, "{:?} {:?}", rgb, round_trip);
+		}
+	}
+}
+
+#[test]
+fn test_rgb_to_lab_backprop(){
+	_rgb_to_lab_backprop().unwrap();
+}
+
+fn _rgb_to_lab_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+	use id::NodeID;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 3], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 3], "xyz", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 3], "lab", tag![])?;
+	let node4 = g.new_node(shape![7, 5, 3], "lab_direct", tag![])?;
+	let node5 = g.new_node(shape![7, 5, 3], "target", tag![])?;
+	let node6 = g.new_node(shape![7, 5, 3], "target_direct", tag![])?;
+
+	let _o1 = g.new_op(RgbToXyz::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(XyzToLab::new(&node2, &node3), tag![])?;
+	let _o3 = g.new_op(RgbToLab::new(&node1, &node4), tag![])?;
+	let _o4 = g.new_op(Mse::new(&node3, &node5), tag![])?;
+	let _o5 = g.new_op(Mse::new(&node4, &node6), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	// stay well away from zero, where the cube root has a large second derivative
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| thread_rng().gen_range(0.1, 1.0)) as Box<FnMut()->f64>,
+		node5.clone() => Box::new(|| thread_rng().gen_range(-100.0, 100.0)) as Box<FnMut()->f64>,
+		node6.clone() => Box::new(|| thread_rng().gen_range(-100.0, 100.0)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+#[test]
+fn test_lab_to_rgb_backprop(){
+	_lab_to_rgb_backprop().unwrap();
+}
+
+fn _lab_to_rgb_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+	use id::NodeID;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 3], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 3], "xyz", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 3], "rgb", tag![])?;
+	let node4 = g.new_node(shape![7, 5, 3], "rgb_direct", tag![])?;
+	let node5 = g.new_node(shape![7, 5, 3], "target", tag![])?;
+	let node6 = g.new_node(shape![7, 5, 3], "target_direct", tag![])?;
+
+	let _o1 = g.new_op(LabToXyz::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(XyzToRgb::new(&node2, &node3), tag![])?;
+	let _o3 = g.new_op(LabToRgb::new(&node1, &node4), tag![])?;
+	let _o4 = g.new_op(Mse::new(&node3, &node5), tag![])?;
+	let _o5 = g.new_op(Mse::new(&node4, &node6), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| thread_rng().gen_range(-50.0, 100.0)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
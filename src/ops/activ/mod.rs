@@ -6,4 +6,15 @@ pub mod elu;
 pub mod tanh;
 pub mod srgb;
 pub mod softmax;
-pub mod spline;
\ No newline at end of file
+pub mod spline;
+pub mod softplus;
+pub mod softexp;
+pub mod swish;
+pub mod gelu;
+pub mod selu;
+pub mod mish;
+pub mod prelu;
+pub mod colour;
+pub mod ycbcr;
+pub mod lab;
+pub mod hsv;
\ No newline at end of file
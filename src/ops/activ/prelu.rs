@@ -0,0 +1,132 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::activ::elementwise::{ParamActivationFunc, ParamElementwiseInstance, param_elementwise_build};
+use smallvec::SmallVec;
+use init::Initialiser;
+
+#[derive(Clone, Debug)] 
+pub struct PReLUFunc{}
+
+impl ParamActivationFunc for PReLUFunc {
+	fn value(&self, input: f32, param: f32) -> f32{
+		if input >= 0.0 {
+			input
+		} else {
+			input*param
+		}
+	}
+
+	fn gradient(&self, input: f32, param: f32, output_grad: f32) -> (f32, f32){
+		if input >= 0.0 {
+			(output_grad, 0.0)
+		} else {
+			(output_grad*param, output_grad*input)
+		}
+	}
+}
+
+/// `PReLU` A LeakyReLU where the negative slopes are learnable parameters.
+#[must_use]
+#[derive(Clone, Debug)] 
+pub struct PReLU {
+	output: NodeID,
+	input: NodeID,
+	weights: Option<NodeID>,
+	shared_axes: SmallVec<[isize; 6]>,
+	initialiser: Option<Initialiser>,
+	name: Option<String>,
+}
+
+impl PReLU {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		PReLU {
+			input: input.clone(),
+			output: output.clone(),
+			weights: None,
+			shared_axes: SmallVec::new(),
+			initialiser: Some(Initialiser::fill(0.25)),
+			name: None,
+		}
+	}
+
+	/// Supply axes which learnable slopes should be shared over.
+	///
+	/// By default all axes with Known size are assigned unique slopes, and sharing via broadcasting is used for non-Known axes.
+	/// Setting an axis as shared will prevent unique slopes being used, and enforce sharing, even if the size is Known.
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	///
+	/// Default: empty
+	pub fn shared_axes(mut self, shared_axes: &[isize]) -> Self {
+		self.shared_axes = shared_axes.iter().cloned().collect();
+		self
+	}
+
+	/// Provide a node to act as the slopes
+	///
+	/// The shape of the slopes must be able to broadcast to the input shape.
+	/// If left as `None` a suitable `Parameter` node will be automatically created.
+	///
+	/// Default value: `None`
+	pub fn weights(mut self, node_id: Option<&NodeID>) -> Self {
+		self.weights = node_id.cloned();
+		self
+	}
+
+	/// Provide an Initialiser for the slopes node
+	///
+	/// Default value: `Initialiser::fill(0.25)`
+	pub fn init(mut self, initialiser: Initialiser) -> Self {
+		self.initialiser = Some(initialiser);
+		self
+	}
+}
+
+impl Op for PReLU {
+	type InstanceType = ParamElementwiseInstance<PReLUFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"PReLU"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		param_elementwise_build(graph, &self, &self.name, &self.input, &self.weights, &self.output, &self.shared_axes, self.initialiser.clone(), PReLUFunc{})
+	}
+}
+
+
+#[test]
+fn test_prelu_backprop(){
+	_prelu_backprop().unwrap();
+}
+
+fn _prelu_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 16], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+
+	let _o1 = g.new_op(PReLU::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(PReLU::new(&node1, &node2).shared_axes(&[0, -1]), tag![])?;
+	let _o3 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
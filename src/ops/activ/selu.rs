@@ -0,0 +1,97 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::activ::elementwise::{ActivationFunc, ElementwiseInstance, elementwise_build};
+
+const LAMBDA: f32 = 1.050_700_987_355_480_5;
+const ALPHA: f32 = 1.673_263_242_354_377_3;
+
+#[derive(Clone, Debug)] 
+pub struct SELUFunc{}
+
+impl ActivationFunc for SELUFunc {
+	fn value(&self, input: f32) -> f32{
+		if input >= 0.0 {
+			LAMBDA*input
+		} else {
+			LAMBDA*ALPHA*input.exp_m1()
+		}
+	}
+
+	fn gradient(&self, input: f32, output_grad: f32) -> f32{
+		if input >= 0.0 {
+			output_grad*LAMBDA
+		} else {
+			output_grad*LAMBDA*ALPHA*input.exp()
+		}
+	}
+
+	fn backprop_requires_input_value() -> bool {true}
+}
+
+/// `SELU` Scaled Exponential Linear Unit, a scaled ELU which is self-normalising for unit gaussian inputs.
+#[must_use]
+#[derive(Clone, Debug)] 
+pub struct SELU {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+}
+
+impl SELU {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		SELU {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for SELU {
+	type InstanceType = ElementwiseInstance<SELUFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"SELU"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		elementwise_build(graph, &self, &self.name, &self.input, &self.output, SELUFunc{})
+	}
+}
+
+
+#[test]
+fn test_selu_backprop(){
+	_selu_backprop().unwrap();
+}
+
+fn _selu_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 16], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+
+	let _o1 = g.new_op(SELU::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
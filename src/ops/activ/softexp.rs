@@ -0,0 +1,160 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::activ::elementwise::{ParamActivationFunc, ParamElementwiseInstance, param_elementwise_build};
+use smallvec::SmallVec;
+use init::Initialiser;
+
+/// Below this magnitude of alpha a series expansion is used, as the gradient of alpha suffers from cancellation
+const ALPHA_EPSILON: f64 = 1e-5;
+
+/// The argument of the logarithm is clamped to this value to avoid infinite outputs for inputs outside the domain when alpha < 0
+const LOG_EPSILON: f64 = 1e-7;
+
+#[derive(Clone, Debug)] 
+pub struct SoftExpFunc{}
+
+impl ParamActivationFunc for SoftExpFunc {
+	fn value(&self, input: f32, param: f32) -> f32{
+		let (x, a) = (input as f64, param as f64);
+		let y = if a.abs() < ALPHA_EPSILON {
+			x + a*(1.0 + 0.5*x*x)
+		} else if a < 0.0 {
+			-(1.0 - a*(x + a)).max(LOG_EPSILON).ln()/a
+		} else {
+			(a*x).exp_m1()/a + a
+		};
+		y as f32
+	}
+
+	// Calculated in f64 as the alpha gradient is the difference of two terms of order x/alpha
+	fn gradient(&self, input: f32, param: f32, output_grad: f32) -> (f32, f32){
+		let (x, a) = (input as f64, param as f64);
+		let (input_grad, param_grad) = if a.abs() < ALPHA_EPSILON {
+			(1.0 + a*x, 1.0 + 0.5*x*x)
+		} else if a < 0.0 {
+			let u = (1.0 - a*(x + a)).max(LOG_EPSILON);
+			(1.0/u, u.ln()/(a*a) + (x + 2.0*a)/(a*u))
+		} else {
+			let exp_m1 = (a*x).exp_m1();
+			(exp_m1 + 1.0, x*(exp_m1 + 1.0)/a - exp_m1/(a*a) + 1.0)
+		};
+		(output_grad*input_grad as f32, output_grad*param_grad as f32)
+	}
+}
+
+/// `SoftExp` A learnable activation which interpolates between logarithmic (alpha < 0), linear (alpha = 0) and exponential (alpha > 0) functions.
+///
+/// When alpha < 0 the function is only defined for `x > 1/alpha - alpha`.
+#[must_use]
+#[derive(Clone, Debug)] 
+pub struct SoftExp {
+	output: NodeID,
+	input: NodeID,
+	weights: Option<NodeID>,
+	shared_axes: SmallVec<[isize; 6]>,
+	initialiser: Option<Initialiser>,
+	name: Option<String>,
+}
+
+impl SoftExp {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		SoftExp {
+			input: input.clone(),
+			output: output.clone(),
+			weights: None,
+			shared_axes: SmallVec::new(),
+			initialiser: Some(Initialiser::fill(0.0)),
+			name: None,
+		}
+	}
+
+	/// Supply axes which learnable alphas should be shared over.
+	///
+	/// By default all axes with Known size are assigned unique alphas, and sharing via broadcasting is used for non-Known axes.
+	/// Setting an axis as shared will prevent unique alphas being used, and enforce sharing, even if the size is Known.
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	///
+	/// Default: empty
+	pub fn shared_axes(mut self, shared_axes: &[isize]) -> Self {
+		self.shared_axes = shared_axes.iter().cloned().collect();
+		self
+	}
+
+	/// Provide a node to act as the alphas
+	///
+	/// The shape of the alphas must be able to broadcast to the input shape.
+	/// If left as `None` a suitable `Parameter` node will be automatically created.
+	///
+	/// Default value: `None`
+	pub fn weights(mut self, node_id: Option<&NodeID>) -> Self {
+		self.weights = node_id.cloned();
+		self
+	}
+
+	/// Provide an Initialiser for the alphas node
+	///
+	/// Default value: `Initialiser::fill(0.0)`
+	pub fn init(mut self, initialiser: Initialiser) -> Self {
+		self.initialiser = Some(initialiser);
+		self
+	}
+}
+
+impl Op for SoftExp {
+	type InstanceType = ParamElementwiseInstance<SoftExpFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"SoftExp"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		param_elementwise_build(graph, &self, &self.name, &self.input, &self.weights, &self.output, &self.shared_axes, self.initialiser.clone(), SoftExpFunc{})
+	}
+}
+
+
+#[test]
+fn test_softexp_backprop(){
+	_softexp_backprop().unwrap();
+}
+
+fn _softexp_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 16], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+
+	let _o1 = g.new_op(SoftExp::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(SoftExp::new(&node1, &node2).shared_axes(&[0, -1]), tag![])?;
+	let _o3 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| thread_rng().gen_range(-2.0, 2.0)) as Box<FnMut()->f64>,
+	];
+	for param in g.parameter_ids() {
+		override_dist.insert(param, Box::new(|| thread_rng().gen_range(-0.2, 0.2)));
+	}
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
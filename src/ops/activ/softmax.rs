@@ -161,8 +161,8 @@ impl Pass for SoftmaxForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input: ArrayViewD<f32> = data.get(&self.input_id.value_id())?;
-		let mut output: ArrayViewMutD<f32> = data.get_mut(&self.output_id.value_id())?;
+		let input: ArrayViewD<f32> = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output: ArrayViewMutD<f32> = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		let group_shape: Vec<usize> = input.shape().iter().enumerate().map(|(i, dim)| if self.mask[i] {*dim} else {1}).collect();
 
@@ -213,9 +213,9 @@ impl Pass for SoftmaxBackward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input: ArrayViewD<f32> = data.get(&self.input_id.value_id())?;
-		let mut input_grad: ArrayViewMutD<f32> = data.get_mut(&self.input_id.gradient_id())?;
-		let output_grad: ArrayViewD<f32> = data.get(&self.output_id.gradient_id())?;
+		let input: ArrayViewD<f32> = data.get::<f32>(&self.input_id.value_id())?;
+		let mut input_grad: ArrayViewMutD<f32> = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+		let output_grad: ArrayViewD<f32> = data.get::<f32>(&self.output_id.gradient_id())?;
 
 		let group_shape: Vec<usize> = input.shape().iter().enumerate().map(|(i, dim)| if self.mask[i] {*dim} else {1}).collect();
 
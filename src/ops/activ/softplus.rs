@@ -0,0 +1,103 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::activ::elementwise::{ActivationFunc, ElementwiseInstance, elementwise_build};
+
+/// Calculates the logistic function, avoiding overflow for large negative inputs
+#[inline(always)]
+pub fn logistic(x: f32) -> f32 {
+	if x >= 0.0 {
+		1.0/(1.0 + (-x).exp())
+	} else {
+		let exp = x.exp();
+		exp/(1.0 + exp)
+	}
+}
+
+/// Calculates `ln(1 + e^x)`, avoiding overflow for large positive inputs
+#[inline(always)]
+pub fn softplus(x: f32) -> f32 {
+	x.max(0.0) + (-x.abs()).exp().ln_1p()
+}
+
+#[derive(Clone, Debug)] 
+pub struct SoftPlusFunc{}
+
+impl ActivationFunc for SoftPlusFunc {
+	fn value(&self, input: f32) -> f32{
+		softplus(input)
+	}
+
+	fn gradient(&self, input: f32, output_grad: f32) -> f32{
+		output_grad*logistic(input)
+	}
+
+	fn backprop_requires_input_value() -> bool {true}
+}
+
+/// `SoftPlus` A smooth approximation of ReLU, `ln(1 + e^x)`.
+#[must_use]
+#[derive(Clone, Debug)] 
+pub struct SoftPlus {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+}
+
+impl SoftPlus {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		SoftPlus {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for SoftPlus {
+	type InstanceType = ElementwiseInstance<SoftPlusFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"SoftPlus"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		elementwise_build(graph, &self, &self.name, &self.input, &self.output, SoftPlusFunc{})
+	}
+}
+
+
+#[test]
+fn test_softplus_backprop(){
+	_softplus_backprop().unwrap();
+}
+
+fn _softplus_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 16], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+
+	let _o1 = g.new_op(SoftPlus::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
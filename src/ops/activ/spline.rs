@@ -244,9 +244,9 @@ impl Pass for SplineForward {
 	}
 
 	fn run(&self, data: &Storage) -> Result<Box<Any>> {
-		let input = data.get(&self.input_id.value_id())?;
-		let weights = data.get(&self.weights_id.value_id())?;
-		let output = data.get_mut(&self.output_id.value_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let weights = data.get::<f32>(&self.weights_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		let input_shape = input.shape();
 		let output_shape = output.shape().to_vec();
@@ -328,9 +328,9 @@ impl Pass for SplineBackward {
 	}
 
 	fn run(&self, data: &Storage) -> Result<Box<Any>> {
-		let input = data.get(&self.input_id.value_id())?;
-		let weights = data.get(&self.weights_id.value_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let weights = data.get::<f32>(&self.weights_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 
 		let input_shape = input.shape();
 		let output_shape = output_grad.shape();
@@ -355,7 +355,7 @@ impl Pass for SplineBackward {
 
 		
 		if data.is_required(&self.input_id.gradient_id()) {
-			let input_grad = data.get_mut(&self.input_id.gradient_id())?;
+			let input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
 
 			// let output_grads: Vec<_> = output_grad.exact_chunks(weights_shape).into_iter().collect();
 			// let inputs: Vec<_> = input.exact_chunks(weights_shape).into_iter().collect();
@@ -385,7 +385,7 @@ impl Pass for SplineBackward {
 		}
 
 		if data.is_required(&self.weights_id.gradient_id()) {
-			let mut weights_grad = data.get_mut(&self.weights_id.gradient_id())?;
+			let mut weights_grad = data.get_mut::<f32>(&self.weights_id.gradient_id())?;
 			let mut weights_grad_iter = weights_grad.outer_iter_mut();
 			let weights_grad0 = weights_grad_iter.next().unwrap();
 			let weights_grad1 = weights_grad_iter.next().unwrap();
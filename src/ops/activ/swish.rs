@@ -0,0 +1,100 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::activ::elementwise::{ActivationFunc, ElementwiseInstance, elementwise_build};
+use ops::activ::softplus::logistic;
+
+#[derive(Clone, Debug)] 
+pub struct SwishFunc{
+	beta: f32,
+}
+
+impl ActivationFunc for SwishFunc {
+	fn value(&self, input: f32) -> f32{
+		input*logistic(self.beta*input)
+	}
+
+	fn gradient(&self, input: f32, output_grad: f32) -> f32{
+		let sig = logistic(self.beta*input);
+		output_grad*(sig + self.beta*input*sig*(1.0 - sig))
+	}
+
+	fn backprop_requires_input_value() -> bool {true}
+}
+
+/// `Swish` A smooth non-monotonic activation, `x * logistic(beta * x)`.
+///
+/// With the default `beta` of 1.0 this is also known as SiLU.
+#[must_use]
+#[derive(Clone, Debug)] 
+pub struct Swish {
+	output: NodeID,
+	input: NodeID,
+	name: Option<String>,
+	beta: f32,
+}
+
+impl Swish {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		Swish {
+			input: input.clone(),
+			output: output.clone(),
+			name: None,
+			beta: 1.0,
+		}
+	}
+
+	pub fn beta(mut self, beta: f32) -> Self{
+		self.beta = beta;
+		self
+	}
+}
+
+impl Op for Swish {
+	type InstanceType = ElementwiseInstance<SwishFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"Swish"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		elementwise_build(graph, &self, &self.name, &self.input, &self.output, SwishFunc{beta: self.beta})
+	}
+}
+
+
+#[test]
+fn test_swish_backprop(){
+	_swish_backprop().unwrap();
+}
+
+fn _swish_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 16], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+
+	let _o1 = g.new_op(Swish::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(Swish::new(&node1, &node2).beta(1.702), tag![])?;
+	let _o3 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
@@ -0,0 +1,208 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::activ::colour::{AffineColour, ColourInstance, colour_build};
+
+/// The luma coefficients used for YCbCr conversion
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YCbCrStandard {
+	/// ITU-R BT.601, as used by JPEG and for most super-resolution benchmarks
+	Bt601,
+	/// ITU-R BT.709, as used by HDTV
+	Bt709,
+}
+
+/// Returns the affine conversion from RGB to YCbCr
+///
+/// Full range output has Y in [0, 1] and Cb, Cr in [0, 1] centred on 0.5 for RGB in [0, 1].
+/// Studio range output has Y in [16/255, 235/255] and Cb, Cr in [16/255, 240/255] centred on 128/255, matching MATLAB's `rgb2ycbcr`.
+fn rgb_to_ycbcr(standard: YCbCrStandard, studio_range: bool) -> AffineColour {
+	let (kr, kb) = match standard {
+		YCbCrStandard::Bt601 => (0.299, 0.114),
+		YCbCrStandard::Bt709 => (0.2126, 0.0722),
+	};
+	let kg = 1.0 - kr - kb;
+
+	let (y_scale, c_scale, y_offset, c_offset) = if studio_range {
+		(219.0/255.0, 224.0/255.0, 16.0/255.0, 128.0/255.0)
+	} else {
+		(1.0, 1.0, 0.0, 0.5)
+	};
+
+	let cb = 0.5/(1.0 - kb);
+	let cr = 0.5/(1.0 - kr);
+	AffineColour::new([
+			[y_scale*kr, y_scale*kg, y_scale*kb],
+			[-c_scale*cb*kr, -c_scale*cb*kg, c_scale*cb*(1.0 - kb)],
+			[c_scale*cr*(1.0 - kr), -c_scale*cr*kg, -c_scale*cr*kb],
+		],
+		[y_offset, c_offset, c_offset])
+}
+
+/// Converts RGB to YCbCr over the innermost (channel) axis
+///
+/// Typically applied to gamma compressed (sRGB) values. The Y (luma) channel is the first output channel.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct RgbToYCbCr {
+	output: NodeID,
+	input: NodeID,
+	standard: YCbCrStandard,
+	studio_range: bool,
+	name: Option<String>,
+}
+
+impl RgbToYCbCr {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		RgbToYCbCr {
+			input: input.clone(),
+			output: output.clone(),
+			standard: YCbCrStandard::Bt601,
+			studio_range: false,
+			name: None,
+		}
+	}
+
+	/// Default: `YCbCrStandard::Bt601`
+	pub fn standard(mut self, standard: YCbCrStandard) -> Self {
+		self.standard = standard;
+		self
+	}
+
+	/// If true, use the studio (limited) range rather than the full range.
+	///
+	/// Default: false
+	pub fn studio_range(mut self, studio_range: bool) -> Self {
+		self.studio_range = studio_range;
+		self
+	}
+}
+
+impl Op for RgbToYCbCr {
+	type InstanceType = ColourInstance<AffineColour>;
+
+	fn type_name(&self) -> &'static str {
+		"RgbToYCbCr"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		colour_build(graph, &self, &self.name, &self.input, &self.output, rgb_to_ycbcr(self.standard, self.studio_range))
+	}
+}
+
+
+/// Converts YCbCr to RGB over the innermost (channel) axis
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct YCbCrToRgb {
+	output: NodeID,
+	input: NodeID,
+	standard: YCbCrStandard,
+	studio_range: bool,
+	name: Option<String>,
+}
+
+impl YCbCrToRgb {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		YCbCrToRgb {
+			input: input.clone(),
+			output: output.clone(),
+			standard: YCbCrStandard::Bt601,
+			studio_range: false,
+			name: None,
+		}
+	}
+
+	/// Default: `YCbCrStandard::Bt601`
+	pub fn standard(mut self, standard: YCbCrStandard) -> Self {
+		self.standard = standard;
+		self
+	}
+
+	/// If true, the input is in the studio (limited) range rather than the full range.
+	///
+	/// Default: false
+	pub fn studio_range(mut self, studio_range: bool) -> Self {
+		self.studio_range = studio_range;
+		self
+	}
+}
+
+impl Op for YCbCrToRgb {
+	type InstanceType = ColourInstance<AffineColour>;
+
+	fn type_name(&self) -> &'static str {
+		"YCbCrToRgb"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		colour_build(graph, &self, &self.name, &self.input, &self.output, rgb_to_ycbcr(self.standard, self.studio_range).inverse())
+	}
+}
+
+
+#[test]
+fn test_ycbcr_values(){
+	use ops::activ::colour::ColourFunc;
+
+	// MATLAB rgb2ycbcr of white, black and pure red
+	let studio = rgb_to_ycbcr(YCbCrStandard::Bt601, true);
+	for &(rgb, ycbcr) in &[([1.0, 1.0, 1.0], [235.0, 128.0, 128.0]), ([0.0, 0.0, 0.0], [16.0, 128.0, 128.0]), ([1.0, 0.0, 0.0], [81.481, 90.203, 240.0])] {
+		let value = studio.value(rgb);
+		for i in 0..3 {
+			assert!((value[i]*255.0 - ycbcr[i]).abs() < 1e-2, "{:?} {:?}", value, ycbcr);
+		}
+	}
+
+	for &standard in &[YCbCrStandard::Bt601, YCbCrStandard::Bt709] {
+		let full = rgb_to_ycbcr(standard, false);
+		let grey = full.value([0.3, 0.3, 0.3]);
+		assert!((grey[0] - 0.3).abs() < 1e-6 && (grey[1] - 0.5).abs() < 1e-6 && (grey[2] - 0.5).abs() < 1e-6);
+		let rgb = [0.1, 0.6, 0.8];
+		let round_trip = full.inverse().value(full.value(rgb));
+		for i in 0..3 {
+			assert!((rgb[i] - round_trip[i]).abs() < 1e-5);
+		}
+	}
+}
+
+#[test]
+fn test_rgb_to_ycbcr_backprop(){
+	_rgb_to_ycbcr_backprop().unwrap();
+}
+
+fn _rgb_to_ycbcr_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 3], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 3], "ycbcr", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 3], "output", tag![])?;
+	let node4 = g.new_node(shape![7, 5, 3], "target", tag![])?;
+
+	let _o1 = g.new_op(RgbToYCbCr::new(&node1, &node2).studio_range(true), tag![])?;
+	let _o2 = g.new_op(YCbCrToRgb::new(&node2, &node3).standard(YCbCrStandard::Bt709), tag![])?;
+	let _o3 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
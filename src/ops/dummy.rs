@@ -161,10 +161,10 @@ impl Pass for DummyForward {
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
 		if self.touch_data {
 			for id in &self.inputs {
-				let _x = data.get(&id.value_id());
+				let _x = data.get::<f32>(&id.value_id());
 			}
 			for id in &self.outputs {
-				let _x = data.get_mut(&id.value_id());
+				let _x = data.get_mut::<f32>(&id.value_id());
 			}
 		}
 		Ok(Box::new(()))
@@ -183,11 +183,11 @@ impl Pass for DummyBackward {
 	fn run (&self, data: &Storage) -> Result<Box<Any>> {
 		if self.touch_data {
 			for id in &self.inputs {
-				let _x = data.get(&id.value_id());
-				let _x = data.get_mut(&id.gradient_id());
+				let _x = data.get::<f32>(&id.value_id());
+				let _x = data.get_mut::<f32>(&id.gradient_id());
 			}
 			for id in &self.outputs {
-				let _x = data.get(&id.gradient_id());
+				let _x = data.get::<f32>(&id.gradient_id());
 			}
 		}
 		Ok(Box::new(()))
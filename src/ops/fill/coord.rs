@@ -161,7 +161,7 @@ impl Pass for CoordForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let mut output = data.get_mut(&self.output_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		let output_shape: SmallVec<[usize; 6]> = output.shape().iter().cloned().collect();
 
@@ -243,7 +243,7 @@ fn _coord_input() -> Result<()>{
 
 	let storage = subgraph.execute(vec![ArrayD::zeros(IxDyn(&[7, 5, 13]))])?;
 
-	let out = storage.get_mut(&output.value_id())?;
+	let out = storage.get_mut::<f32>(&output.value_id())?;
 
 	for x in out.axis_iter(Axis(2)) {
 		println!("{:?}", x);
@@ -287,7 +287,7 @@ fn _coord() -> Result<()>{
 
 	let storage = subgraph.execute(vec![])?;
 
-	let out = storage.get_mut(&output.value_id())?;
+	let out = storage.get_mut::<f32>(&output.value_id())?;
 
 	for x in out.axis_iter(Axis(2)) {
 		println!("{:?}", x);
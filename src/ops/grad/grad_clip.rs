@@ -0,0 +1,210 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::grad::stop_grad::StopGradForward;
+use std::any::Any;
+use rayon::prelude::*;
+
+/// How the gradient flowing through a `GradClip` op is limited
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClipMode {
+	/// Each element of the gradient is clamped to lie within [-limit, limit]
+	Value(f32),
+	/// The gradient is scaled down, if required, so that its L2 norm is no larger than the limit
+	Norm(f32),
+}
+
+/// GradClip Op
+///
+/// The forward pass copies the input to the output, while the backward pass clips the gradient either by value or by norm.
+///
+/// When clipping by norm, the norm is taken over the whole gradient of the output node, including the batch dimension.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct GradClip {
+	output: NodeID,
+	input: NodeID,
+	mode: ClipMode,
+	name: Option<String>,
+}
+
+impl GradClip {
+	pub fn new(input: &NodeID, output: &NodeID, mode: ClipMode) -> Self {
+		GradClip {
+			input: input.clone(),
+			output: output.clone(),
+			mode: mode,
+			name: None,
+		}
+	}
+
+	/// Clamp each element of the gradient to lie within [-limit, limit]
+	pub fn value(input: &NodeID, output: &NodeID, limit: f32) -> Self {
+		GradClip::new(input, output, ClipMode::Value(limit))
+	}
+
+	/// Scale the gradient so that its L2 norm is no larger than the limit
+	pub fn norm(input: &NodeID, output: &NodeID, limit: f32) -> Self {
+		GradClip::new(input, output, ClipMode::Norm(limit))
+	}
+}
+
+impl Op for GradClip {
+	type InstanceType = GradClipInstance;
+
+	fn type_name(&self) -> &'static str {
+		"GradClip"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input.clone()], &[self.output.clone()]);
+
+		match self.mode {
+			ClipMode::Value(limit) | ClipMode::Norm(limit) => {
+				ensure!(limit >= 0.0, ErrorKind::ShapePropagationError(name.clone(), format!("clipping limit ({}) must not be negative", limit)));
+			},
+		}
+
+		Ok(GradClipInstance{
+			name: name,
+			input_id: self.input.clone(),
+			output_id: self.output.clone(),
+			forward_id: graph.add_pass(StopGradForward::new(
+					self.input.clone(),
+					self.output.clone())),
+			backward_id: graph.add_pass(GradClipBackward::new(
+					self.input.clone(),
+					self.output.clone(),
+					self.mode)),
+		})
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct GradClipInstance {
+	name: String,
+	input_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for GradClipInstance {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){(vec![self.input_id.clone()], vec![self.output_id.clone()])}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).clone();
+		shapes.merge_with(&self.output_id, &input_shape)
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct GradClipBackward {
+	input_id: NodeID,
+	output_id: NodeID,
+	mode: ClipMode,
+}
+
+impl GradClipBackward {
+	pub fn new(input_id: NodeID, output_id: NodeID, mode: ClipMode) -> Self {
+		GradClipBackward {
+			input_id,
+			output_id,
+			mode,
+		}
+	}
+}
+
+impl Pass for GradClipBackward {
+	fn type_name(&self) -> &'static str {"GradClipBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.output_id.gradient_id()],
+			vec![self.input_id.gradient_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+
+		ensure!(
+			input_grad.shape() == output_grad.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input_grad.shape(), output_grad.shape()))
+		);
+
+		let output_grad = output_grad.as_slice().unwrap();
+		let input_grad = input_grad.as_slice_mut().unwrap();
+
+		match self.mode {
+			ClipMode::Value(limit) => {
+				output_grad.par_iter().zip(input_grad.par_iter_mut()).for_each(|(og, ig)|{
+					*ig += og.max(-limit).min(limit);
+				});
+			},
+			ClipMode::Norm(limit) => {
+				let norm = output_grad.par_iter().map(|og| (og * og) as f64).sum::<f64>().sqrt() as f32;
+				let scale = if norm > limit {limit/norm} else {1.0};
+				output_grad.par_iter().zip(input_grad.par_iter_mut()).for_each(|(og, ig)|{
+					*ig += *og * scale;
+				});
+			},
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_grad_clip(){
+	_grad_clip().unwrap();
+}
+
+fn _grad_clip() -> Result<()>{
+	use graph::GraphDef;
+	use ndarray::{ArrayD, IxDyn};
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![4], "input", tag![])?;
+	let node2 = g.new_node(shape![4], "value_output", tag![])?;
+	let node3 = g.new_node(shape![4], "norm_output", tag![])?;
+
+	let _o1 = g.new_op(GradClip::value(&node1, &node2, 1.0), tag![])?;
+	let _o2 = g.new_op(GradClip::norm(&node1, &node3, 2.5), tag![])?;
+
+	let mut subgraph = g.subgraph(&[node1.value_id(), node2.gradient_id(), node3.gradient_id()], &[node2.value_id(), node3.value_id(), node1.gradient_id()])?;
+	let input = ArrayD::from_shape_vec(IxDyn(&[4]), vec![1.0, -2.0, 3.0, -4.0]).unwrap();
+	let output_grad2 = ArrayD::from_shape_vec(IxDyn(&[4]), vec![0.5, -3.0, 2.0, 0.0]).unwrap();
+	let output_grad3 = ArrayD::from_shape_vec(IxDyn(&[4]), vec![3.0, 0.0, -4.0, 0.0]).unwrap();
+	let storage = subgraph.execute(vec![input, output_grad2, output_grad3])?;
+
+	let output2 = storage.get::<f32>(&node2.value_id())?;
+	assert_eq!(output2.iter().cloned().collect::<Vec<_>>(), vec![1.0, -2.0, 3.0, -4.0]);
+	let output3 = storage.get::<f32>(&node3.value_id())?;
+	assert_eq!(output3.iter().cloned().collect::<Vec<_>>(), vec![1.0, -2.0, 3.0, -4.0]);
+
+	// value clipping gives [0.5, -1.0, 1.0, 0.0], norm clipping scales [3, 0, -4, 0] by 2.5/5
+	let input_grad = storage.get::<f32>(&node1.gradient_id())?;
+	assert_eq!(input_grad.iter().cloned().collect::<Vec<_>>(), vec![2.0, -1.0, -1.0, 0.0]);
+
+	Ok(())
+}
@@ -0,0 +1,173 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::grad::stop_grad::StopGradForward;
+use std::any::Any;
+use rayon::prelude::*;
+
+/// GradScale Op
+///
+/// The forward pass copies the input to the output, while the backward pass multiplies the gradient by `scale`.
+///
+/// A negative scale produces a gradient reversal layer.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct GradScale {
+	output: NodeID,
+	input: NodeID,
+	scale: f32,
+	name: Option<String>,
+}
+
+impl GradScale {
+	pub fn new(input: &NodeID, output: &NodeID, scale: f32) -> Self {
+		GradScale {
+			input: input.clone(),
+			output: output.clone(),
+			scale: scale,
+			name: None,
+		}
+	}
+
+	/// Creates a gradient reversal op, equivalent to a scale of -1.0
+	pub fn reverse(input: &NodeID, output: &NodeID) -> Self {
+		GradScale::new(input, output, -1.0)
+	}
+}
+
+impl Op for GradScale {
+	type InstanceType = GradScaleInstance;
+
+	fn type_name(&self) -> &'static str {
+		"GradScale"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input.clone()], &[self.output.clone()]);
+
+		Ok(GradScaleInstance{
+			name: name,
+			input_id: self.input.clone(),
+			output_id: self.output.clone(),
+			forward_id: graph.add_pass(StopGradForward::new(
+					self.input.clone(),
+					self.output.clone())),
+			backward_id: graph.add_pass(GradScaleBackward::new(
+					self.input.clone(),
+					self.output.clone(),
+					self.scale)),
+		})
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct GradScaleInstance {
+	name: String,
+	input_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for GradScaleInstance {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){(vec![self.input_id.clone()], vec![self.output_id.clone()])}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).clone();
+		shapes.merge_with(&self.output_id, &input_shape)
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct GradScaleBackward {
+	input_id: NodeID,
+	output_id: NodeID,
+	scale: f32,
+}
+
+impl GradScaleBackward {
+	pub fn new(input_id: NodeID, output_id: NodeID, scale: f32) -> Self {
+		GradScaleBackward {
+			input_id,
+			output_id,
+			scale,
+		}
+	}
+}
+
+impl Pass for GradScaleBackward {
+	fn type_name(&self) -> &'static str {"GradScaleBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.output_id.gradient_id()],
+			vec![self.input_id.gradient_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+
+		ensure!(
+			input_grad.shape() == output_grad.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input_grad.shape(), output_grad.shape()))
+		);
+
+		let output_grad = output_grad.as_slice().unwrap();
+		let input_grad = input_grad.as_slice_mut().unwrap();
+		let scale = self.scale;
+
+		output_grad.par_iter().zip(input_grad.par_iter_mut()).for_each(|(og, ig)|{
+			*ig += *og * scale;
+		});
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_grad_scale(){
+	_grad_scale().unwrap();
+}
+
+fn _grad_scale() -> Result<()>{
+	use graph::GraphDef;
+	use ndarray::{ArrayD, IxDyn};
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![4], "input", tag![])?;
+	let node2 = g.new_node(shape![4], "output", tag![])?;
+
+	let _o1 = g.new_op(GradScale::reverse(&node1, &node2), tag![])?;
+
+	let mut subgraph = g.subgraph(&[node1.value_id(), node2.gradient_id()], &[node2.value_id(), node1.gradient_id()])?;
+	let input = ArrayD::from_shape_vec(IxDyn(&[4]), vec![1.0, -2.0, 3.0, -4.0]).unwrap();
+	let output_grad = ArrayD::from_shape_vec(IxDyn(&[4]), vec![0.5, 0.25, -1.0, 2.0]).unwrap();
+	let storage = subgraph.execute(vec![input, output_grad])?;
+
+	let output = storage.get::<f32>(&node2.value_id())?;
+	assert_eq!(output.iter().cloned().collect::<Vec<_>>(), vec![1.0, -2.0, 3.0, -4.0]);
+	let input_grad = storage.get::<f32>(&node1.gradient_id())?;
+	assert_eq!(input_grad.iter().cloned().collect::<Vec<_>>(), vec![-0.5, -0.25, 1.0, -2.0]);
+
+	Ok(())
+}
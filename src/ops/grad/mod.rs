@@ -1 +1,3 @@
-pub mod stop_grad;
\ No newline at end of file
+pub mod stop_grad;
+pub mod grad_scale;
+pub mod grad_clip;
\ No newline at end of file
@@ -104,8 +104,8 @@ impl Pass for StopGradForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input = data.get(&self.input_id.value_id())?;
-		let mut output = data.get_mut(&self.output_id.value_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		ensure!(
 			input.shape() == output.shape(),
@@ -0,0 +1,340 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::shape::normalise_axis;
+use ops::index::{read_indices, split_shape, index_at, check_index_dtypes};
+use ops::index::scatter_add::ScatterAddPass;
+use shape::NodeShape;
+use ndarray::Dimension;
+use std::any::Any;
+
+/// Picks elements along an axis of the input using an integer indices node.
+///
+/// The indices must have the same number of dimensions as the input, and the same size in all dimensions other than the gathering axis.
+/// The output has the shape of the indices, with `output[.., j, ..] = input[.., indices[.., j, ..], ..]`.
+/// For example, gathering along the last axis of a [batch, classes] input with [batch, 1] indices picks one logit per example.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Gather {
+	name: Option<String>,
+	axis: isize,
+	input_id: NodeID,
+	indices_id: NodeID,
+	output_id: NodeID,
+}
+
+impl Gather {
+	/// The axis can be in the range [-input.ndims(), input.ndims())
+	pub fn new(input_id: &NodeID, indices_id: &NodeID, output_id: &NodeID, axis: isize) -> Self{
+		Gather {
+			name: None,
+			axis,
+			input_id: input_id.clone(),
+			indices_id: indices_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+}
+
+impl Op for Gather {
+	type InstanceType = GatherInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Gather"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		build_instance(&self, &self.name, graph, &self.input_id, &self.indices_id, &self.output_id, self.axis, false)
+	}
+}
+
+/// Selects whole slices along an axis of the input using an integer indices node.
+///
+/// The indices can have any shape, which replaces the selection axis in the output,
+/// i.e. an input of shape [a, b, c] with indices of shape [n, m] and axis 1 produces an output of shape [a, n, m, c].
+/// Indices may repeat, in which case the gradients of the repeated slices are summed.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct IndexSelect {
+	name: Option<String>,
+	axis: isize,
+	input_id: NodeID,
+	indices_id: NodeID,
+	output_id: NodeID,
+}
+
+impl IndexSelect {
+	/// The axis can be in the range [-input.ndims(), input.ndims())
+	pub fn new(input_id: &NodeID, indices_id: &NodeID, output_id: &NodeID, axis: isize) -> Self{
+		IndexSelect {
+			name: None,
+			axis,
+			input_id: input_id.clone(),
+			indices_id: indices_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+}
+
+impl Op for IndexSelect {
+	type InstanceType = GatherInstance;
+
+	fn type_name(&self) -> &'static str {
+		"IndexSelect"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		build_instance(&self, &self.name, graph, &self.input_id, &self.indices_id, &self.output_id, self.axis, true)
+	}
+}
+
+fn build_instance<O: Op>(op: &O, name: &Option<String>, graph: &mut GraphDef, input_id: &NodeID, indices_id: &NodeID, output_id: &NodeID, axis: isize, select: bool) -> Result<GatherInstance> {
+	let name = standard_op_name(op, name, graph, &[input_id.clone(), indices_id.clone()], &[output_id.clone()]);
+
+	Ok(GatherInstance{
+		name: name,
+		axis: axis,
+		select: select,
+		input_id: input_id.clone(),
+		indices_id: indices_id.clone(),
+		output_id: output_id.clone(),
+		forward_id: graph.add_pass(GatherPass::new(
+			input_id.value_id(),
+			indices_id.value_id(),
+			output_id.value_id(),
+			axis,
+			select,
+		)),
+		backward_id: graph.add_pass(ScatterAddPass::new(
+			output_id.gradient_id(),
+			indices_id.value_id(),
+			input_id.gradient_id(),
+			axis,
+			select,
+		)),
+	})
+}
+
+/// The instance of both `Gather` and `IndexSelect`
+#[derive(Debug, Clone)]
+pub struct GatherInstance {
+	name: String,
+	axis: isize,
+	select: bool,
+	input_id: NodeID,
+	indices_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for GatherInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone(), self.indices_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn check_dtypes(&self) -> Result<()> {
+		check_index_dtypes(&self.indices_id, &[&self.input_id, &self.output_id])
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+		let indices_shape = shapes.get_shape(&self.indices_id).to_data_shape()?;
+		let axis = normalise_axis(self.axis, input_shape.ndim()).map_err(|e| ErrorKind::ShapePropagationError(self.name.clone(), e.to_string()))?;
+
+		let output_shape: NodeShape = if self.select {
+			input_shape.slice()[..axis].iter()
+				.chain(indices_shape.slice())
+				.chain(&input_shape.slice()[axis+1..])
+				.cloned().collect::<Vec<_>>().into()
+		} else {
+			ensure!(indices_shape.ndim() == input_shape.ndim() && (0..input_shape.ndim()).all(|i| i == axis || indices_shape[i] == input_shape[i]),
+				ErrorKind::ShapePropagationError(self.name.clone(), format!("indices shape {:?} must match input shape {:?} in all dimensions other than axis {}", indices_shape.slice(), input_shape.slice(), self.axis)));
+			indices_shape.slice().into()
+		};
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+
+/// Adds elements of the input, picked along an axis by the indices, to the output.
+///
+/// If `select` is true the indices pick whole slices as in `IndexSelect`, otherwise they pick individual elements as in `Gather`.
+/// The axis is relative to the input.
+#[derive(Debug, Clone)]
+pub struct GatherPass {
+	input_id: DataID,
+	indices_id: DataID,
+	output_id: DataID,
+	axis: isize,
+	select: bool,
+}
+
+impl GatherPass {
+	pub fn new(input_id: DataID, indices_id: DataID, output_id: DataID, axis: isize, select: bool) -> Self{
+		GatherPass {
+			input_id,
+			indices_id,
+			output_id,
+			axis,
+			select,
+		}
+	}
+}
+
+impl Pass for GatherPass {
+	fn type_name(&self) -> &'static str {"GatherPass"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.clone(), self.indices_id.clone()], vec![self.output_id.clone()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id)?;
+		let axis = normalise_axis(self.axis, input.ndim()).map_err(|e| ErrorKind::PassError(self.name(), e.to_string()))?;
+		let (outer, n, inner) = split_shape(input.shape(), axis);
+		let indices = read_indices(self.name(), data, &self.indices_id, n)?;
+		let mut output = data.get_mut::<f32>(&self.output_id)?;
+
+		let m = if outer * inner == 0 {0} else {output.len() / (outer * inner)};
+		ensure!(m * outer * inner == output.len() && indices.len() == if self.select {m} else {output.len()},
+			ErrorKind::PassError(self.name(), format!("input shape: {:?}, indices shape: {:?} and output shape: {:?} are not compatible along axis {}", input.shape(), self.indices_id.shape(), output.shape(), self.axis)));
+
+		let input = input.into_shape((outer, n, inner)).expect("input should have standard layout");
+		let mut output = output.view_mut().into_shape((outer, m, inner)).expect("output should have standard layout");
+
+		for o in 0..outer {
+			for j in 0..m {
+				for i in 0..inner {
+					output[[o, j, i]] += input[[o, index_at(&indices, self.select, m, inner, o, j, i), i]];
+				}
+			}
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_gather_backprop(){
+	_gather_backprop().unwrap();
+}
+
+fn _gather_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use dtype::DType;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![4, 6, 3], "input", tag![])?;
+	let node2 = g.new_typed_node(shape![4, 5, 3], DType::I64, "indices", tag![])?;
+	let node3 = g.new_node(shape![Unknown, Unknown, Unknown], "output", tag![])?;
+	let node4 = g.new_node(shape![4, 5, 3], "target", tag![])?;
+
+	let _o1 = g.new_op(Gather::new(&node1, &node2, &node3, -2), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![node2.clone() => Box::new(|| thread_rng().gen_range(0, 6) as f64) as Box<FnMut()->f64>];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+#[test]
+fn test_index_select_backprop(){
+	_index_select_backprop().unwrap();
+}
+
+fn _index_select_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use dtype::DType;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 5, 2], "input", tag![])?;
+	let node2 = g.new_typed_node(shape![2, 4], DType::U32, "indices", tag![])?;
+	let node3 = g.new_node(shape![Unknown, Unknown, Unknown, Unknown], "output", tag![])?;
+	let node4 = g.new_node(shape![3, 2, 4, 2], "target", tag![])?;
+
+	let _o1 = g.new_op(IndexSelect::new(&node1, &node2, &node3, 1), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![node2.clone() => Box::new(|| thread_rng().gen_range(0, 5) as f64) as Box<FnMut()->f64>];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+#[test]
+fn test_gather_values(){
+	_gather_values().unwrap();
+}
+
+fn _gather_values() -> Result<()>{
+	use graph::GraphDef;
+	use dtype::DType;
+	use ndarray::{ArrayD, IxDyn};
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 4], "logits", tag![])?;
+	let node2 = g.new_typed_node(shape![3, 1], DType::I64, "labels", tag![])?;
+	let node3 = g.new_node(shape![Unknown, Unknown], "target_logits", tag![])?;
+
+	let _o1 = g.new_op(Gather::new(&node1, &node2, &node3, -1), tag![])?;
+
+	let mut subgraph = g.subgraph(&[node1.value_id(), node2.value_id()], &[node3.value_id()])?;
+	let logits = ArrayD::from_shape_vec(IxDyn(&[3, 4]), (0..12).map(|x| x as f32).collect()).unwrap();
+	let labels = ArrayD::from_shape_vec(IxDyn(&[3, 1]), vec![2i64, 0, 3]).unwrap();
+	let storage = subgraph.execute_typed(vec![logits.into(), labels.into()])?;
+
+	let output = storage.get::<f32>(&node3.value_id())?;
+	assert_eq!(output.shape(), &[3, 1]);
+	assert_eq!(output.iter().cloned().collect::<Vec<_>>(), vec![2.0, 4.0, 11.0]);
+
+	Ok(())
+}
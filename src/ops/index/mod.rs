@@ -0,0 +1,49 @@
+pub mod gather;
+pub mod scatter_add;
+
+use graph::{ErrorKind, Result};
+use id::{NodeID, DataID};
+use storage::Storage;
+use dtype::DType;
+
+/// Reads an `I64` or `U32` indices node, checking that each index is in the range [0, `len`).
+pub fn read_indices(pass_name: String, data: &Storage, indices_id: &DataID, len: usize) -> Result<Vec<usize>> {
+	let indices: Vec<i64> = match indices_id.dtype() {
+		DType::I64 => data.get::<i64>(indices_id)?.iter().cloned().collect(),
+		DType::U32 => data.get::<u32>(indices_id)?.iter().map(|&i| i as i64).collect(),
+		dtype => bail!(ErrorKind::DataTypeMismatch(indices_id.name(), DType::I64, dtype)),
+	};
+
+	if let Some(&i) = indices.iter().find(|&&i| i < 0 || i as usize >= len) {
+		bail!(ErrorKind::PassError(pass_name, format!("index {} is outside the range [0, {})", i, len)));
+	}
+
+	Ok(indices.into_iter().map(|i| i as usize).collect())
+}
+
+/// Splits a shape about `axis` into the product of the outer dimensions, the axis length, and the product of the inner dimensions.
+fn split_shape(shape: &[usize], axis: usize) -> (usize, usize, usize) {
+	(shape[..axis].iter().product(), shape[axis], shape[axis+1..].iter().product())
+}
+
+/// Returns the position along the indexed axis for element `[o, j, i]` of an [outer, m, inner] indexed array.
+///
+/// If `select` is true, `indices` has length `m` and is shared by all outer and inner positions,
+/// otherwise `indices` has the same number of elements as the indexed array.
+#[inline]
+fn index_at(indices: &[usize], select: bool, m: usize, inner: usize, o: usize, j: usize, i: usize) -> usize {
+	if select {
+		indices[j]
+	} else {
+		indices[(o * m + j) * inner + i]
+	}
+}
+
+/// Checks that `indices_id` is an integer node and that all other nodes are `F32`.
+fn check_index_dtypes(indices_id: &NodeID, float_ids: &[&NodeID]) -> Result<()> {
+	ensure!(indices_id.dtype() == DType::I64 || indices_id.dtype() == DType::U32, ErrorKind::DataTypeMismatch(indices_id.value_id().name(), DType::I64, indices_id.dtype()));
+	for node_id in float_ids {
+		ensure!(node_id.dtype() == DType::F32, ErrorKind::DataTypeMismatch(node_id.value_id().name(), DType::F32, node_id.dtype()));
+	}
+	Ok(())
+}
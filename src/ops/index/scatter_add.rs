@@ -0,0 +1,237 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::shape::normalise_axis;
+use ops::index::{read_indices, split_shape, index_at, check_index_dtypes};
+use ops::index::gather::GatherPass;
+use shape::{NodeShape, NodeDim};
+use ndarray::Dimension;
+use std::any::Any;
+
+/// Adds each element of the input to the output at the position given by an integer indices node along an axis.
+///
+/// This is the transpose of `Gather`: the indices must have the same shape as the input,
+/// and `output[.., indices[.., j, ..], ..] += input[.., j, ..]`. Output positions which are not indexed receive nothing.
+/// The output has the same size as the input in all dimensions other than the scatter axis,
+/// where the size is taken from `size()` if set, and otherwise must be determined by the output node.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct ScatterAdd {
+	name: Option<String>,
+	axis: isize,
+	size: Option<usize>,
+	input_id: NodeID,
+	indices_id: NodeID,
+	output_id: NodeID,
+}
+
+impl ScatterAdd {
+	/// The axis can be in the range [-input.ndims(), input.ndims())
+	pub fn new(input_id: &NodeID, indices_id: &NodeID, output_id: &NodeID, axis: isize) -> Self{
+		ScatterAdd {
+			name: None,
+			axis,
+			size: None,
+			input_id: input_id.clone(),
+			indices_id: indices_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+
+	/// The size of the output along the scatter axis.
+	///
+	/// Default: None
+	pub fn size(mut self, size: usize) -> Self {
+		self.size = Some(size);
+		self
+	}
+}
+
+impl Op for ScatterAdd {
+	type InstanceType = ScatterAddInstance;
+
+	fn type_name(&self) -> &'static str {
+		"ScatterAdd"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone(), self.indices_id.clone()], &[self.output_id.clone()]);
+
+		Ok(ScatterAddInstance{
+			name: name,
+			axis: self.axis,
+			size: self.size,
+			input_id: self.input_id.clone(),
+			indices_id: self.indices_id.clone(),
+			output_id: self.output_id.clone(),
+			forward_id: graph.add_pass(ScatterAddPass::new(
+				self.input_id.value_id(),
+				self.indices_id.value_id(),
+				self.output_id.value_id(),
+				self.axis,
+				false,
+			)),
+			backward_id: graph.add_pass(GatherPass::new(
+				self.output_id.gradient_id(),
+				self.indices_id.value_id(),
+				self.input_id.gradient_id(),
+				self.axis,
+				false,
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ScatterAddInstance {
+	name: String,
+	axis: isize,
+	size: Option<usize>,
+	input_id: NodeID,
+	indices_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for ScatterAddInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone(), self.indices_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn check_dtypes(&self) -> Result<()> {
+		check_index_dtypes(&self.indices_id, &[&self.input_id, &self.output_id])
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+		let indices_shape = shapes.get_shape(&self.indices_id).to_data_shape()?;
+		let axis = normalise_axis(self.axis, input_shape.ndim()).map_err(|e| ErrorKind::ShapePropagationError(self.name.clone(), e.to_string()))?;
+		ensure!(indices_shape == input_shape, ErrorKind::ShapePropagationError(self.name.clone(), format!("indices shape {:?} must match input shape {:?}", indices_shape.slice(), input_shape.slice())));
+
+		let output_shape: NodeShape = input_shape.slice().iter().enumerate().map(|(i, &dim)| {
+			if i != axis {
+				NodeDim::Known(dim)
+			} else if let Some(size) = self.size {
+				NodeDim::Known(size)
+			} else {
+				NodeDim::Unknown
+			}
+		}).collect::<Vec<_>>().into();
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+
+/// Adds elements of the input to the output, at positions picked along an axis by the indices.
+///
+/// If `select` is true the indices pick whole slices as in `IndexSelect`, otherwise they pick individual elements as in `Gather`.
+/// The axis is relative to the output.
+#[derive(Debug, Clone)]
+pub struct ScatterAddPass {
+	input_id: DataID,
+	indices_id: DataID,
+	output_id: DataID,
+	axis: isize,
+	select: bool,
+}
+
+impl ScatterAddPass {
+	pub fn new(input_id: DataID, indices_id: DataID, output_id: DataID, axis: isize, select: bool) -> Self{
+		ScatterAddPass {
+			input_id,
+			indices_id,
+			output_id,
+			axis,
+			select,
+		}
+	}
+}
+
+impl Pass for ScatterAddPass {
+	fn type_name(&self) -> &'static str {"ScatterAddPass"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.clone(), self.indices_id.clone()], vec![self.output_id.clone()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id)?;
+		let mut output = data.get_mut::<f32>(&self.output_id)?;
+		let axis = normalise_axis(self.axis, output.ndim()).map_err(|e| ErrorKind::PassError(self.name(), e.to_string()))?;
+		let (outer, n, inner) = split_shape(output.shape(), axis);
+		let indices = read_indices(self.name(), data, &self.indices_id, n)?;
+
+		let m = if outer * inner == 0 {0} else {input.len() / (outer * inner)};
+		ensure!(m * outer * inner == input.len() && indices.len() == if self.select {m} else {input.len()},
+			ErrorKind::PassError(self.name(), format!("input shape: {:?}, indices shape: {:?} and output shape: {:?} are not compatible along axis {}", input.shape(), self.indices_id.shape(), output.shape(), self.axis)));
+
+		let input = input.into_shape((outer, m, inner)).expect("input should have standard layout");
+		let mut output = output.view_mut().into_shape((outer, n, inner)).expect("output should have standard layout");
+
+		for o in 0..outer {
+			for j in 0..m {
+				for i in 0..inner {
+					output[[o, index_at(&indices, self.select, m, inner, o, j, i), i]] += input[[o, j, i]];
+				}
+			}
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_scatter_add_backprop(){
+	_scatter_add_backprop().unwrap();
+}
+
+fn _scatter_add_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use dtype::DType;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 4, 2], "input", tag![])?;
+	let node2 = g.new_typed_node(shape![3, 4, 2], DType::I64, "indices", tag![])?;
+	let node3 = g.new_node(shape![Unknown, Unknown, Unknown], "output", tag![])?;
+	let node4 = g.new_node(shape![3, 5, 2], "target", tag![])?;
+
+	let _o1 = g.new_op(ScatterAdd::new(&node1, &node2, &node3, 1).size(5), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![node2.clone() => Box::new(|| thread_rng().gen_range(0, 5) as f64) as Box<FnMut()->f64>];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
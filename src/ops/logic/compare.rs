@@ -0,0 +1,266 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use dtype::DType;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::math::binary_elementwise::{broadcast_shapes, broadcast_node_shapes};
+use ndarray::{ArrayD, IxDyn, Zip};
+use std::any::Any;
+
+/// The relation tested by a `Compare` op
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+	Greater,
+	GreaterEqual,
+	Less,
+	LessEqual,
+	Equal,
+	NotEqual,
+}
+
+impl Comparison {
+	fn test(&self, input1: f32, input2: f32, tolerance: f32) -> bool {
+		match *self {
+			Comparison::Greater => input1 > input2,
+			Comparison::GreaterEqual => input1 >= input2,
+			Comparison::Less => input1 < input2,
+			Comparison::LessEqual => input1 <= input2,
+			Comparison::Equal => (input1 - input2).abs() <= tolerance,
+			Comparison::NotEqual => !((input1 - input2).abs() <= tolerance),
+		}
+	}
+}
+
+/// Compare Op
+///
+/// Both inputs are broadcast to the output shape following NumPy rules, and each pair of elements is compared.
+/// The output node can be `Bool`, or `F32` in which case 1.0 is added to the output where the comparison is true.
+///
+/// No gradient is propagated through this op.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Compare {
+	input1: NodeID,
+	input2: NodeID,
+	output: NodeID,
+	comparison: Comparison,
+	tolerance: f32,
+	name: Option<String>,
+}
+
+impl Compare {
+	pub fn new(input1: &NodeID, input2: &NodeID, output: &NodeID, comparison: Comparison) -> Self {
+		Compare {
+			input1: input1.clone(),
+			input2: input2.clone(),
+			output: output.clone(),
+			comparison: comparison,
+			tolerance: 0.0,
+			name: None,
+		}
+	}
+
+	/// input1 > input2
+	pub fn greater(input1: &NodeID, input2: &NodeID, output: &NodeID) -> Self {
+		Compare::new(input1, input2, output, Comparison::Greater)
+	}
+
+	/// input1 >= input2
+	pub fn greater_equal(input1: &NodeID, input2: &NodeID, output: &NodeID) -> Self {
+		Compare::new(input1, input2, output, Comparison::GreaterEqual)
+	}
+
+	/// input1 < input2
+	pub fn less(input1: &NodeID, input2: &NodeID, output: &NodeID) -> Self {
+		Compare::new(input1, input2, output, Comparison::Less)
+	}
+
+	/// input1 <= input2
+	pub fn less_equal(input1: &NodeID, input2: &NodeID, output: &NodeID) -> Self {
+		Compare::new(input1, input2, output, Comparison::LessEqual)
+	}
+
+	/// |input1 - input2| <= tolerance
+	pub fn equal(input1: &NodeID, input2: &NodeID, output: &NodeID) -> Self {
+		Compare::new(input1, input2, output, Comparison::Equal)
+	}
+
+	/// |input1 - input2| > tolerance
+	pub fn not_equal(input1: &NodeID, input2: &NodeID, output: &NodeID) -> Self {
+		Compare::new(input1, input2, output, Comparison::NotEqual)
+	}
+
+	/// The largest absolute difference at which elements are considered equal.
+	///
+	/// Only affects `Equal` and `NotEqual` comparisons.
+	///
+	/// Default: 0.0
+	pub fn tolerance(mut self, tolerance: f32) -> Self {
+		self.tolerance = tolerance;
+		self
+	}
+}
+
+impl Op for Compare {
+	type InstanceType = CompareInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Compare"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input1.clone(), self.input2.clone()], &[self.output.clone()]);
+
+		Ok(CompareInstance{
+			name: name,
+			input1_id: self.input1.clone(),
+			input2_id: self.input2.clone(),
+			output_id: self.output.clone(),
+			forward_id: graph.add_pass(CompareForward::new(
+					self.input1.clone(),
+					self.input2.clone(),
+					self.output.clone(),
+					self.comparison,
+					self.tolerance)),
+		})
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct CompareInstance {
+	name: String,
+	input1_id: NodeID,
+	input2_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+}
+
+impl OpInstance for CompareInstance {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){(vec![self.input1_id.clone(), self.input2_id.clone()], vec![self.output_id.clone()])}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{vec![]}
+
+	fn check_dtypes(&self) -> Result<()> {
+		for node_id in &[&self.input1_id, &self.input2_id] {
+			ensure!(node_id.dtype() == DType::F32, ErrorKind::DataTypeMismatch(node_id.value_id().name(), DType::F32, node_id.dtype()));
+		}
+		ensure!(self.output_id.dtype() == DType::Bool || self.output_id.dtype() == DType::F32, ErrorKind::DataTypeMismatch(self.output_id.value_id().name(), DType::Bool, self.output_id.dtype()));
+		Ok(())
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input1_shape = shapes.get_shape(&self.input1_id).clone();
+		let input2_shape = shapes.get_shape(&self.input2_id).clone();
+		let output_shape = broadcast_node_shapes(&input1_shape, &input2_shape)?;
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct CompareForward {
+	input1_id: NodeID,
+	input2_id: NodeID,
+	output_id: NodeID,
+	comparison: Comparison,
+	tolerance: f32,
+}
+
+impl CompareForward {
+	pub fn new(input1_id: NodeID, input2_id: NodeID, output_id: NodeID, comparison: Comparison, tolerance: f32) -> Self {
+		CompareForward {
+			input1_id,
+			input2_id,
+			output_id,
+			comparison,
+			tolerance,
+		}
+	}
+}
+
+impl Pass for CompareForward {
+	fn type_name(&self) -> &'static str {"CompareForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.input1_id.value_id(), self.input2_id.value_id()],
+			vec![self.output_id.value_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+
+		let shape = broadcast_shapes(input1.shape(), input2.shape()).map_err(|msg| ErrorKind::PassError(self.name(), msg))?;
+		let mut mask = ArrayD::from_elem(IxDyn(&shape), false);
+		Zip::from(&mut mask)
+			.and_broadcast(&input1)
+			.and_broadcast(&input2)
+			.apply(|mask, &input1, &input2| {
+				*mask = self.comparison.test(input1, input2, self.tolerance);
+			});
+
+		if self.output_id.dtype() == DType::Bool {
+			let mut output = data.get_mut::<bool>(&self.output_id.value_id())?;
+			ensure!(
+				output.shape() == mask.shape(),
+				ErrorKind::PassError(self.name(), format!("broadcast input shape: {:?} did not match output shape: {:?}", mask.shape(), output.shape()))
+			);
+			output.zip_mut_with(&mask, |output, &mask| *output = *output || mask);
+		} else {
+			let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+			ensure!(
+				output.shape() == mask.shape(),
+				ErrorKind::PassError(self.name(), format!("broadcast input shape: {:?} did not match output shape: {:?}", mask.shape(), output.shape()))
+			);
+			output.zip_mut_with(&mask, |output, &mask| if mask {*output += 1.0});
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_compare_values(){
+	_compare_values().unwrap();
+}
+
+fn _compare_values() -> Result<()>{
+	use graph::GraphDef;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 3], "input1", tag![])?;
+	let node2 = g.new_node(shape![3], "input2", tag![])?;
+	let node3 = g.new_typed_node(shape![Unknown, 3], DType::Bool, "greater", tag![])?;
+	let node4 = g.new_node(shape![Unknown, Unknown], "equal", tag![])?;
+
+	let _o1 = g.new_op(Compare::greater(&node1, &node2, &node3), tag![])?;
+	let _o2 = g.new_op(Compare::equal(&node1, &node2, &node4).tolerance(0.1), tag![])?;
+
+	let mut subgraph = g.subgraph(&[node1.value_id(), node2.value_id()], &[node3.value_id(), node4.value_id()])?;
+	let input1 = ArrayD::from_shape_vec(IxDyn(&[2, 3]), vec![0.0, 1.0, 2.0, 3.0, 1.05, -1.0]).unwrap();
+	let input2 = ArrayD::from_shape_vec(IxDyn(&[3]), vec![1.0, 1.0, 1.0]).unwrap();
+	let storage = subgraph.execute(vec![input1, input2])?;
+
+	let greater = storage.get::<bool>(&node3.value_id())?;
+	assert_eq!(greater.iter().cloned().collect::<Vec<_>>(), vec![false, false, true, true, true, false]);
+	let equal = storage.get::<f32>(&node4.value_id())?;
+	assert_eq!(equal.iter().cloned().collect::<Vec<_>>(), vec![0.0, 1.0, 0.0, 0.0, 1.0, 0.0]);
+
+	Ok(())
+}
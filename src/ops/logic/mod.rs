@@ -0,0 +1,2 @@
+pub mod compare;
+pub mod select;
\ No newline at end of file
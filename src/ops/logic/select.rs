@@ -0,0 +1,321 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use dtype::DType;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::math::binary_elementwise::{broadcast_shapes, broadcast_node_shapes, sum_to_shape};
+use ndarray::{ArrayD, IxDyn, Zip};
+use smallvec::SmallVec;
+use std::any::Any;
+
+/// Select Op, also known as Where
+///
+/// For each element the output is the value of `input_true` where the condition is true, and `input_false` otherwise.
+/// The condition node can be `Bool`, or `F32` in which case non-zero values are true.
+/// All three inputs are broadcast to the output shape following NumPy rules.
+///
+/// Gradients are routed only to the input which was selected, and no gradient is propagated to the condition.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Select {
+	condition: NodeID,
+	input_true: NodeID,
+	input_false: NodeID,
+	output: NodeID,
+	name: Option<String>,
+}
+
+/// Alias for `Select`
+pub type Where = Select;
+
+impl Select {
+	pub fn new(condition: &NodeID, input_true: &NodeID, input_false: &NodeID, output: &NodeID) -> Self {
+		Select {
+			condition: condition.clone(),
+			input_true: input_true.clone(),
+			input_false: input_false.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for Select {
+	type InstanceType = SelectInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Select"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.condition.clone(), self.input_true.clone(), self.input_false.clone()], &[self.output.clone()]);
+
+		Ok(SelectInstance{
+			name: name,
+			condition_id: self.condition.clone(),
+			input_true_id: self.input_true.clone(),
+			input_false_id: self.input_false.clone(),
+			output_id: self.output.clone(),
+			forward_id: graph.add_pass(SelectForward::new(
+					self.condition.clone(),
+					self.input_true.clone(),
+					self.input_false.clone(),
+					self.output.clone())),
+			backward_id: graph.add_pass(SelectBackward::new(
+					self.condition.clone(),
+					self.input_true.clone(),
+					self.input_false.clone(),
+					self.output.clone())),
+		})
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct SelectInstance {
+	name: String,
+	condition_id: NodeID,
+	input_true_id: NodeID,
+	input_false_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for SelectInstance {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){(vec![self.condition_id.clone(), self.input_true_id.clone(), self.input_false_id.clone()], vec![self.output_id.clone()])}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{vec![]}
+
+	fn check_dtypes(&self) -> Result<()> {
+		ensure!(self.condition_id.dtype() == DType::Bool || self.condition_id.dtype() == DType::F32, ErrorKind::DataTypeMismatch(self.condition_id.value_id().name(), DType::Bool, self.condition_id.dtype()));
+		for node_id in &[&self.input_true_id, &self.input_false_id, &self.output_id] {
+			ensure!(node_id.dtype() == DType::F32, ErrorKind::DataTypeMismatch(node_id.value_id().name(), DType::F32, node_id.dtype()));
+		}
+		Ok(())
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let condition_shape = shapes.get_shape(&self.condition_id).clone();
+		let input_true_shape = shapes.get_shape(&self.input_true_id).clone();
+		let input_false_shape = shapes.get_shape(&self.input_false_id).clone();
+		let output_shape = broadcast_node_shapes(&broadcast_node_shapes(&condition_shape, &input_true_shape)?, &input_false_shape)?;
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+/// Reads the condition as a bool array, treating non-zero `F32` values as true
+fn read_condition(data: &Storage, condition_id: &NodeID) -> Result<ArrayD<bool>> {
+	if condition_id.dtype() == DType::Bool {
+		Ok(data.get::<bool>(&condition_id.value_id())?.to_owned())
+	} else {
+		Ok(data.get::<f32>(&condition_id.value_id())?.map(|&x| x != 0.0))
+	}
+}
+
+/// Returns the broadcast shape of all three inputs
+fn check_shapes(pass_name: String, condition_shape: &[usize], input_true_shape: &[usize], input_false_shape: &[usize], output_shape: &[usize]) -> Result<SmallVec<[usize; 6]>> {
+	let shape = broadcast_shapes(condition_shape, input_true_shape)
+		.and_then(|shape| broadcast_shapes(&shape, input_false_shape))
+		.map_err(|msg| ErrorKind::PassError(pass_name.clone(), msg))?;
+	ensure!(
+		shape.as_slice() == output_shape,
+		ErrorKind::PassError(pass_name, format!("broadcast input shape: {:?} did not match output shape: {:?}", shape.as_slice(), output_shape))
+	);
+	Ok(shape)
+}
+
+
+#[derive(Clone, Debug)]
+pub struct SelectForward {
+	condition_id: NodeID,
+	input_true_id: NodeID,
+	input_false_id: NodeID,
+	output_id: NodeID,
+}
+
+impl SelectForward {
+	pub fn new(condition_id: NodeID, input_true_id: NodeID, input_false_id: NodeID, output_id: NodeID) -> Self {
+		SelectForward {
+			condition_id,
+			input_true_id,
+			input_false_id,
+			output_id,
+		}
+	}
+}
+
+impl Pass for SelectForward {
+	fn type_name(&self) -> &'static str {"SelectForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.condition_id.value_id(), self.input_true_id.value_id(), self.input_false_id.value_id()],
+			vec![self.output_id.value_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let condition = read_condition(data, &self.condition_id)?;
+		let input_true = data.get::<f32>(&self.input_true_id.value_id())?;
+		let input_false = data.get::<f32>(&self.input_false_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		check_shapes(self.name(), condition.shape(), input_true.shape(), input_false.shape(), output.shape())?;
+
+		Zip::from(output)
+			.and_broadcast(&condition)
+			.and_broadcast(&input_true)
+			.and_broadcast(&input_false)
+			.apply(|output, &condition, &input_true, &input_false| {
+				*output += if condition {input_true} else {input_false};
+			});
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct SelectBackward {
+	condition_id: NodeID,
+	input_true_id: NodeID,
+	input_false_id: NodeID,
+	output_id: NodeID,
+}
+
+impl SelectBackward {
+	pub fn new(condition_id: NodeID, input_true_id: NodeID, input_false_id: NodeID, output_id: NodeID) -> Self {
+		SelectBackward {
+			condition_id,
+			input_true_id,
+			input_false_id,
+			output_id,
+		}
+	}
+}
+
+impl Pass for SelectBackward {
+	fn type_name(&self) -> &'static str {"SelectBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.condition_id.value_id(), self.input_true_id.value_id(), self.input_false_id.value_id(), self.output_id.gradient_id()],
+			vec![self.input_true_id.gradient_id(), self.input_false_id.gradient_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let condition = read_condition(data, &self.condition_id)?;
+		let input_true_shape = data.get::<f32>(&self.input_true_id.value_id())?.shape().to_vec();
+		let input_false_shape = data.get::<f32>(&self.input_false_id.value_id())?.shape().to_vec();
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+
+		check_shapes(self.name(), condition.shape(), &input_true_shape, &input_false_shape, output_grad.shape())?;
+
+		for &(input_id, input_shape, selected) in &[(&self.input_true_id, &input_true_shape, true), (&self.input_false_id, &input_false_shape, false)] {
+			if !data.is_required(&input_id.gradient_id()) {
+				continue;
+			}
+
+			let mut full_grad = ArrayD::zeros(output_grad.shape());
+			Zip::from(&mut full_grad)
+				.and(&output_grad)
+				.and_broadcast(&condition)
+				.apply(|full_grad, &output_grad, &condition| {
+					if condition == selected {
+						*full_grad = output_grad;
+					}
+				});
+
+			let mut input_grad = data.get_mut::<f32>(&input_id.gradient_id())?;
+			input_grad += &sum_to_shape(full_grad, input_shape);
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_select_backprop(){
+	_select_backprop().unwrap();
+}
+
+fn _select_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_typed_node(shape![7, 5, 1], DType::Bool, "condition", tag![])?;
+	let node2 = g.new_node(shape![7, 1, 16], "input_true", tag![])?;
+	let node3 = g.new_node(shape![16], "input_false", tag![])?;
+	let node4 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node5 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+	let _o1 = g.new_op(Select::new(&node1, &node2, &node3, &node4), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node4, &node5), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| if thread_rng().gen() {1.0} else {0.0}) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_select_values(){
+	_select_values().unwrap();
+}
+
+fn _select_values() -> Result<()>{
+	use graph::GraphDef;
+	use ops::logic::compare::Compare;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 3], "input", tag![])?;
+	let node2 = g.new_node(shape![1], "threshold", tag![])?;
+	let node3 = g.new_typed_node(shape![2, 3], DType::Bool, "mask", tag![])?;
+	let node4 = g.new_node(shape![2, 3], "output", tag![])?;
+
+	let _o1 = g.new_op(Compare::greater(&node1, &node2, &node3), tag![])?;
+	let _o2 = g.new_op(Where::new(&node3, &node1, &node2, &node4), tag![])?;
+
+	let mut subgraph = g.subgraph(&[node1.value_id(), node2.value_id(), node4.gradient_id()], &[node4.value_id(), node1.gradient_id(), node2.gradient_id()])?;
+	let input = ArrayD::from_shape_vec(IxDyn(&[2, 3]), vec![-1.0, 0.5, 2.0, 3.0, -0.5, 0.0]).unwrap();
+	let threshold = ArrayD::from_elem(IxDyn(&[1]), 0.25);
+	let output_grad = ArrayD::from_elem(IxDyn(&[2, 3]), 1.0);
+	let storage = subgraph.execute(vec![input, threshold, output_grad])?;
+
+	let output = storage.get::<f32>(&node4.value_id())?;
+	assert_eq!(output.iter().cloned().collect::<Vec<_>>(), vec![0.25, 0.5, 2.0, 3.0, 0.25, 0.25]);
+	let input_grad = storage.get::<f32>(&node1.gradient_id())?;
+	assert_eq!(input_grad.iter().cloned().collect::<Vec<_>>(), vec![0.0, 1.0, 1.0, 1.0, 0.0, 0.0]);
+	let threshold_grad = storage.get::<f32>(&node2.gradient_id())?;
+	assert_eq!(threshold_grad.iter().cloned().collect::<Vec<_>>(), vec![3.0]);
+
+	Ok(())
+}
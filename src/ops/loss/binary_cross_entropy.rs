@@ -0,0 +1,553 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::loss::LossType;
+use shape::NodeShape;
+use smallvec::SmallVec;
+use ndarray::{ArrayD, ArrayViewD, ArrayViewMutD, Dimension, IxDyn};
+use std::any::Any;
+
+/// Probabilities are clamped to [EPSILON, 1 - EPSILON] to avoid infinite losses when `with_logits` is false
+const EPSILON: f32 = 1e-7;
+
+/// An `Op` which implements the Binary Cross Entropy Loss
+///
+/// This op expects one input tensor of probabilities in the range (0, 1), or of logits if `with_logits()` is set,
+/// and a second tensor of labels in the range [0, 1].
+/// Each element is treated as an independent binary classification, as in multi-label tagging.
+///
+/// By default this `Op` has no output and will generate loss and gradients.
+///
+/// If `output()` is set, the Binary Cross Entropy will be written to that Node,
+/// and instead of generating gradients this loss function will backprop gradients from the output node.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct BinaryCrossEntropy {
+	input_id: NodeID,
+	labels_id: NodeID,
+	output: Option<NodeID>,
+	mean_axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	multiplier: f32,
+	with_logits: bool,
+	pos_weights: Vec<f32>,
+	name: Option<String>,
+}
+
+impl BinaryCrossEntropy {
+	pub fn new(input: &NodeID, labels: &NodeID) -> Self {
+		BinaryCrossEntropy {
+			input_id: input.clone(),
+			labels_id: labels.clone(),
+			output: None,
+			mean_axes: SmallVec::new(),
+			keep_dims: false,
+			multiplier: 1.0,
+			with_logits: false,
+			pos_weights: vec![],
+			name: None,
+		}
+	}
+
+	/// If set this `Op` will output to the supplied node, any rely no other use ops to generate loss and gradients
+	/// The output node must have the same size as the input node unless reductions are applied using `.mean_axes()`.
+	///
+	/// Default: None.
+	pub fn output(mut self, output: &NodeID) -> Self {
+		self.output = Some(output.clone());
+		self
+	}
+
+	/// The axes supplied will be grouped when finding the mean,
+	/// with the operation repeated across the axes not supplied.
+	///
+	/// `axes` can be in the range [-input.ndims(), input.ndims());
+	/// If no axes are supplied then no mean operation is applied.
+	pub fn mean_axes(mut self, mean_axes: &[isize]) -> Self {
+		self.mean_axes = mean_axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the reduced axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+
+	/// Applies a multiplier to the output or to the loss generated.
+	pub fn multiplier(mut self, multiplier: f32) -> Self {
+		self.multiplier = multiplier;
+		self
+	}
+
+	/// If `true` the input is treated as logits, and the sigmoid is applied within the loss.
+	///
+	/// This is more numerically stable than applying a `Logistic` activation before the loss.
+	///
+	/// Default: `false`
+	pub fn with_logits(mut self, with_logits: bool) -> Self {
+		self.with_logits = with_logits;
+		self
+	}
+
+	/// Weights applied to the loss of positive labels, one per class along the innermost axis.
+	///
+	/// Values greater than 1 increase recall, and values less than 1 increase precision.
+	/// If empty, all weights are 1.
+	///
+	/// Default: empty
+	pub fn pos_weights(mut self, pos_weights: &[f32]) -> Self {
+		self.pos_weights = pos_weights.to_vec();
+		self
+	}
+}
+
+
+impl Op for BinaryCrossEntropy {
+	type InstanceType = BinaryCrossEntropyInstance;
+
+	fn type_name(&self) -> &'static str {
+		"BinaryCrossEntropy"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+
+		let name =  if let Some(ref output_id) = self.output {
+			standard_op_name(&self, &self.name, graph, &[self.input_id.clone(), self.labels_id.clone()], &[output_id.clone()])
+		} else {
+			standard_op_name(&self, &self.name, graph, &[self.input_id.clone(), self.labels_id.clone()], &[])
+		};
+
+		let settings = BceSettings {
+			multiplier: self.multiplier,
+			mean_axes: self.mean_axes.clone(),
+			with_logits: self.with_logits,
+			pos_weights: self.pos_weights.clone(),
+		};
+
+		let loss_type = if let Some(output_id) = self.output {
+			LossType::Output{
+				output_id: output_id.clone(),
+				forward_id: graph.add_pass(BinaryCrossEntropyForward::new(
+					settings.clone(),
+					self.input_id.clone(),
+					self.labels_id.clone(),
+					output_id.clone(),
+					self.keep_dims)),
+				backward_id: graph.add_pass(BinaryCrossEntropyBackward::new(
+					settings.clone(),
+					self.input_id.clone(),
+					self.labels_id.clone(),
+					output_id.clone(),
+					self.keep_dims)),
+			}
+		} else {
+			LossType::Joint{
+				pass_id: graph.add_pass(BinaryCrossEntropyJointPass::new(
+					settings.clone(),
+					self.input_id.clone(),
+					self.labels_id.clone()))
+			}
+		};
+
+		Ok(BinaryCrossEntropyInstance{
+			name: name,
+			multiplier: self.multiplier,
+			input_id: self.input_id.clone(),
+			labels_id: self.labels_id.clone(),
+			loss_type: loss_type,
+			mean_axes: self.mean_axes,
+			keep_dims: self.keep_dims,
+		})
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct BinaryCrossEntropyInstance {
+	name: String,
+	multiplier: f32,
+	input_id: NodeID,
+	labels_id: NodeID,
+	loss_type: LossType,
+	mean_axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+}
+
+impl OpInstance for BinaryCrossEntropyInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		match &self.loss_type {
+			&LossType::Joint{..} => (vec![self.input_id.clone(), self.labels_id.clone()], vec![]),
+			&LossType::Output{ref output_id, ..} => (vec![self.input_id.clone(), self.labels_id.clone()], vec![output_id.clone()]),
+		}
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		match &self.loss_type {
+			&LossType::Joint{ref pass_id} => vec![pass_id.clone()],
+			&LossType::Output{ref forward_id, ref backward_id, ..} => vec![forward_id.clone(), backward_id.clone()],
+		}
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {
+		vec![]
+	}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {
+		vec![]
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		if let &LossType::Output{ref output_id, ..} = &self.loss_type {
+			let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+			let labels_shape = shapes.get_shape(&self.labels_id).to_data_shape()?;
+			ensure!(input_shape == labels_shape, "Shape of input did not match shape of labels");
+			let output_shape: NodeShape = calc_output_shape(input_shape.slice(), &self.mean_axes, self.keep_dims).into();
+			shapes.merge_with(output_id, &output_shape)
+		} else {
+			Ok(())
+		}
+	}
+
+}
+
+fn calc_output_shape(input_shape: &[usize], axes: &[isize], keep_dims: bool) -> SmallVec<[usize; 6]> {
+	let reduce_mask = reduction_mask(input_shape.len(), &axes);
+	if keep_dims {
+		input_shape.iter().zip(&reduce_mask).map(|(&dim, &reduce)| {
+				if reduce {1} else {dim}
+			}).collect()
+	} else {
+		input_shape.iter().zip(&reduce_mask).filter_map(|(&dim, &reduce)| {
+				if reduce {None} else {Some(dim)}
+			}).collect()
+	}
+}
+
+/// Returns a mask indicating whether an axis should be reduced based on the axes list
+fn reduction_mask(len: usize, axes: &[isize]) -> SmallVec<[bool; 6]> {
+	let mut reduce = SmallVec::with_capacity(len);
+	for _ in 0..len {
+		reduce.push(false);
+	}
+	for axis in axes {
+		reduce[(axis + len as isize) as usize % len] = true;
+	}
+	reduce
+}
+
+/// Returns the loss, and its derivatives with respect to the input and the label
+fn binary_cross_entropy(x: f32, y: f32, w: f32, with_logits: bool) -> (f32, f32, f32) {
+	if with_logits {
+		// loss = (1 - y) x + (1 + (w - 1) y) softplus(-x), where softplus(-x) is calculated stably
+		let softplus = (-x).max(0.0) + (-x.abs()).exp().ln_1p();
+		let sigmoid_neg = 1.0 / (1.0 + x.exp());
+		let l = 1.0 + (w - 1.0) * y;
+		((1.0 - y) * x + l * softplus, (1.0 - y) - l * sigmoid_neg, -x + (w - 1.0) * softplus)
+	} else {
+		let p = x.max(EPSILON).min(1.0 - EPSILON);
+		(-(w * y * p.ln() + (1.0 - y) * (-p).ln_1p()), -w * y / p + (1.0 - y) / (1.0 - p), -(w * p.ln() - (-p).ln_1p()))
+	}
+}
+
+/// The settings shared by all passes
+#[derive(Clone, Debug)]
+struct BceSettings {
+	multiplier: f32,
+	mean_axes: SmallVec<[isize; 6]>,
+	with_logits: bool,
+	pos_weights: Vec<f32>,
+}
+
+impl BceSettings {
+	/// Checks the input and labels shapes, returning the multiplier divided by the number of elements in each mean, and the keep_dims output shape
+	fn check(&self, pass_name: String, input: &ArrayViewD<f32>, labels: &ArrayViewD<f32>) -> Result<(f32, SmallVec<[usize; 6]>)> {
+		ensure!(
+			labels.shape() == input.shape(),
+			ErrorKind::PassError(pass_name.clone(), format!("input shape: {:?} did not match labels shape: {:?}", input.shape(), labels.shape()))
+		);
+		ensure!(
+			self.pos_weights.is_empty() || input.shape().last() == Some(&self.pos_weights.len()),
+			ErrorKind::PassError(pass_name, format!("input shape: {:?} did not match the number of pos_weights: {}", input.shape(), self.pos_weights.len()))
+		);
+
+		let input_shape = input.shape();
+		let divisor: usize = input_shape.iter().zip(reduction_mask(input_shape.len(), &self.mean_axes)).filter_map(|(dim, reduce)| if reduce{Some(dim)} else {None}).product();
+		Ok((self.multiplier/divisor as f32, calc_output_shape(input_shape, &self.mean_axes, true)))
+	}
+
+	/// Returns the positive weights broadcast to the input shape
+	fn weights(&self, shape: &[usize]) -> ArrayD<f32> {
+		if self.pos_weights.is_empty() {
+			ArrayD::from_elem(IxDyn(&[]), 1.0)
+		} else {
+			ArrayD::from_shape_vec(IxDyn(&[self.pos_weights.len()]), self.pos_weights.clone()).unwrap()
+		}.broadcast(shape).unwrap().to_owned()
+	}
+
+	/// Iterates over each chunk of the input, labels and gradients corresponding to the keep_dims output shape,
+	/// adding the gradients (scaled by the output gradient, if supplied) and returning the total loss.
+	fn accumulate(&self, multiplier: f32, chunk_shape: &[usize], input: &ArrayViewD<f32>, labels: &ArrayViewD<f32>,
+			output_grad: Option<&ArrayViewD<f32>>, mut input_grad: Option<ArrayViewMutD<f32>>, mut labels_grad: Option<ArrayViewMutD<f32>>) -> f32 {
+		let weights = self.weights(input.shape());
+
+		let mut input_grad_chunks: Vec<_> = input_grad.iter_mut().flat_map(|grad| grad.exact_chunks_mut(chunk_shape).into_iter()).collect();
+		let mut labels_grad_chunks: Vec<_> = labels_grad.iter_mut().flat_map(|grad| grad.exact_chunks_mut(chunk_shape).into_iter()).collect();
+
+		let iter = input.exact_chunks(chunk_shape).into_iter()
+			.zip(labels.exact_chunks(chunk_shape))
+			.zip(weights.exact_chunks(chunk_shape));
+
+		let mut error = 0.0;
+		for (i, ((input_chunk, labels_chunk), weights_chunk)) in iter.enumerate() {
+			let mut input_grad_iter = input_grad_chunks.get_mut(i).map(|chunk| chunk.iter_mut());
+			let mut labels_grad_iter = labels_grad_chunks.get_mut(i).map(|chunk| chunk.iter_mut());
+			let mut output_grad_iter = output_grad.map(|grad| grad.iter());
+
+			for ((&x, &y), &w) in input_chunk.iter().zip(&labels_chunk).zip(&weights_chunk) {
+				let (loss, x_grad, y_grad) = binary_cross_entropy(x, y, w, self.with_logits);
+				let scale = output_grad_iter.as_mut().map(|iter| *iter.next().unwrap()).unwrap_or(1.0) * multiplier;
+				error += loss * multiplier;
+				if let Some(ref mut iter) = input_grad_iter {
+					*iter.next().unwrap() += x_grad * scale;
+				}
+				if let Some(ref mut iter) = labels_grad_iter {
+					*iter.next().unwrap() += y_grad * scale;
+				}
+			}
+		}
+		error
+	}
+}
+
+#[derive(Clone, Debug)]
+struct BinaryCrossEntropyJointPass {
+	settings: BceSettings,
+	input_id: NodeID,
+	labels_id: NodeID,
+}
+
+impl BinaryCrossEntropyJointPass {
+	pub fn new(settings: BceSettings, input_id: NodeID, labels_id: NodeID) -> Self {
+		BinaryCrossEntropyJointPass {
+			settings,
+			input_id,
+			labels_id,
+		}
+	}
+}
+
+impl Pass for BinaryCrossEntropyJointPass {
+	fn type_name(&self) -> &'static str {"BinaryCrossEntropyJointPass"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id(), self.labels_id.value_id()],
+		vec![self.input_id.gradient_id(), self.labels_id.gradient_id()])
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let labels = data.get::<f32>(&self.labels_id.value_id())?;
+		let (multiplier, output_shape_keep_dims) = self.settings.check(self.name(), &input, &labels)?;
+
+		let input_grad = if data.is_required(&self.input_id.gradient_id()) {Some(data.get_mut::<f32>(&self.input_id.gradient_id())?)} else {None};
+		let labels_grad = if data.is_required(&self.labels_id.gradient_id()) {Some(data.get_mut::<f32>(&self.labels_id.gradient_id())?)} else {None};
+
+		let error = self.settings.accumulate(multiplier, &output_shape_keep_dims, &input, &labels, None, input_grad, labels_grad);
+		data.loss_add(error);
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[derive(Clone, Debug)]
+struct BinaryCrossEntropyForward {
+	settings: BceSettings,
+	input_id: NodeID,
+	labels_id: NodeID,
+	output_id: NodeID,
+	keep_dims: bool,
+}
+
+impl BinaryCrossEntropyForward {
+	pub fn new(settings: BceSettings, input_id: NodeID, labels_id: NodeID, output_id: NodeID, keep_dims: bool) -> Self {
+		BinaryCrossEntropyForward {
+			settings,
+			input_id,
+			labels_id,
+			output_id,
+			keep_dims,
+		}
+	}
+}
+
+impl Pass for BinaryCrossEntropyForward {
+	fn type_name(&self) -> &'static str {"BinaryCrossEntropyForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id(), self.labels_id.value_id()],
+		vec![self.output_id.value_id()])
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let labels = data.get::<f32>(&self.labels_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
+		let (multiplier, output_shape_keep_dims) = self.settings.check(self.name(), &input, &labels)?;
+
+		let output_shape_actual = calc_output_shape(input.shape(), &self.settings.mean_axes, self.keep_dims);
+		ensure!(output_shape_actual.as_slice() == output.shape(), "Output shape {:?} does not match reduced input shape {:?}", output.shape(), output_shape_actual.as_slice());
+
+		let mut output = output.into_shape(&output_shape_keep_dims[..]).expect("This should have been caught by the ensure above");
+		let weights = self.settings.weights(input.shape());
+
+		let iter = input.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
+			.zip(labels.exact_chunks(output_shape_keep_dims.as_slice()))
+			.zip(weights.exact_chunks(output_shape_keep_dims.as_slice()));
+
+		for ((input_chunk, labels_chunk), weights_chunk) in iter {
+			for (((output, &x), &y), &w) in output.iter_mut().zip(&input_chunk).zip(&labels_chunk).zip(&weights_chunk) {
+				*output += binary_cross_entropy(x, y, w, self.settings.with_logits).0 * multiplier;
+			}
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Clone, Debug)]
+struct BinaryCrossEntropyBackward {
+	settings: BceSettings,
+	input_id: NodeID,
+	labels_id: NodeID,
+	output_id: NodeID,
+	keep_dims: bool,
+}
+
+impl BinaryCrossEntropyBackward {
+	pub fn new(settings: BceSettings, input_id: NodeID, labels_id: NodeID, output_id: NodeID, keep_dims: bool) -> Self {
+		BinaryCrossEntropyBackward {
+			settings,
+			input_id,
+			labels_id,
+			output_id,
+			keep_dims,
+		}
+	}
+}
+
+impl Pass for BinaryCrossEntropyBackward {
+	fn type_name(&self) -> &'static str {"BinaryCrossEntropyBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id(), self.labels_id.value_id(), self.output_id.gradient_id()],
+		vec![self.input_id.gradient_id(), self.labels_id.gradient_id()])
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let labels = data.get::<f32>(&self.labels_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let (multiplier, output_shape_keep_dims) = self.settings.check(self.name(), &input, &labels)?;
+
+		let output_shape_actual = calc_output_shape(input.shape(), &self.settings.mean_axes, self.keep_dims);
+		ensure!(output_shape_actual.as_slice() == output_grad.shape(), "Output shape {:?} does not match reduced input shape {:?}", output_grad.shape(), output_shape_actual.as_slice());
+		let output_grad = output_grad.into_shape(&output_shape_keep_dims[..]).expect("This should have been caught by the ensure above");
+
+		let input_grad = if data.is_required(&self.input_id.gradient_id()) {Some(data.get_mut::<f32>(&self.input_id.gradient_id())?)} else {None};
+		let labels_grad = if data.is_required(&self.labels_id.gradient_id()) {Some(data.get_mut::<f32>(&self.labels_id.gradient_id())?)} else {None};
+
+		self.settings.accumulate(multiplier, &output_shape_keep_dims, &input, &labels, Some(&output_grad), input_grad, labels_grad);
+
+		Ok(Box::new(()))
+	}
+}
+
+#[test]
+fn test_binary_cross_entropy_backprop(){
+	_binary_cross_entropy_backprop().unwrap();
+}
+
+fn _binary_cross_entropy_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 4], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 4], "labels", tag![])?;
+
+	let _o1 = g.new_op(BinaryCrossEntropy::new(&node1, &node2).pos_weights(&[1.0, 2.0, 0.5, 3.0]), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| thread_rng().gen_range(0.1, 0.9)) as Box<FnMut()->f64>,
+		node2.clone() => Box::new(|| thread_rng().gen_range(0.0, 1.0)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+#[test]
+fn test_binary_cross_entropy_logits_output_backprop(){
+	_binary_cross_entropy_logits_output_backprop().unwrap();
+}
+
+fn _binary_cross_entropy_logits_output_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::proportional::Proportional;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 4], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 4], "labels", tag![])?;
+	let node3 = g.new_node(shape![5], "output", tag![])?;
+
+	let _o1 = g.new_op(BinaryCrossEntropy::new(&node1, &node2).with_logits(true).pos_weights(&[1.0, 2.0, 0.5, 3.0]).mean_axes(&[0, -1]).output(&node3), tag![])?;
+	let _o2 = g.new_op(Proportional::new(&node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node2.clone() => Box::new(|| thread_rng().gen_range(0.0, 1.0)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+#[test]
+fn test_binary_cross_entropy_logits_stable(){
+	for &(x, y) in &[(100.0, 0.0), (-100.0, 1.0), (100.0, 1.0), (-100.0, 0.0)] {
+		let (loss, x_grad, _) = binary_cross_entropy(x, y, 1.0, true);
+		assert!(loss.is_finite() && x_grad.is_finite());
+	}
+	let (loss, x_grad, _) = binary_cross_entropy(0.0, 1.0, 2.0, true);
+	assert!((loss - 2.0 * 2.0f32.ln()).abs() < 1e-6);
+	assert!((x_grad + 1.0).abs() < 1e-6);
+}
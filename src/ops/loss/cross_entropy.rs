@@ -182,8 +182,8 @@ impl Pass for CrossEntropyJointPass {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let logits_val = data.get(&self.logits_id.value_id())?;
-		let labels_val = data.get(&self.labels_id.value_id())?;
+		let logits_val = data.get::<f32>(&self.logits_id.value_id())?;
+		let labels_val = data.get::<f32>(&self.labels_id.value_id())?;
 
 		ensure!(
 			labels_val.shape() == logits_val.shape(),
@@ -202,9 +202,9 @@ impl Pass for CrossEntropyJointPass {
 		let mut error = 0.0;
 
 		if data.is_required(&self.logits_id.gradient_id()) && data.is_required(&self.labels_id.gradient_id()) {
-			let mut logits_grad = data.get_mut(&self.logits_id.gradient_id())?;
+			let mut logits_grad = data.get_mut::<f32>(&self.logits_id.gradient_id())?;
 			let logits_grad = logits_grad.as_slice_mut().unwrap();
-			let mut labels_grad = data.get_mut(&self.labels_id.gradient_id())?;
+			let mut labels_grad = data.get_mut::<f32>(&self.labels_id.gradient_id())?;
 			let labels_grad = labels_grad.as_slice_mut().unwrap();
 			assert!(logits_grad.len() == n);
 			assert!(labels_grad.len() == n);
@@ -216,7 +216,7 @@ impl Pass for CrossEntropyJointPass {
 			}
 
 		} else if data.is_required(&self.logits_id.gradient_id()) {
-			let mut logits_grad = data.get_mut(&self.logits_id.gradient_id())?;
+			let mut logits_grad = data.get_mut::<f32>(&self.logits_id.gradient_id())?;
 			let logits_grad = logits_grad.as_slice_mut().unwrap();
 			assert!(logits_grad.len() == n);
 
@@ -227,7 +227,7 @@ impl Pass for CrossEntropyJointPass {
 			}
 
 		} else if data.is_required(&self.labels_id.gradient_id()) {
-			let mut labels_grad = data.get_mut(&self.labels_id.gradient_id())?;
+			let mut labels_grad = data.get_mut::<f32>(&self.labels_id.gradient_id())?;
 			let labels_grad = labels_grad.as_slice_mut().unwrap();
 			assert!(labels_grad.len() == n);
 
@@ -272,9 +272,9 @@ impl Pass for CrossEntropyForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let logits_val = data.get(&self.logits_id.value_id())?;
-		let labels_val = data.get(&self.labels_id.value_id())?;
-		let mut output_val = data.get_mut(&self.output_id.value_id())?;
+		let logits_val = data.get::<f32>(&self.logits_id.value_id())?;
+		let labels_val = data.get::<f32>(&self.labels_id.value_id())?;
+		let mut output_val = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		ensure!(
 			labels_val.shape() == logits_val.shape(),
@@ -327,9 +327,9 @@ impl Pass for CrossEntropyBackward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let logits_val = data.get(&self.logits_id.value_id())?;
-		let labels_val = data.get(&self.labels_id.value_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let logits_val = data.get::<f32>(&self.logits_id.value_id())?;
+		let labels_val = data.get::<f32>(&self.labels_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 
 		ensure!(
 			labels_val.shape() == logits_val.shape(),
@@ -346,9 +346,9 @@ impl Pass for CrossEntropyBackward {
 		let multiplier = self.multiplier;
 
 		if data.is_required(&self.logits_id.gradient_id()) && data.is_required(&self.labels_id.gradient_id()) {
-			let mut logits_grad = data.get_mut(&self.logits_id.gradient_id())?;
+			let mut logits_grad = data.get_mut::<f32>(&self.logits_id.gradient_id())?;
 			let logits_grad = logits_grad.as_slice_mut().unwrap();
-			let mut labels_grad = data.get_mut(&self.labels_id.gradient_id())?;
+			let mut labels_grad = data.get_mut::<f32>(&self.labels_id.gradient_id())?;
 			let labels_grad = labels_grad.as_slice_mut().unwrap();
 			assert!(logits_grad.len() == n);
 			assert!(labels_grad.len() == n);
@@ -360,7 +360,7 @@ impl Pass for CrossEntropyBackward {
 			}
 
 		} else if data.is_required(&self.logits_id.gradient_id()) {
-			let mut logits_grad = data.get_mut(&self.logits_id.gradient_id())?;
+			let mut logits_grad = data.get_mut::<f32>(&self.logits_id.gradient_id())?;
 			let logits_grad = logits_grad.as_slice_mut().unwrap();
 			assert!(logits_grad.len() == n);
 			assert!(output_grad.len() == n);
@@ -370,7 +370,7 @@ impl Pass for CrossEntropyBackward {
 			}
 
 		} else if data.is_required(&self.labels_id.gradient_id()) {
-			let mut labels_grad = data.get_mut(&self.labels_id.gradient_id())?;
+			let mut labels_grad = data.get_mut::<f32>(&self.labels_id.gradient_id())?;
 			let labels_grad = labels_grad.as_slice_mut().unwrap();
 			assert!(labels_grad.len() == n);
 			assert!(output_grad.len() == n);
@@ -0,0 +1,332 @@
+use graph::{GraphDef, GraphShapes, Result, ErrorKind};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::loss::ssim::SsimSettings;
+use shape::{NodeShape, NodeDim};
+use ndarray::Dimension;
+use std::any::Any;
+use smallvec::SmallVec;
+
+
+/// The image quality measure calculated by `ImageQuality`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QualityMetric {
+	/// Peak Signal to Noise Ratio in decibels, `10 log10(data_range^2 / mse)`
+	Psnr,
+	/// Mean Structural Similarity Index
+	Ssim,
+}
+
+/// `ImageQuality` is a non differentiable image quality metric, for use in validation
+///
+/// This operation does not provide gradients but rather returns the PSNR or mean SSIM between the input and the target for each group.
+/// SSIM expects the layout [batch, spatial.., channels], and is calculated as for the `Ssim` loss before being averaged over each group.
+/// Identical groups have an infinite PSNR.
+///
+/// The output shape is the same as the input/target shape with the axes provided for grouping removed.
+/// If `keep_dims(true)` then the provided axes arent removed but are instead replaced with `1`.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct ImageQuality {
+	name: Option<String>,
+	input_id: NodeID,
+	target_id: NodeID,
+	output_id: NodeID,
+	metric: QualityMetric,
+	settings: SsimSettings,
+	data_range: f32,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+}
+
+impl ImageQuality {
+	pub fn new(input_id: &NodeID, target_id: &NodeID, output_id: &NodeID, metric: QualityMetric) -> Self {
+		ImageQuality{
+			name: None,
+			input_id: input_id.clone(),
+			target_id: target_id.clone(),
+			output_id: output_id.clone(),
+			metric: metric,
+			settings: SsimSettings::new(),
+			data_range: 1.0,
+			axes: SmallVec::new(),
+			keep_dims: false,
+		}
+	}
+
+	pub fn psnr(input_id: &NodeID, target_id: &NodeID, output_id: &NodeID) -> Self {
+		ImageQuality::new(input_id, target_id, output_id, QualityMetric::Psnr)
+	}
+
+	pub fn ssim(input_id: &NodeID, target_id: &NodeID, output_id: &NodeID) -> Self {
+		ImageQuality::new(input_id, target_id, output_id, QualityMetric::Ssim)
+	}
+
+	/// The axes supplied will be grouped together when calculating the metric,
+	/// with the operation repeated across the axes not supplied.
+	///
+	/// `axes` can be in the range [-input.ndims(), input.ndims());
+	/// If no axes are supplied then all dimensions with Known size will be grouped, typically giving one value per image.
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the grouped axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+
+	/// The difference between the largest and smallest possible input values, e.g. 1.0 or 255.0.
+	///
+	/// Default: 1.0
+	pub fn data_range(mut self, data_range: f32) -> Self {
+		self.data_range = data_range;
+		self.settings = self.settings.data_range(data_range);
+		self
+	}
+
+	/// Replaces the SSIM window and constant settings, including the data range.
+	pub fn settings(mut self, settings: SsimSettings) -> Self {
+		self.settings = settings;
+		self
+	}
+}
+
+impl Op for ImageQuality {
+	type InstanceType = ImageQualityInstance;
+
+	fn type_name(&self) -> &'static str {
+		"ImageQuality"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(mut self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone(), self.target_id.clone()], &[self.output_id.clone()]);
+
+		self.settings.validate(name.clone())?;
+		ensure!(self.data_range > 0.0, ErrorKind::ShapePropagationError(name.clone(), format!("data_range ({}) must be greater than zero", self.data_range)));
+
+		{
+			let input_shape = self.input_id.shape();
+			if self.axes.len() == 0 {
+				for i in 0..input_shape.ndim() {
+					if matches!(input_shape.dimensions()[i], NodeDim::Known(_)) {
+						self.axes.push(i as isize);
+					}
+				}
+			}
+		}
+
+		Ok(ImageQualityInstance{
+			name: name,
+			input_id: self.input_id.clone(),
+			target_id: self.target_id.clone(),
+			output_id: self.output_id.clone(),
+			axes: self.axes.clone(),
+			keep_dims: self.keep_dims,
+			forward_id:graph.add_pass(ImageQualityForward::new(
+				self.input_id.clone(),
+				self.target_id.clone(),
+				self.output_id.clone(),
+				self.metric,
+				self.settings,
+				self.data_range,
+				self.axes.clone(),
+				self.keep_dims,
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageQualityInstance {
+	name: String,
+	input_id: NodeID,
+	target_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	forward_id: PassID,
+}
+
+impl OpInstance for ImageQualityInstance {
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone(), self.target_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+		let target_shape = shapes.get_shape(&self.target_id).to_data_shape()?;
+		ensure!(target_shape == input_shape, "input shape doesnt match target shape");
+
+		let output_shape: NodeShape = calc_output_shape(input_shape.slice(), &self.axes, self.keep_dims).into();
+
+		shapes.merge_with(&self.output_id, &output_shape)?;
+		Ok(())
+	}
+}
+
+fn calc_output_shape(input_shape: &[usize], axes: &[isize], keep_dims: bool) -> SmallVec<[usize; 6]> {
+	let group_mask = group_mask(input_shape.len(), &axes);
+	if keep_dims {
+		input_shape.iter().zip(&group_mask).map(|(&dim, &group)| {
+				if group {1} else {dim}
+			}).collect()
+	} else {
+		input_shape.iter().zip(&group_mask).filter_map(|(&dim, &group)| {
+				if group {None} else {Some(dim)}
+			}).collect()
+	}
+}
+
+fn group_mask(len: usize, axes: &[isize]) -> SmallVec<[bool; 6]> {
+	let mut group = SmallVec::with_capacity(len);
+	for _ in 0..len {
+		group.push(false);
+	}
+	for axis in axes {
+		group[(axis + len as isize) as usize % len] = true;
+	}
+	group
+}
+
+#[derive(Debug, Clone)]
+struct ImageQualityForward {
+	input_id: NodeID,
+	target_id: NodeID,
+	output_id: NodeID,
+	metric: QualityMetric,
+	settings: SsimSettings,
+	data_range: f32,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+}
+
+impl ImageQualityForward {
+	pub fn new(input_id: NodeID, target_id: NodeID, output_id: NodeID, metric: QualityMetric, settings: SsimSettings, data_range: f32, axes: SmallVec<[isize; 6]>, keep_dims: bool) -> Self{
+		ImageQualityForward  {
+			input_id,
+			target_id,
+			output_id,
+			metric,
+			settings,
+			data_range,
+			axes,
+			keep_dims,
+		}
+	}
+}
+
+impl Pass for ImageQualityForward {
+	fn type_name(&self) -> &'static str {"ImageQualityForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.input_id.value_id(), self.target_id.value_id()],
+			vec![self.output_id.value_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let target = data.get::<f32>(&self.target_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		let input_shape: SmallVec<[usize; 6]> = input.shape().iter().cloned().collect();
+		let output_shape: SmallVec<[usize; 6]> = output.shape().iter().cloned().collect();
+
+		let group_mask = group_mask(input_shape.len(), &self.axes);
+
+		let output_shape_actual = calc_output_shape(&input_shape, &self.axes, self.keep_dims);
+
+		ensure!(output_shape_actual.as_slice() == output_shape.as_slice(), "Output shape {:?} does not match reduced input shape {:?}", output_shape.as_slice(), output_shape_actual.as_slice());
+		ensure!(input.shape() == target.shape(),ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match target shape: {:?}", input.shape(), target.shape())));
+
+		let map = match self.metric {
+			QualityMetric::Psnr => (&input - &target).mapv_into(|diff| diff * diff),
+			QualityMetric::Ssim => {
+				ensure!(input.ndim() > 2, ErrorKind::PassError(self.name(), format!("input shape: {:?} must have batch, spatial and channel axes", input.shape())));
+				self.settings.ssim_map(input.view(), target.view())
+			},
+		};
+
+		let group_shape: Vec<usize> = input.shape().iter().enumerate().map(|(i, dim)| if group_mask[i] {*dim} else {1}).collect();
+		let group_len: usize = group_shape.iter().product();
+
+		for (map_chunk, output_element) in map.exact_chunks(group_shape.as_slice()).into_iter().zip(output.iter_mut()) {
+			let mean = map_chunk.scalar_sum()/group_len as f32;
+			*output_element += match self.metric {
+				QualityMetric::Psnr => 10.0 * (self.data_range * self.data_range/mean).log10(),
+				QualityMetric::Ssim => mean,
+			};
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_image_quality(){
+	_image_quality().unwrap();
+}
+
+fn _image_quality() -> Result<()>{
+	use graph::GraphDef;
+	use ndarray::ArrayD;
+	use rand::{thread_rng, Rng};
+
+	let mut g = GraphDef::new();
+
+	let input = g.new_node(shape![Unknown, 9, 8, 3], "input", tag![])?;
+	let target = g.new_node(shape![Unknown, 9, 8, 3], "target", tag![])?;
+
+	let psnr = g.new_node(shape![Unknown], "psnr", tag![])?;
+	let ssim = g.new_node(shape![Unknown, 3], "ssim", tag![])?;
+
+	let _o1 = g.new_op(ImageQuality::psnr(&input, &target, &psnr).data_range(2.0), tag![])?;
+	let _o2 = g.new_op(ImageQuality::ssim(&input, &target, &ssim).axes(&[1, 2]), tag![])?;
+
+	let mut subgraph = g.subgraph(&[input.value_id(), target.value_id()], &[psnr.value_id(), ssim.value_id()])?;
+
+	let input_data = ArrayD::from_shape_fn(vec![2, 9, 8, 3], |_| thread_rng().gen_range(0.0, 1.0));
+	let target_data = input_data.mapv(|x| x + 0.2);
+
+	let storage = subgraph.execute(vec![input_data.clone(), target_data])?;
+	let psnr_data = storage.get::<f32>(&psnr.value_id())?;
+	let ssim_data = storage.get::<f32>(&ssim.value_id())?;
+
+	assert_eq!(psnr_data.shape(), &[2]);
+	assert_eq!(ssim_data.shape(), &[2, 3]);
+	// 10 log10(2^2/0.2^2) = 20dB
+	assert!(psnr_data.iter().all(|&v| (v - 20.0).abs() < 1e-3), "{:?}", psnr_data);
+	assert!(ssim_data.iter().all(|&v| v > 0.5 && v < 1.0), "{:?}", ssim_data);
+
+	let storage = subgraph.execute(vec![input_data.clone(), input_data])?;
+	assert!(storage.get::<f32>(&ssim.value_id())?.iter().all(|&v| (v - 1.0).abs() < 1e-5));
+
+	Ok(())
+}
@@ -239,8 +239,8 @@ impl Pass for MaeJointPass {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input1 = data.get(&self.input1_id.value_id())?;
-		let input2 = data.get(&self.input2_id.value_id())?;
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
 
 		ensure!(
 			input2.shape() == input1.shape(),
@@ -258,8 +258,8 @@ impl Pass for MaeJointPass {
 		let mut error = 0.0;
 
 		if data.is_required(&self.input1_id.gradient_id()) && data.is_required(&self.input2_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -279,7 +279,7 @@ impl Pass for MaeJointPass {
 				});
 			}
 		} else if data.is_required(&self.input1_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -296,7 +296,7 @@ impl Pass for MaeJointPass {
 				});
 			}
 		} else if data.is_required(&self.input2_id.gradient_id()) {
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -353,9 +353,9 @@ impl Pass for MaeForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input1 = data.get(&self.input1_id.value_id())?;
-		let input2 = data.get(&self.input2_id.value_id())?;
-		let output = data.get_mut(&self.output_id.value_id())?;
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		ensure!(
 			input2.shape() == input1.shape(),
@@ -424,9 +424,9 @@ impl Pass for MaeBackward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input1 = data.get(&self.input1_id.value_id())?;
-		let input2 = data.get(&self.input2_id.value_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 
 		ensure!(
 			input2.shape() == input1.shape(),
@@ -447,8 +447,8 @@ impl Pass for MaeBackward {
 		let output_grad = output_grad.into_shape(&output_shape_keep_dims[..]).expect("This should have been caught by the ensure above");;
 
 		if data.is_required(&self.input1_id.gradient_id()) && data.is_required(&self.input2_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -469,7 +469,7 @@ impl Pass for MaeBackward {
 			}
 
 		} else if data.is_required(&self.input1_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -486,7 +486,7 @@ impl Pass for MaeBackward {
 				});
 			}
 		} else if data.is_required(&self.input2_id.gradient_id()) {
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -2,8 +2,12 @@ pub mod proportional;
 pub mod mse;
 pub mod mae;
 pub mod cross_entropy;
+pub mod binary_cross_entropy;
+pub mod softmax_cross_entropy;
 pub mod prediction;
 pub mod robust;
+pub mod ssim;
+pub mod image_quality;
 
 
 use id::{NodeID, PassID};
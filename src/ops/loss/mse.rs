@@ -239,8 +239,8 @@ impl Pass for MseJointPass {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input1 = data.get(&self.input1_id.value_id())?;
-		let input2 = data.get(&self.input2_id.value_id())?;
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
 
 		ensure!(
 			input2.shape() == input1.shape(),
@@ -258,8 +258,8 @@ impl Pass for MseJointPass {
 		let mut error = 0.0;
 
 		if data.is_required(&self.input1_id.gradient_id()) && data.is_required(&self.input2_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -280,7 +280,7 @@ impl Pass for MseJointPass {
 			}
 
 		} else if data.is_required(&self.input1_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -297,7 +297,7 @@ impl Pass for MseJointPass {
 				});
 			}
 		} else if data.is_required(&self.input2_id.gradient_id()) {
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -354,9 +354,9 @@ impl Pass for MseForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input1 = data.get(&self.input1_id.value_id())?;
-		let input2 = data.get(&self.input2_id.value_id())?;
-		let output = data.get_mut(&self.output_id.value_id())?;
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		ensure!(
 			input2.shape() == input1.shape(),
@@ -425,9 +425,9 @@ impl Pass for MseBackward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input1 = data.get(&self.input1_id.value_id())?;
-		let input2 = data.get(&self.input2_id.value_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 
 		ensure!(
 			input2.shape() == input1.shape(),
@@ -448,8 +448,8 @@ impl Pass for MseBackward {
 		let output_grad = output_grad.into_shape(&output_shape_keep_dims[..]).expect("This should have been caught by the ensure above");;
 
 		if data.is_required(&self.input1_id.gradient_id()) && data.is_required(&self.input2_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -470,7 +470,7 @@ impl Pass for MseBackward {
 			}
 
 		} else if data.is_required(&self.input1_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -487,7 +487,7 @@ impl Pass for MseBackward {
 				});
 			}
 		} else if data.is_required(&self.input2_id.gradient_id()) {
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
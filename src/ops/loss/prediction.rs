@@ -198,9 +198,9 @@ impl Pass for PredictionForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input = data.get(&self.input_id.value_id())?;
-		let target = data.get(&self.target_id.value_id())?;
-		let mut output = data.get_mut(&self.output_id.value_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let target = data.get::<f32>(&self.target_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		let input_shape: SmallVec<[usize; 6]> = input.shape().iter().cloned().collect();
 		let output_shape: SmallVec<[usize; 6]> = output.shape().iter().cloned().collect();
@@ -279,7 +279,7 @@ fn _prediction() -> Result<()>{
 	}
 
 	let storage = subgraph.execute(vec![input_arr, target_arr])?;
-	let out = storage.get_mut(&output.value_id())?;
+	let out = storage.get_mut::<f32>(&output.value_id())?;
 
 	let expect: Vec<f32> = coords.iter().map(|coords| coords.2).collect();
 
@@ -105,8 +105,8 @@ impl Pass for ProportionalBackward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input_val = data.get(&self.input_id.value_id())?;
-		let mut input_grad = data.get_mut(&self.input_id.gradient_id())?;
+		let input_val = data.get::<f32>(&self.input_id.value_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
 		let input_val = input_val.as_slice().unwrap();
 		let input_grad = input_grad.as_slice_mut().unwrap();
 
@@ -283,8 +283,8 @@ impl Pass for RobustJointPass {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input1 = data.get(&self.input1_id.value_id())?;
-		let input2 = data.get(&self.input2_id.value_id())?;
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
 
 		ensure!(
 			input2.shape() == input1.shape(),
@@ -302,8 +302,8 @@ impl Pass for RobustJointPass {
 		let mut error = 0.0;
 
 		if data.is_required(&self.input1_id.gradient_id()) && data.is_required(&self.input2_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			// let iter1 = input1.into_iter().zip(input2);
 			// let iter2 = input1_grad.into_iter().zip(input2_grad);
@@ -370,7 +370,7 @@ impl Pass for RobustJointPass {
 			
 
 		} else if data.is_required(&self.input1_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
 
 			// let iter1 = input1.into_iter().zip(input2);
 			// let iter2 = input1_grad.into_iter();
@@ -426,7 +426,7 @@ impl Pass for RobustJointPass {
 			}
 
 		} else if data.is_required(&self.input2_id.gradient_id()) {
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			// let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 			// 	.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -528,9 +528,9 @@ impl Pass for RobustForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input1 = data.get(&self.input1_id.value_id())?;
-		let input2 = data.get(&self.input2_id.value_id())?;
-		let output = data.get_mut(&self.output_id.value_id())?;
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		ensure!(
 			input2.shape() == input1.shape(),
@@ -640,9 +640,9 @@ impl Pass for RobustBackward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input1 = data.get(&self.input1_id.value_id())?;
-		let input2 = data.get(&self.input2_id.value_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 
 		ensure!(
 			input2.shape() == input1.shape(),
@@ -663,8 +663,8 @@ impl Pass for RobustBackward {
 		let output_grad = output_grad.into_shape(&output_shape_keep_dims[..]).expect("This should have been caught by the ensure above");;
 
 		if data.is_required(&self.input1_id.gradient_id()) && data.is_required(&self.input2_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -734,7 +734,7 @@ impl Pass for RobustBackward {
 			}
 
 		} else if data.is_required(&self.input1_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -792,7 +792,7 @@ impl Pass for RobustBackward {
 				}
 			}
 		} else if data.is_required(&self.input2_id.gradient_id()) {
-			let mut input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+			let mut input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 			let iter1 = input1.exact_chunks(output_shape_keep_dims.as_slice()).into_iter()
 				.zip(input2.exact_chunks(output_shape_keep_dims.as_slice()));
@@ -0,0 +1,547 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::loss::LossType;
+use shape::{NodeShape, NodeDim};
+use smallvec::SmallVec;
+use ndarray::{ArrayViewD, ArrayViewMutD, Dimension};
+use std::any::Any;
+use std::f32;
+
+/// An `Op` which implements a fused Softmax and Cross Entropy Loss
+///
+/// This op expects one input tensor of logits, and a second tensor of labels which sum to one over each group.
+/// The log-softmax is calculated over the grouped axes using the log-sum-exp trick,
+/// so this is more numerically stable than chaining a `Softmax` into a `CrossEntropy` loss.
+/// The gradient w.r.t. the logits is `softmax - labels`, scaled by the sum of the labels in each group if it isn't one.
+///
+/// By default this `Op` has no output and will generate loss and gradients.
+///
+/// If `output()` is set, the Cross Entropy of each group will be written to that Node,
+/// and instead of generating gradients this loss function will backprop gradients from the output node.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct SoftmaxCrossEntropy {
+	logits_id: NodeID,
+	labels_id: NodeID,
+	weights_id: Option<NodeID>,
+	output: Option<NodeID>,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	label_smoothing: f32,
+	multiplier: f32,
+	name: Option<String>,
+}
+
+impl SoftmaxCrossEntropy {
+	pub fn new(logits: &NodeID, labels: &NodeID) -> Self {
+		SoftmaxCrossEntropy {
+			logits_id: logits.clone(),
+			labels_id: labels.clone(),
+			weights_id: None,
+			output: None,
+			axes: SmallVec::new(),
+			keep_dims: false,
+			label_smoothing: 0.0,
+			multiplier: 1.0,
+			name: None,
+		}
+	}
+
+	/// If set this `Op` will output to the supplied node, any rely no other use ops to generate loss and gradients
+	/// The output node must have the shape of the input with the grouped axes removed, or set to 1 if `keep_dims` is `true`.
+	///
+	/// Default: None.
+	pub fn output(mut self, output: &NodeID) -> Self {
+		self.output = Some(output.clone());
+		self
+	}
+
+	/// The axes supplied will be grouped for the Softmax operation,
+	/// with the operation repeated across the axes not supplied.
+	///
+	/// `axes` can be in the range [-input.ndims(), input.ndims());
+	/// If no axes are supplied then all dimensions with Known size will be grouped.
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the grouped axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+
+	/// Mixes the labels with a uniform distribution over each group: `labels * (1 - smoothing) + smoothing / group_size`.
+	///
+	/// Default: 0.0
+	pub fn label_smoothing(mut self, label_smoothing: f32) -> Self {
+		self.label_smoothing = label_smoothing;
+		self
+	}
+
+	/// If set, the loss of each group is multiplied by the corresponding element of this node.
+	///
+	/// The weights node must have one element per group, in the same order as the output.
+	///
+	/// Default: None.
+	pub fn weights(mut self, weights: Option<&NodeID>) -> Self {
+		self.weights_id = weights.cloned();
+		self
+	}
+
+	/// Applies a multiplier to the output or to the loss generated.
+	pub fn multiplier(mut self, multiplier: f32) -> Self {
+		self.multiplier = multiplier;
+		self
+	}
+}
+
+
+impl Op for SoftmaxCrossEntropy {
+	type InstanceType = SoftmaxCrossEntropyInstance;
+
+	fn type_name(&self) -> &'static str {
+		"SoftmaxCrossEntropy"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(mut self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+
+		let mut inputs = vec![self.logits_id.clone(), self.labels_id.clone()];
+		inputs.extend(self.weights_id.iter().cloned());
+
+		let name =  if let Some(ref output_id) = self.output {
+			standard_op_name(&self, &self.name, graph, &inputs, &[output_id.clone()])
+		} else {
+			standard_op_name(&self, &self.name, graph, &inputs, &[])
+		};
+
+		let mask = {
+			let input_shape = self.logits_id.shape();
+			if self.axes.len() == 0 {
+				for i in 0..input_shape.ndim() {
+					if matches!(input_shape.dimensions()[i], NodeDim::Known(_)) {
+						self.axes.push(i as isize);
+					}
+				}
+			}
+			group_mask(input_shape.ndims(), &self.axes)
+		};
+
+		let settings = SceSettings {
+			multiplier: self.multiplier,
+			label_smoothing: self.label_smoothing,
+			mask: mask.clone(),
+			logits_id: self.logits_id.clone(),
+			labels_id: self.labels_id.clone(),
+			weights_id: self.weights_id.clone(),
+		};
+
+		let loss_type = if let Some(output_id) = self.output {
+			LossType::Output{
+				output_id: output_id.clone(),
+				forward_id: graph.add_pass(SoftmaxCrossEntropyForward::new(
+					settings.clone(),
+					output_id.clone())),
+				backward_id: graph.add_pass(SoftmaxCrossEntropyBackward::new(
+					settings.clone(),
+					output_id.clone())),
+			}
+		} else {
+			LossType::Joint{
+				pass_id: graph.add_pass(SoftmaxCrossEntropyJointPass::new(
+					settings.clone()))
+			}
+		};
+
+		Ok(SoftmaxCrossEntropyInstance{
+			name: name,
+			multiplier: self.multiplier,
+			logits_id: self.logits_id.clone(),
+			labels_id: self.labels_id.clone(),
+			weights_id: self.weights_id.clone(),
+			loss_type: loss_type,
+			mask: mask,
+			keep_dims: self.keep_dims,
+		})
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct SoftmaxCrossEntropyInstance {
+	name: String,
+	multiplier: f32,
+	logits_id: NodeID,
+	labels_id: NodeID,
+	weights_id: Option<NodeID>,
+	loss_type: LossType,
+	mask: SmallVec<[bool; 6]>,
+	keep_dims: bool,
+}
+
+impl OpInstance for SoftmaxCrossEntropyInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		let mut inputs = vec![self.logits_id.clone(), self.labels_id.clone()];
+		inputs.extend(self.weights_id.iter().cloned());
+		match &self.loss_type {
+			&LossType::Joint{..} => (inputs, vec![]),
+			&LossType::Output{ref output_id, ..} => (inputs, vec![output_id.clone()]),
+		}
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		match &self.loss_type {
+			&LossType::Joint{ref pass_id} => vec![pass_id.clone()],
+			&LossType::Output{ref forward_id, ref backward_id, ..} => vec![forward_id.clone(), backward_id.clone()],
+		}
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {
+		vec![]
+	}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {
+		vec![]
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		if let &LossType::Output{ref output_id, ..} = &self.loss_type {
+			let logits_shape = shapes.get_shape(&self.logits_id).to_data_shape()?;
+			let labels_shape = shapes.get_shape(&self.labels_id).to_data_shape()?;
+			ensure!(logits_shape == labels_shape, "Shape of logits did not match shape of labels");
+			let output_shape: NodeShape = calc_output_shape(logits_shape.slice(), &self.mask, self.keep_dims).into();
+			shapes.merge_with(output_id, &output_shape)
+		} else {
+			Ok(())
+		}
+	}
+
+}
+
+/// Returns a mask indicating whether an axis should be grouped based on the axes list
+fn group_mask(len: usize, axes: &[isize]) -> SmallVec<[bool; 6]> {
+	let mut group = SmallVec::with_capacity(len);
+	for _ in 0..len {
+		group.push(false);
+	}
+	for axis in axes {
+		group[(axis + len as isize) as usize % len] = true;
+	}
+	group
+}
+
+fn calc_output_shape(input_shape: &[usize], mask: &[bool], keep_dims: bool) -> SmallVec<[usize; 6]> {
+	if keep_dims {
+		input_shape.iter().zip(mask).map(|(&dim, &group)| {
+				if group {1} else {dim}
+			}).collect()
+	} else {
+		input_shape.iter().zip(mask).filter_map(|(&dim, &group)| {
+				if group {None} else {Some(dim)}
+			}).collect()
+	}
+}
+
+/// The settings and nodes shared by all passes
+#[derive(Clone, Debug)]
+struct SceSettings {
+	multiplier: f32,
+	label_smoothing: f32,
+	mask: SmallVec<[bool; 6]>,
+	logits_id: NodeID,
+	labels_id: NodeID,
+	weights_id: Option<NodeID>,
+}
+
+impl SceSettings {
+	fn inputs(&self) -> Vec<DataID> {
+		let mut inputs = vec![self.logits_id.value_id(), self.labels_id.value_id()];
+		inputs.extend(self.weights_id.iter().map(|id| id.value_id()));
+		inputs
+	}
+
+	fn input_grads(&self) -> Vec<DataID> {
+		let mut outputs = vec![self.logits_id.gradient_id(), self.labels_id.gradient_id()];
+		outputs.extend(self.weights_id.iter().map(|id| id.gradient_id()));
+		outputs
+	}
+
+	/// Calculates the multiplied loss of each group, writing each into `losses`.
+	///
+	/// If `grads` is true, gradients are added to the logits, labels and weights, scaled by `output_grad` if supplied.
+	fn run(&self, pass_name: String, data: &Storage, output_grad: Option<&ArrayViewD<f32>>, losses: &mut [f32], grads: bool) -> Result<()> {
+		let logits = data.get::<f32>(&self.logits_id.value_id())?;
+		let labels = data.get::<f32>(&self.labels_id.value_id())?;
+
+		ensure!(
+			labels.shape() == logits.shape(),
+			ErrorKind::PassError(pass_name.clone(), format!("labels shape: {:?} did not match logits shape: {:?}", labels.shape(), logits.shape()))
+		);
+		ensure!(
+			logits.ndim() == self.mask.len(),
+			ErrorKind::PassError(pass_name.clone(), format!("logits shape: {:?} did not match the number of dimensions expected: {}", logits.shape(), self.mask.len()))
+		);
+
+		let group_shape: SmallVec<[usize; 6]> = logits.shape().iter().zip(&self.mask).map(|(&dim, &group)| if group {dim} else {1}).collect();
+		let group_size: usize = group_shape.iter().product();
+		let groups = logits.len()/group_size.max(1);
+		ensure!(
+			losses.len() == groups,
+			ErrorKind::PassError(pass_name.clone(), format!("output size: {} did not match the number of groups: {}", losses.len(), groups))
+		);
+
+		let weights = if let Some(ref weights_id) = self.weights_id {
+			let weights = data.get::<f32>(&weights_id.value_id())?;
+			ensure!(
+				weights.len() == groups,
+				ErrorKind::PassError(pass_name.clone(), format!("weights shape: {:?} did not match the number of groups: {}", weights.shape(), groups))
+			);
+			Some(weights)
+		} else {
+			None
+		};
+		let mut weights_iter = weights.iter().flat_map(|weights| weights.iter());
+
+		let mut logits_grad = if grads && data.is_required(&self.logits_id.gradient_id()) {Some(data.get_mut::<f32>(&self.logits_id.gradient_id())?)} else {None};
+		let mut labels_grad = if grads && data.is_required(&self.labels_id.gradient_id()) {Some(data.get_mut::<f32>(&self.labels_id.gradient_id())?)} else {None};
+		let mut weights_grad = match self.weights_id {
+			Some(ref weights_id) if grads && data.is_required(&weights_id.gradient_id()) => Some(data.get_mut::<f32>(&weights_id.gradient_id())?),
+			_ => None,
+		};
+
+		let mut logits_grad_chunks: Vec<ArrayViewMutD<f32>> = logits_grad.iter_mut().flat_map(|grad| grad.exact_chunks_mut(group_shape.as_slice()).into_iter()).collect();
+		let mut labels_grad_chunks: Vec<ArrayViewMutD<f32>> = labels_grad.iter_mut().flat_map(|grad| grad.exact_chunks_mut(group_shape.as_slice()).into_iter()).collect();
+		let mut weights_grad_iter = weights_grad.iter_mut().flat_map(|grad| grad.iter_mut());
+		let mut output_grad_iter = output_grad.iter().flat_map(|grad| grad.iter());
+
+		let smoothing = self.label_smoothing;
+		let uniform = smoothing/group_size as f32;
+
+		let iter = logits.exact_chunks(group_shape.as_slice()).into_iter()
+			.zip(labels.exact_chunks(group_shape.as_slice()))
+			.zip(losses.iter_mut());
+
+		for (i, ((logits_chunk, labels_chunk), loss)) in iter.enumerate() {
+			let max = logits_chunk.iter().fold(f32::NEG_INFINITY, |max, &v| v.max(max));
+			let log_sum = max + logits_chunk.iter().fold(0., |sum, &v| sum + (v-max).exp()).ln();
+
+			let mut error = 0.0;
+			let mut label_sum = 0.0;
+			for (&x, &y) in logits_chunk.iter().zip(&labels_chunk) {
+				let y = y * (1.0 - smoothing) + uniform;
+				error -= y * (x - log_sum);
+				label_sum += y;
+			}
+
+			let weight = weights_iter.next().cloned().unwrap_or(1.0);
+			*loss = error * weight * self.multiplier;
+
+			if !grads {
+				continue;
+			}
+
+			let out_grad = output_grad_iter.next().cloned().unwrap_or(1.0) * self.multiplier;
+			let scale = out_grad * weight;
+
+			if let Some(logits_grad) = logits_grad_chunks.get_mut(i) {
+				for ((g, &x), &y) in logits_grad.iter_mut().zip(&logits_chunk).zip(&labels_chunk) {
+					let y = y * (1.0 - smoothing) + uniform;
+					*g += ((x - log_sum).exp() * label_sum - y) * scale;
+				}
+			}
+
+			if let Some(labels_grad) = labels_grad_chunks.get_mut(i) {
+				for (g, &x) in labels_grad.iter_mut().zip(&logits_chunk) {
+					*g -= (x - log_sum) * (1.0 - smoothing) * scale;
+				}
+			}
+
+			if let Some(g) = weights_grad_iter.next() {
+				*g += error * out_grad;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Clone, Debug)]
+struct SoftmaxCrossEntropyJointPass {
+	settings: SceSettings,
+}
+
+impl SoftmaxCrossEntropyJointPass {
+	pub fn new(settings: SceSettings) -> Self {
+		SoftmaxCrossEntropyJointPass {
+			settings,
+		}
+	}
+}
+
+impl Pass for SoftmaxCrossEntropyJointPass {
+	fn type_name(&self) -> &'static str {"SoftmaxCrossEntropyJointPass"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(self.settings.inputs(), self.settings.input_grads())
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let logits = data.get::<f32>(&self.settings.logits_id.value_id())?;
+		let group_size: usize = logits.shape().iter().zip(&self.settings.mask).filter_map(|(&dim, &group)| if group {Some(dim)} else {None}).product();
+
+		let mut losses = vec![0.0; logits.len()/group_size.max(1)];
+		self.settings.run(self.name(), data, None, &mut losses, true)?;
+		data.loss_add(losses.iter().sum());
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[derive(Clone, Debug)]
+struct SoftmaxCrossEntropyForward {
+	settings: SceSettings,
+	output_id: NodeID,
+}
+
+impl SoftmaxCrossEntropyForward {
+	pub fn new(settings: SceSettings, output_id: NodeID) -> Self {
+		SoftmaxCrossEntropyForward {
+			settings,
+			output_id,
+		}
+	}
+}
+
+impl Pass for SoftmaxCrossEntropyForward {
+	fn type_name(&self) -> &'static str {"SoftmaxCrossEntropyForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(self.settings.inputs(), vec![self.output_id.value_id()])
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		let mut losses = vec![0.0; output.len()];
+		self.settings.run(self.name(), data, None, &mut losses, false)?;
+		for (o, loss) in output.iter_mut().zip(losses) {
+			*o += loss;
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Clone, Debug)]
+struct SoftmaxCrossEntropyBackward {
+	settings: SceSettings,
+	output_id: NodeID,
+}
+
+impl SoftmaxCrossEntropyBackward {
+	pub fn new(settings: SceSettings, output_id: NodeID) -> Self {
+		SoftmaxCrossEntropyBackward {
+			settings,
+			output_id,
+		}
+	}
+}
+
+impl Pass for SoftmaxCrossEntropyBackward {
+	fn type_name(&self) -> &'static str {"SoftmaxCrossEntropyBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		let mut inputs = self.settings.inputs();
+		inputs.push(self.output_id.gradient_id());
+		(inputs, self.settings.input_grads())
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+
+		let mut losses = vec![0.0; output_grad.len()];
+		self.settings.run(self.name(), data, Some(&output_grad), &mut losses, true)?;
+
+		Ok(Box::new(()))
+	}
+}
+
+#[test]
+fn test_softmax_cross_entropy_backprop(){
+	_softmax_cross_entropy_backprop().unwrap();
+}
+
+fn _softmax_cross_entropy_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 16], "logits", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 16], "labels", tag![])?;
+	let node3 = g.new_node(shape![7, 5], "weights", tag![])?;
+
+	let _o1 = g.new_op(SoftmaxCrossEntropy::new(&node1, &node2).axes(&[-1]).label_smoothing(0.1).weights(Some(&node3)), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node2.clone() => Box::new(|| thread_rng().gen_range(0.0, 0.125)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+#[test]
+fn test_softmax_cross_entropy_output_backprop(){
+	_softmax_cross_entropy_output_backprop().unwrap();
+}
+
+fn _softmax_cross_entropy_output_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::proportional::Proportional;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 16], "logits", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 16], "labels", tag![])?;
+	let node3 = g.new_node(shape![1, 5, 1], "output", tag![])?;
+
+	let _o1 = g.new_op(SoftmaxCrossEntropy::new(&node1, &node2).axes(&[0, -1]).keep_dims(true).output(&node3), tag![])?;
+	let _o2 = g.new_op(Proportional::new(&node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node2.clone() => Box::new(|| thread_rng().gen_range(0.0, 0.02)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
@@ -0,0 +1,1094 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::loss::LossType;
+use ops::shape::resize::{resize_axis, resize_axis_transpose};
+use shape::NodeShape;
+use smallvec::SmallVec;
+use ndarray::{ArrayD, ArrayViewD, Dimension, Zip};
+use std::any::Any;
+
+/// The value of each scale of MS-SSIM is smoothly kept above zero on the scale of EPSILON before being raised to its weight, avoiding infinite gradients
+const EPSILON: f32 = 1e-6;
+
+/// A smooth approximation of `max(value, 0)` which is always positive, `(v + sqrt(v^2 + 4 EPSILON^2))/2`
+///
+/// Its derivative divided by its value is `1/sqrt(v^2 + 4 EPSILON^2)`.
+fn smooth_positive(value: f32) -> f32 {
+	0.5 * (value + (value * value + 4.0 * EPSILON * EPSILON).sqrt())
+}
+
+/// The scale weights from Wang et al. "Multiscale structural similarity for image quality assessment"
+const MS_SSIM_WEIGHTS: [f32; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+
+/// The window and constants used to calculate the Structural Similarity Index (SSIM)
+///
+/// Inputs are expected to have the layout [batch, spatial.., channels], with each channel compared separately.
+/// Local statistics are calculated using a separable Gaussian window over the spatial axes.
+/// At the borders the window is truncated and renormalised, so the SSIM map is the same shape as the input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SsimSettings {
+	window_size: usize,
+	sigma: f32,
+	k1: f32,
+	k2: f32,
+	data_range: f32,
+}
+
+impl SsimSettings {
+	pub fn new() -> Self {
+		SsimSettings {
+			window_size: 11,
+			sigma: 1.5,
+			k1: 0.01,
+			k2: 0.03,
+			data_range: 1.0,
+		}
+	}
+
+	/// The width of the Gaussian window along each spatial axis, which must be odd.
+	///
+	/// Default: 11
+	pub fn window_size(mut self, window_size: usize) -> Self {
+		self.window_size = window_size;
+		self
+	}
+
+	/// The standard deviation of the Gaussian window.
+	///
+	/// Default: 1.5
+	pub fn sigma(mut self, sigma: f32) -> Self {
+		self.sigma = sigma;
+		self
+	}
+
+	/// The constant stabilising the luminance term is (k1 * data_range)^2.
+	///
+	/// Default: 0.01
+	pub fn k1(mut self, k1: f32) -> Self {
+		self.k1 = k1;
+		self
+	}
+
+	/// The constant stabilising the contrast-structure term is (k2 * data_range)^2.
+	///
+	/// Default: 0.03
+	pub fn k2(mut self, k2: f32) -> Self {
+		self.k2 = k2;
+		self
+	}
+
+	/// The difference between the largest and smallest possible input values, e.g. 1.0 or 255.0.
+	///
+	/// Default: 1.0
+	pub fn data_range(mut self, data_range: f32) -> Self {
+		self.data_range = data_range;
+		self
+	}
+
+	/// Returns an error describing the first invalid setting, if any
+	pub(crate) fn validate(&self, op_name: String) -> Result<()> {
+		ensure!(self.window_size % 2 == 1, ErrorKind::ShapePropagationError(op_name.clone(), format!("window_size ({}) must be odd", self.window_size)));
+		ensure!(self.sigma > 0.0, ErrorKind::ShapePropagationError(op_name.clone(), format!("sigma ({}) must be greater than zero", self.sigma)));
+		ensure!(self.data_range > 0.0, ErrorKind::ShapePropagationError(op_name, format!("data_range ({}) must be greater than zero", self.data_range)));
+		Ok(())
+	}
+
+	fn constants(&self) -> (f32, f32) {
+		((self.k1 * self.data_range).powi(2), (self.k2 * self.data_range).powi(2))
+	}
+
+	/// Returns the input elements and weights of the truncated Gaussian window centred on each element of an axis
+	fn window(&self, len: usize) -> Vec<Vec<(usize, f32)>> {
+		let radius = self.window_size/2;
+		let sigma = self.sigma as f64;
+		(0..len).map(|o| {
+			let weights: Vec<(usize, f64)> = (o.saturating_sub(radius)..(o + radius + 1).min(len)).map(|i| {
+				let d = i as f64 - o as f64;
+				(i, (-d * d/(2.0 * sigma * sigma)).exp())
+			}).collect();
+			let sum: f64 = weights.iter().map(|&(_, w)| w).sum();
+			weights.iter().map(|&(i, w)| (i, (w/sum) as f32)).collect()
+		}).collect()
+	}
+
+	/// Applies the window along each spatial axis
+	fn filter(&self, input: ArrayViewD<f32>) -> ArrayD<f32> {
+		let mut output = input.to_owned();
+		for axis in 1..input.ndim() - 1 {
+			let len = output.shape()[axis];
+			output = resize_axis(output.view(), axis, &self.window(len));
+		}
+		output
+	}
+
+	/// The transpose of `filter()`
+	fn filter_transpose(&self, output_grad: ArrayD<f32>) -> ArrayD<f32> {
+		let mut input_grad = output_grad;
+		for axis in 1..input_grad.ndim() - 1 {
+			let len = input_grad.shape()[axis];
+			input_grad = resize_axis_transpose(input_grad.view(), axis, &self.window(len), len);
+		}
+		input_grad
+	}
+
+	fn maps(&self, x: ArrayViewD<f32>, y: ArrayViewD<f32>) -> SsimMaps {
+		let (c1, c2) = self.constants();
+		let mean_x = self.filter(x.view());
+		let mean_y = self.filter(y.view());
+		let mean_xx = self.filter((&x * &x).view());
+		let mean_yy = self.filter((&y * &y).view());
+		let mean_xy = self.filter((&x * &y).view());
+
+		let luminance_denom = &mean_x * &mean_x + &(&mean_y * &mean_y) + c1;
+		let contrast_structure_denom = mean_xx - &(&mean_x * &mean_x) + &mean_yy - &(&mean_y * &mean_y) + c2;
+		let luminance = (&mean_x * &mean_y * 2.0 + c1)/&luminance_denom;
+		let contrast_structure = ((mean_xy - &(&mean_x * &mean_y)) * 2.0 + c2)/&contrast_structure_denom;
+
+		SsimMaps {
+			mean_x,
+			mean_y,
+			luminance,
+			contrast_structure,
+			luminance_denom,
+			contrast_structure_denom,
+		}
+	}
+
+	/// Returns the SSIM of each element, comparing the local statistics around it
+	pub(crate) fn ssim_map(&self, x: ArrayViewD<f32>, y: ArrayViewD<f32>) -> ArrayD<f32> {
+		let maps = self.maps(x, y);
+		maps.luminance * &maps.contrast_structure
+	}
+
+	/// Returns the gradients of x and y, given the gradients of the luminance and contrast-structure maps
+	fn backward(&self, x: ArrayViewD<f32>, y: ArrayViewD<f32>, maps: &SsimMaps, luminance_grad: Option<ArrayD<f32>>, contrast_structure_grad: ArrayD<f32>) -> (ArrayD<f32>, ArrayD<f32>) {
+		let cs = &maps.contrast_structure;
+		let cs_denom = &maps.contrast_structure_denom;
+
+		let mut mean_x_grad = &contrast_structure_grad * &((cs * &maps.mean_x - &maps.mean_y) * 2.0/cs_denom);
+		let mut mean_y_grad = &contrast_structure_grad * &((cs * &maps.mean_y - &maps.mean_x) * 2.0/cs_denom);
+		if let Some(luminance_grad) = luminance_grad {
+			let l = &maps.luminance;
+			let l_denom = &maps.luminance_denom;
+			mean_x_grad += &(&luminance_grad * &((&maps.mean_y - &(l * &maps.mean_x)) * 2.0/l_denom));
+			mean_y_grad += &(&luminance_grad * &((&maps.mean_x - &(l * &maps.mean_y)) * 2.0/l_denom));
+		}
+		// the squares of x and y have the same gradient
+		let mean_square_grad = &contrast_structure_grad * &(cs * -1.0/cs_denom);
+		let mean_xy_grad = contrast_structure_grad * 2.0/cs_denom;
+
+		let mut x_grad = self.filter_transpose(mean_x_grad);
+		let mut y_grad = self.filter_transpose(mean_y_grad);
+		let square_grad = self.filter_transpose(mean_square_grad);
+		let xy_grad = self.filter_transpose(mean_xy_grad);
+
+		Zip::from(&mut x_grad).and(&x).and(&y).and(&square_grad).and(&xy_grad).apply(|x_grad, &x, &y, &square_grad, &xy_grad| {
+			*x_grad += 2.0 * x * square_grad + y * xy_grad;
+		});
+		Zip::from(&mut y_grad).and(&x).and(&y).and(&square_grad).and(&xy_grad).apply(|y_grad, &x, &y, &square_grad, &xy_grad| {
+			*y_grad += 2.0 * y * square_grad + x * xy_grad;
+		});
+
+		(x_grad, y_grad)
+	}
+}
+
+/// The local statistics of two inputs, and the luminance and contrast-structure terms of SSIM calculated from them
+struct SsimMaps {
+	mean_x: ArrayD<f32>,
+	mean_y: ArrayD<f32>,
+	luminance: ArrayD<f32>,
+	contrast_structure: ArrayD<f32>,
+	luminance_denom: ArrayD<f32>,
+	contrast_structure_denom: ArrayD<f32>,
+}
+
+
+/// An `Op` which implements a Structural Similarity (SSIM) loss
+///
+/// This op expects two inputs of the same shape, with the layout [batch, spatial.., channels].
+/// The loss of each element is `1 - SSIM`, where the SSIM map is calculated with a Gaussian window over the spatial axes.
+///
+/// By default this `Op` has no output and will generate loss and gradients.
+///
+/// If `output()` is set, the loss will be written to that Node,
+/// and instead of generating gradients this loss function will backprop gradients from the output node.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Ssim {
+	input1_id: NodeID,
+	input2_id: NodeID,
+	output: Option<NodeID>,
+	mean_axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	multiplier: f32,
+	settings: SsimSettings,
+	name: Option<String>,
+}
+
+impl Ssim {
+	pub fn new(input1: &NodeID, input2: &NodeID) -> Self {
+		Ssim {
+			input1_id: input1.clone(),
+			input2_id: input2.clone(),
+			output: None,
+			mean_axes: SmallVec::new(),
+			keep_dims: false,
+			multiplier: 1.0,
+			settings: SsimSettings::new(),
+			name: None,
+		}
+	}
+
+	/// If set this `Op` will output to the supplied node, any rely no other use ops to generate loss and gradients
+	/// The output node must have the same size as the input node unless reductions are applied using `.mean_axes()`.
+	///
+	/// Default: None.
+	pub fn output(mut self, output: &NodeID) -> Self {
+		self.output = Some(output.clone());
+		self
+	}
+
+	/// The axes supplied will be averaged over, rather than summed.
+	///
+	/// Default: empty
+	pub fn mean_axes(mut self, mean_axes: &[isize]) -> Self {
+		self.mean_axes = mean_axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the reduced mean_axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+
+	/// Applies a multiplier to the output or to the loss generated.
+	///
+	/// Default: 1.0
+	pub fn multiplier(mut self, multiplier: f32) -> Self {
+		self.multiplier = multiplier;
+		self
+	}
+
+	/// Replaces all SSIM window and constant settings.
+	pub fn settings(mut self, settings: SsimSettings) -> Self {
+		self.settings = settings;
+		self
+	}
+
+	/// Default: 11
+	pub fn window_size(mut self, window_size: usize) -> Self {
+		self.settings = self.settings.window_size(window_size);
+		self
+	}
+
+	/// Default: 1.5
+	pub fn sigma(mut self, sigma: f32) -> Self {
+		self.settings = self.settings.sigma(sigma);
+		self
+	}
+
+	/// Default: 0.01
+	pub fn k1(mut self, k1: f32) -> Self {
+		self.settings = self.settings.k1(k1);
+		self
+	}
+
+	/// Default: 0.03
+	pub fn k2(mut self, k2: f32) -> Self {
+		self.settings = self.settings.k2(k2);
+		self
+	}
+
+	/// Default: 1.0
+	pub fn data_range(mut self, data_range: f32) -> Self {
+		self.settings = self.settings.data_range(data_range);
+		self
+	}
+}
+
+impl Op for Ssim {
+	type InstanceType = SsimInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Ssim"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let settings = SsimLossSettings {
+			ssim: self.settings,
+			scale_weights: None,
+			multiplier: self.multiplier,
+			mean_axes: self.mean_axes.clone(),
+		};
+		ssim_build(graph, &self, &self.name, &self.input1_id, &self.input2_id, &self.output, settings, self.keep_dims)
+	}
+}
+
+
+/// An `Op` which implements a Multi-Scale Structural Similarity (MS-SSIM) loss
+///
+/// This op expects two inputs of the same shape, with the layout [batch, spatial.., channels].
+/// Both inputs are repeatedly downsampled by a factor of 2 using average pooling over the spatial axes.
+/// The spatial mean of the contrast-structure term at each scale, and of the full SSIM at the coarsest scale,
+/// are raised to the weight of that scale then multiplied together giving one MS-SSIM value for each batch element and channel.
+///
+/// The loss is `1 - MS-SSIM`, and the spatial axes are always included in `mean_axes`.
+///
+/// By default this `Op` has no output and will generate loss and gradients.
+///
+/// If `output()` is set, the loss will be written to that Node,
+/// and instead of generating gradients this loss function will backprop gradients from the output node.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct MsSsim {
+	input1_id: NodeID,
+	input2_id: NodeID,
+	output: Option<NodeID>,
+	mean_axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	multiplier: f32,
+	settings: SsimSettings,
+	scale_weights: Vec<f32>,
+	name: Option<String>,
+}
+
+impl MsSsim {
+	pub fn new(input1: &NodeID, input2: &NodeID) -> Self {
+		MsSsim {
+			input1_id: input1.clone(),
+			input2_id: input2.clone(),
+			output: None,
+			mean_axes: SmallVec::new(),
+			keep_dims: false,
+			multiplier: 1.0,
+			settings: SsimSettings::new(),
+			scale_weights: MS_SSIM_WEIGHTS.to_vec(),
+			name: None,
+		}
+	}
+
+	/// If set this `Op` will output to the supplied node, any rely no other use ops to generate loss and gradients
+	/// The output node must have the same size as the input node with the spatial axes and `.mean_axes()` reduced.
+	///
+	/// Default: None.
+	pub fn output(mut self, output: &NodeID) -> Self {
+		self.output = Some(output.clone());
+		self
+	}
+
+	/// The axes supplied will be averaged over, rather than summed, in addition to the spatial axes.
+	///
+	/// Default: empty
+	pub fn mean_axes(mut self, mean_axes: &[isize]) -> Self {
+		self.mean_axes = mean_axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the reduced axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+
+	/// Applies a multiplier to the output or to the loss generated.
+	///
+	/// Default: 1.0
+	pub fn multiplier(mut self, multiplier: f32) -> Self {
+		self.multiplier = multiplier;
+		self
+	}
+
+	/// The weight of each scale, from finest to coarsest. The number of weights sets the number of scales,
+	/// and each spatial dimension of the input must be at least 2^(scales - 1).
+	///
+	/// Default: [0.0448, 0.2856, 0.3001, 0.2363, 0.1333]
+	pub fn scale_weights(mut self, scale_weights: &[f32]) -> Self {
+		self.scale_weights = scale_weights.to_vec();
+		self
+	}
+
+	/// Replaces all SSIM window and constant settings.
+	pub fn settings(mut self, settings: SsimSettings) -> Self {
+		self.settings = settings;
+		self
+	}
+
+	/// Default: 11
+	pub fn window_size(mut self, window_size: usize) -> Self {
+		self.settings = self.settings.window_size(window_size);
+		self
+	}
+
+	/// Default: 1.5
+	pub fn sigma(mut self, sigma: f32) -> Self {
+		self.settings = self.settings.sigma(sigma);
+		self
+	}
+
+	/// Default: 0.01
+	pub fn k1(mut self, k1: f32) -> Self {
+		self.settings = self.settings.k1(k1);
+		self
+	}
+
+	/// Default: 0.03
+	pub fn k2(mut self, k2: f32) -> Self {
+		self.settings = self.settings.k2(k2);
+		self
+	}
+
+	/// Default: 1.0
+	pub fn data_range(mut self, data_range: f32) -> Self {
+		self.settings = self.settings.data_range(data_range);
+		self
+	}
+}
+
+impl Op for MsSsim {
+	type InstanceType = SsimInstance;
+
+	fn type_name(&self) -> &'static str {
+		"MsSsim"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let ndim = self.input1_id.shape().ndim();
+		let mut mean_axes = self.mean_axes.clone();
+		if ndim > 2 {
+			mean_axes.extend((1..ndim - 1).map(|axis| axis as isize));
+		}
+
+		let settings = SsimLossSettings {
+			ssim: self.settings,
+			scale_weights: Some(self.scale_weights.clone()),
+			multiplier: self.multiplier,
+			mean_axes: mean_axes,
+		};
+		ssim_build(graph, &self, &self.name, &self.input1_id, &self.input2_id, &self.output, settings, self.keep_dims)
+	}
+}
+
+
+fn ssim_build<O: Op>(graph: &mut GraphDef, op: &O, name: &Option<String>, input1_id: &NodeID, input2_id: &NodeID, output: &Option<NodeID>, settings: SsimLossSettings, keep_dims: bool) -> Result<SsimInstance> {
+	let name = if let Some(ref output_id) = *output {
+		standard_op_name(op, name, graph, &[input1_id.clone(), input2_id.clone()], &[output_id.clone()])
+	} else {
+		standard_op_name(op, name, graph, &[input1_id.clone(), input2_id.clone()], &[])
+	};
+
+	settings.ssim.validate(name.clone())?;
+	if let Some(ref scale_weights) = settings.scale_weights {
+		ensure!(!scale_weights.is_empty(), ErrorKind::ShapePropagationError(name.clone(), "at least one scale weight must be supplied".to_string()));
+	}
+
+	let loss_type = if let Some(ref output_id) = *output {
+		LossType::Output{
+			output_id: output_id.clone(),
+			forward_id: graph.add_pass(SsimForward::new(
+				settings.clone(),
+				input1_id.clone(),
+				input2_id.clone(),
+				output_id.clone(),
+				keep_dims)),
+			backward_id: graph.add_pass(SsimBackward::new(
+				settings.clone(),
+				input1_id.clone(),
+				input2_id.clone(),
+				output_id.clone(),
+				keep_dims)),
+		}
+	} else {
+		LossType::Joint{
+			pass_id: graph.add_pass(SsimJointPass::new(
+				settings.clone(),
+				input1_id.clone(),
+				input2_id.clone()))
+		}
+	};
+
+	Ok(SsimInstance{
+		name: name,
+		input1_id: input1_id.clone(),
+		input2_id: input2_id.clone(),
+		loss_type: loss_type,
+		mean_axes: settings.mean_axes,
+		keep_dims: keep_dims,
+	})
+}
+
+
+#[derive(Clone, Debug)]
+pub struct SsimInstance {
+	name: String,
+	input1_id: NodeID,
+	input2_id: NodeID,
+	loss_type: LossType,
+	mean_axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+}
+
+impl OpInstance for SsimInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		match &self.loss_type {
+			&LossType::Joint{..} => (vec![self.input1_id.clone(), self.input2_id.clone()], vec![]),
+			&LossType::Output{ref output_id, ..} => (vec![self.input1_id.clone(), self.input2_id.clone()], vec![output_id.clone()]),
+		}
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		match &self.loss_type {
+			&LossType::Joint{ref pass_id} => vec![pass_id.clone()],
+			&LossType::Output{ref forward_id, ref backward_id, ..} => vec![forward_id.clone(), backward_id.clone()],
+		}
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {
+		vec![]
+	}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {
+		vec![]
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		if let &LossType::Output{ref output_id, ..} = &self.loss_type {
+			let input1_shape = shapes.get_shape(&self.input1_id).to_data_shape()?;
+			let input2_shape = shapes.get_shape(&self.input2_id).to_data_shape()?;
+			ensure!(input1_shape == input2_shape, "Shape of input1 did not match shape of input2");
+			let output_shape: NodeShape = calc_output_shape(input1_shape.slice(), &self.mean_axes, self.keep_dims).into();
+			shapes.merge_with(output_id, &output_shape)
+		} else {
+			Ok(())
+		}
+	}
+
+}
+
+fn calc_output_shape(input_shape: &[usize], axes: &[isize], keep_dims: bool) -> SmallVec<[usize; 6]> {
+	let reduce_mask = reduction_mask(input_shape.len(), &axes);
+	if keep_dims {
+		input_shape.iter().zip(&reduce_mask).map(|(&dim, &reduce)| {
+				if reduce {1} else {dim}
+			}).collect()
+	} else {
+		input_shape.iter().zip(&reduce_mask).filter_map(|(&dim, &reduce)| {
+				if reduce {None} else {Some(dim)}
+			}).collect()
+	}
+}
+
+/// Returns a mask indicating whether an axis should be reduced based on the axes list
+fn reduction_mask(len: usize, axes: &[isize]) -> SmallVec<[bool; 6]> {
+	let mut reduce = SmallVec::with_capacity(len);
+	for _ in 0..len {
+		reduce.push(false);
+	}
+	for axis in axes {
+		reduce[(axis + len as isize) as usize % len] = true;
+	}
+	reduce
+}
+
+/// Returns the shape with each spatial axis reduced to size 1
+fn spatial_reduced_shape(shape: &[usize]) -> Vec<usize> {
+	shape.iter().enumerate().map(|(i, &dim)| if i == 0 || i == shape.len() - 1 {dim} else {1}).collect()
+}
+
+/// Sums over the spatial axes, keeping them with size 1
+fn spatial_sum(input: ArrayViewD<f32>) -> ArrayD<f32> {
+	let shape = spatial_reduced_shape(input.shape());
+	let mut output = ArrayD::zeros(shape.clone());
+	for chunk in input.exact_chunks(shape.as_slice()) {
+		output += &chunk;
+	}
+	output
+}
+
+/// The element weights of average pooling by a factor of 2 along an axis, discarding any odd element at the end
+fn pool_weights(len: usize) -> Vec<Vec<(usize, f32)>> {
+	(0..len/2).map(|i| vec![(2 * i, 0.5), (2 * i + 1, 0.5)]).collect()
+}
+
+fn downsample(input: ArrayViewD<f32>) -> ArrayD<f32> {
+	let mut output = input.to_owned();
+	for axis in 1..input.ndim() - 1 {
+		let len = output.shape()[axis];
+		output = resize_axis(output.view(), axis, &pool_weights(len));
+	}
+	output
+}
+
+/// The transpose of `downsample()`, returning a gradient with the supplied input shape
+fn downsample_transpose(output_grad: ArrayD<f32>, input_shape: &[usize]) -> ArrayD<f32> {
+	let mut input_grad = output_grad;
+	for axis in 1..input_shape.len() - 1 {
+		input_grad = resize_axis_transpose(input_grad.view(), axis, &pool_weights(input_shape[axis]), input_shape[axis]);
+	}
+	input_grad
+}
+
+
+/// The settings shared by all passes
+#[derive(Clone, Debug)]
+struct SsimLossSettings {
+	ssim: SsimSettings,
+	/// If set, multi-scale SSIM is used with one scale per weight
+	scale_weights: Option<Vec<f32>>,
+	multiplier: f32,
+	mean_axes: SmallVec<[isize; 6]>,
+}
+
+impl SsimLossSettings {
+	/// Checks the input shapes, returning the multiplier divided by the number of elements in each mean, and the keep_dims output shape
+	fn check(&self, pass_name: String, input1: &ArrayViewD<f32>, input2: &ArrayViewD<f32>) -> Result<(f32, SmallVec<[usize; 6]>)> {
+		ensure!(
+			input1.shape() == input2.shape(),
+			ErrorKind::PassError(pass_name.clone(), format!("input1 shape: {:?} did not match input2 shape: {:?}", input1.shape(), input2.shape()))
+		);
+		ensure!(
+			input1.ndim() > 2,
+			ErrorKind::PassError(pass_name.clone(), format!("input shape: {:?} must have batch, spatial and channel axes", input1.shape()))
+		);
+		if let Some(ref scale_weights) = self.scale_weights {
+			let min_len = 1 << (scale_weights.len() - 1);
+			ensure!(
+				input1.shape()[1..input1.ndim() - 1].iter().all(|&dim| dim >= min_len),
+				ErrorKind::PassError(pass_name, format!("spatial dimensions of input shape: {:?} must be at least {} for {} scales", input1.shape(), min_len, scale_weights.len()))
+			);
+		}
+
+		let input_shape = input1.shape();
+		let divisor: usize = input_shape.iter().zip(reduction_mask(input_shape.len(), &self.mean_axes)).filter_map(|(dim, reduce)| if reduce{Some(dim)} else {None}).product();
+		Ok((self.multiplier/divisor as f32, calc_output_shape(input_shape, &self.mean_axes, true)))
+	}
+
+	/// Returns the loss of each element, before reduction and the multiplier
+	fn loss(&self, input1: ArrayViewD<f32>, input2: ArrayViewD<f32>) -> ArrayD<f32> {
+		match self.scale_weights {
+			None => {
+				self.ssim.ssim_map(input1, input2).mapv_into(|s| 1.0 - s)
+			},
+			Some(ref scale_weights) => {
+				let (_, _, ms_ssim) = self.multi_scale(scale_weights, input1.view(), input2.view());
+				ms_ssim.mapv_into(|s| 1.0 - s).broadcast(input1.shape()).unwrap().to_owned()
+			},
+		}
+	}
+
+	/// Returns the gradients of both inputs, given the gradient of the loss of each element
+	fn gradients(&self, input1: ArrayViewD<f32>, input2: ArrayViewD<f32>, loss_grad: ArrayD<f32>) -> (ArrayD<f32>, ArrayD<f32>) {
+		match self.scale_weights {
+			None => {
+				let maps = self.ssim.maps(input1.view(), input2.view());
+				let luminance_grad = &loss_grad * &maps.contrast_structure * -1.0;
+				let contrast_structure_grad = loss_grad * &maps.luminance * -1.0;
+				self.ssim.backward(input1, input2, &maps, Some(luminance_grad), contrast_structure_grad)
+			},
+			Some(ref scale_weights) => {
+				let (scales, values, ms_ssim) = self.multi_scale(scale_weights, input1.view(), input2.view());
+				let ms_ssim_grad = spatial_sum(loss_grad.view()) * -1.0;
+
+				let mut grads: Option<(ArrayD<f32>, ArrayD<f32>)> = None;
+				for (i, ((x, y, maps), value)) in scales.iter().zip(&values).enumerate().rev() {
+					let weight = scale_weights[i];
+					let count: usize = x.shape()[1..x.ndim() - 1].iter().product();
+					let mut value_grad = ms_ssim_grad.clone();
+					Zip::from(&mut value_grad).and(value).and(&ms_ssim).apply(|grad, &value, &ms_ssim| {
+						*grad = *grad * weight * ms_ssim/((value * value + 4.0 * EPSILON * EPSILON).sqrt() * count as f32);
+					});
+					let map_grad = value_grad.broadcast(x.shape()).unwrap().to_owned();
+
+					let (mut x_grad, mut y_grad) = if i == scales.len() - 1 {
+						let luminance_grad = &map_grad * &maps.contrast_structure;
+						let contrast_structure_grad = map_grad * &maps.luminance;
+						self.ssim.backward(x.view(), y.view(), maps, Some(luminance_grad), contrast_structure_grad)
+					} else {
+						self.ssim.backward(x.view(), y.view(), maps, None, map_grad)
+					};
+
+					if let Some((coarse_x_grad, coarse_y_grad)) = grads.take() {
+						x_grad += &downsample_transpose(coarse_x_grad, x.shape());
+						y_grad += &downsample_transpose(coarse_y_grad, y.shape());
+					}
+					grads = Some((x_grad, y_grad));
+				}
+				grads.expect("At least one scale weight is checked for in build")
+			},
+		}
+	}
+
+	/// Returns the inputs and SSIM maps at each scale, the spatial mean value of each scale, and the combined MS-SSIM,
+	/// with the spatial axes of the values reduced to size 1
+	fn multi_scale(&self, scale_weights: &[f32], input1: ArrayViewD<f32>, input2: ArrayViewD<f32>) -> (Vec<(ArrayD<f32>, ArrayD<f32>, SsimMaps)>, Vec<ArrayD<f32>>, ArrayD<f32>) {
+		let mut scales = vec![];
+		let mut values = vec![];
+		let mut ms_ssim = ArrayD::from_elem(spatial_reduced_shape(input1.shape()), 1.0);
+
+		let mut x = input1.to_owned();
+		let mut y = input2.to_owned();
+		for (i, &weight) in scale_weights.iter().enumerate() {
+			if i > 0 {
+				x = downsample(x.view());
+				y = downsample(y.view());
+			}
+			let maps = self.ssim.maps(x.view(), y.view());
+			let count: usize = x.shape()[1..x.ndim() - 1].iter().product();
+			let value = if i == scale_weights.len() - 1 {
+				spatial_sum((&maps.luminance * &maps.contrast_structure).view())
+			} else {
+				spatial_sum(maps.contrast_structure.view())
+			} / count as f32;
+
+			Zip::from(&mut ms_ssim).and(&value).apply(|ms_ssim, &value| {
+				*ms_ssim *= smooth_positive(value).powf(weight);
+			});
+			scales.push((x.clone(), y.clone(), maps));
+			values.push(value);
+		}
+		(scales, values, ms_ssim)
+	}
+}
+
+#[derive(Clone, Debug)]
+struct SsimJointPass {
+	settings: SsimLossSettings,
+	input1_id: NodeID,
+	input2_id: NodeID,
+}
+
+impl SsimJointPass {
+	pub fn new(settings: SsimLossSettings, input1_id: NodeID, input2_id: NodeID) -> Self {
+		SsimJointPass {
+			settings,
+			input1_id,
+			input2_id,
+		}
+	}
+}
+
+impl Pass for SsimJointPass {
+	fn type_name(&self) -> &'static str {"SsimJointPass"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input1_id.value_id(), self.input2_id.value_id()],
+		vec![self.input1_id.gradient_id(), self.input2_id.gradient_id()])
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+		let (multiplier, _) = self.settings.check(self.name(), &input1, &input2)?;
+
+		let error = self.settings.loss(input1.view(), input2.view()).scalar_sum() * multiplier;
+		data.loss_add(error);
+
+		let input1_required = data.is_required(&self.input1_id.gradient_id());
+		let input2_required = data.is_required(&self.input2_id.gradient_id());
+		if input1_required || input2_required {
+			let loss_grad = ArrayD::from_elem(input1.shape(), multiplier);
+			let (input1_grad, input2_grad) = self.settings.gradients(input1.view(), input2.view(), loss_grad);
+			if input1_required {
+				let mut input1_grad_data = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
+				input1_grad_data += &input1_grad;
+			}
+			if input2_required {
+				let mut input2_grad_data = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
+				input2_grad_data += &input2_grad;
+			}
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[derive(Clone, Debug)]
+struct SsimForward {
+	settings: SsimLossSettings,
+	input1_id: NodeID,
+	input2_id: NodeID,
+	output_id: NodeID,
+	keep_dims: bool,
+}
+
+impl SsimForward {
+	pub fn new(settings: SsimLossSettings, input1_id: NodeID, input2_id: NodeID, output_id: NodeID, keep_dims: bool) -> Self {
+		SsimForward {
+			settings,
+			input1_id,
+			input2_id,
+			output_id,
+			keep_dims,
+		}
+	}
+}
+
+impl Pass for SsimForward {
+	fn type_name(&self) -> &'static str {"SsimForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input1_id.value_id(), self.input2_id.value_id()],
+		vec![self.output_id.value_id()])
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
+		let (multiplier, output_shape_keep_dims) = self.settings.check(self.name(), &input1, &input2)?;
+
+		let output_shape_actual = calc_output_shape(input1.shape(), &self.settings.mean_axes, self.keep_dims);
+		ensure!(output_shape_actual.as_slice() == output.shape(), "Output shape {:?} does not match reduced input shape {:?}", output.shape(), output_shape_actual.as_slice());
+
+		let mut output = output.into_shape(&output_shape_keep_dims[..]).expect("This should have been caught by the ensure above");
+		let loss = self.settings.loss(input1.view(), input2.view());
+
+		for loss_chunk in loss.exact_chunks(output_shape_keep_dims.as_slice()) {
+			output.scaled_add(multiplier, &loss_chunk);
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Clone, Debug)]
+struct SsimBackward {
+	settings: SsimLossSettings,
+	input1_id: NodeID,
+	input2_id: NodeID,
+	output_id: NodeID,
+	keep_dims: bool,
+}
+
+impl SsimBackward {
+	pub fn new(settings: SsimLossSettings, input1_id: NodeID, input2_id: NodeID, output_id: NodeID, keep_dims: bool) -> Self {
+		SsimBackward {
+			settings,
+			input1_id,
+			input2_id,
+			output_id,
+			keep_dims,
+		}
+	}
+}
+
+impl Pass for SsimBackward {
+	fn type_name(&self) -> &'static str {"SsimBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input1_id.value_id(), self.input2_id.value_id(), self.output_id.gradient_id()],
+		vec![self.input1_id.gradient_id(), self.input2_id.gradient_id()])
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let (multiplier, output_shape_keep_dims) = self.settings.check(self.name(), &input1, &input2)?;
+
+		let output_shape_actual = calc_output_shape(input1.shape(), &self.settings.mean_axes, self.keep_dims);
+		ensure!(output_shape_actual.as_slice() == output_grad.shape(), "Output shape {:?} does not match reduced input shape {:?}", output_grad.shape(), output_shape_actual.as_slice());
+		let output_grad = output_grad.into_shape(&output_shape_keep_dims[..]).expect("This should have been caught by the ensure above");
+
+		let input1_required = data.is_required(&self.input1_id.gradient_id());
+		let input2_required = data.is_required(&self.input2_id.gradient_id());
+		if input1_required || input2_required {
+			let loss_grad = output_grad.broadcast(input1.shape()).unwrap().mapv(|grad| grad * multiplier);
+			let (input1_grad, input2_grad) = self.settings.gradients(input1.view(), input2.view(), loss_grad);
+			if input1_required {
+				let mut input1_grad_data = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
+				input1_grad_data += &input1_grad;
+			}
+			if input2_required {
+				let mut input2_grad_data = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
+				input2_grad_data += &input2_grad;
+			}
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_ssim_window(){
+	let settings = SsimSettings::new().window_size(5).sigma(1.0);
+	let window = settings.window(7);
+	assert_eq!(window[0].len(), 3);
+	assert_eq!(window[3].len(), 5);
+	for weights in &window {
+		let sum: f32 = weights.iter().map(|&(_, w)| w).sum();
+		assert!((sum - 1.0).abs() < 1e-6);
+	}
+}
+
+#[test]
+fn test_ssim_values(){
+	_ssim_values().unwrap();
+}
+
+fn _ssim_values() -> Result<()>{
+	use graph::GraphDef;
+	use rand::{thread_rng, Rng};
+
+	let mut g = GraphDef::new();
+
+	let input1 = g.new_node(shape![2, 9, 8, 3], "input1", tag![])?;
+	let input2 = g.new_node(shape![2, 9, 8, 3], "input2", tag![])?;
+	let output1 = g.new_node(shape![2, 3], "output1", tag![])?;
+	let output2 = g.new_node(shape![2, 3], "output2", tag![])?;
+
+	let _o1 = g.new_op(Ssim::new(&input1, &input2).output(&output1).mean_axes(&[1, 2]).window_size(7), tag![])?;
+	let _o2 = g.new_op(MsSsim::new(&input1, &input2).output(&output2).scale_weights(&[0.5, 0.5]).window_size(7), tag![])?;
+
+	let mut subgraph = g.subgraph(&[input1.value_id(), input2.value_id()], &[output1.value_id(), output2.value_id()])?;
+
+	let input1_data = ArrayD::from_shape_fn(vec![2, 9, 8, 3], |_| thread_rng().gen_range(0.0, 1.0));
+
+	// identical inputs have no loss
+	let storage = subgraph.execute(vec![input1_data.clone(), input1_data.clone()])?;
+	assert!(storage.get::<f32>(&output1.value_id())?.iter().all(|&v| v.abs() < 1e-5));
+	assert!(storage.get::<f32>(&output2.value_id())?.iter().all(|&v| v.abs() < 1e-5));
+
+	// a uniform offset only changes the luminance term
+	let offset = 0.1;
+	let storage = subgraph.execute(vec![input1_data.clone(), input1_data.mapv(|x| x + offset)])?;
+	let settings = SsimSettings::new();
+	let (c1, _) = settings.constants();
+	let maps = settings.window_size(7).maps(input1_data.view(), input1_data.mapv(|x| x + offset).view());
+	assert!(maps.contrast_structure.iter().all(|&cs| (cs - 1.0).abs() < 1e-4));
+	assert!(maps.luminance.iter().zip(&maps.mean_x).all(|(&l, &m)| {
+		let expected = (2.0*m*(m + offset) + c1)/(m*m + (m + offset)*(m + offset) + c1);
+		(l - expected).abs() < 1e-4
+	}));
+	assert!(storage.get::<f32>(&output1.value_id())?.iter().all(|&v| v > 0.0 && v < 0.2));
+
+	Ok(())
+}
+
+#[test]
+fn test_ssim_backprop(){
+	_ssim_backprop().unwrap();
+}
+
+fn _ssim_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 7, 6, 2], "input1", tag![])?;
+	let node2 = g.new_node(shape![2, 7, 6, 2], "input2", tag![])?;
+
+	let _o1 = g.new_op(Ssim::new(&node1, &node2).window_size(5).multiplier(10.0), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| thread_rng().gen_range(0.0, 1.0)) as Box<FnMut()->f64>,
+		node2.clone() => Box::new(|| thread_rng().gen_range(0.0, 1.0)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+#[test]
+fn test_ssim_output_backprop(){
+	_ssim_output_backprop().unwrap();
+}
+
+fn _ssim_output_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 7, 6, 2], "input1", tag![])?;
+	let node2 = g.new_node(shape![2, 7, 6, 2], "input2", tag![])?;
+	let node3 = g.new_node(shape![2, 2], "output", tag![])?;
+	let node4 = g.new_node(shape![2, 2], "target", tag![])?;
+
+	let _o1 = g.new_op(Ssim::new(&node1, &node2).output(&node3).mean_axes(&[1, 2]).window_size(3).sigma(0.8), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| thread_rng().gen_range(0.0, 1.0)) as Box<FnMut()->f64>,
+		node2.clone() => Box::new(|| thread_rng().gen_range(0.0, 1.0)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+#[test]
+fn test_ms_ssim_backprop(){
+	_ms_ssim_backprop().unwrap();
+}
+
+fn _ms_ssim_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::math::add::Add;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	// the second input is a noisy copy of the first, so that every scale is well correlated
+	// and large enough that the coarsest scale (4x4) still covers the window
+	let node1 = g.new_node(shape![2, 16, 16, 2], "input1", tag![])?;
+	let node2 = g.new_node(shape![2, 16, 16, 2], "noise", tag![])?;
+	let node3 = g.new_node(shape![2, 16, 16, 2], "input2", tag![])?;
+
+	let _o1 = g.new_op(Add::new(&node1, &node3), tag![])?;
+	let _o2 = g.new_op(Add::new(&node2, &node3), tag![])?;
+	let _o3 = g.new_op(MsSsim::new(&node1, &node3).scale_weights(&[0.3, 0.3, 0.4]).window_size(3).multiplier(10.0), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| thread_rng().gen_range(0.0, 1.0)) as Box<FnMut()->f64>,
+		node2.clone() => Box::new(|| thread_rng().gen_range(-0.2, 0.2)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
@@ -174,8 +174,8 @@ impl Pass for AddForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input: ArrayViewD<f32> = data.get(&self.input_id.value_id())?;
-		let mut output: ArrayViewMutD<f32> = data.get_mut(&self.output_id.value_id())?;
+		let input: ArrayViewD<f32> = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output: ArrayViewMutD<f32> = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		let effective_shape = effective_shape(input.shape(), &self.extra_axes, output.ndim())?;
 		let input_effective = input.into_shape(effective_shape.as_slice()).expect("must be a bug in effective_shape()");
@@ -220,8 +220,8 @@ impl Pass for AddBackward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input_grad = data.get_mut(&self.input_id.gradient_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 		
 
 		let effective_shape = effective_shape(input_grad.shape(), &self.extra_axes, output_grad.ndim())?;
@@ -0,0 +1,101 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::math::binary_elementwise::{BinaryFunc, BinaryElementwiseInstance, binary_elementwise_build};
+
+#[derive(Clone, Debug)]
+pub struct Atan2Func{}
+
+impl BinaryFunc for Atan2Func {
+	fn value(&self, input1: f32, input2: f32) -> f32 {
+		input1.atan2(input2)
+	}
+
+	fn gradient(&self, input1: f32, input2: f32, output_grad: f32) -> (f32, f32) {
+		let r2 = input1*input1 + input2*input2;
+		if r2 == 0.0 {
+			(0.0, 0.0)
+		} else {
+			(output_grad*input2/r2, -output_grad*input1/r2)
+		}
+	}
+}
+
+/// Atan2 Op
+///
+/// Both inputs are broadcast to the output shape, and the four quadrant arctangent of input1 (y) and input2 (x) is added to the output.
+///
+/// No gradient is propagated where both inputs are zero.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Atan2 {
+	input1: NodeID,
+	input2: NodeID,
+	output: NodeID,
+	name: Option<String>,
+}
+
+impl Atan2 {
+	pub fn new(input1: &NodeID, input2: &NodeID, output: &NodeID) -> Self {
+		Atan2 {
+			input1: input1.clone(),
+			input2: input2.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for Atan2 {
+	type InstanceType = BinaryElementwiseInstance<Atan2Func>;
+
+	fn type_name(&self) -> &'static str {
+		"Atan2"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		binary_elementwise_build(graph, &self, &self.name, &self.input1, &self.input2, &self.output, Atan2Func{})
+	}
+}
+
+
+#[test]
+fn test_atan2_backprop(){
+	_atan2_backprop().unwrap();
+}
+
+fn _atan2_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 1, 16], "input1", tag![])?;
+	let node2 = g.new_node(shape![5, 1], "input2", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node4 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+	let _o1 = g.new_op(Atan2::new(&node1, &node2, &node3), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	// keep away from the branch cut along the negative x axis
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node2.clone() => Box::new(|| thread_rng().gen_range(0.5, 2.0)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
@@ -0,0 +1,398 @@
+#![allow(non_snake_case)]
+
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use shape::NodeShape;
+use ndarray::Dimension;
+use rayon::prelude::*;
+use std::cmp;
+use std::any::Any;
+use matrixmultiply;
+
+/// Calculate C[..] += α A[..] B[..] for each matrix in a stack of matrices
+///
+/// The last two dimensions of each node are the matrix dimensions, and all leading dimensions are batch dimensions.
+/// Batch dimensions are broadcast as in numpy: shapes are aligned from the innermost batch dimension,
+/// missing dimensions are treated as 1, and dimensions of size 1 are repeated to match the other inputs.
+/// For example, A of shape [8, 1, M, K] and B of shape [3, K, N] produce C of shape [8, 3, M, N].
+///
+/// Unlike `MatMul`, the M, N and K dimensions are never inferred by flattening.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct BatchMatMul {
+	name: Option<String>,
+	A_id: NodeID,
+	B_id: NodeID,
+	C_id: NodeID,
+	A_trans: bool,
+	B_trans: bool,
+	C_trans: bool,
+	alpha: f32,
+}
+
+impl BatchMatMul {
+	pub fn new(A_id: &NodeID, B_id: &NodeID, C_id: &NodeID) -> Self{
+		BatchMatMul {
+			name: None,
+			A_id: A_id.clone(),
+			B_id: B_id.clone(),
+			C_id: C_id.clone(),
+			A_trans: false,
+			B_trans: false,
+			C_trans: false,
+			alpha: 1.0,
+		}
+	}
+
+	pub fn alpha(mut self, alpha: f32) -> Self {
+		self.alpha = alpha;
+		self
+	}
+
+	/// If true, each matrix of A has shape [K, M]
+	pub fn a_trans(mut self, trans: bool) -> Self {
+		self.A_trans = trans;
+		self
+	}
+
+	/// If true, each matrix of B has shape [N, K]
+	pub fn b_trans(mut self, trans: bool) -> Self {
+		self.B_trans = trans;
+		self
+	}
+
+	/// If true, each matrix of C has shape [N, M]
+	pub fn c_trans(mut self, trans: bool) -> Self {
+		self.C_trans = trans;
+		self
+	}
+}
+
+
+impl Op for BatchMatMul {
+	type InstanceType = BatchMatMulInstance;
+
+	fn type_name(&self) -> &'static str {
+		"BatchMatMul"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.A_id.clone(), self.B_id.clone()], &[self.C_id.clone()]);
+
+		Ok(BatchMatMulInstance{
+			name: name,
+			A_id: self.A_id.clone(),
+			B_id: self.B_id.clone(),
+			C_id: self.C_id.clone(),
+			A_trans: self.A_trans,
+			B_trans: self.B_trans,
+			C_trans: self.C_trans,
+			forward_id: graph.add_pass(BatchMatMulPass::new( // C += A B
+				self.A_id.value_id(),
+				self.B_id.value_id(),
+				self.C_id.value_id(),
+				self.A_trans,
+				self.B_trans,
+				self.C_trans,
+				self.alpha,
+			)),
+			backward1_id: graph.add_pass(BatchMatMulPass::new( // B' += At C'
+				self.A_id.value_id(),
+				self.C_id.gradient_id(),
+				self.B_id.gradient_id(),
+				!self.A_trans,
+				self.C_trans,
+				self.B_trans,
+				self.alpha,
+			)),
+			backward2_id: graph.add_pass(BatchMatMulPass::new( // A' += C' Bt
+				self.C_id.gradient_id(),
+				self.B_id.value_id(),
+				self.A_id.gradient_id(),
+				self.C_trans,
+				!self.B_trans,
+				self.A_trans,
+				self.alpha,
+			)),
+		})
+	}
+}
+
+
+#[derive(Debug, Clone)]
+pub struct BatchMatMulInstance {
+	name: String,
+	A_id: NodeID,
+	B_id: NodeID,
+	C_id: NodeID,
+	pub A_trans: bool,
+	pub B_trans: bool,
+	pub C_trans: bool,
+	forward_id: PassID,
+	backward1_id: PassID,
+	backward2_id: PassID,
+}
+
+impl OpInstance for BatchMatMulInstance {
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(vec![self.A_id.clone(), self.B_id.clone()], vec![self.C_id.clone()])
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward1_id.clone(), self.backward2_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let A_shape = shapes.get_shape(&self.A_id).to_data_shape()?;
+		let B_shape = shapes.get_shape(&self.B_id).to_data_shape()?;
+
+		let shape_error = |message: String| ErrorKind::ShapePropagationError(self.name.clone(), message);
+		ensure!(A_shape.ndim() >= 2 && B_shape.ndim() >= 2, shape_error(format!("inputs must have at least 2 dimensions, found shapes A: {:?} B: {:?}", A_shape.slice(), B_shape.slice())));
+
+		let (m, k) = matrix_dims(A_shape.slice(), self.A_trans);
+		let (k2, n) = matrix_dims(B_shape.slice(), self.B_trans);
+		ensure!(k == k2, shape_error(format!("the K dimension of A: {:?} (transpose:{}) does not match B: {:?} (transpose:{})", A_shape.slice(), self.A_trans, B_shape.slice(), self.B_trans)));
+
+		let mut C_shape = broadcast_batch(&[batch_dims(A_shape.slice()), batch_dims(B_shape.slice())]).map_err(|message| shape_error(message))?;
+		if self.C_trans {
+			C_shape.extend_from_slice(&[n, m]);
+		} else {
+			C_shape.extend_from_slice(&[m, n]);
+		}
+		shapes.merge_with(&self.C_id, &NodeShape::from(C_shape))
+	}
+}
+
+
+/// Calculate C[..] += α A[..] B[..], broadcasting the batch dimensions of all of A, B, and C.
+///
+/// If the batch dimensions of C are broadcast, each matrix of C receives the sum of the products of all batch elements which map to it.
+/// This allows the same pass to be used to calculate gradients for inputs with broadcast batch dimensions.
+/// Each matrix of C is calculated in parallel.
+#[derive(Debug, Clone)]
+pub struct BatchMatMulPass{
+	mat_A: DataID,
+	mat_B: DataID,
+	mat_C: DataID,
+	A_trans: bool,
+	B_trans: bool,
+	C_trans: bool,
+	alpha: f32,
+}
+
+impl BatchMatMulPass {
+	pub fn new(mat_A: DataID,
+			mat_B: DataID,
+			mat_C: DataID,
+			A_trans: bool,
+			B_trans: bool,
+			C_trans: bool,
+			alpha: f32) -> Self {
+		BatchMatMulPass {
+			mat_A: mat_A,
+			mat_B: mat_B,
+			mat_C: mat_C,
+			A_trans: A_trans,
+			B_trans: B_trans,
+			C_trans: C_trans,
+			alpha: alpha,
+		}
+	}
+}
+
+impl Pass for BatchMatMulPass {
+	fn type_name(&self) -> &'static str {"BatchMatMulPass"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.mat_A.clone(), self.mat_B.clone()],
+		vec![self.mat_C.clone()])
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let mat_A = data.get::<f32>(&self.mat_A)?;
+		let mat_B = data.get::<f32>(&self.mat_B)?;
+		let mut mat_C = data.get_mut::<f32>(&self.mat_C)?;
+
+		ensure!(mat_A.ndim() >= 2 && mat_B.ndim() >= 2 && mat_C.ndim() >= 2, ErrorKind::PassError(self.name(), format!("all arguments must have at least 2 dimensions, found shapes A: {:?} B: {:?} C: {:?}", mat_A.shape(), mat_B.shape(), mat_C.shape())));
+
+		let (m, k) = matrix_dims(mat_A.shape(), self.A_trans);
+		let (k2, n) = matrix_dims(mat_B.shape(), self.B_trans);
+		let (m2, n2) = if self.C_trans {
+			let (n2, m2) = matrix_dims(mat_C.shape(), false);
+			(m2, n2)
+		} else {
+			matrix_dims(mat_C.shape(), false)
+		};
+		ensure!(k == k2 && m == m2 && n == n2, ErrorKind::PassError(self.name(), format!("matrix dimensions of A: {:?} (transpose:{}), B: {:?} (transpose:{}) and C: {:?} (transpose:{}) are not compatible",
+			mat_A.shape(), self.A_trans, mat_B.shape(), self.B_trans, mat_C.shape(), self.C_trans)));
+
+		let batch_shapes = [batch_dims(mat_A.shape()), batch_dims(mat_B.shape()), batch_dims(mat_C.shape())];
+		let batch = match broadcast_batch(&batch_shapes) {
+			Err(message) => bail!(ErrorKind::PassError(self.name(), message)),
+			Ok(x) => x,
+		};
+
+		// For each matrix of C, collect the offsets of the matrices of A and B which contribute to it
+		let mut groups = vec![vec![]; batch_shapes[2].iter().product()];
+		for i in 0..batch.iter().product() {
+			let A_ind = broadcast_index(i, &batch, batch_shapes[0]);
+			let B_ind = broadcast_index(i, &batch, batch_shapes[1]);
+			let C_ind = broadcast_index(i, &batch, batch_shapes[2]);
+			groups[C_ind].push((A_ind * m * k, B_ind * k * n));
+		}
+
+		if m * n == 0 {
+			return Ok(Box::new(()));
+		}
+
+		let mat_A = mat_A.as_slice().unwrap();
+		let mat_B = mat_B.as_slice().unwrap();
+		let mat_C = mat_C.as_slice_mut().unwrap();
+
+		let (rsa, csa) = if self.A_trans{(1, m)} else {(k, 1)};
+		let (rsb, csb) = if self.B_trans{(1, k)} else {(n, 1)};
+		let (rsc, csc) = if self.C_trans{(1, m)} else {(n, 1)};
+
+		mat_C.par_chunks_mut(m * n).zip(groups.par_iter()).for_each(|(C_chunk, group)| {
+			for &(A_offset, B_offset) in group {
+				unsafe{
+					matrixmultiply::sgemm_st(m, k, n,
+						self.alpha,
+						mat_A[A_offset..].as_ptr(), rsa as isize, csa as isize,
+						mat_B[B_offset..].as_ptr(), rsb as isize, csb as isize,
+						1.0,
+						C_chunk.as_mut_ptr(), rsc as isize, csc as isize,);
+				}
+			}
+		});
+
+		Ok(Box::new(()))
+	}
+}
+
+/// Returns the (rows, cols) of the matrices in a shape, swapped if transposed
+fn matrix_dims(shape: &[usize], trans: bool) -> (usize, usize) {
+	let (rows, cols) = (shape[shape.len() - 2], shape[shape.len() - 1]);
+	if trans {
+		(cols, rows)
+	} else {
+		(rows, cols)
+	}
+}
+
+/// Returns the leading batch dimensions of a shape
+fn batch_dims(shape: &[usize]) -> &[usize] {
+	&shape[..shape.len() - 2]
+}
+
+/// Returns the broadcast shape of the batch dimensions, aligned from the innermost dimension
+fn broadcast_batch(shapes: &[&[usize]]) -> ::std::result::Result<Vec<usize>, String> {
+	let ndim = shapes.iter().map(|shape| shape.len()).max().unwrap_or(0);
+	let mut batch = vec![1; ndim];
+	for shape in shapes {
+		for (out_dim, &dim) in batch.iter_mut().rev().zip(shape.iter().rev()) {
+			if *out_dim == 1 {
+				*out_dim = dim;
+			} else if dim != 1 && dim != *out_dim {
+				return Err(format!("batch dimensions could not be broadcast together: {:?}", shapes));
+			}
+		}
+	}
+	Ok(batch)
+}
+
+/// Maps the flat index `i` of the full batch shape to the flat index of the matrix in a (possibly broadcast) batch shape
+fn broadcast_index(mut i: usize, batch: &[usize], shape: &[usize]) -> usize {
+	let mut index = 0;
+	let mut stride = 1;
+	for (&batch_dim, &dim) in batch.iter().rev().zip(shape.iter().rev().chain(::std::iter::repeat(&1))) {
+		let pos = i % batch_dim;
+		i /= batch_dim;
+		index += cmp::min(pos, dim - 1) * stride;
+		stride *= dim;
+	}
+	index
+}
+
+
+#[test]
+fn test_broadcast_batch(){
+	assert_eq!(broadcast_batch(&[&[8, 1], &[3], &[]]), Ok(vec![8, 3]));
+	assert!(broadcast_batch(&[&[8, 2], &[3]]).is_err());
+	assert_eq!(broadcast_index(5, &[2, 3], &[3]), 2);
+	assert_eq!(broadcast_index(5, &[2, 3], &[2, 1]), 1);
+	assert_eq!(broadcast_index(5, &[2, 3], &[2, 3]), 5);
+}
+
+#[test]
+fn test_batch_matmul_backprop(){
+	_batch_matmul_backprop().unwrap();
+}
+
+fn _batch_matmul_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 3, 7, 5], "input1", tag![])?;
+	let node2 = g.new_node(shape![2, 3, 5, 4], "input2", tag![])?;
+	let node3 = g.new_node(shape![Unknown, Unknown, Unknown, Unknown], "output", tag![])?;
+	let node4 = g.new_node(shape![2, 3, 7, 4], "target", tag![])?;
+
+	let _o1 = g.new_op(BatchMatMul::new(&node1, &node2, &node3), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_batch_matmul_broadcast_trans_backprop(){
+	_batch_matmul_broadcast_trans_backprop().unwrap();
+}
+
+fn _batch_matmul_broadcast_trans_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 1, 5, 7], "input1", tag![])?;
+	let node2 = g.new_node(shape![3, 4, 5], "input2", tag![])?;
+	let node3 = g.new_node(shape![Unknown, Unknown, Unknown, Unknown], "output", tag![])?;
+	let node4 = g.new_node(shape![2, 3, 4, 7], "target", tag![])?;
+
+	let _o1 = g.new_op(BatchMatMul::new(&node1, &node2, &node3).a_trans(true).b_trans(true).c_trans(true).alpha(0.5), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
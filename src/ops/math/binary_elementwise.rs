@@ -0,0 +1,294 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use storage::Storage;
+use id::{NodeID, DataID, OpID, PassID};
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use shape::{NodeShape, NodeDim};
+use ndarray::{ArrayD, ArrayViewMutD, Axis, IxDyn, Zip};
+use ndarray_parallel::prelude::*;
+use smallvec::SmallVec;
+use std::any::Any;
+use std::fmt::Debug;
+
+
+pub fn binary_elementwise_build<O: Op, F: BinaryFunc>(graph: &mut GraphDef, op: &O, name: &Option<String>, input1: &NodeID, input2: &NodeID, output: &NodeID, func: F) -> Result<BinaryElementwiseInstance<F>> {
+	let name = standard_op_name(op, name, graph, &[input1.clone(), input2.clone()], &[output.clone()]);
+
+	Ok(BinaryElementwiseInstance{
+		name: name,
+		input1_id: input1.clone(),
+		input2_id: input2.clone(),
+		output_id: output.clone(),
+		func: func.clone(),
+		forward_id: graph.add_pass(BinaryElementwiseForward::new(
+				input1.clone(),
+				input2.clone(),
+				output.clone(),
+				func.clone())),
+		backward_id: graph.add_pass(BinaryElementwiseBackward::new(
+				input1.clone(),
+				input2.clone(),
+				output.clone(),
+				func.clone())),
+	})
+}
+
+
+/// Used to define graph op with two inputs, where the output depends only on the corresponding elements of each input.
+///
+/// Both inputs are broadcast to the output shape following NumPy rules: shapes are aligned at the innermost axis,
+/// and axes of size 1 (or missing outer axes) are repeated. Gradients are summed over the broadcast axes.
+pub trait BinaryFunc: Send + Sync + Clone + Debug + 'static {
+	/// For given inputs a and b, what is the output
+	fn value(&self, input1: f32, input2: f32) -> f32;
+
+	/// For given inputs a and b, and the gradient of the output, what are the gradients of a and b
+	fn gradient(&self, input1: f32, input2: f32, output_grad: f32) -> (f32, f32);
+}
+
+#[derive(Clone, Debug)]
+pub struct BinaryElementwiseInstance<F: BinaryFunc> {
+	name: String,
+	input1_id: NodeID,
+	input2_id: NodeID,
+	output_id: NodeID,
+	func: F,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl<F: BinaryFunc> OpInstance for BinaryElementwiseInstance<F> {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){(vec![self.input1_id.clone(), self.input2_id.clone()], vec![self.output_id.clone()])}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input1_shape = shapes.get_shape(&self.input1_id).clone();
+		let input2_shape = shapes.get_shape(&self.input2_id).clone();
+		let output_shape = broadcast_node_shapes(&input1_shape, &input2_shape)?;
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+/// Returns true if the dimension can not be 1, and so must determine the broadcast size
+fn is_not_one(dim: &NodeDim) -> bool {
+	match dim {
+		&NodeDim::Known(x) => x != 1,
+		&NodeDim::Interval{lower, ..} => lower > 1,
+		&NodeDim::Unknown => false,
+	}
+}
+
+/// Broadcasts two node shapes together, following NumPy rules
+///
+/// Where it can't yet be determined whether a dimension is broadcast, the output dimension is left Unknown.
+pub fn broadcast_node_shapes(shape1: &NodeShape, shape2: &NodeShape) -> Result<NodeShape> {
+	let ndim = ::std::cmp::max(shape1.ndim(), shape2.ndim());
+	let one = NodeDim::Known(1);
+	let mut dims: SmallVec<[NodeDim; 6]> = SmallVec::new();
+	for i in 0..ndim {
+		let dim1 = if i < shape1.ndim() {&shape1.dimensions()[shape1.ndim() - 1 - i]} else {&one};
+		let dim2 = if i < shape2.ndim() {&shape2.dimensions()[shape2.ndim() - 1 - i]} else {&one};
+
+		let dim = if dim1 == &one {
+			dim2.clone()
+		} else if dim2 == &one {
+			dim1.clone()
+		} else if is_not_one(dim1) && is_not_one(dim2) {
+			NodeShape::from(vec![dim1.clone()]).merge(&NodeShape::from(vec![dim2.clone()]))?.dimensions()[0].clone()
+		} else if is_not_one(dim1) {
+			dim1.clone()
+		} else if is_not_one(dim2) {
+			dim2.clone()
+		} else {
+			NodeDim::Unknown
+		};
+		dims.push(dim);
+	}
+	Ok(dims.into_iter().rev().collect::<Vec<_>>().into())
+}
+
+/// Broadcasts two data shapes together, following NumPy rules
+pub fn broadcast_shapes(shape1: &[usize], shape2: &[usize]) -> ::std::result::Result<SmallVec<[usize; 6]>, String> {
+	let ndim = ::std::cmp::max(shape1.len(), shape2.len());
+	let mut shape: SmallVec<[usize; 6]> = (0..ndim).map(|_| 1).collect();
+	for input_shape in &[shape1, shape2] {
+		for (out_dim, &dim) in shape.iter_mut().rev().zip(input_shape.iter().rev()) {
+			if *out_dim == 1 {
+				*out_dim = dim;
+			} else if dim != 1 && dim != *out_dim {
+				return Err(format!("shapes could not be broadcast together: {:?} {:?}", shape1, shape2));
+			}
+		}
+	}
+	Ok(shape)
+}
+
+/// Sums an array over the leading and size 1 axes of `shape`, the reverse of broadcasting to the shape of the array
+pub fn sum_to_shape(mut arr: ArrayD<f32>, shape: &[usize]) -> ArrayD<f32> {
+	while arr.ndim() > shape.len() {
+		arr = arr.sum_axis(Axis(0));
+	}
+	for (axis, &dim) in shape.iter().enumerate() {
+		if dim == 1 && arr.shape()[axis] != 1 {
+			arr = arr.sum_axis(Axis(axis)).insert_axis(Axis(axis));
+		}
+	}
+	arr
+}
+
+
+#[derive(Clone, Debug)]
+pub struct BinaryElementwiseForward<F: BinaryFunc> {
+	input1_id: NodeID,
+	input2_id: NodeID,
+	output_id: NodeID,
+	func: F,
+}
+
+impl<F: BinaryFunc> BinaryElementwiseForward<F> {
+	pub fn new(input1_id: NodeID, input2_id: NodeID, output_id: NodeID, func: F) -> Self {
+		BinaryElementwiseForward {
+			input1_id,
+			input2_id,
+			output_id,
+			func,
+		}
+	}
+}
+
+impl<F: BinaryFunc> Pass for BinaryElementwiseForward<F> {
+	fn type_name(&self) -> &'static str {"BinaryElementwiseForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.input1_id.value_id(), self.input2_id.value_id()],
+			vec![self.output_id.value_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		let shape = broadcast_shapes(input1.shape(), input2.shape()).map_err(|msg| ErrorKind::PassError(self.name(), msg))?;
+		ensure!(
+			shape.as_slice() == output.shape(),
+			ErrorKind::PassError(self.name(), format!("broadcast input shape: {:?} did not match output shape: {:?}", shape.as_slice(), output.shape()))
+		);
+
+		Zip::from(output)
+			.and_broadcast(&input1)
+			.and_broadcast(&input2)
+			.par_apply(|output, &input1, &input2| {
+				*output += self.func.value(input1, input2);
+			});
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct BinaryElementwiseBackward<F: BinaryFunc> {
+	input1_id: NodeID,
+	input2_id: NodeID,
+	output_id: NodeID,
+	func: F,
+}
+
+impl<F: BinaryFunc> BinaryElementwiseBackward<F> {
+	pub fn new(input1_id: NodeID, input2_id: NodeID, output_id: NodeID, func: F) -> Self {
+		BinaryElementwiseBackward {
+			input1_id,
+			input2_id,
+			output_id,
+			func,
+		}
+	}
+
+	/// Adds the gradient of one input, summing over broadcast axes if required
+	fn accumulate<G: Fn(f32, f32, f32) -> f32 + Sync>(&self, input_grad: ArrayViewMutD<f32>, input1: &ArrayD<f32>, input2: &ArrayD<f32>, output_grad: &ArrayD<f32>, select: G) {
+		if input_grad.shape() == output_grad.shape() {
+			Zip::from(input_grad)
+				.and(output_grad)
+				.and(input1)
+				.and(input2)
+				.par_apply(|input_grad, &output_grad, &input1, &input2| {
+					*input_grad += select(input1, input2, output_grad);
+				});
+		} else {
+			let mut full_grad = ArrayD::zeros(output_grad.shape());
+			Zip::from(&mut full_grad)
+				.and(output_grad)
+				.and(input1)
+				.and(input2)
+				.par_apply(|full_grad, &output_grad, &input1, &input2| {
+					*full_grad = select(input1, input2, output_grad);
+				});
+			let shape = input_grad.shape().to_vec();
+			let mut input_grad = input_grad;
+			input_grad += &sum_to_shape(full_grad, &shape);
+		}
+	}
+}
+
+impl<F: BinaryFunc> Pass for BinaryElementwiseBackward<F> {
+	fn type_name(&self) -> &'static str {"BinaryElementwiseBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.input1_id.value_id(), self.input2_id.value_id(), self.output_id.gradient_id()],
+			vec![self.input1_id.gradient_id(), self.input2_id.gradient_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = data.get::<f32>(&self.input2_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+
+		let shape = broadcast_shapes(input1.shape(), input2.shape()).map_err(|msg| ErrorKind::PassError(self.name(), msg))?;
+		ensure!(
+			shape.as_slice() == output_grad.shape(),
+			ErrorKind::PassError(self.name(), format!("broadcast input shape: {:?} did not match output shape: {:?}", shape.as_slice(), output_grad.shape()))
+		);
+
+		let input1 = input1.broadcast(IxDyn(&shape)).expect("Shapes already checked").to_owned();
+		let input2 = input2.broadcast(IxDyn(&shape)).expect("Shapes already checked").to_owned();
+		let output_grad = output_grad.to_owned();
+
+		if data.is_required(&self.input1_id.gradient_id()) {
+			let input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
+			self.accumulate(input1_grad, &input1, &input2, &output_grad, |a, b, g| self.func.gradient(a, b, g).0);
+		}
+
+		if data.is_required(&self.input2_id.gradient_id()) {
+			let input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
+			self.accumulate(input2_grad, &input1, &input2, &output_grad, |a, b, g| self.func.gradient(a, b, g).1);
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_broadcast_shapes(){
+	assert_eq!(broadcast_shapes(&[7, 1, 16], &[5, 1]).unwrap().as_slice(), &[7, 5, 16]);
+	assert_eq!(broadcast_shapes(&[3], &[2, 4, 3]).unwrap().as_slice(), &[2, 4, 3]);
+	assert_eq!(broadcast_shapes(&[], &[2, 4]).unwrap().as_slice(), &[2, 4]);
+	assert!(broadcast_shapes(&[2, 3], &[4, 3]).is_err());
+
+	let shape = broadcast_node_shapes(&shape![Unknown, 1, 16], &shape![5, 1]).unwrap();
+	assert_eq!(shape, shape![Unknown, 5, 16]);
+	let shape = broadcast_node_shapes(&shape![Unknown, 16], &shape![Unknown, 1]).unwrap();
+	assert_eq!(shape, shape![Unknown, 16]);
+}
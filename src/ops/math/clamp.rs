@@ -0,0 +1,177 @@
+use graph::{GraphDef, ErrorKind, Result};
+use id::NodeID;
+use ops::Op;
+use ops::activ::elementwise::{ActivationFunc, ElementwiseInstance, elementwise_build};
+use std::f32;
+
+#[derive(Clone, Debug)]
+pub struct ClampFunc{
+	lower: f32,
+	upper: f32,
+	straight_through: bool,
+}
+
+impl ActivationFunc for ClampFunc {
+	fn value(&self, input: f32) -> f32{
+		input.max(self.lower).min(self.upper)
+	}
+
+	fn gradient(&self, input: f32, output_grad: f32) -> f32{
+		if self.straight_through || (input >= self.lower && input <= self.upper) {
+			output_grad
+		} else {
+			0.0
+		}
+	}
+
+	fn backprop_requires_input_value() -> bool {true}
+}
+
+/// Clamp Op
+///
+/// Limits each element of the input to lie within the optional lower and upper bounds.
+///
+/// By default the gradient is passed through where the input is within the bounds and is zero elsewhere.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Clamp {
+	output: NodeID,
+	input: NodeID,
+	lower: Option<f32>,
+	upper: Option<f32>,
+	straight_through: bool,
+	name: Option<String>,
+}
+
+impl Clamp {
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		Clamp {
+			input: input.clone(),
+			output: output.clone(),
+			lower: None,
+			upper: None,
+			straight_through: false,
+			name: None,
+		}
+	}
+
+	/// The smallest value of the output.
+	///
+	/// Default: None
+	pub fn lower(mut self, lower: f32) -> Self {
+		self.lower = Some(lower);
+		self
+	}
+
+	/// The largest value of the output.
+	///
+	/// Default: None
+	pub fn upper(mut self, upper: f32) -> Self {
+		self.upper = Some(upper);
+		self
+	}
+
+	/// If true, the gradient is passed through unchanged even where the input was clamped.
+	///
+	/// Default: false
+	pub fn straight_through(mut self, straight_through: bool) -> Self {
+		self.straight_through = straight_through;
+		self
+	}
+}
+
+impl Op for Clamp {
+	type InstanceType = ElementwiseInstance<ClampFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"Clamp"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let lower = self.lower.unwrap_or(f32::NEG_INFINITY);
+		let upper = self.upper.unwrap_or(f32::INFINITY);
+		ensure!(lower <= upper, ErrorKind::ShapePropagationError(self.name.clone().unwrap_or_else(|| self.type_name().to_string()), format!("lower bound ({}) must not be greater than the upper bound ({})", lower, upper)));
+		elementwise_build(graph, &self, &self.name, &self.input, &self.output, ClampFunc{lower, upper, straight_through: self.straight_through})
+	}
+}
+
+
+#[test]
+fn test_clamp_backprop(){
+	_clamp_backprop().unwrap();
+}
+
+fn _clamp_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 16], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+
+	let _o1 = g.new_op(Clamp::new(&node1, &node2).lower(-0.5).upper(0.5), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	// keep the inputs away from the bounds so that no step crosses the discontinuity in the gradient
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| match thread_rng().gen_range(0, 3) {
+			0 => thread_rng().gen_range(-1.5, -0.6),
+			1 => thread_rng().gen_range(-0.4, 0.4),
+			_ => thread_rng().gen_range(0.6, 1.5),
+		}) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_clamp_values(){
+	_clamp_values().unwrap();
+}
+
+fn _clamp_values() -> Result<()>{
+	use graph::GraphDef;
+	use ndarray::{ArrayD, IxDyn};
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![5], "input", tag![])?;
+	let node2 = g.new_node(shape![5], "lower_output", tag![])?;
+	let node3 = g.new_node(shape![5], "straight_through_output", tag![])?;
+
+	let _o1 = g.new_op(Clamp::new(&node1, &node2).lower(0.0), tag![])?;
+	let _o2 = g.new_op(Clamp::new(&node1, &node3).lower(-1.0).upper(1.0).straight_through(true), tag![])?;
+
+	let mut subgraph = g.subgraph(&[node1.value_id(), node2.gradient_id(), node3.gradient_id()], &[node2.value_id(), node3.value_id(), node1.gradient_id()])?;
+	let input = ArrayD::from_shape_vec(IxDyn(&[5]), vec![-2.0, -0.5, 0.0, 0.5, 2.0]).unwrap();
+	let output_grad2 = ArrayD::from_elem(IxDyn(&[5]), 1.0);
+	let output_grad3 = ArrayD::from_elem(IxDyn(&[5]), 10.0);
+	let storage = subgraph.execute(vec![input, output_grad2, output_grad3])?;
+
+	let output2 = storage.get::<f32>(&node2.value_id())?;
+	assert_eq!(output2.iter().cloned().collect::<Vec<_>>(), vec![0.0, 0.0, 0.0, 0.5, 2.0]);
+	let output3 = storage.get::<f32>(&node3.value_id())?;
+	assert_eq!(output3.iter().cloned().collect::<Vec<_>>(), vec![-1.0, -0.5, 0.0, 0.5, 1.0]);
+	let input_grad = storage.get::<f32>(&node1.gradient_id())?;
+	assert_eq!(input_grad.iter().cloned().collect::<Vec<_>>(), vec![10.0, 10.0, 11.0, 11.0, 11.0]);
+
+	Ok(())
+}
@@ -156,9 +156,9 @@ impl Pass for DivForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let numerator: ArrayViewD<f32> = data.get(&self.numerator_id.value_id())?;
-		let denominator: ArrayViewD<f32> = data.get(&self.denominator_id.value_id())?;
-		let mut output: ArrayViewMutD<f32> = data.get_mut(&self.output_id.value_id())?;
+		let numerator: ArrayViewD<f32> = data.get::<f32>(&self.numerator_id.value_id())?;
+		let denominator: ArrayViewD<f32> = data.get::<f32>(&self.denominator_id.value_id())?;
+		let mut output: ArrayViewMutD<f32> = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 
 		if self.broadcast_numerator {
@@ -229,9 +229,9 @@ impl Pass for DivBackward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let numerator: ArrayViewD<f32> = data.get(&self.numerator_id.value_id())?;
-		let denominator: ArrayViewD<f32> = data.get(&self.denominator_id.value_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let numerator: ArrayViewD<f32> = data.get::<f32>(&self.numerator_id.value_id())?;
+		let denominator: ArrayViewD<f32> = data.get::<f32>(&self.denominator_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 		
 		if self.broadcast_numerator {
 			ensure!(
@@ -245,7 +245,7 @@ impl Pass for DivBackward {
 
 			if data.is_required(&self.numerator_id.gradient_id()) {
 				unsafe{
-					let numerator_grad = data.get_mut(&self.numerator_id.gradient_id())?;
+					let numerator_grad = data.get_mut::<f32>(&self.numerator_id.gradient_id())?;
 
 					// do not split/parallelise this Zip!
 					Zip::from(&denominator)
@@ -258,7 +258,7 @@ impl Pass for DivBackward {
 				}
 			}
 			if data.is_required(&self.denominator_id.gradient_id()) {
-				let mut denominator_grad = data.get_mut(&self.denominator_id.gradient_id())?;
+				let mut denominator_grad = data.get_mut::<f32>(&self.denominator_id.gradient_id())?;
 				Zip::from(&mut denominator_grad)
 					.and_broadcast(&numerator)
 					.and(&denominator)
@@ -278,7 +278,7 @@ impl Pass for DivBackward {
 			);
 
 			if data.is_required(&self.numerator_id.gradient_id()) {
-				let mut numerator_grad = data.get_mut(&self.numerator_id.gradient_id())?;
+				let mut numerator_grad = data.get_mut::<f32>(&self.numerator_id.gradient_id())?;
 
 				Zip::from(&mut numerator_grad)
 					.and_broadcast(&denominator)
@@ -290,7 +290,7 @@ impl Pass for DivBackward {
 			
 			if data.is_required(&self.denominator_id.gradient_id()) {
 				unsafe {
-					let denominator_grad = data.get_mut(&self.denominator_id.gradient_id())?;
+					let denominator_grad = data.get_mut::<f32>(&self.denominator_id.gradient_id())?;
 
 					// do not split/parallelise this Zip!
 					Zip::from(&numerator)
@@ -11,6 +11,8 @@ use std::any::Any;
 use matrixmultiply;
 
 /// Calculate C += α A B
+///
+/// All dimensions are flattened into a single matrix for each argument, see `BatchMatMul` for multiplying stacks of matrices.
 #[must_use]
 #[derive(Clone, Debug)]
 pub struct MatMul {
@@ -461,9 +463,9 @@ impl Pass for MatMulPass {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let mat_A = data.get(&self.mat_A)?;
-		let mat_B = data.get(&self.mat_B)?;
-		let mut mat_C = data.get_mut(&self.mat_C)?;
+		let mat_A = data.get::<f32>(&self.mat_A)?;
+		let mat_B = data.get::<f32>(&self.mat_B)?;
+		let mut mat_C = data.get_mut::<f32>(&self.mat_C)?;
 
 		let (m, n, k) = match self.find_mnk(mat_A.shape(), mat_B.shape(), mat_C.shape()){
 			Err(message) => bail!(ErrorKind::PassError(self.name(), message)),
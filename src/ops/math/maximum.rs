@@ -0,0 +1,201 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::math::binary_elementwise::{BinaryFunc, BinaryElementwiseInstance, binary_elementwise_build};
+
+#[derive(Clone, Debug)]
+pub struct MaximumFunc{}
+
+impl BinaryFunc for MaximumFunc {
+	fn value(&self, input1: f32, input2: f32) -> f32 {
+		input1.max(input2)
+	}
+
+	fn gradient(&self, input1: f32, input2: f32, output_grad: f32) -> (f32, f32) {
+		if input1 > input2 {
+			(output_grad, 0.0)
+		} else if input1 < input2 {
+			(0.0, output_grad)
+		} else {
+			(0.5*output_grad, 0.5*output_grad)
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct MinimumFunc{}
+
+impl BinaryFunc for MinimumFunc {
+	fn value(&self, input1: f32, input2: f32) -> f32 {
+		input1.min(input2)
+	}
+
+	fn gradient(&self, input1: f32, input2: f32, output_grad: f32) -> (f32, f32) {
+		if input1 < input2 {
+			(output_grad, 0.0)
+		} else if input1 > input2 {
+			(0.0, output_grad)
+		} else {
+			(0.5*output_grad, 0.5*output_grad)
+		}
+	}
+}
+
+/// Maximum Op
+///
+/// Both inputs are broadcast to the output shape, and the elementwise maximum is added to the output.
+/// Where the inputs are equal the gradient is split evenly between them.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Maximum {
+	input1: NodeID,
+	input2: NodeID,
+	output: NodeID,
+	name: Option<String>,
+}
+
+impl Maximum {
+	pub fn new(input1: &NodeID, input2: &NodeID, output: &NodeID) -> Self {
+		Maximum {
+			input1: input1.clone(),
+			input2: input2.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for Maximum {
+	type InstanceType = BinaryElementwiseInstance<MaximumFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"Maximum"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		binary_elementwise_build(graph, &self, &self.name, &self.input1, &self.input2, &self.output, MaximumFunc{})
+	}
+}
+
+
+/// Minimum Op
+///
+/// Both inputs are broadcast to the output shape, and the elementwise minimum is added to the output.
+/// Where the inputs are equal the gradient is split evenly between them.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Minimum {
+	input1: NodeID,
+	input2: NodeID,
+	output: NodeID,
+	name: Option<String>,
+}
+
+impl Minimum {
+	pub fn new(input1: &NodeID, input2: &NodeID, output: &NodeID) -> Self {
+		Minimum {
+			input1: input1.clone(),
+			input2: input2.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for Minimum {
+	type InstanceType = BinaryElementwiseInstance<MinimumFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"Minimum"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		binary_elementwise_build(graph, &self, &self.name, &self.input1, &self.input2, &self.output, MinimumFunc{})
+	}
+}
+
+
+#[test]
+fn test_maximum_backprop(){
+	_maximum_backprop().unwrap();
+}
+
+fn _maximum_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 1, 16], "input1", tag![])?;
+	let node2 = g.new_node(shape![5, 1], "input2", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node4 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+	let _o1 = g.new_op(Maximum::new(&node1, &node2, &node3), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	// keep the inputs apart so that no step crosses the discontinuity in the gradient
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| thread_rng().gen_range(1.0, 1.5) * if thread_rng().gen() {1.0} else {-1.0}) as Box<FnMut()->f64>,
+		node2.clone() => Box::new(|| thread_rng().gen_range(-0.5, 0.5)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_minimum_backprop(){
+	_minimum_backprop().unwrap();
+}
+
+fn _minimum_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 1, 16], "input1", tag![])?;
+	let node2 = g.new_node(shape![5, 1], "input2", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node4 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+	let _o1 = g.new_op(Minimum::new(&node1, &node2, &node3), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	// keep the inputs apart so that no step crosses the discontinuity in the gradient
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| thread_rng().gen_range(1.0, 1.5) * if thread_rng().gen() {1.0} else {-1.0}) as Box<FnMut()->f64>,
+		node2.clone() => Box::new(|| thread_rng().gen_range(-0.5, 0.5)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
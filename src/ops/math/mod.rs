@@ -1,7 +1,10 @@
 pub mod add;
+pub mod sub;
 pub mod mul;
 pub mod div;
 pub mod matmul;
+pub mod batch_matmul;
+pub mod binary_elementwise;
 pub mod square;
 pub mod sqrt;
 pub mod exp;
@@ -10,4 +13,8 @@ pub mod sin;
 pub mod cos;
 pub mod abs;
 pub mod reciprocal;
-pub mod scale;
\ No newline at end of file
+pub mod scale;
+pub mod maximum;
+pub mod pow;
+pub mod atan2;
+pub mod clamp;
\ No newline at end of file
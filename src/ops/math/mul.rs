@@ -131,9 +131,9 @@ impl Pass for MulForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input1: ArrayViewD<f32> = data.get(&self.input1_id.value_id())?;
-		let input2: ArrayViewD<f32> = data.get(&self.input2_id.value_id())?;
-		let mut output: ArrayViewMutD<f32> = data.get_mut(&self.output_id.value_id())?;
+		let input1: ArrayViewD<f32> = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2: ArrayViewD<f32> = data.get::<f32>(&self.input2_id.value_id())?;
+		let mut output: ArrayViewMutD<f32> = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		ensure!(
 			input1.shape() == output.shape(),
@@ -191,9 +191,9 @@ impl Pass for MulBackward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input1: ArrayViewD<f32> = data.get(&self.input1_id.value_id())?;
-		let input2: ArrayViewD<f32> = data.get(&self.input2_id.value_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let input1: ArrayViewD<f32> = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2: ArrayViewD<f32> = data.get::<f32>(&self.input2_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 		
 		ensure!(
 			input1.shape() == output_grad.shape(),
@@ -209,7 +209,7 @@ impl Pass for MulBackward {
 		);
 
 		if data.is_required(&self.input1_id.gradient_id()) {
-			let mut input1_grad = data.get_mut(&self.input1_id.gradient_id())?;
+			let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
 
 			Zip::from(&mut input1_grad)
 				.and(&output_grad)
@@ -222,7 +222,7 @@ impl Pass for MulBackward {
 		if data.is_required(&self.input2_id.gradient_id()) {
 			
 			unsafe{
-				let input2_grad = data.get_mut(&self.input2_id.gradient_id())?;
+				let input2_grad = data.get_mut::<f32>(&self.input2_id.gradient_id())?;
 
 				// do not split/parallelise this Zip!
 				Zip::from(&input1)
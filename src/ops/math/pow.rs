@@ -0,0 +1,106 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::math::binary_elementwise::{BinaryFunc, BinaryElementwiseInstance, binary_elementwise_build};
+
+#[derive(Clone, Debug)]
+pub struct PowFunc{}
+
+impl BinaryFunc for PowFunc {
+	fn value(&self, input1: f32, input2: f32) -> f32 {
+		input1.powf(input2)
+	}
+
+	fn gradient(&self, input1: f32, input2: f32, output_grad: f32) -> (f32, f32) {
+		let base_grad = if input2 == 0.0 {
+			0.0
+		} else {
+			input2*input1.powf(input2 - 1.0)
+		};
+		// The exponent gradient is only defined for positive bases
+		let exponent_grad = if input1 > 0.0 {
+			input1.powf(input2)*input1.ln()
+		} else {
+			0.0
+		};
+		(output_grad*base_grad, output_grad*exponent_grad)
+	}
+}
+
+/// Pow Op
+///
+/// Both inputs are broadcast to the output shape, then input1 is raised to the power of input2 and the result added to the output.
+///
+/// Negative bases produce NaN for non-integer exponents, and do not propagate a gradient to the exponent.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Pow {
+	input1: NodeID,
+	input2: NodeID,
+	output: NodeID,
+	name: Option<String>,
+}
+
+impl Pow {
+	pub fn new(input1: &NodeID, input2: &NodeID, output: &NodeID) -> Self {
+		Pow {
+			input1: input1.clone(),
+			input2: input2.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for Pow {
+	type InstanceType = BinaryElementwiseInstance<PowFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"Pow"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		binary_elementwise_build(graph, &self, &self.name, &self.input1, &self.input2, &self.output, PowFunc{})
+	}
+}
+
+
+#[test]
+fn test_pow_backprop(){
+	_pow_backprop().unwrap();
+}
+
+fn _pow_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 1, 16], "input1", tag![])?;
+	let node2 = g.new_node(shape![5, 1], "input2", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node4 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+	let _o1 = g.new_op(Pow::new(&node1, &node2, &node3), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+		node1.clone() => Box::new(|| thread_rng().gen_range(0.5, 2.0)) as Box<FnMut()->f64>,
+	];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
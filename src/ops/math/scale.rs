@@ -123,8 +123,8 @@ impl Pass for ScaleForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input: ArrayViewD<f32> = data.get(&self.input_id.value_id())?;
-		let mut output: ArrayViewMutD<f32> = data.get_mut(&self.output_id.value_id())?;
+		let input: ArrayViewD<f32> = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output: ArrayViewMutD<f32> = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		let input_broadcast = if let Some(view) = input.broadcast(output.shape()) {
 			view
@@ -166,8 +166,8 @@ impl Pass for ScaleBackward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let mut input_grad = data.get_mut(&self.input_id.gradient_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 		
 		ensure!(
 			input_grad.broadcast(output_grad.shape()).is_some(), 
@@ -0,0 +1,88 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::math::binary_elementwise::{BinaryFunc, BinaryElementwiseInstance, binary_elementwise_build};
+
+#[derive(Clone, Debug)]
+pub struct SubFunc{}
+
+impl BinaryFunc for SubFunc {
+	fn value(&self, input1: f32, input2: f32) -> f32 {
+		input1 - input2
+	}
+
+	fn gradient(&self, _input1: f32, _input2: f32, output_grad: f32) -> (f32, f32) {
+		(output_grad, -output_grad)
+	}
+}
+
+/// Sub Op
+///
+/// Both inputs are broadcast to the output shape, then input2 is subtracted from input1 and the result added to the output
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Sub {
+	input1: NodeID,
+	input2: NodeID,
+	output: NodeID,
+	name: Option<String>,
+}
+
+impl Sub {
+	pub fn new(input1: &NodeID, input2: &NodeID, output: &NodeID) -> Self {
+		Sub {
+			input1: input1.clone(),
+			input2: input2.clone(),
+			output: output.clone(),
+			name: None,
+		}
+	}
+}
+
+impl Op for Sub {
+	type InstanceType = BinaryElementwiseInstance<SubFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"Sub"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		binary_elementwise_build(graph, &self, &self.name, &self.input1, &self.input2, &self.output, SubFunc{})
+	}
+}
+
+
+#[test]
+fn test_sub_backprop(){
+	_sub_backprop().unwrap();
+}
+
+fn _sub_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 1, 16], "input1", tag![])?;
+	let node2 = g.new_node(shape![5, 1], "input2", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node4 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+	let _o1 = g.new_op(Sub::new(&node1, &node2, &node3), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
@@ -5,12 +5,16 @@ pub mod nn;
 pub mod math;
 pub mod activ;
 pub mod shape;
+pub mod index;
 pub mod reduce;
 pub mod regularisation;
 pub mod grad;
 pub mod fill;
+pub mod norm;
+pub mod logic;
 
-use graph::{GraphDef, GraphShapes, Result};
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use dtype::DType;
 use storage::Storage;
 use id::{NodeID, DataID, OpID, PassID, OpTag};
 use std::any::Any;
@@ -152,6 +156,18 @@ pub trait OpInstance: Any + OpClone + OpAny + Debug + Send + Sync{
 
 	/// TODO
 	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>;
+
+	/// Checks that the element types of the nodes used by this Op are supported.
+	///
+	/// Called prior to shape propagation. The default implementation requires all input and output nodes to be `F32`,
+	/// ops which accept labels, indices or masks should override this.
+	fn check_dtypes(&self) -> Result<()> {
+		let (inputs, outputs) = self.dependencies();
+		for node_id in inputs.iter().chain(&outputs) {
+			ensure!(node_id.dtype() == DType::F32, ErrorKind::DataTypeMismatch(node_id.value_id().name(), DType::F32, node_id.dtype()));
+		}
+		Ok(())
+	}
 }
 
 
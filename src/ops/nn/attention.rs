@@ -0,0 +1,689 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use init::Initialiser;
+use dtype::DType;
+use ops::{standard_op_name, standard_inner_parameter_name, standard_inner_node_name, Op, OpInstance, Pass};
+use ops::nn::linear::Linear;
+use ops::math::batch_matmul::BatchMatMul;
+use shape::{NodeShape, NodeDim};
+use ndarray::{ArrayViewMutD, Dimension};
+use rayon::prelude::*;
+use std::any::Any;
+use std::f32;
+
+/// Multi-head scaled dot-product attention, including the query, key, value and output projections.
+///
+/// For an input of shape [batch, queries, d_in] and a context of shape [batch, keys, d_ctx]
+/// the output has shape [batch, queries, d_out].
+/// The input and context are projected to `heads` sets of queries, keys, and values, each of length `head_dim`.
+/// For each head, each query attends to the keys with weights `softmax(q.k/sqrt(head_dim))`, and the weighted sum of the values is taken.
+/// The concatenated results of all heads are then projected to the output.
+/// The projections are inner `BatchMatMul` ops, with the weights broadcast over the batch.
+///
+/// The attention itself is calculated by a single fused pass, which does not store the attention weights.
+/// Instead they are recalculated one query at a time during the backward pass.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct MultiHeadAttention {
+	input_id: NodeID,
+	output_id: NodeID,
+	context_id: Option<NodeID>,
+	key_mask_id: Option<NodeID>,
+	heads: usize,
+	head_dim: Option<usize>,
+	causal: bool,
+	name: Option<String>,
+	initialiser: Option<Initialiser>,
+}
+
+impl MultiHeadAttention {
+	/// Constructs a new `MultiHeadAttention` Op.
+	///
+	/// The innermost dimension of the input must be Known.
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		MultiHeadAttention {
+			input_id: input.clone(),
+			output_id: output.clone(),
+			context_id: None,
+			key_mask_id: None,
+			heads: 1,
+			head_dim: None,
+			causal: false,
+			name: None,
+			initialiser: None,
+		}
+	}
+
+	/// Provide a node from which the keys and values are calculated, of shape [batch, keys, d_ctx].
+	///
+	/// If left as `None` the input is used, i.e. self-attention.
+	///
+	/// Default value: `None`
+	pub fn context(mut self, node_id: Option<&NodeID>) -> Self {
+		self.context_id = node_id.cloned();
+		self
+	}
+
+	/// Provide a node of shape [batch, keys] which is non-zero (or `true`) for keys which can be attended to.
+	///
+	/// The key mask must have the element type `F32` or `Bool`. Queries for which all keys are masked produce zeros.
+	///
+	/// Default value: `None`
+	pub fn key_mask(mut self, node_id: Option<&NodeID>) -> Self {
+		self.key_mask_id = node_id.cloned();
+		self
+	}
+
+	/// The number of attention heads
+	///
+	/// Default value: 1
+	pub fn heads(mut self, heads: usize) -> Self {
+		self.heads = heads;
+		self
+	}
+
+	/// The length of the queries, keys, and values of each head
+	///
+	/// If not set, this will be the innermost dimension of the input divided by the number of heads.
+	pub fn head_dim(mut self, head_dim: usize) -> Self {
+		self.head_dim = Some(head_dim);
+		self
+	}
+
+	/// If true, each query can only attend to keys at the same or earlier positions.
+	///
+	/// If there are more keys than queries, the last query is aligned with the last key.
+	///
+	/// Default value: false
+	pub fn causal(mut self, causal: bool) -> Self {
+		self.causal = causal;
+		self
+	}
+
+	/// Provide an Initialiser for the four projection weights
+	///
+	/// If no initialiser is set, `Linear::xavier()` is used.
+	pub fn init(mut self, initialiser: Initialiser) -> Self {
+		self.initialiser = Some(initialiser);
+		self
+	}
+}
+
+impl Op for MultiHeadAttention {
+	type InstanceType = MultiHeadAttentionInstance;
+
+	fn type_name(&self) -> &'static str {
+		"MultiHeadAttention"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let context_id = self.context_id.clone().unwrap_or_else(|| self.input_id.clone());
+
+		let mut inputs = vec![self.input_id.clone()];
+		if let Some(ref context_id) = self.context_id {inputs.push(context_id.clone())}
+		if let Some(ref key_mask_id) = self.key_mask_id {inputs.push(key_mask_id.clone())}
+		let name = standard_op_name(&self, &self.name, graph, &inputs, &[self.output_id.clone()]);
+
+		let innermost = |node_id: &NodeID| match node_id.shape().dimensions().last() {
+			Some(&NodeDim::Known(dim)) => Ok(dim),
+			_ => Err(ErrorKind::ShapePropagationError(name.clone(), format!("the innermost dimension of {} must be Known", node_id.name()))),
+		};
+
+		ensure!(self.heads > 0, ErrorKind::ShapePropagationError(name.clone(), "heads must be greater than 0".to_string()));
+		let d_in = innermost(&self.input_id)?;
+		let d_ctx = innermost(&context_id)?;
+		let d_out = innermost(&self.output_id).unwrap_or(d_in);
+		let head_dim = match self.head_dim {
+			Some(head_dim) => head_dim,
+			None => {
+				ensure!(d_in % self.heads == 0, ErrorKind::ShapePropagationError(name.clone(), format!("head_dim must be set if the input dimension ({}) is not divisible by the number of heads ({})", d_in, self.heads)));
+				d_in / self.heads
+			},
+		};
+		let hidden = self.heads * head_dim;
+
+		let new_weights = |rows: usize, cols: usize, graph: &mut GraphDef| -> Result<NodeID> {
+			let weights_name = standard_inner_parameter_name(&name, graph);
+			let weights = graph.new_node(shape![rows, cols], weights_name, tag![Parameter])?;
+			graph.set_initialiser(&weights, self.initialiser.clone().unwrap_or_else(|| Linear::xavier()));
+			Ok(weights)
+		};
+		let query_weights = new_weights(d_in, hidden, graph)?;
+		let key_weights = new_weights(d_ctx, hidden, graph)?;
+		let value_weights = new_weights(d_ctx, hidden, graph)?;
+		let output_weights = new_weights(hidden, d_out, graph)?;
+
+		let new_hidden = |graph: &mut GraphDef| -> Result<NodeID> {
+			let node_name = standard_inner_node_name(&name, graph);
+			graph.new_node(shape![Unknown, Unknown, hidden], node_name, tag![])
+		};
+		let query_id = new_hidden(graph)?;
+		let key_id = new_hidden(graph)?;
+		let value_id = new_hidden(graph)?;
+		let attention_id = new_hidden(graph)?;
+
+		let matmul_ids = vec![
+			graph.new_op(BatchMatMul::new(&self.input_id, &query_weights, &query_id), tag![])?,
+			graph.new_op(BatchMatMul::new(&context_id, &key_weights, &key_id), tag![])?,
+			graph.new_op(BatchMatMul::new(&context_id, &value_weights, &value_id), tag![])?,
+			graph.new_op(BatchMatMul::new(&attention_id, &output_weights, &self.output_id), tag![])?,
+		];
+
+		let key_mask_id = self.key_mask_id.as_ref().map(|id| id.value_id());
+
+		Ok(MultiHeadAttentionInstance{
+			name: name,
+			input_id: self.input_id.clone(),
+			context_id: self.context_id.clone(),
+			key_mask_id: self.key_mask_id.clone(),
+			output_id: self.output_id.clone(),
+			hidden: hidden,
+			d_out: d_out,
+			weights: vec![query_weights, key_weights, value_weights, output_weights],
+			hidden_nodes: vec![query_id.clone(), key_id.clone(), value_id.clone(), attention_id.clone()],
+			matmul_ids: matmul_ids,
+			forward_id: graph.add_pass(AttentionForward::new(
+				query_id.value_id(),
+				key_id.value_id(),
+				value_id.value_id(),
+				key_mask_id.clone(),
+				attention_id.value_id(),
+				self.heads,
+				self.causal,
+			)),
+			backward_id: graph.add_pass(AttentionBackward::new(
+				query_id.clone(),
+				key_id.clone(),
+				value_id.clone(),
+				key_mask_id,
+				attention_id.clone(),
+				self.heads,
+				self.causal,
+			)),
+		})
+	}
+}
+
+
+/// MultiHeadAttention Op
+#[derive(Clone, Debug)]
+pub struct MultiHeadAttentionInstance{
+	name: String,
+	input_id: NodeID,
+	context_id: Option<NodeID>,
+	key_mask_id: Option<NodeID>,
+	output_id: NodeID,
+	hidden: usize,
+	d_out: usize,
+	weights: Vec<NodeID>,
+	hidden_nodes: Vec<NodeID>,
+	matmul_ids: Vec<OpID>,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl MultiHeadAttentionInstance {
+	/// Returns the query, key, value, and output projection weights
+	pub fn weights(&self) -> &[NodeID] {
+		&self.weights
+	}
+}
+
+impl OpInstance for MultiHeadAttentionInstance {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		let mut inputs = vec![self.input_id.clone()];
+		if let Some(ref context_id) = self.context_id {inputs.push(context_id.clone())}
+		if let Some(ref key_mask_id) = self.key_mask_id {inputs.push(key_mask_id.clone())}
+		// the attention output is only given a shape by this op, so it is listed as an output to always be propagated
+		(inputs, vec![self.output_id.clone(), self.hidden_nodes[3].clone()])
+	}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{self.matmul_ids.clone()}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{
+		self.weights.iter().chain(&self.hidden_nodes).cloned().collect()
+	}
+
+	fn check_dtypes(&self) -> Result<()> {
+		for node_id in self.context_id.iter().chain(Some(&self.input_id)).chain(Some(&self.output_id)) {
+			ensure!(node_id.dtype() == DType::F32, ErrorKind::DataTypeMismatch(node_id.value_id().name(), DType::F32, node_id.dtype()));
+		}
+		if let Some(ref key_mask_id) = self.key_mask_id {
+			ensure!(key_mask_id.dtype() == DType::F32 || key_mask_id.dtype() == DType::Bool, ErrorKind::DataTypeMismatch(key_mask_id.value_id().name(), DType::Bool, key_mask_id.dtype()));
+		}
+		Ok(())
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+		let context_shape = match self.context_id {
+			Some(ref context_id) => shapes.get_shape(context_id).to_data_shape()?,
+			None => input_shape.clone(),
+		};
+		ensure!(input_shape.ndim() == 3 && context_shape.ndim() == 3 && input_shape[0] == context_shape[0],
+			ErrorKind::ShapePropagationError(self.name.clone(), format!("input shape {:?} and context shape {:?} must both be [batch, length, features] with the same batch size", input_shape.slice(), context_shape.slice())));
+
+		let (batch, queries, keys) = (input_shape[0], input_shape[1], context_shape[1]);
+		let query_shape: NodeShape = shape![batch, queries, self.hidden];
+		let key_shape: NodeShape = shape![batch, keys, self.hidden];
+
+		shapes.merge_with(&self.hidden_nodes[0], &query_shape)?;
+		shapes.merge_with(&self.hidden_nodes[1], &key_shape)?;
+		shapes.merge_with(&self.hidden_nodes[2], &key_shape)?;
+		shapes.merge_with(&self.hidden_nodes[3], &query_shape)?;
+		shapes.merge_with(&self.output_id, &shape![batch, queries, self.d_out])
+	}
+}
+
+/// Reads the key mask as one `bool` per key, checking that it has `batch * keys` elements
+fn read_key_mask(pass_name: String, data: &Storage, key_mask_id: &Option<DataID>, batch: usize, keys: usize) -> Result<Option<Vec<bool>>> {
+	let key_mask: Vec<bool> = match *key_mask_id {
+		None => return Ok(None),
+		Some(ref id) if id.dtype() == DType::Bool => data.get::<bool>(id)?.iter().cloned().collect(),
+		Some(ref id) => data.get::<f32>(id)?.iter().map(|&x| x != 0.0).collect(),
+	};
+	ensure!(key_mask.len() == batch * keys, ErrorKind::PassError(pass_name, format!("key mask has {} elements, but the batch size is {} and the number of keys is {}", key_mask.len(), batch, keys)));
+	Ok(Some(key_mask))
+}
+
+/// Returns the data if it is required by the subgraph, otherwise `None`
+fn get_required_mut<'a>(data: &'a Storage, data_id: &DataID) -> Result<Option<ArrayViewMutD<'a, f32>>> {
+	if data.is_required(data_id) {
+		data.get_mut::<f32>(data_id).map(Some)
+	} else {
+		Ok(None)
+	}
+}
+
+/// Splits optional data into one chunk per batch element, so that the batch can be processed in parallel
+fn batch_chunks<'a>(data: &'a mut Option<ArrayViewMutD<f32>>, chunk_len: usize, batch: usize) -> Vec<Option<&'a mut [f32]>> {
+	match *data {
+		Some(ref mut data) => data.as_slice_mut().unwrap().chunks_mut(chunk_len).map(Some).collect(),
+		None => (0..batch).map(|_| None).collect(),
+	}
+}
+
+/// Shapes and settings shared by the forward and backward attention passes
+#[derive(Clone, Copy, Debug)]
+struct AttentionDims {
+	queries: usize,
+	keys: usize,
+	heads: usize,
+	head_dim: usize,
+	causal: bool,
+}
+
+impl AttentionDims {
+	fn new(pass_name: String, query_shape: &[usize], key_shape: &[usize], value_shape: &[usize], heads: usize, causal: bool) -> Result<AttentionDims> {
+		ensure!(query_shape.len() == 3 && key_shape == value_shape && key_shape.len() == 3 && query_shape[0] == key_shape[0] && query_shape[2] == key_shape[2] && query_shape[2] % heads == 0,
+			ErrorKind::PassError(pass_name, format!("query shape: {:?}, key shape: {:?}, and value shape: {:?} are not compatible for {} heads", query_shape, key_shape, value_shape, heads)));
+		Ok(AttentionDims {
+			queries: query_shape[1],
+			keys: key_shape[1],
+			heads: heads,
+			head_dim: query_shape[2] / heads,
+			causal: causal,
+		})
+	}
+
+	/// Calculates the attention weights of query `i` for head `h` into `weights`, returning false if no keys can be attended to.
+	///
+	/// `query` is all queries of one batch element and `key` is all keys of the same batch element.
+	fn attention_weights(&self, query: &[f32], key: &[f32], key_mask: Option<&[bool]>, h: usize, i: usize, weights: &mut [f32]) -> bool {
+		let hidden = self.heads * self.head_dim;
+		let scale = 1.0 / (self.head_dim as f32).sqrt();
+		let q = &query[i * hidden + h * self.head_dim..][..self.head_dim];
+
+		let mut max = f32::NEG_INFINITY;
+		for (j, weight) in weights.iter_mut().enumerate() {
+			let masked = key_mask.map(|mask| !mask[j]).unwrap_or(false)
+				|| (self.causal && j as isize > i as isize + self.keys as isize - self.queries as isize);
+			*weight = if masked {
+				f32::NEG_INFINITY
+			} else {
+				let k = &key[j * hidden + h * self.head_dim..][..self.head_dim];
+				q.iter().zip(k).fold(0.0, |sum, (q, k)| sum + q * k) * scale
+			};
+			max = max.max(*weight);
+		}
+
+		if max == f32::NEG_INFINITY {
+			return false;
+		}
+
+		let mut sum = 0.0;
+		for weight in weights.iter_mut() {
+			*weight = (*weight - max).exp();
+			sum += *weight;
+		}
+		for weight in weights.iter_mut() {
+			*weight /= sum;
+		}
+		true
+	}
+}
+
+
+/// Adds the attention result for each query and head to the output, parallelised over the batch
+#[derive(Clone, Debug)]
+pub struct AttentionForward {
+	query_id: DataID,
+	key_id: DataID,
+	value_id: DataID,
+	key_mask_id: Option<DataID>,
+	output_id: DataID,
+	heads: usize,
+	causal: bool,
+}
+
+impl AttentionForward {
+	pub fn new(query_id: DataID, key_id: DataID, value_id: DataID, key_mask_id: Option<DataID>, output_id: DataID, heads: usize, causal: bool) -> Self {
+		AttentionForward {
+			query_id,
+			key_id,
+			value_id,
+			key_mask_id,
+			output_id,
+			heads,
+			causal,
+		}
+	}
+}
+
+impl Pass for AttentionForward {
+	fn type_name(&self) -> &'static str {"AttentionForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		let mut inputs = vec![self.query_id.clone(), self.key_id.clone(), self.value_id.clone()];
+		inputs.extend(self.key_mask_id.clone());
+		(inputs, vec![self.output_id.clone()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let query = data.get::<f32>(&self.query_id)?;
+		let key = data.get::<f32>(&self.key_id)?;
+		let value = data.get::<f32>(&self.value_id)?;
+		let mut output = data.get_mut::<f32>(&self.output_id)?;
+
+		let dims = AttentionDims::new(self.name(), query.shape(), key.shape(), value.shape(), self.heads, self.causal)?;
+		ensure!(output.shape() == query.shape(), ErrorKind::PassError(self.name(), format!("output shape: {:?} does not match query shape: {:?}", output.shape(), query.shape())));
+		let batch = query.shape()[0];
+		let key_mask = read_key_mask(self.name(), data, &self.key_mask_id, batch, dims.keys)?;
+
+		let hidden = dims.heads * dims.head_dim;
+		let (query_len, key_len) = (dims.queries * hidden, dims.keys * hidden);
+		if batch == 0 || query_len == 0 {
+			return Ok(Box::new(()));
+		}
+
+		let query = query.as_slice().unwrap();
+		let key = key.as_slice().unwrap();
+		let value = value.as_slice().unwrap();
+
+		output.as_slice_mut().unwrap().par_chunks_mut(query_len).enumerate().for_each(|(b, output)| {
+			let query = &query[b * query_len..][..query_len];
+			let key = &key[b * key_len..][..key_len];
+			let value = &value[b * key_len..][..key_len];
+			let key_mask = key_mask.as_ref().map(|mask| &mask[b * dims.keys..][..dims.keys]);
+
+			let mut weights = vec![0.0; dims.keys];
+			for h in 0..dims.heads {
+				for i in 0..dims.queries {
+					if !dims.attention_weights(query, key, key_mask, h, i, &mut weights) {
+						continue;
+					}
+					let out = &mut output[i * hidden + h * dims.head_dim..][..dims.head_dim];
+					for (j, &weight) in weights.iter().enumerate() {
+						let v = &value[j * hidden + h * dims.head_dim..][..dims.head_dim];
+						for (out, v) in out.iter_mut().zip(v) {
+							*out += weight * v;
+						}
+					}
+				}
+			}
+		});
+
+		Ok(Box::new(()))
+	}
+}
+
+
+/// Adds the query, key, and value gradients, recalculating the attention weights from the queries and keys
+#[derive(Clone, Debug)]
+pub struct AttentionBackward {
+	query_id: NodeID,
+	key_id: NodeID,
+	value_id: NodeID,
+	key_mask_id: Option<DataID>,
+	output_id: NodeID,
+	heads: usize,
+	causal: bool,
+}
+
+impl AttentionBackward {
+	pub fn new(query_id: NodeID, key_id: NodeID, value_id: NodeID, key_mask_id: Option<DataID>, output_id: NodeID, heads: usize, causal: bool) -> Self {
+		AttentionBackward {
+			query_id,
+			key_id,
+			value_id,
+			key_mask_id,
+			output_id,
+			heads,
+			causal,
+		}
+	}
+}
+
+impl Pass for AttentionBackward {
+	fn type_name(&self) -> &'static str {"AttentionBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		let mut inputs = vec![self.query_id.value_id(), self.key_id.value_id(), self.value_id.value_id(), self.output_id.gradient_id()];
+		inputs.extend(self.key_mask_id.clone());
+		(inputs, vec![self.query_id.gradient_id(), self.key_id.gradient_id(), self.value_id.gradient_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let query = data.get::<f32>(&self.query_id.value_id())?;
+		let key = data.get::<f32>(&self.key_id.value_id())?;
+		let value = data.get::<f32>(&self.value_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let mut query_grad = get_required_mut(data, &self.query_id.gradient_id())?;
+		let mut key_grad = get_required_mut(data, &self.key_id.gradient_id())?;
+		let mut value_grad = get_required_mut(data, &self.value_id.gradient_id())?;
+		let score_grad_required = query_grad.is_some() || key_grad.is_some();
+
+		let dims = AttentionDims::new(self.name(), query.shape(), key.shape(), value.shape(), self.heads, self.causal)?;
+		ensure!(output_grad.shape() == query.shape(), ErrorKind::PassError(self.name(), format!("output shape: {:?} does not match query shape: {:?}", output_grad.shape(), query.shape())));
+		let batch = query.shape()[0];
+		let key_mask = read_key_mask(self.name(), data, &self.key_mask_id, batch, dims.keys)?;
+
+		let hidden = dims.heads * dims.head_dim;
+		let scale = 1.0 / (dims.head_dim as f32).sqrt();
+		let (query_len, key_len) = (dims.queries * hidden, dims.keys * hidden);
+		if batch == 0 || query_len == 0 || key_len == 0 {
+			return Ok(Box::new(()));
+		}
+
+		let query = query.as_slice().unwrap();
+		let key = key.as_slice().unwrap();
+		let value = value.as_slice().unwrap();
+		let output_grad = output_grad.as_slice().unwrap();
+
+		batch_chunks(&mut query_grad, query_len, batch).into_par_iter()
+		.zip(batch_chunks(&mut key_grad, key_len, batch))
+		.zip(batch_chunks(&mut value_grad, key_len, batch))
+		.enumerate().for_each(|(b, ((mut query_grad, mut key_grad), mut value_grad))| {
+			let query = &query[b * query_len..][..query_len];
+			let key = &key[b * key_len..][..key_len];
+			let value = &value[b * key_len..][..key_len];
+			let output_grad = &output_grad[b * query_len..][..query_len];
+			let key_mask = key_mask.as_ref().map(|mask| &mask[b * dims.keys..][..dims.keys]);
+
+			let mut weights = vec![0.0; dims.keys];
+			let mut weight_grads = vec![0.0; dims.keys];
+			for h in 0..dims.heads {
+				for i in 0..dims.queries {
+					if !dims.attention_weights(query, key, key_mask, h, i, &mut weights) {
+						continue;
+					}
+					let row = i * hidden + h * dims.head_dim;
+					let q = &query[row..][..dims.head_dim];
+					let out_grad = &output_grad[row..][..dims.head_dim];
+
+					// gradient of the weighted sum of values
+					let mut weighted_sum = 0.0;
+					for (j, (&weight, weight_grad)) in weights.iter().zip(weight_grads.iter_mut()).enumerate() {
+						let col = j * hidden + h * dims.head_dim;
+						let v = &value[col..][..dims.head_dim];
+						*weight_grad = out_grad.iter().zip(v).fold(0.0, |sum, (g, v)| sum + g * v);
+						weighted_sum += weight * *weight_grad;
+
+						if let Some(ref mut value_grad) = value_grad {
+							for (v_grad, g) in value_grad[col..][..dims.head_dim].iter_mut().zip(out_grad) {
+								*v_grad += weight * g;
+							}
+						}
+					}
+					if !score_grad_required {
+						continue;
+					}
+
+					// gradient of the softmax and scaled dot product
+					for (j, (&weight, &weight_grad)) in weights.iter().zip(&weight_grads).enumerate() {
+						let score_grad = weight * (weight_grad - weighted_sum) * scale;
+						if score_grad == 0.0 {
+							continue;
+						}
+						let col = j * hidden + h * dims.head_dim;
+						let k = &key[col..][..dims.head_dim];
+						if let Some(ref mut query_grad) = query_grad {
+							for (q_grad, k) in query_grad[row..][..dims.head_dim].iter_mut().zip(k) {
+								*q_grad += score_grad * k;
+							}
+						}
+						if let Some(ref mut key_grad) = key_grad {
+							for (k_grad, q) in key_grad[col..][..dims.head_dim].iter_mut().zip(q) {
+								*k_grad += score_grad * q;
+							}
+						}
+					}
+				}
+			}
+		});
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_self_attention_causal_backprop(){
+	_self_attention_causal_backprop().unwrap();
+}
+
+fn _self_attention_causal_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 5, 6], "input", tag![])?;
+	let node2 = g.new_node(shape![Unknown, Unknown, 6], "output", tag![])?;
+	let node3 = g.new_node(shape![2, 5, 6], "target", tag![])?;
+
+	let o1 = g.new_op(MultiHeadAttention::new(&node1, &node2).heads(2).causal(true), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	assert_eq!(o1.instance().inner_nodes().len(), 8);
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_cross_attention_key_mask_backprop(){
+	_cross_attention_key_mask_backprop().unwrap();
+}
+
+fn _cross_attention_key_mask_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 3, 4], "input", tag![])?;
+	let node2 = g.new_node(shape![2, 6, 5], "context", tag![])?;
+	let node3 = g.new_typed_node(shape![2, 6], DType::Bool, "key_mask", tag![])?;
+	let node4 = g.new_node(shape![Unknown, Unknown, 7], "output", tag![])?;
+	let node5 = g.new_node(shape![2, 3, 7], "target", tag![])?;
+
+	let _o1 = g.new_op(MultiHeadAttention::new(&node1, &node4).context(Some(&node2)).key_mask(Some(&node3)).heads(3).head_dim(2), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node4, &node5), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![node3.clone() => Box::new(|| thread_rng().gen_range(0, 2) as f64) as Box<FnMut()->f64>];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+#[test]
+fn test_attention_known_output(){
+	_attention_known_output().unwrap();
+}
+
+fn _attention_known_output() -> Result<()>{
+	use graph::GraphDef;
+	use ops::loss::mse::Mse;
+	use ndarray::ArrayD;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 3, 4], "input", tag![])?;
+	let node2 = g.new_node(shape![2, 3, 4], "output", tag![])?;
+	let node3 = g.new_node(shape![2, 3, 4], "target", tag![])?;
+
+	let o1 = g.new_op(MultiHeadAttention::new(&node1, &node2).heads(2), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let mut subgraph = g.default_subgraph()?;
+	let inputs = subgraph.inputs().iter().map(|data_id| ArrayD::from_elem(data_id.node_id().shape().to_data_shape().unwrap(), 0.5)).collect();
+	subgraph.execute(inputs)?;
+
+	// only the value projection requires gradients when only its weights gradient is requested
+	let input_ids: Vec<DataID> = subgraph.inputs().to_vec();
+	let value_weights = o1.instance().inner_nodes()[2].clone();
+	let mut subgraph = g.subgraph(&input_ids, &[value_weights.gradient_id()])?;
+	let inputs = input_ids.iter().map(|data_id| ArrayD::from_elem(data_id.node_id().shape().to_data_shape().unwrap(), 0.5)).collect();
+	let storage = subgraph.execute(inputs)?;
+	assert!(!storage.is_required(&o1.instance().inner_nodes()[0].gradient_id()));
+	assert!(storage.get::<f32>(&value_weights.gradient_id())?.iter().any(|&x| x != 0.0));
+
+	Ok(())
+}
@@ -0,0 +1,649 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use init::Initialiser;
+use ops::{standard_op_name, standard_inner_parameter_name, Op, OpInstance, Pass};
+use shape::NodeDim;
+use ndarray::{ArrayD, ArrayViewD, Zip};
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+use smallvec::SmallVec;
+
+/// Batch Normalisation
+///
+/// Normalises the input to zero mean and unit variance over the reduction axes, then applies a learnable scale and shift.
+/// During training the statistics of the current batch are used, and an exponential moving average of the statistics is maintained.
+/// During inference the running statistics are used in place of the batch statistics.
+/// The mode is determined by the executing `Subgraph`, see `Subgraph::training()`.
+///
+/// The statistics, scale and shift have the shape of the input with the reduction axes set to 1.
+/// Axes which are not of Known size are always included in the reduction.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct BatchNorm {
+	name: Option<String>,
+	input_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	scale_id: Option<NodeID>,
+	shift_id: Option<NodeID>,
+	scale_initialiser: Option<Initialiser>,
+	shift_initialiser: Option<Initialiser>,
+	epsilon: f32,
+	momentum: f32,
+}
+
+impl BatchNorm {
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self {
+		BatchNorm {
+			name: None,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+			axes: SmallVec::new(),
+			scale_id: None,
+			shift_id: None,
+			scale_initialiser: None,
+			shift_initialiser: None,
+			epsilon: 1e-5,
+			momentum: 0.9,
+		}
+	}
+
+	/// Supply which axes the statistics are calculated over.
+	///
+	/// If axes is empty, all axes except the innermost (channel) axis are reduced.
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	///
+	/// Default: empty
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// Provide a node to act as the scale
+	///
+	/// If left as `None` a suitable `Parameter` node will be automatically created, initialised to 1.0.
+	///
+	/// Default value: `None`
+	pub fn scale(mut self, node_id: Option<&NodeID>) -> Self {
+		self.scale_id = node_id.cloned();
+		self
+	}
+
+	/// Provide a node to act as the shift
+	///
+	/// If left as `None` a suitable `Parameter` node will be automatically created, initialised to 0.0.
+	///
+	/// Default value: `None`
+	pub fn shift(mut self, node_id: Option<&NodeID>) -> Self {
+		self.shift_id = node_id.cloned();
+		self
+	}
+
+	/// Provide an Initialiser for the scale node
+	pub fn scale_init(mut self, initialiser: Initialiser) -> Self {
+		self.scale_initialiser = Some(initialiser);
+		self
+	}
+
+	/// Provide an Initialiser for the shift node
+	pub fn shift_init(mut self, initialiser: Initialiser) -> Self {
+		self.shift_initialiser = Some(initialiser);
+		self
+	}
+
+	/// A small value added to the variance before normalising, for numerical stability.
+	///
+	/// Default: 1e-5
+	pub fn epsilon(mut self, epsilon: f32) -> Self {
+		self.epsilon = epsilon;
+		self
+	}
+
+	/// The fraction of the running statistics retained at each training step.
+	///
+	/// The running statistics are updated as `running = momentum * running + (1 - momentum) * batch`.
+	///
+	/// Default: 0.9
+	pub fn momentum(mut self, momentum: f32) -> Self {
+		self.momentum = momentum;
+		self
+	}
+}
+
+impl Op for BatchNorm {
+	type InstanceType = BatchNormInstance;
+
+	fn type_name(&self) -> &'static str {
+		"BatchNorm"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let mut inputs = vec![self.input_id.clone()];
+		inputs.extend(self.scale_id.iter().cloned());
+		inputs.extend(self.shift_id.iter().cloned());
+		let name = standard_op_name(&self, &self.name, graph, &inputs, &[self.output_id.clone()]);
+
+		let stats_shape = {
+			let input_shape = self.input_id.shape();
+			let reduce = reduction_mask(input_shape.ndim(), &self.axes);
+			let mut stats_shape = vec![1; input_shape.ndim()];
+			for axis in 0..input_shape.ndim() {
+				if let NodeDim::Known(dim) = input_shape.dimensions()[axis] {
+					if !reduce[axis] {
+						stats_shape[axis] = dim;
+					}
+				}
+			}
+			stats_shape
+		};
+
+		let scale_is_inner = self.scale_id.is_none();
+		let scale_id = if let Some(scale_id) = self.scale_id {
+			scale_id
+		} else {
+			let scale_name = standard_inner_parameter_name(&name, graph);
+			let scale_id = graph.new_node(stats_shape.clone().into(), scale_name, tag![Parameter])?;
+			graph.set_initialiser(&scale_id, Initialiser::fill(1.0));
+			scale_id
+		};
+
+		let shift_is_inner = self.shift_id.is_none();
+		let shift_id = if let Some(shift_id) = self.shift_id {
+			shift_id
+		} else {
+			let shift_name = standard_inner_parameter_name(&name, graph);
+			graph.new_node(stats_shape.clone().into(), shift_name, tag![Parameter])?
+		};
+
+		if let Some(initialiser) = self.scale_initialiser {
+			graph.set_initialiser(&scale_id, initialiser);
+		}
+		if let Some(initialiser) = self.shift_initialiser {
+			graph.set_initialiser(&shift_id, initialiser);
+		}
+
+		let running = Arc::new(Mutex::new(RunningStatistics{
+			mean: ArrayD::zeros(stats_shape.clone()),
+			variance: ArrayD::from_elem(stats_shape, 1.0),
+		}));
+
+		Ok(BatchNormInstance{
+			name: name,
+			input_id: self.input_id.clone(),
+			output_id: self.output_id.clone(),
+			scale_id: scale_id.clone(),
+			shift_id: shift_id.clone(),
+			scale_is_inner: scale_is_inner,
+			shift_is_inner: shift_is_inner,
+			running: running.clone(),
+			forward_id: graph.add_pass(BatchNormForward::new(
+				self.input_id.clone(),
+				scale_id.clone(),
+				shift_id.clone(),
+				self.output_id.clone(),
+				running.clone(),
+				self.epsilon,
+				self.momentum,
+			)),
+			backward_id: graph.add_pass(BatchNormBackward::new(
+				self.input_id.clone(),
+				scale_id.clone(),
+				shift_id.clone(),
+				self.output_id.clone(),
+				running.clone(),
+				self.epsilon,
+			)),
+		})
+	}
+}
+
+/// The running mean and variance of a `BatchNorm` Op.
+///
+/// These are updated during each execution of a subgraph in training mode, and persist across executions.
+#[derive(Clone, Debug)]
+pub struct RunningStatistics {
+	pub mean: ArrayD<f32>,
+	pub variance: ArrayD<f32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BatchNormInstance {
+	name: String,
+	input_id: NodeID,
+	output_id: NodeID,
+	scale_id: NodeID,
+	shift_id: NodeID,
+	scale_is_inner: bool,
+	shift_is_inner: bool,
+	running: Arc<Mutex<RunningStatistics>>,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl BatchNormInstance {
+	/// Returns a copy of the current running statistics, e.g. for checkpointing.
+	pub fn running_statistics(&self) -> RunningStatistics {
+		self.running.lock().expect("Could not acquire lock on running statistics").clone()
+	}
+
+	/// Replaces the running statistics, e.g. when restoring from a checkpoint.
+	///
+	/// The shapes of the supplied mean and variance must match the existing statistics.
+	pub fn set_running_statistics(&self, statistics: RunningStatistics) -> Result<()> {
+		let mut running = self.running.lock().expect("Could not acquire lock on running statistics");
+		ensure!(statistics.mean.shape() == running.mean.shape() && statistics.variance.shape() == running.variance.shape(),
+			"Running statistics shapes (mean: {:?}, variance: {:?}) did not match the expected shape: {:?}", statistics.mean.shape(), statistics.variance.shape(), running.mean.shape());
+		*running = statistics;
+		Ok(())
+	}
+}
+
+impl OpInstance for BatchNormInstance {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		let mut inputs = vec![self.input_id.clone()];
+		if !self.scale_is_inner {
+			inputs.push(self.scale_id.clone());
+		}
+		if !self.shift_is_inner {
+			inputs.push(self.shift_id.clone());
+		}
+		(inputs, vec![self.output_id.clone()])
+	}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{
+		let mut nodes = vec![];
+		if self.scale_is_inner {
+			nodes.push(self.scale_id.clone());
+		}
+		if self.shift_is_inner {
+			nodes.push(self.shift_id.clone());
+		}
+		nodes
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).clone();
+		shapes.merge_with(&self.output_id, &input_shape)
+	}
+}
+
+/// Returns a mask indicating whether an axis should be reduced based on the axes list
+/// If axes is empty this returns true for all but the innermost axis,
+/// else only the axis provided are marked true.
+fn reduction_mask(len: usize, axes: &[isize]) -> SmallVec<[bool; 6]> {
+	let mut reduce = SmallVec::with_capacity(len);
+	if axes.len() == 0 {
+		for i in 0..len {
+			reduce.push(i + 1 < len);
+		}
+	} else {
+		for _ in 0..len {
+			reduce.push(false);
+		}
+		for axis in axes {
+			reduce[(axis + len as isize) as usize % len] = true;
+		}
+	}
+	reduce
+}
+
+/// Returns the mean and biased variance of each element of the stats shape over all chunks of the input
+fn batch_statistics(input: &ArrayViewD<f32>, stats_shape: &[usize]) -> (ArrayD<f32>, ArrayD<f32>) {
+	let n = (input.len()/stats_shape.iter().product::<usize>()) as f32;
+
+	let mut mean = ArrayD::zeros(stats_shape);
+	for in_chunk in input.exact_chunks(stats_shape) {
+		mean.scaled_add(1.0/n, &in_chunk);
+	}
+
+	let mut variance = ArrayD::zeros(stats_shape);
+	for in_chunk in input.exact_chunks(stats_shape) {
+		Zip::from(&mut variance).and(&in_chunk).and(&mean).apply(|variance, &x, &mean|{
+			*variance += (x - mean)*(x - mean)/n;
+		});
+	}
+
+	(mean, variance)
+}
+
+fn check_shapes(pass_name: String, input_shape: &[usize], stats_shape: &[usize], scale_shape: &[usize], shift_shape: &[usize]) -> Result<()> {
+	ensure!(
+		input_shape.len() == stats_shape.len() && input_shape.iter().zip(stats_shape).all(|(&i, &s)| s == 1 || s == i),
+		ErrorKind::PassError(pass_name.clone(), format!("input shape: {:?} is not compatible with statistics shape: {:?}", input_shape, stats_shape))
+	);
+	ensure!(
+		scale_shape == stats_shape && shift_shape == stats_shape,
+		ErrorKind::PassError(pass_name, format!("scale shape: {:?} and shift shape: {:?} must match statistics shape: {:?}", scale_shape, shift_shape, stats_shape))
+	);
+	Ok(())
+}
+
+#[derive(Clone, Debug)]
+pub struct BatchNormForward {
+	input_id: NodeID,
+	scale_id: NodeID,
+	shift_id: NodeID,
+	output_id: NodeID,
+	running: Arc<Mutex<RunningStatistics>>,
+	epsilon: f32,
+	momentum: f32,
+}
+
+impl BatchNormForward {
+	pub fn new(input_id: NodeID, scale_id: NodeID, shift_id: NodeID, output_id: NodeID, running: Arc<Mutex<RunningStatistics>>, epsilon: f32, momentum: f32) -> Self {
+		BatchNormForward {
+			input_id,
+			scale_id,
+			shift_id,
+			output_id,
+			running,
+			epsilon,
+			momentum,
+		}
+	}
+}
+
+impl Pass for BatchNormForward {
+	fn type_name(&self) -> &'static str {"BatchNormForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id(), self.scale_id.value_id(), self.shift_id.value_id()],
+		vec![self.output_id.value_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>> {
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let scale = data.get::<f32>(&self.scale_id.value_id())?;
+		let shift = data.get::<f32>(&self.shift_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		ensure!(
+			input.shape() == output.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input.shape(), output.shape()))
+		);
+
+		let mut running = self.running.lock().expect("Could not acquire lock on running statistics");
+		let stats_shape = running.mean.shape().to_vec();
+		check_shapes(self.name(), input.shape(), &stats_shape, scale.shape(), shift.shape())?;
+
+		let (mean, variance) = if data.is_training() {
+			let (mean, variance) = batch_statistics(&input, &stats_shape);
+
+			let n = input.len()/mean.len();
+			let correction = if n > 1 {n as f32/(n - 1) as f32} else {1.0};
+			let momentum = self.momentum;
+			let RunningStatistics{mean: ref mut running_mean, variance: ref mut running_variance} = *running;
+			Zip::from(running_mean).and(running_variance).and(&mean).and(&variance).apply(|running_mean, running_variance, &mean, &variance|{
+				*running_mean = momentum * *running_mean + (1.0 - momentum) * mean;
+				*running_variance = momentum * *running_variance + (1.0 - momentum) * variance * correction;
+			});
+
+			(mean, variance)
+		} else {
+			(running.mean.clone(), running.variance.clone())
+		};
+
+		let epsilon = self.epsilon;
+		let inv_std = variance.mapv(|v| 1.0/(v + epsilon).sqrt());
+
+		for (in_chunk, out_chunk) in input.exact_chunks(stats_shape.as_slice()).into_iter().zip(output.exact_chunks_mut(stats_shape.as_slice())) {
+			Zip::from(out_chunk)
+				.and(&in_chunk)
+				.and(&mean)
+				.and(&inv_std)
+				.and(&scale)
+				.and(&shift)
+				.apply(|output, &x, &mean, &inv_std, &scale, &shift|{
+					*output += (x - mean) * inv_std * scale + shift;
+				});
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct BatchNormBackward {
+	input_id: NodeID,
+	scale_id: NodeID,
+	shift_id: NodeID,
+	output_id: NodeID,
+	running: Arc<Mutex<RunningStatistics>>,
+	epsilon: f32,
+}
+
+impl BatchNormBackward {
+	pub fn new(input_id: NodeID, scale_id: NodeID, shift_id: NodeID, output_id: NodeID, running: Arc<Mutex<RunningStatistics>>, epsilon: f32) -> Self {
+		BatchNormBackward {
+			input_id,
+			scale_id,
+			shift_id,
+			output_id,
+			running,
+			epsilon,
+		}
+	}
+}
+
+impl Pass for BatchNormBackward {
+	fn type_name(&self) -> &'static str {"BatchNormBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id(), self.scale_id.value_id(), self.shift_id.value_id(), self.output_id.gradient_id()],
+		vec![self.input_id.gradient_id(), self.scale_id.gradient_id(), self.shift_id.gradient_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>> {
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let scale = data.get::<f32>(&self.scale_id.value_id())?;
+		let shift = data.get::<f32>(&self.shift_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+
+		ensure!(
+			input.shape() == output_grad.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input.shape(), output_grad.shape()))
+		);
+
+		// Batch statistics are recalculated rather than retrieved from the forward pass, which may not have been run.
+		let (stats_shape, mean, variance) = {
+			let running = self.running.lock().expect("Could not acquire lock on running statistics");
+			let stats_shape = running.mean.shape().to_vec();
+			check_shapes(self.name(), input.shape(), &stats_shape, scale.shape(), shift.shape())?;
+			if data.is_training() {
+				let (mean, variance) = batch_statistics(&input, &stats_shape);
+				(stats_shape, mean, variance)
+			} else {
+				(stats_shape, running.mean.clone(), running.variance.clone())
+			}
+		};
+
+		let epsilon = self.epsilon;
+		let inv_std = variance.mapv(|v| 1.0/(v + epsilon).sqrt());
+
+		// sums over the reduction axes of the output gradient, and of the output gradient times the normalised input
+		let mut sum_grad = ArrayD::zeros(stats_shape.as_slice());
+		let mut sum_grad_norm = ArrayD::zeros(stats_shape.as_slice());
+		for (in_chunk, out_grad_chunk) in input.exact_chunks(stats_shape.as_slice()).into_iter().zip(output_grad.exact_chunks(stats_shape.as_slice())) {
+			Zip::from(&mut sum_grad)
+				.and(&mut sum_grad_norm)
+				.and(&in_chunk)
+				.and(&out_grad_chunk)
+				.and(&mean)
+				.and(&inv_std)
+				.apply(|sum_grad, sum_grad_norm, &x, &output_grad, &mean, &inv_std|{
+					*sum_grad += output_grad;
+					*sum_grad_norm += output_grad * (x - mean) * inv_std;
+				});
+		}
+
+		if data.is_required(&self.input_id.gradient_id()) {
+			let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+
+			if data.is_training() {
+				// dx = scale * inv_std * (dy - mean(dy) - x_norm * mean(dy * x_norm)), rearranged into a * dy + b * x + c
+				let n = (input.len()/mean.len()) as f32;
+				let mut a: ArrayD<f32> = ArrayD::zeros(stats_shape.as_slice());
+				let mut b: ArrayD<f32> = ArrayD::zeros(stats_shape.as_slice());
+				let mut c: ArrayD<f32> = ArrayD::zeros(stats_shape.as_slice());
+				Zip::from(&mut a)
+					.and(&mut b)
+					.and(&inv_std)
+					.and(&scale)
+					.and(&sum_grad_norm)
+					.apply(|a, b, &inv_std, &scale, &sum_grad_norm|{
+						*a = scale * inv_std;
+						*b = -*a * inv_std * sum_grad_norm/n;
+					});
+				Zip::from(&mut c)
+					.and(&a)
+					.and(&b)
+					.and(&mean)
+					.and(&sum_grad)
+					.apply(|c, &a, &b, &mean, &sum_grad|{
+						*c = -b * mean - a * sum_grad/n;
+					});
+
+				for ((in_chunk, out_grad_chunk), in_grad_chunk) in input.exact_chunks(stats_shape.as_slice()).into_iter().zip(output_grad.exact_chunks(stats_shape.as_slice())).zip(input_grad.exact_chunks_mut(stats_shape.as_slice())) {
+					Zip::from(in_grad_chunk)
+						.and(&in_chunk)
+						.and(&out_grad_chunk)
+						.and(&a)
+						.and(&b)
+						.and(&c)
+						.apply(|input_grad, &x, &output_grad, &a, &b, &c|{
+							*input_grad += a * output_grad + b * x + c;
+						});
+				}
+			} else {
+				for (out_grad_chunk, in_grad_chunk) in output_grad.exact_chunks(stats_shape.as_slice()).into_iter().zip(input_grad.exact_chunks_mut(stats_shape.as_slice())) {
+					Zip::from(in_grad_chunk)
+						.and(&out_grad_chunk)
+						.and(&inv_std)
+						.and(&scale)
+						.apply(|input_grad, &output_grad, &inv_std, &scale|{
+							*input_grad += scale * inv_std * output_grad;
+						});
+				}
+			}
+		}
+
+		if data.is_required(&self.scale_id.gradient_id()) {
+			let mut scale_grad = data.get_mut::<f32>(&self.scale_id.gradient_id())?;
+			scale_grad += &sum_grad_norm;
+		}
+
+		if data.is_required(&self.shift_id.gradient_id()) {
+			let mut shift_grad = data.get_mut::<f32>(&self.shift_id.gradient_id())?;
+			shift_grad += &sum_grad;
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_batch_norm_backprop(){
+	_batch_norm_backprop().unwrap();
+}
+
+fn _batch_norm_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 16], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 5, 16], "target", tag![])?;
+
+	let _o1 = g.new_op(BatchNorm::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(BatchNorm::new(&node1, &node2).axes(&[0, -1]), tag![])?;
+	let _o3 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_batch_norm_running_statistics(){
+	_batch_norm_running_statistics().unwrap();
+}
+
+fn _batch_norm_running_statistics() -> Result<()>{
+	use graph::GraphDef;
+	use ndarray::ArrayD;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![Unknown, 3], "input", tag![])?;
+	let node2 = g.new_node(shape![Unknown, 3], "output", tag![])?;
+
+	let o1 = g.new_op(BatchNorm::new(&node1, &node2).momentum(0.0), tag![])?;
+	let params = g.initialise_nodes(&o1.instance().inner_nodes())?;
+
+	let input = ArrayD::from_shape_vec(vec![4, 3], vec![
+		0.0, 1.0, -2.0,
+		2.0, 1.0, -2.0,
+		4.0, 1.0, 2.0,
+		6.0, 1.0, 2.0,
+	]).unwrap();
+
+	let inputs = [node1.value_id()].iter().chain(o1.instance().inner_nodes().iter().map(|node_id| node_id.value_id()).collect::<Vec<_>>().iter()).cloned().collect::<Vec<_>>();
+
+	let mut subgraph = g.subgraph(&inputs, &[node2.value_id()])?;
+	assert!(!subgraph.is_training());
+
+	// Running statistics are updated in training mode
+	subgraph.training(true);
+	subgraph.execute(vec![input.clone(), params[0].clone(), params[1].clone()])?;
+
+	let instance = o1.instance().as_any().downcast_ref::<BatchNormInstance>().unwrap();
+	let statistics = instance.running_statistics();
+	assert_eq!(statistics.mean.as_slice().unwrap(), &[3.0, 1.0, 0.0]);
+	for (&x, &e) in statistics.variance.iter().zip(&[20.0/3.0, 0.0, 16.0/3.0]) {
+		assert!((x - e).abs() < 1e-5, "variance: {:?}", statistics.variance);
+	}
+
+	// and then used in inference mode
+	subgraph.training(false);
+	instance.set_running_statistics(RunningStatistics{
+		mean: ArrayD::from_shape_vec(vec![1, 3], vec![1.0, 0.0, -1.0]).unwrap(),
+		variance: ArrayD::from_shape_vec(vec![1, 3], vec![4.0, 1.0, 0.25]).unwrap(),
+	})?;
+	let storage = subgraph.execute(vec![input.clone(), params[0].clone(), params[1].clone()])?;
+	let output = storage.get::<f32>(&node2.value_id())?;
+
+	let expected = [
+		-0.5, 1.0, -2.0,
+		0.5, 1.0, -2.0,
+		1.5, 1.0, 6.0,
+		2.5, 1.0, 6.0,
+	];
+	for (&x, &e) in output.iter().zip(expected.iter()) {
+		assert!((x - e).abs() < 1e-3, "output: {:?} expected: {:?}", output, expected);
+	}
+
+	Ok(())
+}
@@ -16,6 +16,7 @@ use unchecked_index as ui;
 use num_cpus;
 use matrixmultiply;
 use init::Initialiser;
+use ops::nn::reparameterisation::{reparameterise, Reparameterisation, ReparameterisedWeights};
 use rand::{thread_rng, Isaac64Rng, SeedableRng};
 use rand::distributions::{Distribution, Normal};
 use smallvec::SmallVec;
@@ -65,6 +66,7 @@ pub struct Conv {
 	output_id: NodeID,
 	filter_id: Option<NodeID>,
 	initialiser: Option<Initialiser>,
+	reparameterisation: Reparameterisation,
 	lowering_memory: usize,
 }
 
@@ -78,6 +80,7 @@ impl Conv {
 			output_id: output_id.clone(),
 			filter_id: None,
 			initialiser: None,
+			reparameterisation: Reparameterisation::None,
 			lowering_memory: 1024*384,
 		}
 	}
@@ -106,6 +109,16 @@ impl Conv {
 		self
 	}
 
+	/// Reparameterise the filter using weight or spectral normalisation
+	///
+	/// Weight normalisation creates a magnitude for each output channel.
+	///
+	/// Default value: `Reparameterisation::None`
+	pub fn reparameterisation(mut self, reparameterisation: Reparameterisation) -> Self {
+		self.reparameterisation = reparameterisation;
+		self
+	}
+
 
 	/// MSRA/He initialisation
 	///
@@ -181,10 +194,14 @@ impl Op for Conv {
 		};
 
 
+		let reparameterised = reparameterise(graph, &name, &filter, &self.reparameterisation, 0, self.initialiser.as_ref())?;
+
 		if let Some(initialiser) = self.initialiser {
 			graph.set_initialiser(&filter, initialiser);
 		};
 
+		let effective_filter = reparameterised.as_ref().map_or(filter.clone(), |r| r.weights_id.clone());
+
 		Ok(ConvInstance{
 			name: name,
 			padding: self.padding,
@@ -193,17 +210,18 @@ impl Op for Conv {
 			output_id: self.output_id.clone(),
 			filter_id: filter.clone(),
 			filter_is_inner: filter_is_inner,
+			reparameterised: reparameterised,
 			forward_id: graph.add_pass(ConvForward::new(
 				self.input_id.clone(),
 				self.output_id.clone(),
-				filter.clone(),
+				effective_filter.clone(),
 				self.lowering_memory,
 				//self.kernel_shape.clone(),
 			)),
 			backward_id: graph.add_pass(ConvBackward::new(
 				self.input_id.clone(),
 				self.output_id.clone(),
-				filter.clone(),
+				effective_filter.clone(),
 				self.lowering_memory,
 				//self.kernel_shape.clone(),
 			)),
@@ -220,6 +238,7 @@ pub struct ConvInstance {
 	output_id: NodeID,
 	filter_id: NodeID,
 	filter_is_inner: bool,
+	reparameterised: Option<ReparameterisedWeights>,
 	forward_id: PassID,
 	backward_id: PassID,
 }
@@ -242,14 +261,20 @@ impl OpInstance for ConvInstance {
 		vec![self.forward_id.clone(), self.backward_id.clone()]
 	}
 
-	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+	fn inner_ops(&self) -> Vec<OpID> {
+		self.reparameterised.iter().map(|r| r.op_id.clone()).collect()
+	}
 
 	fn inner_nodes(&self) -> Vec<NodeID> {
-		if self.filter_is_inner {
+		let mut nodes = if self.filter_is_inner {
 			vec![self.filter_id.clone()]
 		} else {
 			vec![]
+		};
+		if let Some(ref reparameterised) = self.reparameterised {
+			nodes.extend(reparameterised.inner_nodes.iter().cloned());
 		}
+		nodes
 	}
 
 	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
@@ -338,9 +363,9 @@ impl Pass for ConvForward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>> {
-		let input = data.get(&self.input_id.value_id())?;
-		let filter = data.get(&self.filter_id.value_id())?;
-		let output = data.get_mut(&self.output_id.value_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let filter = data.get::<f32>(&self.filter_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		let n = input.shape()[0]; //TODO use ensure to guard against zero length shapes
 		let in_size: usize = input.shape()[1..].iter().product();
@@ -475,9 +500,9 @@ impl Pass for ConvBackward {
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>> {
 
-		let input = data.get(&self.input_id.value_id())?;
-		let filter = data.get(&self.filter_id.value_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let filter = data.get::<f32>(&self.filter_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 
 		let n = input.shape()[0]; //TODO use ensure to guard against zero length shapes
 		let _in_size: usize = input.shape()[1..].iter().product();
@@ -509,7 +534,7 @@ impl Pass for ConvBackward {
 		let output_grad = output_grad.as_slice().unwrap();
 
 		let input_grad = if data.is_required(&self.input_id.gradient_id()) {
-			Some(data.get_mut(&self.input_id.gradient_id())?)
+			Some(data.get_mut::<f32>(&self.input_id.gradient_id())?)
 		} else {
 			None
 		};
@@ -651,7 +676,7 @@ impl Pass for ConvBackward {
 
 		// Write accumulated gradients back to the original (non-ROT180) format
 		if require_filter_gradients {
-			let mut filter_grad = data.get_mut(&self.filter_id.gradient_id())?;
+			let mut filter_grad = data.get_mut::<f32>(&self.filter_id.gradient_id())?;
 
 			let mut inverted_filter_grad_actual = filter_grad.view_mut();
 			inverted_filter_grad_actual.swap_axes(0, filter.ndim()-1);
@@ -1103,6 +1128,44 @@ fn _conv_backprop() -> Result<()>{
 	Ok(())
 }
 
+#[test]
+fn test_conv_weight_norm_backprop(){
+	_conv_reparameterisation_backprop(Reparameterisation::WeightNorm).unwrap();
+}
+
+#[test]
+fn test_conv_spectral_norm_backprop(){
+	_conv_reparameterisation_backprop(Reparameterisation::SpectralNorm(200)).unwrap();
+}
+
+fn _conv_reparameterisation_backprop(reparameterisation: Reparameterisation) -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 5, 7, 4], "input", tag![])?;
+	let node2 = g.new_node(shape![Unknown, Unknown, Unknown, 6], "conv", tag![])?;
+	let node3 = g.new_node(shape![3, 5, 7, 6], "target", tag![])?;
+
+	let o1 = g.new_op(Conv::new(&node1, &node2, &[3, 3]).init(Conv::msra(1.0)).reparameterisation(reparameterisation), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let init_values = g.initialise_nodes(&o1.instance().inner_nodes())?;
+	assert!(init_values.len() >= 2);
+	assert_ne!(init_values[0].scalar_sum(), 0.0);
+
+	let iters = 50;
+	let failures = 2;
+	let tolerance = 0.01;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
 
 #[test]
 fn test_kernel_shuffles(){
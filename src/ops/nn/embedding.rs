@@ -0,0 +1,376 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use init::Initialiser;
+use dtype::DType;
+use ops::{standard_op_name, standard_inner_parameter_name, Op, OpInstance, Pass};
+use ops::index::read_indices;
+use shape::{NodeShape, NodeDim};
+use ndarray::{ArrayViewMutD, Axis};
+use std::any::Any;
+
+/// A lookup table of learnable vectors, indexed by an integer node.
+///
+/// For an indices node of shape [..] and a weights node of shape [vocab, dim], the output has shape [.., dim],
+/// where each output vector is the row of the weights selected by the corresponding index.
+/// This is equivalent to `Linear` applied to one-hot vectors, without materialising them.
+///
+/// The backwards pass only writes the gradients of the rows which were looked up,
+/// and these are marked using `Storage::get_mut_sparse_rows()` so that optimisers can skip untouched rows.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Embedding {
+	indices_id: NodeID,
+	output_id: NodeID,
+	weights_id: Option<NodeID>,
+	vocab_size: Option<usize>,
+	dim: Option<usize>,
+	name: Option<String>,
+	initialiser: Option<Initialiser>,
+}
+
+impl Embedding {
+	/// Constructs a new `Embedding` Op.
+	///
+	/// The indices node must have the element type `I64` or `U32`.
+	pub fn new(indices: &NodeID, output: &NodeID) -> Self {
+		Embedding {
+			indices_id: indices.clone(),
+			output_id: output.clone(),
+			weights_id: None,
+			vocab_size: None,
+			dim: None,
+			name: None,
+			initialiser: None,
+		}
+	}
+
+	/// The number of rows in the weights, and the exclusive upper bound of the indices.
+	///
+	/// This must be set if the weights are not supplied.
+	pub fn vocab_size(mut self, vocab_size: usize) -> Self {
+		self.vocab_size = Some(vocab_size);
+		self
+	}
+
+	/// The length of each embedding vector
+	///
+	/// If not set, this will be inferred from the innermost dimension of the output, which must then be Known.
+	pub fn dim(mut self, dim: usize) -> Self {
+		self.dim = Some(dim);
+		self
+	}
+
+	/// Provide a node to replace the weights matrix of shape [vocab, dim]
+	///
+	/// If left as `None` a suitable `Parameter` node will be automatically created.
+	///
+	/// Default value: `None`
+	pub fn weights(mut self, node_id: Option<&NodeID>) -> Self {
+		self.weights_id = node_id.cloned();
+		self
+	}
+
+	/// Provide an Initialiser for the weights node
+	///
+	/// If the weights are created by this op and no initialiser is set, `Initialiser::gaussian(0.0, 1.0)` is used.
+	pub fn init(mut self, initialiser: Initialiser) -> Self {
+		self.initialiser = Some(initialiser);
+		self
+	}
+}
+
+impl Op for Embedding {
+	type InstanceType = EmbeddingInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Embedding"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+
+		let (name, weights_are_inner) = if let Some(ref weights) = self.weights_id {
+			(standard_op_name(&self, &self.name, graph, &[self.indices_id.clone(), weights.clone()], &[self.output_id.clone()]), false)
+		} else {
+			(standard_op_name(&self, &self.name, graph, &[self.indices_id.clone()], &[self.output_id.clone()]), true)
+		};
+
+		let weights = if let Some(weights) = self.weights_id {
+			weights
+		} else {
+			let vocab_size = match self.vocab_size {
+				Some(vocab_size) => vocab_size,
+				None => bail!(ErrorKind::ShapePropagationError(name, "vocab_size must be set if weights are not supplied".to_string())),
+			};
+			let dim = match (self.dim, self.output_id.shape().dimensions().last()) {
+				(Some(dim), _) => dim,
+				(None, Some(&NodeDim::Known(dim))) => dim,
+				_ => bail!(ErrorKind::ShapePropagationError(name, "dim must be set if the innermost dimension of the output is not Known".to_string())),
+			};
+
+			let weights_name = standard_inner_parameter_name(&name, graph);
+			let weights = graph.new_node(shape![vocab_size, dim], weights_name, tag![Parameter])?;
+			if self.initialiser.is_none() {
+				graph.set_initialiser(&weights, Initialiser::gaussian(0.0, 1.0));
+			}
+			weights
+		};
+
+		if let Some(initialiser) = self.initialiser {
+			graph.set_initialiser(&weights, initialiser);
+		}
+
+		Ok(EmbeddingInstance{
+			name: name,
+			indices_id: self.indices_id.clone(),
+			weights_id: weights.clone(),
+			output_id: self.output_id.clone(),
+			weights_are_inner: weights_are_inner,
+			forward_id: graph.add_pass(EmbeddingForward::new(
+				self.indices_id.clone(),
+				weights.clone(),
+				self.output_id.clone(),
+			)),
+			backward_id: graph.add_pass(EmbeddingBackward::new(
+				self.indices_id.clone(),
+				weights.clone(),
+				self.output_id.clone(),
+			)),
+		})
+	}
+}
+
+
+/// Embedding Op
+#[derive(Clone, Debug)]
+pub struct EmbeddingInstance{
+	name: String,
+	indices_id: NodeID,
+	weights_id: NodeID,
+	output_id: NodeID,
+	weights_are_inner: bool,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl EmbeddingInstance {
+	/// Returns the weights node, of shape [vocab, dim]
+	pub fn weights(&self) -> &NodeID {
+		&self.weights_id
+	}
+}
+
+impl OpInstance for EmbeddingInstance {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			if self.weights_are_inner {
+				vec![self.indices_id.clone()]
+			} else {
+				vec![self.indices_id.clone(), self.weights_id.clone()]
+			},
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{
+		if self.weights_are_inner {
+			vec![self.weights_id.clone()]
+		} else {
+			vec![]
+		}
+	}
+
+	fn check_dtypes(&self) -> Result<()> {
+		ensure!(matches!(self.indices_id.dtype(), DType::I64 | DType::U32), ErrorKind::DataTypeMismatch(self.indices_id.value_id().name(), DType::I64, self.indices_id.dtype()));
+		ensure!(self.weights_id.dtype() == DType::F32, ErrorKind::DataTypeMismatch(self.weights_id.value_id().name(), DType::F32, self.weights_id.dtype()));
+		ensure!(self.output_id.dtype() == DType::F32, ErrorKind::DataTypeMismatch(self.output_id.value_id().name(), DType::F32, self.output_id.dtype()));
+		Ok(())
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let indices_shape = shapes.get_shape(&self.indices_id).clone();
+		let weights_shape = shapes.get_shape(&self.weights_id).clone();
+		ensure!(weights_shape.ndims() == 2, ErrorKind::ShapePropagationError(self.name.clone(), format!("weights must have 2 dimensions, found shape: {:?}", weights_shape)));
+
+		let output_shape: NodeShape = indices_shape.dimensions().iter().chain(&weights_shape.dimensions()[1..]).cloned().into();
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct EmbeddingForward {
+	indices_id: NodeID,
+	weights_id: NodeID,
+	output_id: NodeID,
+}
+
+impl EmbeddingForward {
+	pub fn new(indices_id: NodeID, weights_id: NodeID, output_id: NodeID) -> Self {
+		EmbeddingForward {
+			indices_id,
+			weights_id,
+			output_id,
+		}
+	}
+}
+
+impl Pass for EmbeddingForward {
+	fn type_name(&self) -> &'static str {"EmbeddingForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.indices_id.value_id(), self.weights_id.value_id()],
+			vec![self.output_id.value_id()]
+		)
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let weights = data.get::<f32>(&self.weights_id.value_id())?;
+		let dim = weights.shape()[1];
+		let indices = read_indices(self.name(), data, &self.indices_id.value_id(), weights.shape()[0])?;
+
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+		ensure!(output.len() == indices.len() * dim, ErrorKind::PassError(self.name(), format!("output shape: {:?} does not match indices shape: {:?} and embedding dim: {}", output.shape(), self.indices_id.shape(), dim)));
+
+		let mut output = output.view_mut().into_shape(&[indices.len(), dim][..]).expect("output was checked to be contiguous with matching size");
+		for (mut out_row, &index) in output.outer_iter_mut().zip(&indices) {
+			out_row += &weights.subview(Axis(0), index);
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct EmbeddingBackward {
+	indices_id: NodeID,
+	weights_id: NodeID,
+	output_id: NodeID,
+}
+
+impl EmbeddingBackward {
+	pub fn new(indices_id: NodeID, weights_id: NodeID, output_id: NodeID) -> Self {
+		EmbeddingBackward {
+			indices_id,
+			weights_id,
+			output_id,
+		}
+	}
+}
+
+impl Pass for EmbeddingBackward {
+	fn type_name(&self) -> &'static str {"EmbeddingBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.indices_id.value_id(), self.weights_id.value_id(), self.output_id.gradient_id()],
+			vec![self.weights_id.gradient_id()]
+		)
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		if !data.is_required(&self.weights_id.gradient_id()) {
+			return Ok(Box::new(()));
+		}
+
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let (vocab_size, dim) = {
+			let weights = data.get::<f32>(&self.weights_id.value_id())?;
+			(weights.shape()[0], weights.shape()[1])
+		};
+		let indices = read_indices(self.name(), data, &self.indices_id.value_id(), vocab_size)?;
+		ensure!(output_grad.len() == indices.len() * dim, ErrorKind::PassError(self.name(), format!("output gradient shape: {:?} does not match indices shape: {:?} and embedding dim: {}", output_grad.shape(), self.indices_id.shape(), dim)));
+		let output_grad = output_grad.into_shape(&[indices.len(), dim][..]).expect("output gradient was checked to be contiguous with matching size");
+
+		let mut weights_grad: ArrayViewMutD<f32> = data.get_mut_sparse_rows(&self.weights_id.gradient_id(), &indices)?;
+		for (out_grad_row, &index) in output_grad.outer_iter().zip(&indices) {
+			let mut weights_grad_row = weights_grad.subview_mut(Axis(0), index);
+			weights_grad_row += &out_grad_row;
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_embedding_backprop(){
+	_embedding_backprop().unwrap();
+}
+
+fn _embedding_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_typed_node(shape![7, 3], DType::I64, "indices", tag![])?;
+	let node2 = g.new_node(shape![7, 3, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 3, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(Embedding::new(&node1, &node2).vocab_size(11), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![node1.clone() => Box::new(|| thread_rng().gen_range(0, 11) as f64) as Box<FnMut()->f64>];
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+
+	Ok(())
+}
+
+#[test]
+fn test_embedding_sparse_rows(){
+	_embedding_sparse_rows().unwrap();
+}
+
+fn _embedding_sparse_rows() -> Result<()>{
+	use graph::GraphDef;
+	use ops::loss::proportional::Proportional;
+	use ndarray::{ArrayD, IxDyn};
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_typed_node(shape![Unknown], DType::U32, "indices", tag![])?;
+	let node2 = g.new_node(shape![Unknown, 4], "output", tag![])?;
+
+	let o1 = g.new_op(Embedding::new(&node1, &node2).vocab_size(10), tag![])?;
+	let _o2 = g.new_op(Proportional::new(&node2), tag![])?;
+
+	let weights = o1.instance().inner_nodes()[0].clone();
+	let mut subgraph = g.subgraph(&[node1.value_id(), weights.value_id()], &[weights.gradient_id()])?;
+
+	let weights_val = g.initialise_nodes(&[weights.clone()])?.remove(0);
+	let storage = subgraph.execute_typed(vec![ArrayD::from_shape_vec(IxDyn(&[3]), vec![7u32, 2, 7]).unwrap().into(), weights_val.into()])?;
+
+	assert_eq!(storage.sparse_rows(&weights.gradient_id()), Some(vec![2, 7]));
+
+	let weights_grad = storage.get::<f32>(&weights.gradient_id())?;
+	let n = 12.0; // proportional loss averages over all output elements
+	assert!((weights_grad[[7, 0]]*n - 2.0).abs() < 1e-5);
+	assert!((weights_grad[[2, 0]]*n - 1.0).abs() < 1e-5);
+	assert_eq!(weights_grad[[0, 0]], 0.0);
+
+	let storage = subgraph.execute_typed(vec![ArrayD::from_shape_vec(IxDyn(&[1]), vec![10u32]).unwrap().into(), g.initialise_nodes(&[weights.clone()])?.remove(0).into()]);
+	assert!(storage.is_err());
+
+	Ok(())
+}
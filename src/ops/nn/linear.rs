@@ -4,9 +4,11 @@ use init::Initialiser;
 use ops::{standard_op_name, standard_inner_parameter_name, Op, OpInstance};
 use shape::{NodeShape, NodeDim};
 use ops::math::matmul::{MatMul, MatMulInstance};
+use ops::nn::reparameterisation::{reparameterise, Reparameterisation, ReparameterisedWeights};
 use rand::{thread_rng, Isaac64Rng, SeedableRng};
 use rand::distributions::{Distribution, Normal};
 use ndarray::ArrayViewMutD;
+use std::iter;
 
 /// The Linear portion of a fully connected layer
 ///
@@ -23,6 +25,7 @@ pub struct Linear {
 	n: Option<usize>,
 	name: Option<String>,
 	initialiser: Option<Initialiser>,
+	reparameterisation: Reparameterisation,
 }
 
 impl Linear {
@@ -36,6 +39,7 @@ impl Linear {
 			n: None,
 			name: None,
 			initialiser: None,
+			reparameterisation: Reparameterisation::None,
 		}
 	}
 
@@ -74,6 +78,15 @@ impl Linear {
 		self
 	}
 
+	/// Reparameterise the weights matrix, B, using weight or spectral normalisation
+	///
+	/// Weight normalisation creates a magnitude for each of the n output columns.
+	///
+	/// Default value: `Reparameterisation::None`
+	pub fn reparameterisation(mut self, reparameterisation: Reparameterisation) -> Self {
+		self.reparameterisation = reparameterisation;
+		self
+	}
 
 	/// MSRA/He initialisation
 	///
@@ -151,11 +164,14 @@ impl Op for Linear {
 			graph.new_node(shape![k.unwrap(), n.unwrap()], weights_name, tag![Parameter])?
 		};
 
+		let reparameterised = reparameterise(graph, &name, &weights, &self.reparameterisation, -1, self.initialiser.as_ref())?;
+
 		if let Some(initialiser) = self.initialiser {
 			graph.set_initialiser(&weights, initialiser);
 		}
 
-		let mut mat_mul = MatMul::new(&self.input_id.clone(), &weights, &self.output_id.clone());
+		let effective_weights = reparameterised.as_ref().map_or(&weights, |r| &r.weights_id);
+		let mut mat_mul = MatMul::new(&self.input_id.clone(), effective_weights, &self.output_id.clone());
 		if let Some(n) = self.n {mat_mul = mat_mul.n(n)}
 		if let Some(k) = self.k {mat_mul = mat_mul.k(k)}
 		let matmul_id = graph.new_op(mat_mul, tag![])?;
@@ -168,6 +184,7 @@ impl Op for Linear {
 			weights_id: weights,
 			weights_are_inner: weights_are_inner,
 			matmul_id: matmul_id,
+			reparameterised: reparameterised,
 		})
 	}
 }
@@ -182,6 +199,7 @@ pub struct LinearInstance{
 	weights_id: NodeID,
 	weights_are_inner: bool,
 	matmul_id: OpID,
+	reparameterised: Option<ReparameterisedWeights>,
 }
 
 impl OpInstance for LinearInstance {
@@ -201,14 +219,20 @@ impl OpInstance for LinearInstance {
 
 	fn inner_passes(&self) -> Vec<PassID>{vec![]}
 
-	fn inner_ops(&self) -> Vec<OpID>{vec![self.matmul_id.clone()]}
+	fn inner_ops(&self) -> Vec<OpID>{
+		iter::once(self.matmul_id.clone()).chain(self.reparameterised.iter().map(|r| r.op_id.clone())).collect()
+	}
 
 	fn inner_nodes(&self) -> Vec<NodeID>{
-		if self.weights_are_inner {
+		let mut nodes = if self.weights_are_inner {
 			vec![self.weights_id.clone()]
 		} else {
 			vec![]
+		};
+		if let Some(ref reparameterised) = self.reparameterised {
+			nodes.extend(reparameterised.inner_nodes.iter().cloned());
 		}
+		nodes
 	}
 
 	fn propagate_shape_constraints(&self, _shapes: &mut GraphShapes) -> Result<()>{Ok(())}
@@ -248,5 +272,43 @@ fn _linear_init_backprop() -> Result<()>{
 	let default_variance = 1.0;
 	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
 
+	Ok(())
+}
+
+#[test]
+fn test_linear_weight_norm_backprop(){
+	_linear_reparameterisation_backprop(Reparameterisation::WeightNorm).unwrap();
+}
+
+#[test]
+fn test_linear_spectral_norm_backprop(){
+	_linear_reparameterisation_backprop(Reparameterisation::SpectralNorm(200)).unwrap();
+}
+
+fn _linear_reparameterisation_backprop(reparameterisation: Reparameterisation) -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5], "input1", tag![])?;
+	let node2 = g.new_node(shape![7, 6], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 6], "target", tag![])?;
+
+	let o1 = g.new_op(Linear::new(&node1, &node2).init(Linear::msra(1.0)).reparameterisation(reparameterisation), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let init_values = g.initialise_nodes(&o1.instance().inner_nodes())?;
+	assert!(init_values.len() >= 2);
+	assert_ne!(init_values[0].scalar_sum(), 0.0);
+
+	let iters = 100;
+	let failures = 2;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
 	Ok(())
 }
\ No newline at end of file
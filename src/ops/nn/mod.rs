@@ -1,3 +1,10 @@
 pub mod bias;
 pub mod linear;
-pub mod conv;
\ No newline at end of file
+pub mod conv;
+pub mod batch_norm;
+pub mod embedding;
+pub mod attention;
+pub mod recurrent;
+pub mod weight_norm;
+pub mod spectral_norm;
+pub mod reparameterisation;
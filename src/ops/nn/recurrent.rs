@@ -0,0 +1,824 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use init::Initialiser;
+use ops::{standard_op_name, standard_inner_parameter_name, Op, OpInstance, Pass};
+use ops::nn::linear::Linear;
+use shape::{NodeShape, NodeDim};
+use ndarray::{Array2, Array3, ArrayViewD, ArrayViewMutD, ArrayView2, Axis, Ix2, Dimension};
+use ndarray::linalg::general_mat_mul;
+use std::any::Any;
+
+/// The type of recurrent cell, which determines the gates and state update of each time step
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CellType {
+	/// Long short-term memory, with input, forget, cell and output gates, in that order.
+	Lstm,
+	/// Gated recurrent unit, with reset, update and new gates, in that order.
+	///
+	/// The reset gate is applied after the hidden state is multiplied by the weights, i.e. `n = tanh(x W_n + b_n + r * (h U_n))`.
+	Gru,
+}
+
+impl CellType {
+	/// The number of gates, each of which is `hidden_size` wide in the weights and bias
+	fn gates(&self) -> usize {
+		match *self {
+			CellType::Lstm => 4,
+			CellType::Gru => 3,
+		}
+	}
+}
+
+fn sigmoid(x: f32) -> f32 {
+	1.0 / (1.0 + (-x).exp())
+}
+
+/// Long short-term memory recurrent layer
+///
+/// Consumes an input of shape [batch, time, features] and produces the hidden state for each time step, of shape [batch, time, hidden].
+/// The time loop runs inside a single forward pass, and backpropagation through time inside a single backward pass.
+///
+/// Creates three `Parameter` nodes: input weights [features, 4 * hidden], hidden weights [hidden, 4 * hidden], and bias [4 * hidden],
+/// with the gates in the order input, forget, cell, output.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Lstm {
+	builder: RecurrentBuilder,
+}
+
+impl Lstm {
+	/// Constructs a new `Lstm` Op.
+	///
+	/// The innermost dimension of the input must be Known.
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		Lstm {
+			builder: RecurrentBuilder::new(CellType::Lstm, input, output),
+		}
+	}
+
+	/// The length of the hidden and cell state
+	///
+	/// If not set, this will be inferred from the innermost dimension of the output, which must then be Known.
+	pub fn hidden_size(mut self, hidden_size: usize) -> Self {
+		self.builder.hidden_size = Some(hidden_size);
+		self
+	}
+
+	/// Provide a node of shape [batch, hidden] for the hidden state before the first time step
+	///
+	/// If left as `None` the initial hidden state is zero.
+	///
+	/// Default value: `None`
+	pub fn initial_hidden(mut self, node_id: Option<&NodeID>) -> Self {
+		self.builder.initial_hidden = node_id.cloned();
+		self
+	}
+
+	/// Provide a node of shape [batch, hidden] for the cell state before the first time step
+	///
+	/// If left as `None` the initial cell state is zero.
+	///
+	/// Default value: `None`
+	pub fn initial_cell(mut self, node_id: Option<&NodeID>) -> Self {
+		self.builder.initial_cell = node_id.cloned();
+		self
+	}
+
+	/// Provide a node of shape [batch, hidden] to output the hidden state after the last time step
+	///
+	/// Default value: `None`
+	pub fn final_hidden(mut self, node_id: Option<&NodeID>) -> Self {
+		self.builder.final_hidden = node_id.cloned();
+		self
+	}
+
+	/// Provide a node of shape [batch, hidden] to output the cell state after the last time step
+	///
+	/// Default value: `None`
+	pub fn final_cell(mut self, node_id: Option<&NodeID>) -> Self {
+		self.builder.final_cell = node_id.cloned();
+		self
+	}
+
+	/// Provide an Initialiser for the input and hidden weights
+	///
+	/// If no initialiser is set, `Linear::xavier()` is used.
+	/// The bias is always initialised to zero, except for the forget gate which is initialised to one.
+	pub fn init(mut self, initialiser: Initialiser) -> Self {
+		self.builder.initialiser = Some(initialiser);
+		self
+	}
+}
+
+impl Op for Lstm {
+	type InstanceType = RecurrentInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Lstm"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.builder.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		self.builder.build(&self, graph)
+	}
+}
+
+/// Gated recurrent unit layer
+///
+/// Consumes an input of shape [batch, time, features] and produces the hidden state for each time step, of shape [batch, time, hidden].
+/// The time loop runs inside a single forward pass, and backpropagation through time inside a single backward pass.
+///
+/// Creates three `Parameter` nodes: input weights [features, 3 * hidden], hidden weights [hidden, 3 * hidden], and bias [3 * hidden],
+/// with the gates in the order reset, update, new.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Gru {
+	builder: RecurrentBuilder,
+}
+
+impl Gru {
+	/// Constructs a new `Gru` Op.
+	///
+	/// The innermost dimension of the input must be Known.
+	pub fn new(input: &NodeID, output: &NodeID) -> Self {
+		Gru {
+			builder: RecurrentBuilder::new(CellType::Gru, input, output),
+		}
+	}
+
+	/// The length of the hidden state
+	///
+	/// If not set, this will be inferred from the innermost dimension of the output, which must then be Known.
+	pub fn hidden_size(mut self, hidden_size: usize) -> Self {
+		self.builder.hidden_size = Some(hidden_size);
+		self
+	}
+
+	/// Provide a node of shape [batch, hidden] for the hidden state before the first time step
+	///
+	/// If left as `None` the initial hidden state is zero.
+	///
+	/// Default value: `None`
+	pub fn initial_hidden(mut self, node_id: Option<&NodeID>) -> Self {
+		self.builder.initial_hidden = node_id.cloned();
+		self
+	}
+
+	/// Provide a node of shape [batch, hidden] to output the hidden state after the last time step
+	///
+	/// Default value: `None`
+	pub fn final_hidden(mut self, node_id: Option<&NodeID>) -> Self {
+		self.builder.final_hidden = node_id.cloned();
+		self
+	}
+
+	/// Provide an Initialiser for the input and hidden weights
+	///
+	/// If no initialiser is set, `Linear::xavier()` is used. The bias is always initialised to zero.
+	pub fn init(mut self, initialiser: Initialiser) -> Self {
+		self.builder.initialiser = Some(initialiser);
+		self
+	}
+}
+
+impl Op for Gru {
+	type InstanceType = RecurrentInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Gru"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.builder.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		self.builder.build(&self, graph)
+	}
+}
+
+/// The settings shared by `Lstm` and `Gru`
+#[derive(Clone, Debug)]
+struct RecurrentBuilder {
+	cell: CellType,
+	name: Option<String>,
+	input_id: NodeID,
+	output_id: NodeID,
+	hidden_size: Option<usize>,
+	initial_hidden: Option<NodeID>,
+	initial_cell: Option<NodeID>,
+	final_hidden: Option<NodeID>,
+	final_cell: Option<NodeID>,
+	initialiser: Option<Initialiser>,
+}
+
+impl RecurrentBuilder {
+	fn new(cell: CellType, input: &NodeID, output: &NodeID) -> Self {
+		RecurrentBuilder {
+			cell: cell,
+			name: None,
+			input_id: input.clone(),
+			output_id: output.clone(),
+			hidden_size: None,
+			initial_hidden: None,
+			initial_cell: None,
+			final_hidden: None,
+			final_cell: None,
+			initialiser: None,
+		}
+	}
+
+	fn build<O: Op>(&self, op: &O, graph: &mut GraphDef) -> Result<RecurrentInstance> {
+		let inputs: Vec<NodeID> = Some(&self.input_id).into_iter().chain(&self.initial_hidden).chain(&self.initial_cell).cloned().collect();
+		let outputs: Vec<NodeID> = Some(&self.output_id).into_iter().chain(&self.final_hidden).chain(&self.final_cell).cloned().collect();
+		let name = standard_op_name(op, &self.name, graph, &inputs, &outputs);
+
+		let features = match self.input_id.shape().dimensions().last() {
+			Some(&NodeDim::Known(dim)) => dim,
+			_ => bail!(ErrorKind::ShapePropagationError(name, "the innermost dimension of the input must be Known".to_string())),
+		};
+		let hidden_size = match (self.hidden_size, self.output_id.shape().dimensions().last()) {
+			(Some(hidden_size), _) => hidden_size,
+			(None, Some(&NodeDim::Known(dim))) => dim,
+			_ => bail!(ErrorKind::ShapePropagationError(name, "hidden_size must be set if the innermost dimension of the output is not Known".to_string())),
+		};
+		let width = self.cell.gates() * hidden_size;
+
+		let weights_name = standard_inner_parameter_name(&name, graph);
+		let input_weights = graph.new_node(shape![features, width], weights_name, tag![Parameter])?;
+		let weights_name = standard_inner_parameter_name(&name, graph);
+		let hidden_weights = graph.new_node(shape![hidden_size, width], weights_name, tag![Parameter])?;
+		let weights_name = standard_inner_parameter_name(&name, graph);
+		let bias = graph.new_node(shape![width], weights_name, tag![Parameter])?;
+
+		let initialiser = self.initialiser.clone().unwrap_or_else(|| Linear::xavier());
+		graph.set_initialiser(&input_weights, initialiser.clone());
+		graph.set_initialiser(&hidden_weights, initialiser);
+		if self.cell == CellType::Lstm {
+			graph.set_initialiser(&bias, Initialiser::new("Forget Gate Bias Initialiser for Lstm Op".to_string(), move |mut arr: ArrayViewMutD<f32>, _instance: Option<&OpInstance>|{
+				for (i, e) in arr.iter_mut().enumerate() {
+					*e = if i / hidden_size == 1 {1.0} else {0.0};
+				}
+			}));
+		}
+
+		let nodes = RecurrentNodes {
+			input: self.input_id.clone(),
+			output: self.output_id.clone(),
+			input_weights: input_weights,
+			hidden_weights: hidden_weights,
+			bias: bias,
+			initial_hidden: self.initial_hidden.clone(),
+			initial_cell: if self.cell == CellType::Lstm {self.initial_cell.clone()} else {None},
+			final_hidden: self.final_hidden.clone(),
+			final_cell: if self.cell == CellType::Lstm {self.final_cell.clone()} else {None},
+		};
+
+		let forward_id = graph.add_pass(RecurrentForward::new(self.cell, nodes.clone()));
+		let backward_id = graph.add_pass(RecurrentBackward::new(self.cell, nodes.clone(), forward_id.clone()));
+
+		Ok(RecurrentInstance{
+			name: name,
+			cell: self.cell,
+			hidden_size: hidden_size,
+			nodes: nodes,
+			forward_id: forward_id,
+			backward_id: backward_id,
+		})
+	}
+}
+
+/// The nodes used by the passes of a recurrent layer
+#[derive(Clone, Debug)]
+pub struct RecurrentNodes {
+	input: NodeID,
+	output: NodeID,
+	input_weights: NodeID,
+	hidden_weights: NodeID,
+	bias: NodeID,
+	initial_hidden: Option<NodeID>,
+	initial_cell: Option<NodeID>,
+	final_hidden: Option<NodeID>,
+	final_cell: Option<NodeID>,
+}
+
+impl RecurrentNodes {
+	fn inputs(&self) -> Vec<NodeID> {
+		vec![self.input.clone(), self.input_weights.clone(), self.hidden_weights.clone(), self.bias.clone()].into_iter()
+			.chain(self.initial_hidden.clone())
+			.chain(self.initial_cell.clone())
+			.collect()
+	}
+
+	fn outputs(&self) -> Vec<NodeID> {
+		Some(self.output.clone()).into_iter()
+			.chain(self.final_hidden.clone())
+			.chain(self.final_cell.clone())
+			.collect()
+	}
+}
+
+
+/// The instance of both `Lstm` and `Gru`
+#[derive(Clone, Debug)]
+pub struct RecurrentInstance{
+	name: String,
+	cell: CellType,
+	hidden_size: usize,
+	nodes: RecurrentNodes,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl RecurrentInstance {
+	pub fn cell(&self) -> CellType {
+		self.cell
+	}
+
+	/// Returns the input weights, hidden weights and bias
+	pub fn weights(&self) -> Vec<NodeID> {
+		vec![self.nodes.input_weights.clone(), self.nodes.hidden_weights.clone(), self.nodes.bias.clone()]
+	}
+}
+
+impl OpInstance for RecurrentInstance {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			Some(self.nodes.input.clone()).into_iter()
+				.chain(self.nodes.initial_hidden.clone())
+				.chain(self.nodes.initial_cell.clone())
+				.collect(),
+			self.nodes.outputs()
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{self.weights()}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.nodes.input).to_data_shape()?;
+		ensure!(input_shape.ndim() == 3, ErrorKind::ShapePropagationError(self.name.clone(), format!("input shape {:?} must be [batch, time, features]", input_shape.slice())));
+
+		let state_shape: NodeShape = shape![input_shape[0], self.hidden_size];
+		shapes.merge_with(&self.nodes.output, &shape![input_shape[0], input_shape[1], self.hidden_size])?;
+		for node_id in self.nodes.final_hidden.iter().chain(&self.nodes.final_cell) {
+			shapes.merge_with(node_id, &state_shape)?;
+		}
+		Ok(())
+	}
+}
+
+
+/// The values of each time step, stored by the forward pass for use in the backward pass
+#[derive(Clone, Debug)]
+struct RecurrentPassData {
+	/// The activated gates, [batch, time, gates * hidden]
+	gates: Array3<f32>,
+	/// For Lstm the cell state, for Gru the hidden state multiplied by the new gate hidden weights, [batch, time, hidden]
+	cells: Array3<f32>,
+	/// The hidden state, [batch, time, hidden]
+	hidden: Array3<f32>,
+}
+
+/// Returns the value of an optional [batch, hidden] state node, or zeros
+fn read_state(pass_name: String, data: &Storage, node_id: &Option<NodeID>, batch: usize, hidden_size: usize) -> Result<Array2<f32>> {
+	match *node_id {
+		Some(ref node_id) => {
+			let state = data.get::<f32>(&node_id.value_id())?;
+			ensure!(state.shape() == &[batch, hidden_size], ErrorKind::PassError(pass_name, format!("state shape: {:?} does not match [batch, hidden]: {:?}", state.shape(), [batch, hidden_size])));
+			Ok(state.into_dimensionality::<Ix2>().unwrap().to_owned())
+		},
+		None => Ok(Array2::zeros((batch, hidden_size))),
+	}
+}
+
+/// Checks the weight shapes against the input, returning (batch, time, features, hidden)
+fn recurrent_dims(pass_name: String, cell: CellType, input: &ArrayViewD<f32>, input_weights: &ArrayViewD<f32>, hidden_weights: &ArrayViewD<f32>, bias: &ArrayViewD<f32>) -> Result<(usize, usize, usize, usize)> {
+	ensure!(input.ndim() == 3, ErrorKind::PassError(pass_name, format!("input shape: {:?} must be [batch, time, features]", input.shape())));
+	let (batch, time, features) = (input.shape()[0], input.shape()[1], input.shape()[2]);
+	let hidden_size = hidden_weights.shape()[0];
+	let width = cell.gates() * hidden_size;
+	ensure!(input_weights.shape() == &[features, width] && hidden_weights.shape() == &[hidden_size, width] && bias.shape() == &[width],
+		ErrorKind::PassError(pass_name, format!("input shape: {:?} is not compatible with the input weights: {:?}, hidden weights: {:?}, and bias: {:?}", input.shape(), input_weights.shape(), hidden_weights.shape(), bias.shape())));
+	Ok((batch, time, features, hidden_size))
+}
+
+/// Runs the recurrent layer over all time steps, adding to the outputs
+///
+/// The activated gates and states of every time step are returned for use by the backward pass.
+#[derive(Clone, Debug)]
+pub struct RecurrentForward {
+	cell: CellType,
+	nodes: RecurrentNodes,
+}
+
+impl RecurrentForward {
+	pub fn new(cell: CellType, nodes: RecurrentNodes) -> Self {
+		RecurrentForward {
+			cell,
+			nodes,
+		}
+	}
+}
+
+impl Pass for RecurrentForward {
+	fn type_name(&self) -> &'static str {
+		match self.cell {
+			CellType::Lstm => "LstmForward",
+			CellType::Gru => "GruForward",
+		}
+	}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			self.nodes.inputs().iter().map(|id| id.value_id()).collect(),
+			self.nodes.outputs().iter().map(|id| id.value_id()).collect()
+		)
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.nodes.input.value_id())?;
+		let input_weights = data.get::<f32>(&self.nodes.input_weights.value_id())?;
+		let hidden_weights = data.get::<f32>(&self.nodes.hidden_weights.value_id())?;
+		let bias = data.get::<f32>(&self.nodes.bias.value_id())?;
+		let (batch, time, features, hidden_size) = recurrent_dims(self.name(), self.cell, &input, &input_weights, &hidden_weights, &bias)?;
+		let width = self.cell.gates() * hidden_size;
+
+		let mut h_prev = read_state(self.name(), data, &self.nodes.initial_hidden, batch, hidden_size)?;
+		let mut c_prev = read_state(self.name(), data, &self.nodes.initial_cell, batch, hidden_size)?;
+
+		let input_weights = input_weights.into_dimensionality::<Ix2>().unwrap();
+		let hidden_weights = hidden_weights.into_dimensionality::<Ix2>().unwrap();
+
+		// the input contribution to the gates of all time steps: x W + b
+		let mut input_gates = Array2::zeros((batch * time, width));
+		input_gates += &bias;
+		general_mat_mul(1.0, &input.into_shape((batch * time, features)).unwrap(), &input_weights, 1.0, &mut input_gates);
+		let input_gates = input_gates.into_shape((batch, time, width)).unwrap();
+
+		let mut pass_data = RecurrentPassData {
+			gates: Array3::zeros((batch, time, width)),
+			cells: Array3::zeros((batch, time, hidden_size)),
+			hidden: Array3::zeros((batch, time, hidden_size)),
+		};
+
+		let mut hidden_gates = Array2::zeros((batch, width));
+		for t in 0..time {
+			hidden_gates.fill(0.0);
+			general_mat_mul(1.0, &h_prev, &hidden_weights, 0.0, &mut hidden_gates);
+
+			for b in 0..batch {
+				for j in 0..hidden_size {
+					let x = |k: usize| input_gates[[b, t, k * hidden_size + j]];
+					let h = |k: usize| hidden_gates[[b, k * hidden_size + j]];
+					match self.cell {
+						CellType::Lstm => {
+							let i = sigmoid(x(0) + h(0));
+							let f = sigmoid(x(1) + h(1));
+							let g = (x(2) + h(2)).tanh();
+							let o = sigmoid(x(3) + h(3));
+							let c = f * c_prev[[b, j]] + i * g;
+							for (k, &gate) in [i, f, g, o].iter().enumerate() {
+								pass_data.gates[[b, t, k * hidden_size + j]] = gate;
+							}
+							pass_data.cells[[b, t, j]] = c;
+							pass_data.hidden[[b, t, j]] = o * c.tanh();
+						},
+						CellType::Gru => {
+							let r = sigmoid(x(0) + h(0));
+							let z = sigmoid(x(1) + h(1));
+							let n = (x(2) + r * h(2)).tanh();
+							for (k, &gate) in [r, z, n].iter().enumerate() {
+								pass_data.gates[[b, t, k * hidden_size + j]] = gate;
+							}
+							pass_data.cells[[b, t, j]] = h(2);
+							pass_data.hidden[[b, t, j]] = (1.0 - z) * n + z * h_prev[[b, j]];
+						},
+					}
+				}
+			}
+
+			h_prev.assign(&pass_data.hidden.subview(Axis(1), t));
+			if self.cell == CellType::Lstm {
+				c_prev.assign(&pass_data.cells.subview(Axis(1), t));
+			}
+		}
+
+		let mut output = data.get_mut::<f32>(&self.nodes.output.value_id())?;
+		ensure!(output.shape() == pass_data.hidden.shape(), ErrorKind::PassError(self.name(), format!("output shape: {:?} does not match [batch, time, hidden]: {:?}", output.shape(), pass_data.hidden.shape())));
+		output += &pass_data.hidden;
+
+		for (node_id, state) in self.nodes.final_hidden.iter().zip(Some(&h_prev)).chain(self.nodes.final_cell.iter().zip(Some(&c_prev))) {
+			if !data.is_required(&node_id.value_id()) {
+				continue;
+			}
+			let mut final_state = data.get_mut::<f32>(&node_id.value_id())?;
+			ensure!(final_state.shape() == state.shape(), ErrorKind::PassError(self.name(), format!("final state shape: {:?} does not match [batch, hidden]: {:?}", final_state.shape(), state.shape())));
+			final_state += state;
+		}
+
+		Ok(Box::new(pass_data))
+	}
+}
+
+
+/// Backpropagation through time, using the gates and states stored by the forward pass
+#[derive(Clone, Debug)]
+pub struct RecurrentBackward {
+	cell: CellType,
+	nodes: RecurrentNodes,
+	forward_id: PassID,
+}
+
+impl RecurrentBackward {
+	pub fn new(cell: CellType, nodes: RecurrentNodes, forward_id: PassID) -> Self {
+		RecurrentBackward {
+			cell,
+			nodes,
+			forward_id,
+		}
+	}
+}
+
+impl Pass for RecurrentBackward {
+	fn type_name(&self) -> &'static str {
+		match self.cell {
+			CellType::Lstm => "LstmBackward",
+			CellType::Gru => "GruBackward",
+		}
+	}
+
+	// The output value is listed as an input to ensure that the forward pass, which stores the gates, runs first.
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		let mut inputs: Vec<DataID> = self.nodes.inputs().iter().map(|id| id.value_id()).collect();
+		inputs.push(self.nodes.output.value_id());
+		inputs.extend(self.nodes.outputs().iter().map(|id| id.gradient_id()));
+		(
+			inputs,
+			self.nodes.inputs().iter().map(|id| id.gradient_id()).collect()
+		)
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.nodes.input.value_id())?;
+		let input_weights = data.get::<f32>(&self.nodes.input_weights.value_id())?;
+		let hidden_weights = data.get::<f32>(&self.nodes.hidden_weights.value_id())?;
+		let bias = data.get::<f32>(&self.nodes.bias.value_id())?;
+		let (batch, time, features, hidden_size) = recurrent_dims(self.name(), self.cell, &input, &input_weights, &hidden_weights, &bias)?;
+		let width = self.cell.gates() * hidden_size;
+
+		let pass_data = data.get_pass_data(&self.forward_id)
+			.and_then(|pass_data| pass_data.downcast_ref::<RecurrentPassData>());
+		let pass_data = if let Some(pass_data) = pass_data {
+			pass_data
+		} else {
+			bail!(ErrorKind::PassError(self.name(), format!("the gates from the forward pass were not available")));
+		};
+		ensure!(pass_data.hidden.shape() == &[batch, time, hidden_size], ErrorKind::PassError(self.name(), format!("the stored gates do not match the input shape: {:?}", input.shape())));
+
+		let h_initial = read_state(self.name(), data, &self.nodes.initial_hidden, batch, hidden_size)?;
+		let c_initial = read_state(self.name(), data, &self.nodes.initial_cell, batch, hidden_size)?;
+		let output_grad = data.get::<f32>(&self.nodes.output.gradient_id())?;
+		ensure!(output_grad.shape() == pass_data.hidden.shape(), ErrorKind::PassError(self.name(), format!("output shape: {:?} does not match [batch, time, hidden]: {:?}", output_grad.shape(), pass_data.hidden.shape())));
+
+		// gradients flowing back from the next time step, starting with the final state gradients
+		let mut h_grad = Array2::zeros((batch, hidden_size));
+		let mut c_grad = Array2::zeros((batch, hidden_size));
+		if let Some(ref node_id) = self.nodes.final_hidden {
+			h_grad += &data.get::<f32>(&node_id.gradient_id())?;
+		}
+		if let Some(ref node_id) = self.nodes.final_cell {
+			c_grad += &data.get::<f32>(&node_id.gradient_id())?;
+		}
+
+		let input_weights = input_weights.into_dimensionality::<Ix2>().unwrap();
+		let hidden_weights = hidden_weights.into_dimensionality::<Ix2>().unwrap();
+
+		// gradients of the input and hidden contributions to the gates before activation
+		let mut input_gates_grad = Array3::zeros((batch, time, width));
+		let mut hidden_gates_grad = Array2::zeros((batch, width));
+		let mut hidden_weights_grad = Array2::zeros((hidden_size, width));
+
+		for t in (0..time).rev() {
+			let h_prev: ArrayView2<f32> = if t > 0 {pass_data.hidden.subview(Axis(1), t - 1)} else {h_initial.view()};
+			let mut h_prev_grad = Array2::zeros((batch, hidden_size));
+
+			for b in 0..batch {
+				for j in 0..hidden_size {
+					let gate = |k: usize| pass_data.gates[[b, t, k * hidden_size + j]];
+					let dh = output_grad[[b, t, j]] + h_grad[[b, j]];
+					match self.cell {
+						CellType::Lstm => {
+							let (i, f, g, o) = (gate(0), gate(1), gate(2), gate(3));
+							let c_prev = if t > 0 {pass_data.cells[[b, t - 1, j]]} else {c_initial[[b, j]]};
+							let tc = pass_data.cells[[b, t, j]].tanh();
+							let dc = c_grad[[b, j]] + dh * o * (1.0 - tc * tc);
+							let grads = [dc * g * i * (1.0 - i), dc * c_prev * f * (1.0 - f), dc * i * (1.0 - g * g), dh * tc * o * (1.0 - o)];
+							for (k, &grad) in grads.iter().enumerate() {
+								input_gates_grad[[b, t, k * hidden_size + j]] = grad;
+								hidden_gates_grad[[b, k * hidden_size + j]] = grad;
+							}
+							c_grad[[b, j]] = dc * f;
+						},
+						CellType::Gru => {
+							let (r, z, n) = (gate(0), gate(1), gate(2));
+							let dn = dh * (1.0 - z) * (1.0 - n * n);
+							let dr = dn * pass_data.cells[[b, t, j]] * r * (1.0 - r);
+							let dz = dh * (h_prev[[b, j]] - n) * z * (1.0 - z);
+							for (k, &(input_grad, hidden_grad)) in [(dr, dr), (dz, dz), (dn, dn * r)].iter().enumerate() {
+								input_gates_grad[[b, t, k * hidden_size + j]] = input_grad;
+								hidden_gates_grad[[b, k * hidden_size + j]] = hidden_grad;
+							}
+							h_prev_grad[[b, j]] = dh * z;
+						},
+					}
+				}
+			}
+
+			general_mat_mul(1.0, &hidden_gates_grad, &hidden_weights.t(), 1.0, &mut h_prev_grad);
+			general_mat_mul(1.0, &h_prev.t(), &hidden_gates_grad, 1.0, &mut hidden_weights_grad);
+			h_grad = h_prev_grad;
+		}
+
+		let input_gates_grad = input_gates_grad.into_shape((batch * time, width)).unwrap();
+		let input = input.into_shape((batch * time, features)).unwrap();
+		if data.is_required(&self.nodes.input.gradient_id()) {
+			let mut input_grad = data.get_mut::<f32>(&self.nodes.input.gradient_id())?;
+			let mut input_grad = input_grad.view_mut().into_shape((batch * time, features)).unwrap();
+			general_mat_mul(1.0, &input_gates_grad, &input_weights.t(), 1.0, &mut input_grad);
+		}
+		if data.is_required(&self.nodes.input_weights.gradient_id()) {
+			let mut input_weights_grad = data.get_mut::<f32>(&self.nodes.input_weights.gradient_id())?;
+			let mut input_weights_grad = input_weights_grad.view_mut().into_dimensionality::<Ix2>().unwrap();
+			general_mat_mul(1.0, &input.t(), &input_gates_grad, 1.0, &mut input_weights_grad);
+		}
+		if data.is_required(&self.nodes.hidden_weights.gradient_id()) {
+			let mut hidden_weights_grad_data = data.get_mut::<f32>(&self.nodes.hidden_weights.gradient_id())?;
+			hidden_weights_grad_data += &hidden_weights_grad;
+		}
+		if data.is_required(&self.nodes.bias.gradient_id()) {
+			let mut bias_grad = data.get_mut::<f32>(&self.nodes.bias.gradient_id())?;
+			bias_grad += &input_gates_grad.sum_axis(Axis(0));
+		}
+
+		if let Some(ref node_id) = self.nodes.initial_hidden {
+			if data.is_required(&node_id.gradient_id()) {
+				let mut initial_grad = data.get_mut::<f32>(&node_id.gradient_id())?;
+				initial_grad += &h_grad;
+			}
+		}
+		if let Some(ref node_id) = self.nodes.initial_cell {
+			if data.is_required(&node_id.gradient_id()) {
+				let mut initial_grad = data.get_mut::<f32>(&node_id.gradient_id())?;
+				initial_grad += &c_grad;
+			}
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_lstm_backprop(){
+	_lstm_backprop().unwrap();
+}
+
+fn _lstm_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 4, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![3, 6], "initial_hidden", tag![])?;
+	let node3 = g.new_node(shape![3, 6], "initial_cell", tag![])?;
+	let node4 = g.new_node(shape![Unknown, Unknown, 6], "output", tag![])?;
+	let node5 = g.new_node(shape![Unknown, 6], "final_hidden", tag![])?;
+	let node6 = g.new_node(shape![Unknown, 6], "final_cell", tag![])?;
+	let node7 = g.new_node(shape![3, 4, 6], "target", tag![])?;
+	let node8 = g.new_node(shape![3, 6], "final_hidden_target", tag![])?;
+	let node9 = g.new_node(shape![3, 6], "final_cell_target", tag![])?;
+
+	let o1 = g.new_op(Lstm::new(&node1, &node4)
+		.initial_hidden(Some(&node2))
+		.initial_cell(Some(&node3))
+		.final_hidden(Some(&node5))
+		.final_cell(Some(&node6)), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node4, &node7), tag![])?;
+	let _o3 = g.new_op(Mse::new(&node5, &node8), tag![])?;
+	let _o4 = g.new_op(Mse::new(&node6, &node9), tag![])?;
+
+	let bias = g.initialise_nodes(&o1.instance().inner_nodes()[2..])?.remove(0);
+	assert_eq!(bias.scalar_sum(), 6.0);
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_gru_backprop(){
+	_gru_backprop().unwrap();
+}
+
+fn _gru_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 4, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![3, 6], "initial_hidden", tag![])?;
+	let node3 = g.new_node(shape![Unknown, Unknown, Unknown], "output", tag![])?;
+	let node4 = g.new_node(shape![Unknown, Unknown], "final_hidden", tag![])?;
+	let node5 = g.new_node(shape![3, 4, 6], "target", tag![])?;
+	let node6 = g.new_node(shape![3, 6], "final_hidden_target", tag![])?;
+
+	let _o1 = g.new_op(Gru::new(&node1, &node3).hidden_size(6)
+		.initial_hidden(Some(&node2))
+		.final_hidden(Some(&node4)), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node5), tag![])?;
+	let _o3 = g.new_op(Mse::new(&node4, &node6), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_recurrent_default_subgraph(){
+	_recurrent_default_subgraph().unwrap();
+}
+
+fn _recurrent_default_subgraph() -> Result<()>{
+	use graph::GraphDef;
+	use ops::loss::mse::Mse;
+	use ndarray::ArrayD;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 4, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![3, 6], "initial_hidden", tag![])?;
+	let node3 = g.new_node(shape![3, 4, 6], "lstm_output", tag![])?;
+	let node4 = g.new_node(shape![3, 6], "lstm_final_hidden", tag![])?;
+	let node5 = g.new_node(shape![3, 6], "lstm_final_cell", tag![])?;
+	let node6 = g.new_node(shape![3, 4, 6], "gru_output", tag![])?;
+	let node7 = g.new_node(shape![3, 6], "gru_final_hidden", tag![])?;
+	let node8 = g.new_node(shape![3, 4, 6], "target", tag![])?;
+	let node9 = g.new_node(shape![3, 6], "final_target", tag![])?;
+
+	let _o1 = g.new_op(Lstm::new(&node1, &node3)
+		.initial_hidden(Some(&node2))
+		.final_hidden(Some(&node4))
+		.final_cell(Some(&node5)), tag![])?;
+	let _o2 = g.new_op(Gru::new(&node3, &node6)
+		.initial_hidden(Some(&node2))
+		.final_hidden(Some(&node7)), tag![])?;
+	let _o3 = g.new_op(Mse::new(&node6, &node8), tag![])?;
+	let _o4 = g.new_op(Mse::new(&node4, &node9), tag![])?;
+	let _o5 = g.new_op(Mse::new(&node5, &node9), tag![])?;
+	let _o6 = g.new_op(Mse::new(&node7, &node9), tag![])?;
+
+	let inputs = |data_ids: &[DataID]| data_ids.iter().map(|data_id| ArrayD::from_elem(data_id.node_id().shape().to_data_shape().unwrap(), 0.5)).collect::<Vec<_>>();
+
+	// the data inputs and initial state do not require gradients
+	let mut subgraph = g.default_subgraph()?;
+	let input_values = inputs(subgraph.inputs());
+	let storage = subgraph.execute(input_values)?;
+	assert!(!storage.is_required(&node1.gradient_id()));
+	assert!(!storage.is_required(&node2.gradient_id()));
+
+	// when only the outputs are requested, the final states are not required
+	let input_ids: Vec<DataID> = subgraph.inputs().iter().filter(|data_id| data_id.node_id() != node8 && data_id.node_id() != node9).cloned().collect();
+	let mut subgraph = g.subgraph(&input_ids, &[node6.value_id()])?;
+	let storage = subgraph.execute(inputs(&input_ids))?;
+	assert!(!storage.is_required(&node5.value_id()));
+	assert!(!storage.is_required(&node7.value_id()));
+
+	Ok(())
+}
@@ -0,0 +1,93 @@
+use graph::{GraphDef, Result};
+use id::{NodeID, OpID};
+use init::Initialiser;
+use ops::{standard_inner_parameter_name, standard_inner_node_name, OpInstance};
+use ops::nn::weight_norm::WeightNorm;
+use ops::nn::spectral_norm::SpectralNorm;
+use ndarray::{ArrayD, ArrayViewMutD, Axis};
+
+/// Reparameterisations of the weights of `Linear` and `Conv` Ops
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reparameterisation {
+	/// Weights are used directly.
+	None,
+	/// Weights are split into a direction and a magnitude for each output channel, see `WeightNorm`.
+	///
+	/// The magnitude is an additional inner `Parameter` node,
+	/// initialised to match the norms of the weights initialiser if one is set, otherwise to 1.
+	WeightNorm,
+	/// Weights are divided by an estimate of their largest singular value, see `SpectralNorm`.
+	///
+	/// The value is the number of power iterations to run during each training execution.
+	SpectralNorm(usize),
+}
+
+/// The nodes and op created when reparameterising the weights of an Op
+#[derive(Clone, Debug)]
+pub(crate) struct ReparameterisedWeights {
+	/// The node containing the effective weights, to be used in place of the original weights
+	pub weights_id: NodeID,
+	/// All inner nodes created, including the effective weights
+	pub inner_nodes: Vec<NodeID>,
+	pub op_id: OpID,
+}
+
+/// Creates the nodes and op for a reparameterisation of `weights`, for use inside the build of the Op named `name`.
+///
+/// `axis` is the output channel axis of the weights, along which `WeightNorm` magnitudes are created.
+/// Returns `None` for `Reparameterisation::None`.
+pub(crate) fn reparameterise(graph: &mut GraphDef, name: &str, weights: &NodeID, reparameterisation: &Reparameterisation, axis: isize, initialiser: Option<&Initialiser>) -> Result<Option<ReparameterisedWeights>> {
+	let new_effective = |graph: &mut GraphDef| -> Result<NodeID> {
+		let node_name = standard_inner_node_name(name, graph);
+		graph.new_node(weights.shape().clone(), node_name, tag![])
+	};
+
+	match reparameterisation {
+		&Reparameterisation::None => Ok(None),
+		&Reparameterisation::WeightNorm => {
+			let ndim = weights.shape().ndim() as isize;
+			ensure!(axis < ndim && axis >= -ndim, "Weight norm axis ({}) is out of range for weights shape: {:?}", axis, weights.shape());
+			let axis = ((axis + ndim) % ndim) as usize;
+
+			let magnitude_name = standard_inner_parameter_name(name, graph);
+			let magnitude = graph.new_node(shape![weights.shape().dimensions()[axis].clone()], magnitude_name, tag![Parameter])?;
+			graph.set_initialiser(&magnitude, magnitude_initialiser(weights, axis, initialiser));
+
+			let effective = new_effective(graph)?;
+			let op_id = graph.new_op(WeightNorm::new(weights, &magnitude, &effective).axis(axis as isize), tag![])?;
+
+			Ok(Some(ReparameterisedWeights{
+				weights_id: effective.clone(),
+				inner_nodes: vec![magnitude, effective],
+				op_id: op_id,
+			}))
+		},
+		&Reparameterisation::SpectralNorm(iterations) => {
+			let effective = new_effective(graph)?;
+			let op_id = graph.new_op(SpectralNorm::new(weights, &effective).iterations(iterations), tag![])?;
+
+			Ok(Some(ReparameterisedWeights{
+				weights_id: effective.clone(),
+				inner_nodes: vec![effective],
+				op_id: op_id,
+			}))
+		},
+	}
+}
+
+/// Initialises the magnitude so that the initial effective weights follow the same distribution as the weights initialiser
+fn magnitude_initialiser(weights: &NodeID, axis: usize, initialiser: Option<&Initialiser>) -> Initialiser {
+	match (initialiser, weights.shape().to_data_shape()) {
+		(Some(initialiser), Ok(shape)) => {
+			let initialiser = initialiser.clone();
+			Initialiser::new("Weight Norm Magnitude Initialiser".to_string(), move |mut arr: ArrayViewMutD<f32>, instance: Option<&OpInstance>|{
+				let mut sample = ArrayD::zeros(shape.clone());
+				initialiser.call(sample.view_mut(), instance);
+				for (e, v) in arr.iter_mut().zip(sample.axis_iter(Axis(axis))) {
+					*e = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+				}
+			})
+		},
+		_ => Initialiser::fill(1.0),
+	}
+}
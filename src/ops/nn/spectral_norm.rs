@@ -0,0 +1,368 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use shape::NodeDim;
+use ndarray::{ArrayD, ArrayView2, Array1, IxDyn};
+use rand::{thread_rng, Isaac64Rng, SeedableRng};
+use rand::distributions::{Distribution, Normal};
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+
+/// Spectral Normalisation
+///
+/// Divides a weight tensor by an estimate of its largest singular value, `w = v / sigma(v)`.
+/// The weight tensor is treated as a matrix by flattening all but the outermost axis.
+///
+/// The largest singular value is estimated by power iteration, using a left singular vector which persists across executions.
+/// The vector is refined by `iterations` steps each time a subgraph is executed in training mode,
+/// and is left unchanged otherwise (except to initialise it on first use).
+///
+/// See Miyato et al., "Spectral Normalization for Generative Adversarial Networks".
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct SpectralNorm {
+	name: Option<String>,
+	input_id: NodeID,
+	output_id: NodeID,
+	iterations: usize,
+	epsilon: f32,
+}
+
+impl SpectralNorm {
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self {
+		SpectralNorm {
+			name: None,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+			iterations: 1,
+			epsilon: 1e-12,
+		}
+	}
+
+	/// The number of power iterations used to refine the singular vector during each training execution.
+	///
+	/// Default: 1
+	pub fn iterations(mut self, iterations: usize) -> Self {
+		self.iterations = iterations;
+		self
+	}
+
+	/// A small value used to avoid dividing by zero when normalising vectors.
+	///
+	/// Default: 1e-12
+	pub fn epsilon(mut self, epsilon: f32) -> Self {
+		self.epsilon = epsilon;
+		self
+	}
+}
+
+impl Op for SpectralNorm {
+	type InstanceType = SpectralNormInstance;
+
+	fn type_name(&self) -> &'static str {
+		"SpectralNorm"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone()], &[self.output_id.clone()]);
+
+		ensure!(self.input_id.shape().ndim() >= 2, ErrorKind::ShapePropagationError(name.clone(), format!("input must have at least 2 dimensions, found shape: {:?}", self.input_id.shape())));
+
+		// The singular vector is empty until first use, when the input shape is known
+		let singular_vector = Arc::new(Mutex::new(ArrayD::zeros(IxDyn(&[0]))));
+
+		Ok(SpectralNormInstance{
+			name: name,
+			input_id: self.input_id.clone(),
+			output_id: self.output_id.clone(),
+			singular_vector: singular_vector.clone(),
+			forward_id: graph.add_pass(SpectralNormForward::new(
+				self.input_id.clone(),
+				self.output_id.clone(),
+				singular_vector.clone(),
+				self.iterations,
+				self.epsilon,
+			)),
+			backward_id: graph.add_pass(SpectralNormBackward::new(
+				self.input_id.clone(),
+				self.output_id.clone(),
+				singular_vector.clone(),
+				self.epsilon,
+			)),
+		})
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct SpectralNormInstance {
+	name: String,
+	input_id: NodeID,
+	output_id: NodeID,
+	singular_vector: Arc<Mutex<ArrayD<f32>>>,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl SpectralNormInstance {
+	/// Returns a copy of the current left singular vector estimate, e.g. for checkpointing.
+	///
+	/// This is empty if the Op has not yet been executed.
+	pub fn singular_vector(&self) -> ArrayD<f32> {
+		self.singular_vector.lock().expect("Could not acquire lock on singular vector").clone()
+	}
+
+	/// Replaces the left singular vector estimate, e.g. when restoring from a checkpoint.
+	///
+	/// The supplied vector must be one dimensional, with a length matching the outermost dimension of the input.
+	pub fn set_singular_vector(&self, vector: ArrayD<f32>) -> Result<()> {
+		ensure!(vector.ndim() == 1, "Singular vector must be one dimensional, found shape: {:?}", vector.shape());
+		if let Some(&NodeDim::Known(rows)) = self.input_id.shape().dimensions().first() {
+			ensure!(vector.len() == rows, "Singular vector length ({}) did not match the outermost input dimension ({})", vector.len(), rows);
+		}
+		*self.singular_vector.lock().expect("Could not acquire lock on singular vector") = vector;
+		Ok(())
+	}
+}
+
+impl OpInstance for SpectralNormInstance {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(vec![self.input_id.clone()], vec![self.output_id.clone()])
+	}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).clone();
+		shapes.merge_with(&self.output_id, &input_shape)
+	}
+}
+
+/// Views the input as a matrix, flattening all but the outermost axis
+fn as_matrix<'a>(input: &'a ArrayD<f32>) -> ArrayView2<'a, f32> {
+	let rows = input.shape()[0];
+	input.view().into_shape((rows, input.len()/rows)).expect("Spectral norm input must be contiguous")
+}
+
+fn normalise(mut vector: Array1<f32>, epsilon: f32) -> Array1<f32> {
+	let norm = vector.dot(&vector).sqrt();
+	vector /= norm + epsilon;
+	vector
+}
+
+/// A single power iteration step from the left singular vector estimate `u`
+///
+/// Returns the updated left singular vector, the right singular vector, and the singular value estimate.
+fn estimate(matrix: &ArrayView2<f32>, u: &Array1<f32>, epsilon: f32) -> (Array1<f32>, Array1<f32>, f32) {
+	let v = normalise(matrix.t().dot(u), epsilon);
+	let wv = matrix.dot(&v);
+	let sigma = wv.dot(&wv).sqrt();
+	(wv / (sigma + epsilon), v, sigma)
+}
+
+fn random_vector(len: usize, epsilon: f32) -> Array1<f32> {
+	let mut rng = Isaac64Rng::from_rng(thread_rng()).unwrap();
+	let norm = Normal::new(0.0, 1.0);
+	normalise(Array1::from_shape_fn(len, |_| norm.sample(&mut rng) as f32), epsilon)
+}
+
+#[derive(Clone, Debug)]
+pub struct SpectralNormForward {
+	input_id: NodeID,
+	output_id: NodeID,
+	singular_vector: Arc<Mutex<ArrayD<f32>>>,
+	iterations: usize,
+	epsilon: f32,
+}
+
+impl SpectralNormForward {
+	pub fn new(input_id: NodeID, output_id: NodeID, singular_vector: Arc<Mutex<ArrayD<f32>>>, iterations: usize, epsilon: f32) -> Self {
+		SpectralNormForward {
+			input_id,
+			output_id,
+			singular_vector,
+			iterations,
+			epsilon,
+		}
+	}
+}
+
+impl Pass for SpectralNormForward {
+	fn type_name(&self) -> &'static str {"SpectralNormForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id()],
+		vec![self.output_id.value_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>> {
+		let input = data.get::<f32>(&self.input_id.value_id())?.to_owned();
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		ensure!(
+			input.shape() == output.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input.shape(), output.shape()))
+		);
+		ensure!(
+			input.ndim() >= 2,
+			ErrorKind::PassError(self.name(), format!("input must have at least 2 dimensions, found shape: {:?}", input.shape()))
+		);
+
+		let matrix = as_matrix(&input);
+		let mut singular_vector = self.singular_vector.lock().expect("Could not acquire lock on singular vector");
+
+		let initialise = singular_vector.len() != matrix.rows();
+		let mut u = if initialise {
+			random_vector(matrix.rows(), self.epsilon)
+		} else {
+			singular_vector.view().into_shape(matrix.rows()).unwrap().to_owned()
+		};
+
+		if initialise || data.is_training() {
+			for _ in 0..self.iterations {
+				u = estimate(&matrix, &u, self.epsilon).0;
+			}
+			*singular_vector = u.clone().into_dyn();
+		}
+
+		let (_, _, sigma) = estimate(&matrix, &u, self.epsilon);
+		output.scaled_add(1.0/(sigma + self.epsilon), &input);
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct SpectralNormBackward {
+	input_id: NodeID,
+	output_id: NodeID,
+	singular_vector: Arc<Mutex<ArrayD<f32>>>,
+	epsilon: f32,
+}
+
+impl SpectralNormBackward {
+	pub fn new(input_id: NodeID, output_id: NodeID, singular_vector: Arc<Mutex<ArrayD<f32>>>, epsilon: f32) -> Self {
+		SpectralNormBackward {
+			input_id,
+			output_id,
+			singular_vector,
+			epsilon,
+		}
+	}
+}
+
+impl Pass for SpectralNormBackward {
+	fn type_name(&self) -> &'static str {"SpectralNormBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id(), self.output_id.gradient_id()],
+		vec![self.input_id.gradient_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>> {
+		let input = data.get::<f32>(&self.input_id.value_id())?.to_owned();
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+
+		ensure!(
+			input.shape() == output_grad.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input.shape(), output_grad.shape()))
+		);
+
+		let matrix = as_matrix(&input);
+		let u = {
+			let singular_vector = self.singular_vector.lock().expect("Could not acquire lock on singular vector");
+			ensure!(
+				singular_vector.len() == matrix.rows(),
+				ErrorKind::PassError(self.name(), format!("singular vector length ({}) did not match the outermost input dimension ({}), the forward pass must run first", singular_vector.len(), matrix.rows()))
+			);
+			singular_vector.view().into_shape(matrix.rows()).unwrap().to_owned()
+		};
+
+		// treating the singular vectors as constant, d(sigma)/dW = u v^T
+		let (u, v, sigma) = estimate(&matrix, &u, self.epsilon);
+		let sigma = sigma + self.epsilon;
+		let projection = output_grad.iter().zip(input.iter()).map(|(grad, w)| grad * w).sum::<f32>()/(sigma * sigma);
+
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+		input_grad.scaled_add(1.0/sigma, &output_grad);
+
+		let outer = u.into_shape((matrix.rows(), 1)).unwrap().dot(&v.into_shape((1, matrix.cols())).unwrap());
+		input_grad.scaled_add(-projection, &outer.into_shape(input.shape()).unwrap());
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_spectral_norm_backprop(){
+	_spectral_norm_backprop().unwrap();
+}
+
+fn _spectral_norm_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![5, 3, 2], "input", tag![])?;
+	let node2 = g.new_node(shape![5, 3, 2], "output", tag![])?;
+	let node3 = g.new_node(shape![5, 3, 2], "target", tag![])?;
+
+	let _o1 = g.new_op(SpectralNorm::new(&node1, &node2).iterations(200), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 2;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_spectral_norm_values(){
+	_spectral_norm_values().unwrap();
+}
+
+fn _spectral_norm_values() -> Result<()>{
+	use graph::GraphDef;
+	use ndarray::arr1;
+
+	let mut g = GraphDef::new();
+
+	let input = g.new_node(shape![2, 2], "input", tag![])?;
+	let output = g.new_node(shape![2, 2], "output", tag![])?;
+
+	let o1 = g.new_op(SpectralNorm::new(&input, &output).iterations(20), tag![])?;
+
+	let mut subgraph = g.subgraph(&[input.value_id()], &[output.value_id()])?;
+	let storage = subgraph.execute(vec![arr1(&[4.0, 0.0, 0.0, -2.0]).into_shape(vec![2, 2]).unwrap()])?;
+
+	let output = storage.get::<f32>(&output.value_id())?;
+	for (&x, &expected) in output.iter().zip(&[1.0, 0.0, 0.0, -0.5]) {
+		assert!((x - expected).abs() < 1e-4, "{:?}", output);
+	}
+
+	let instance = o1.instance().as_any().downcast_ref::<SpectralNormInstance>().unwrap();
+	assert_eq!(instance.singular_vector().shape(), &[2]);
+	assert!((instance.singular_vector()[0].abs() - 1.0).abs() < 1e-4);
+
+	Ok(())
+}
@@ -0,0 +1,315 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use shape::NodeShape;
+use ndarray::{ArrayViewD, Axis};
+use std::any::Any;
+
+/// Weight Normalisation
+///
+/// Reparameterises a weight tensor as `w = g v / ||v||`, decoupling the direction `v` from the magnitude `g`.
+/// The norm of the direction is taken over all axes except `axis`, and the magnitude has one element for each index along `axis`,
+/// so that each output channel has its own learnable magnitude.
+///
+/// See Salimans & Kingma, "Weight Normalization: A Simple Reparameterization to Accelerate Training of Deep Neural Networks".
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct WeightNorm {
+	name: Option<String>,
+	direction_id: NodeID,
+	magnitude_id: NodeID,
+	output_id: NodeID,
+	axis: isize,
+	epsilon: f32,
+}
+
+impl WeightNorm {
+	pub fn new(direction_id: &NodeID, magnitude_id: &NodeID, output_id: &NodeID) -> Self {
+		WeightNorm {
+			name: None,
+			direction_id: direction_id.clone(),
+			magnitude_id: magnitude_id.clone(),
+			output_id: output_id.clone(),
+			axis: 0,
+			epsilon: 1e-8,
+		}
+	}
+
+	/// The axis which is not included in the norm, and which the magnitude corresponds to.
+	///
+	/// Use 0 for `Conv` filters, and -1 for `Linear` weights.
+	///
+	/// Default: 0
+	pub fn axis(mut self, axis: isize) -> Self {
+		self.axis = axis;
+		self
+	}
+
+	/// A small value added to the squared norm, to avoid dividing by zero.
+	///
+	/// Default: 1e-8
+	pub fn epsilon(mut self, epsilon: f32) -> Self {
+		self.epsilon = epsilon;
+		self
+	}
+}
+
+impl Op for WeightNorm {
+	type InstanceType = WeightNormInstance;
+
+	fn type_name(&self) -> &'static str {
+		"WeightNorm"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.direction_id.clone(), self.magnitude_id.clone()], &[self.output_id.clone()]);
+
+		let ndim = self.direction_id.shape().ndim();
+		ensure!(self.axis < ndim as isize && self.axis >= -(ndim as isize), ErrorKind::ShapePropagationError(name.clone(), format!("axis ({}) is out of range for direction shape: {:?}", self.axis, self.direction_id.shape())));
+		let axis = (self.axis + ndim as isize) as usize % ndim;
+
+		Ok(WeightNormInstance{
+			name: name,
+			direction_id: self.direction_id.clone(),
+			magnitude_id: self.magnitude_id.clone(),
+			output_id: self.output_id.clone(),
+			axis: axis,
+			forward_id: graph.add_pass(WeightNormForward::new(
+				self.direction_id.clone(),
+				self.magnitude_id.clone(),
+				self.output_id.clone(),
+				axis,
+				self.epsilon,
+			)),
+			backward_id: graph.add_pass(WeightNormBackward::new(
+				self.direction_id.clone(),
+				self.magnitude_id.clone(),
+				self.output_id.clone(),
+				axis,
+				self.epsilon,
+			)),
+		})
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct WeightNormInstance {
+	name: String,
+	direction_id: NodeID,
+	magnitude_id: NodeID,
+	output_id: NodeID,
+	axis: usize,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for WeightNormInstance {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(vec![self.direction_id.clone(), self.magnitude_id.clone()], vec![self.output_id.clone()])
+	}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let direction_shape = shapes.get_shape(&self.direction_id).clone();
+		let magnitude_shape: NodeShape = shape![direction_shape.dimensions()[self.axis].clone()];
+		shapes.merge_with(&self.magnitude_id, &magnitude_shape)?;
+		shapes.merge_with(&self.output_id, &direction_shape)
+	}
+}
+
+fn check_shapes(pass_name: String, direction_shape: &[usize], magnitude_shape: &[usize], output_shape: &[usize], axis: usize) -> Result<()> {
+	ensure!(
+		direction_shape == output_shape,
+		ErrorKind::PassError(pass_name.clone(), format!("direction shape: {:?} did not match output shape: {:?}", direction_shape, output_shape))
+	);
+	ensure!(
+		magnitude_shape == [direction_shape[axis]],
+		ErrorKind::PassError(pass_name, format!("magnitude shape: {:?} did not match axis {} of direction shape: {:?}", magnitude_shape, axis, direction_shape))
+	);
+	Ok(())
+}
+
+/// Returns the norm of the direction for each index along the axis
+fn norms(direction: &ArrayViewD<f32>, axis: usize, epsilon: f32) -> Vec<f32> {
+	direction.axis_iter(Axis(axis)).map(|v| (v.iter().map(|x| x * x).sum::<f32>() + epsilon).sqrt()).collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct WeightNormForward {
+	direction_id: NodeID,
+	magnitude_id: NodeID,
+	output_id: NodeID,
+	axis: usize,
+	epsilon: f32,
+}
+
+impl WeightNormForward {
+	pub fn new(direction_id: NodeID, magnitude_id: NodeID, output_id: NodeID, axis: usize, epsilon: f32) -> Self {
+		WeightNormForward {
+			direction_id,
+			magnitude_id,
+			output_id,
+			axis,
+			epsilon,
+		}
+	}
+}
+
+impl Pass for WeightNormForward {
+	fn type_name(&self) -> &'static str {"WeightNormForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.direction_id.value_id(), self.magnitude_id.value_id()],
+		vec![self.output_id.value_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>> {
+		let direction = data.get::<f32>(&self.direction_id.value_id())?;
+		let magnitude = data.get::<f32>(&self.magnitude_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		check_shapes(self.name(), direction.shape(), magnitude.shape(), output.shape(), self.axis)?;
+
+		let norms = norms(&direction, self.axis, self.epsilon);
+		for (((v, mut w), &g), norm) in direction.axis_iter(Axis(self.axis)).zip(output.axis_iter_mut(Axis(self.axis))).zip(magnitude.iter()).zip(norms) {
+			w.scaled_add(g/norm, &v);
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct WeightNormBackward {
+	direction_id: NodeID,
+	magnitude_id: NodeID,
+	output_id: NodeID,
+	axis: usize,
+	epsilon: f32,
+}
+
+impl WeightNormBackward {
+	pub fn new(direction_id: NodeID, magnitude_id: NodeID, output_id: NodeID, axis: usize, epsilon: f32) -> Self {
+		WeightNormBackward {
+			direction_id,
+			magnitude_id,
+			output_id,
+			axis,
+			epsilon,
+		}
+	}
+}
+
+impl Pass for WeightNormBackward {
+	fn type_name(&self) -> &'static str {"WeightNormBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.direction_id.value_id(), self.magnitude_id.value_id(), self.output_id.gradient_id()],
+		vec![self.direction_id.gradient_id(), self.magnitude_id.gradient_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>> {
+		let direction = data.get::<f32>(&self.direction_id.value_id())?;
+		let magnitude = data.get::<f32>(&self.magnitude_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+
+		check_shapes(self.name(), direction.shape(), magnitude.shape(), output_grad.shape(), self.axis)?;
+
+		let norms = norms(&direction, self.axis, self.epsilon);
+
+		// the component of the output gradient along the direction, for each index along the axis
+		let projections: Vec<f32> = direction.axis_iter(Axis(self.axis)).zip(output_grad.axis_iter(Axis(self.axis))).zip(&norms)
+			.map(|((v, grad), norm)| v.iter().zip(grad.iter()).map(|(v, grad)| v * grad).sum::<f32>()/norm).collect();
+
+		if data.is_required(&self.magnitude_id.gradient_id()) {
+			let mut magnitude_grad = data.get_mut::<f32>(&self.magnitude_id.gradient_id())?;
+			for (g_grad, &projection) in magnitude_grad.iter_mut().zip(&projections) {
+				*g_grad += projection;
+			}
+		}
+
+		if data.is_required(&self.direction_id.gradient_id()) {
+			let mut direction_grad = data.get_mut::<f32>(&self.direction_id.gradient_id())?;
+			// dv = g/||v|| (dw - (dw.v/||v||) v/||v||)
+			let iter = direction.axis_iter(Axis(self.axis)).zip(output_grad.axis_iter(Axis(self.axis))).zip(direction_grad.axis_iter_mut(Axis(self.axis)))
+				.zip(magnitude.iter()).zip(norms.iter().zip(&projections));
+			for ((((v, grad), mut v_grad), &g), (&norm, &projection)) in iter {
+				v_grad.scaled_add(g/norm, &grad);
+				v_grad.scaled_add(-g * projection/(norm * norm), &v);
+			}
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_weight_norm_backprop(){
+	_weight_norm_backprop().unwrap();
+}
+
+fn _weight_norm_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![5, 3, 4], "direction", tag![])?;
+	let node2 = g.new_node(shape![4], "magnitude", tag![])?;
+	let node3 = g.new_node(shape![5, 3, 4], "output", tag![])?;
+	let node4 = g.new_node(shape![5, 3, 4], "target", tag![])?;
+
+	let _o1 = g.new_op(WeightNorm::new(&node1, &node2, &node3).axis(-1), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_weight_norm_values(){
+	_weight_norm_values().unwrap();
+}
+
+fn _weight_norm_values() -> Result<()>{
+	use graph::GraphDef;
+	use ndarray::arr1;
+
+	let mut g = GraphDef::new();
+
+	let direction = g.new_node(shape![2, 2], "direction", tag![])?;
+	let magnitude = g.new_node(shape![2], "magnitude", tag![])?;
+	let output = g.new_node(shape![2, 2], "output", tag![])?;
+
+	let _o1 = g.new_op(WeightNorm::new(&direction, &magnitude, &output).epsilon(0.0), tag![])?;
+
+	let mut subgraph = g.subgraph(&[direction.value_id(), magnitude.value_id()], &[output.value_id()])?;
+	let storage = subgraph.execute(vec![arr1(&[3.0, 4.0, 0.0, -2.0]).into_shape(vec![2, 2]).unwrap(), arr1(&[10.0, 0.5]).into_dyn()])?;
+
+	assert_eq!(storage.get::<f32>(&output.value_id())?.iter().cloned().collect::<Vec<f32>>(), vec![6.0, 8.0, 0.0, -0.5]);
+
+	Ok(())
+}
@@ -0,0 +1,45 @@
+use id::NodeID;
+use ops::norm::normalise::{Normalise, NormaliseKind};
+use smallvec::SmallVec;
+
+/// Group Normalisation
+///
+/// The innermost (channel) axis is split into groups of equal size,
+/// and each group of each example is normalised to zero mean and unit variance over the normalisation (spatial) axes and the channels in the group.
+/// A learnable per-channel scale and shift are then optionally applied.
+///
+/// If no axes are supplied, all axes except the outermost (batch) and innermost (channel) axes are used.
+/// The innermost axis should not be included in the supplied axes, as the channels in each group are always normalised together.
+///
+/// With one group this is equivalent to `LayerNorm`, and with one channel per group this is equivalent to `InstanceNorm`.
+pub type GroupNorm = Normalise<Group>;
+
+#[derive(Clone, Debug)]
+pub struct Group {
+	groups: usize,
+}
+
+impl NormaliseKind for Group {
+	fn type_name(&self) -> &'static str {
+		"GroupNorm"
+	}
+
+	fn default_axis(&self, axis: usize, ndim: usize) -> bool {
+		axis > 0 && axis + 1 < ndim
+	}
+
+	fn affine_mask(&self, ndim: usize, _normalise_mask: &[bool]) -> SmallVec<[bool; 6]> {
+		(0..ndim).map(|axis| axis + 1 == ndim).collect()
+	}
+
+	fn groups(&self) -> Option<usize> {
+		Some(self.groups)
+	}
+}
+
+impl GroupNorm {
+	/// Creates a `GroupNorm` op, the number of channels must be divisible by `groups`.
+	pub fn new(input_id: &NodeID, output_id: &NodeID, groups: usize) -> Self {
+		Normalise::with_kind(input_id, output_id, Group{groups: groups})
+	}
+}
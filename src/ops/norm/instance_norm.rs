@@ -0,0 +1,35 @@
+use id::NodeID;
+use ops::norm::normalise::{Normalise, NormaliseKind};
+use smallvec::SmallVec;
+
+/// Instance Normalisation
+///
+/// Normalises each channel of each example to zero mean and unit variance over the normalisation (spatial) axes,
+/// then optionally applies a learnable per-channel scale and shift.
+///
+/// The innermost axis is taken to be the channel axis.
+/// If no axes are supplied, all axes except the outermost (batch) and innermost (channel) axes are used.
+pub type InstanceNorm = Normalise<Instance>;
+
+#[derive(Clone, Debug)]
+pub struct Instance;
+
+impl NormaliseKind for Instance {
+	fn type_name(&self) -> &'static str {
+		"InstanceNorm"
+	}
+
+	fn default_axis(&self, axis: usize, ndim: usize) -> bool {
+		axis > 0 && axis + 1 < ndim
+	}
+
+	fn affine_mask(&self, ndim: usize, _normalise_mask: &[bool]) -> SmallVec<[bool; 6]> {
+		(0..ndim).map(|axis| axis + 1 == ndim).collect()
+	}
+}
+
+impl InstanceNorm {
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self {
+		Normalise::with_kind(input_id, output_id, Instance)
+	}
+}
@@ -0,0 +1,35 @@
+use id::NodeID;
+use ops::norm::normalise::{Normalise, NormaliseKind};
+use smallvec::SmallVec;
+
+/// Layer Normalisation
+///
+/// Normalises each example to zero mean and unit variance over the normalisation axes, then optionally applies a learnable scale and shift.
+/// Unlike `BatchNorm` the statistics do not depend on the other examples in the batch.
+///
+/// If no axes are supplied, all axes except the outermost (batch) axis are used.
+/// Unique scale and shift parameters are assigned to each element of the normalisation axes with Known size.
+pub type LayerNorm = Normalise<Layer>;
+
+#[derive(Clone, Debug)]
+pub struct Layer;
+
+impl NormaliseKind for Layer {
+	fn type_name(&self) -> &'static str {
+		"LayerNorm"
+	}
+
+	fn default_axis(&self, axis: usize, _ndim: usize) -> bool {
+		axis > 0
+	}
+
+	fn affine_mask(&self, _ndim: usize, normalise_mask: &[bool]) -> SmallVec<[bool; 6]> {
+		normalise_mask.iter().cloned().collect()
+	}
+}
+
+impl LayerNorm {
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self {
+		Normalise::with_kind(input_id, output_id, Layer)
+	}
+}
@@ -0,0 +1,4 @@
+pub mod normalise;
+pub mod layer_norm;
+pub mod instance_norm;
+pub mod group_norm;
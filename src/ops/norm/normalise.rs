@@ -0,0 +1,515 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use storage::Storage;
+use id::{NodeID, DataID, OpID, PassID};
+use init::Initialiser;
+use ops::{standard_op_name, standard_inner_parameter_name, Op, OpInstance, Pass};
+use ops::shape::normalise_axis;
+use shape::NodeDim;
+use ndarray::{ArrayD, ArrayViewD, ArrayViewMutD, Zip};
+use smallvec::SmallVec;
+use std::any::Any;
+use std::fmt::Debug;
+
+
+/// The behaviour which distinguishes each normalisation op that calculates statistics per example rather than per batch.
+pub trait NormaliseKind: Clone + Debug + 'static {
+	fn type_name(&self) -> &'static str;
+
+	/// Returns whether `axis` is normalised when no axes are supplied.
+	fn default_axis(&self, axis: usize, ndim: usize) -> bool;
+
+	/// Returns the mask of axes which are assigned unique scale and shift parameters.
+	fn affine_mask(&self, ndim: usize, normalise_mask: &[bool]) -> SmallVec<[bool; 6]>;
+
+	/// If not `None` the innermost axis is split into this many groups, and statistics are also calculated over the channels in each group.
+	fn groups(&self) -> Option<usize> {
+		None
+	}
+}
+
+/// Shared builder for `LayerNorm`, `InstanceNorm` and `GroupNorm`.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Normalise<K: NormaliseKind> {
+	name: Option<String>,
+	input_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	affine: bool,
+	epsilon: f32,
+	kind: K,
+}
+
+impl<K: NormaliseKind> Normalise<K> {
+	/// Creates a normalisation op of the given kind, usually called via `LayerNorm::new`, `InstanceNorm::new` or `GroupNorm::new`.
+	pub fn with_kind(input_id: &NodeID, output_id: &NodeID, kind: K) -> Self {
+		Normalise {
+			name: None,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+			axes: SmallVec::new(),
+			affine: true,
+			epsilon: 1e-5,
+			kind: kind,
+		}
+	}
+
+	/// Supply which axes the statistics are calculated over.
+	///
+	/// If axes is empty, the default axes of the op are used.
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	///
+	/// Default: empty
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` learnable scale and shift `Parameter` nodes are created and applied after normalisation.
+	///
+	/// Default: `true`
+	pub fn affine(mut self, affine: bool) -> Self {
+		self.affine = affine;
+		self
+	}
+
+	/// A small value added to the variance before normalising, for numerical stability.
+	///
+	/// Default: 1e-5
+	pub fn epsilon(mut self, epsilon: f32) -> Self {
+		self.epsilon = epsilon;
+		self
+	}
+}
+
+impl<K: NormaliseKind> Op for Normalise<K> {
+	type InstanceType = NormaliseInstance;
+
+	fn type_name(&self) -> &'static str {
+		self.kind.type_name()
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone()], &[self.output_id.clone()]);
+
+		let ndim = self.input_id.shape().ndim();
+		let groups = self.kind.groups();
+		if groups.is_some() {
+			ensure!(ndim >= 1, ErrorKind::ShapePropagationError(name.clone(), format!("input shape: {:?} must have an innermost axis to split into groups", self.input_id.shape())));
+		}
+
+		let mut normalise_mask = if self.axes.len() == 0 {
+			(0..ndim).map(|axis| self.kind.default_axis(axis, ndim)).collect()
+		} else {
+			axes_mask(ndim, &self.axes).map_err(|e| ErrorKind::ShapePropagationError(name.clone(), e.to_string()))?
+		};
+		if groups.is_some() {
+			// the channels are normalised within each group rather than as a whole
+			normalise_mask[ndim - 1] = false;
+		}
+
+		let (scale_id, shift_id) = if self.affine {
+			let affine_mask = self.kind.affine_mask(ndim, &normalise_mask);
+			let affine_shape = {
+				let input_shape = self.input_id.shape();
+				let mut affine_shape = vec![1; input_shape.ndim()];
+				for axis in 0..input_shape.ndim() {
+					if let NodeDim::Known(dim) = input_shape.dimensions()[axis] {
+						if affine_mask[axis] {
+							affine_shape[axis] = dim;
+						}
+					}
+				}
+				affine_shape
+			};
+
+			let scale_name = standard_inner_parameter_name(&name, graph);
+			let scale_id = graph.new_node(affine_shape.clone().into(), scale_name, tag![Parameter])?;
+			graph.set_initialiser(&scale_id, Initialiser::fill(1.0));
+
+			let shift_name = standard_inner_parameter_name(&name, graph);
+			let shift_id = graph.new_node(affine_shape.into(), shift_name, tag![Parameter])?;
+
+			(Some(scale_id), Some(shift_id))
+		} else {
+			(None, None)
+		};
+
+		Ok(NormaliseInstance{
+			name: name,
+			input_id: self.input_id.clone(),
+			output_id: self.output_id.clone(),
+			scale_id: scale_id.clone(),
+			shift_id: shift_id.clone(),
+			forward_id: graph.add_pass(NormaliseForward::new(
+					self.input_id.clone(),
+					scale_id.clone(),
+					shift_id.clone(),
+					self.output_id.clone(),
+					normalise_mask.clone(),
+					groups,
+					self.epsilon)),
+			backward_id: graph.add_pass(NormaliseBackward::new(
+					self.input_id.clone(),
+					scale_id.clone(),
+					shift_id.clone(),
+					self.output_id.clone(),
+					normalise_mask,
+					groups,
+					self.epsilon)),
+		})
+	}
+}
+
+/// Returns a mask with the listed axes marked true.
+///
+/// Each element of `axes` must be in the range [-len, len).
+pub fn axes_mask(len: usize, axes: &[isize]) -> Result<SmallVec<[bool; 6]>> {
+	let mut mask: SmallVec<[bool; 6]> = (0..len).map(|_| false).collect();
+	for &axis in axes {
+		mask[normalise_axis(axis, len)?] = true;
+	}
+	Ok(mask)
+}
+
+#[derive(Clone, Debug)]
+pub struct NormaliseInstance {
+	name: String,
+	input_id: NodeID,
+	output_id: NodeID,
+	scale_id: Option<NodeID>,
+	shift_id: Option<NodeID>,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for NormaliseInstance {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){(vec![self.input_id.clone()], vec![self.output_id.clone()])}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{
+		self.scale_id.iter().chain(&self.shift_id).cloned().collect()
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).clone();
+		shapes.merge_with(&self.output_id, &input_shape)
+	}
+}
+
+/// Returns the shape of the input after splitting the innermost axis into groups, and the shape of the chunks over which statistics are calculated.
+fn group_shapes(pass_name: String, input_shape: &[usize], normalise_mask: &[bool], groups: Option<usize>) -> Result<(SmallVec<[usize; 6]>, SmallVec<[usize; 6]>)> {
+	ensure!(
+		input_shape.len() == normalise_mask.len(),
+		ErrorKind::PassError(pass_name, format!("input shape: {:?} did not have the expected number of dimensions: {}", input_shape, normalise_mask.len()))
+	);
+
+	let mut grouped_shape: SmallVec<[usize; 6]> = input_shape.iter().cloned().collect();
+	let mut mask: SmallVec<[bool; 6]> = normalise_mask.iter().cloned().collect();
+	if let Some(groups) = groups {
+		let channels = input_shape[input_shape.len() - 1];
+		ensure!(
+			groups > 0 && channels % groups == 0,
+			ErrorKind::PassError(pass_name, format!("innermost dimension of input shape: {:?} is not divisible into {} groups", input_shape, groups))
+		);
+		let len = grouped_shape.len();
+		grouped_shape[len - 1] = groups;
+		grouped_shape.push(channels/groups);
+		mask.push(true);
+	}
+
+	let chunk_shape = grouped_shape.iter().zip(&mask).map(|(&dim, &normalise)| if normalise {dim} else {1}).collect();
+
+	Ok((grouped_shape, chunk_shape))
+}
+
+/// Writes the normalised input to `normalised`, and returns the inverse standard deviation of each chunk.
+fn normalise(input: &ArrayViewD<f32>, mut normalised: ArrayViewMutD<f32>, chunk_shape: &[usize], epsilon: f32) -> Vec<f32> {
+	let n = chunk_shape.iter().product::<usize>() as f32;
+
+	input.exact_chunks(chunk_shape).into_iter().zip(normalised.exact_chunks_mut(chunk_shape)).map(|(in_chunk, mut norm_chunk)|{
+		let mean = in_chunk.scalar_sum()/n;
+		let variance = in_chunk.fold(0.0, |acc, &x| acc + (x - mean)*(x - mean))/n;
+		let inv_std = 1.0/(variance + epsilon).sqrt();
+		Zip::from(&mut norm_chunk).and(&in_chunk).apply(|norm, &x|{
+			*norm = (x - mean) * inv_std;
+		});
+		inv_std
+	}).collect()
+}
+
+/// Sums `values` over the axes which are broadcast in `grad`, and adds the result to `grad`.
+fn accumulate_broadcast(pass_name: String, grad: &mut ArrayViewMutD<f32>, values: &ArrayD<f32>) -> Result<()> {
+	ensure!(
+		grad.ndim() == values.ndim() && grad.shape().iter().zip(values.shape()).all(|(&g, &v)| g == 1 || g == v),
+		ErrorKind::PassError(pass_name, format!("parameter shape: {:?} could not be broadcast to input shape: {:?}", grad.shape(), values.shape()))
+	);
+	let grad_shape = grad.shape().to_vec();
+	for chunk in values.exact_chunks(grad_shape.as_slice()) {
+		*grad += &chunk;
+	}
+	Ok(())
+}
+
+#[derive(Clone, Debug)]
+pub struct NormaliseForward {
+	input_id: NodeID,
+	scale_id: Option<NodeID>,
+	shift_id: Option<NodeID>,
+	output_id: NodeID,
+	normalise_mask: SmallVec<[bool; 6]>,
+	groups: Option<usize>,
+	epsilon: f32,
+}
+
+impl NormaliseForward {
+	pub fn new(input_id: NodeID, scale_id: Option<NodeID>, shift_id: Option<NodeID>, output_id: NodeID, normalise_mask: SmallVec<[bool; 6]>, groups: Option<usize>, epsilon: f32) -> Self {
+		NormaliseForward {
+			input_id,
+			scale_id,
+			shift_id,
+			output_id,
+			normalise_mask,
+			groups,
+			epsilon,
+		}
+	}
+}
+
+impl Pass for NormaliseForward {
+	fn type_name(&self) -> &'static str {"NormaliseForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			Some(&self.input_id).into_iter().chain(&self.scale_id).chain(&self.shift_id).map(|node_id| node_id.value_id()).collect(),
+			vec![self.output_id.value_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		ensure!(
+			input.shape() == output.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input.shape(), output.shape()))
+		);
+
+		let input_shape = input.shape().to_vec();
+		let (grouped_shape, chunk_shape) = group_shapes(self.name(), &input_shape, &self.normalise_mask, self.groups)?;
+
+		let mut normalised = ArrayD::zeros(grouped_shape.as_slice());
+		normalise(&input.into_shape(grouped_shape.as_slice()).expect("input should be contiguous"), normalised.view_mut(), &chunk_shape, self.epsilon);
+		let normalised = normalised.into_shape(input_shape.as_slice()).expect("grouped shape should have the same number of elements as input shape");
+
+		match (&self.scale_id, &self.shift_id) {
+			(&Some(ref scale_id), &Some(ref shift_id)) => {
+				let scale = data.get::<f32>(&scale_id.value_id())?;
+				let shift = data.get::<f32>(&shift_id.value_id())?;
+				ensure!(
+					scale.broadcast(input_shape.as_slice()).is_some() && shift.broadcast(input_shape.as_slice()).is_some(),
+					ErrorKind::PassError(self.name(), format!("scale shape: {:?} or shift shape: {:?} could not be broadcast to input shape: {:?}", scale.shape(), shift.shape(), input_shape))
+				);
+				Zip::from(&mut output)
+					.and(&normalised)
+					.and_broadcast(&scale)
+					.and_broadcast(&shift)
+					.apply(|output, &normalised, &scale, &shift|{
+						*output += normalised * scale + shift;
+					});
+			},
+			_ => {
+				output += &normalised;
+			},
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct NormaliseBackward {
+	input_id: NodeID,
+	scale_id: Option<NodeID>,
+	shift_id: Option<NodeID>,
+	output_id: NodeID,
+	normalise_mask: SmallVec<[bool; 6]>,
+	groups: Option<usize>,
+	epsilon: f32,
+}
+
+impl NormaliseBackward {
+	pub fn new(input_id: NodeID, scale_id: Option<NodeID>, shift_id: Option<NodeID>, output_id: NodeID, normalise_mask: SmallVec<[bool; 6]>, groups: Option<usize>, epsilon: f32) -> Self {
+		NormaliseBackward {
+			input_id,
+			scale_id,
+			shift_id,
+			output_id,
+			normalise_mask,
+			groups,
+			epsilon,
+		}
+	}
+}
+
+impl Pass for NormaliseBackward {
+	fn type_name(&self) -> &'static str {"NormaliseBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			Some(&self.input_id).into_iter().chain(&self.scale_id).map(|node_id| node_id.value_id()).chain(Some(self.output_id.gradient_id())).collect(),
+			Some(&self.input_id).into_iter().chain(&self.scale_id).chain(&self.shift_id).map(|node_id| node_id.gradient_id()).collect()
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+
+		ensure!(
+			input.shape() == output_grad.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input.shape(), output_grad.shape()))
+		);
+
+		let input_shape = input.shape().to_vec();
+		let (grouped_shape, chunk_shape) = group_shapes(self.name(), &input_shape, &self.normalise_mask, self.groups)?;
+
+		let mut normalised = ArrayD::zeros(grouped_shape.as_slice());
+		let inv_stds = normalise(&input.into_shape(grouped_shape.as_slice()).expect("input should be contiguous"), normalised.view_mut(), &chunk_shape, self.epsilon);
+		let normalised = normalised.into_shape(input_shape.as_slice()).expect("grouped shape should have the same number of elements as input shape");
+
+		if let Some(ref shift_id) = self.shift_id {
+			if data.is_required(&shift_id.gradient_id()) {
+				let mut shift_grad = data.get_mut::<f32>(&shift_id.gradient_id())?;
+				accumulate_broadcast(self.name(), &mut shift_grad, &output_grad.to_owned())?;
+			}
+		}
+
+		// the gradient w.r.t. the normalised input
+		let normalised_grad = if let Some(ref scale_id) = self.scale_id {
+			if data.is_required(&scale_id.gradient_id()) {
+				let mut scale_grad = data.get_mut::<f32>(&scale_id.gradient_id())?;
+				let mut product = output_grad.to_owned();
+				product *= &normalised;
+				accumulate_broadcast(self.name(), &mut scale_grad, &product)?;
+			}
+
+			let scale = data.get::<f32>(&scale_id.value_id())?;
+			ensure!(
+				scale.broadcast(input_shape.as_slice()).is_some(),
+				ErrorKind::PassError(self.name(), format!("scale shape: {:?} could not be broadcast to input shape: {:?}", scale.shape(), input_shape))
+			);
+			let mut normalised_grad = output_grad.to_owned();
+			normalised_grad *= &scale;
+			normalised_grad
+		} else {
+			output_grad.to_owned()
+		};
+
+		if data.is_required(&self.input_id.gradient_id()) {
+			let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+			let mut input_grad = input_grad.view_mut().into_shape(grouped_shape.as_slice()).expect("input gradient should be contiguous");
+			let normalised = normalised.into_shape(grouped_shape.as_slice()).expect("grouped shape should have the same number of elements as input shape");
+			let normalised_grad = normalised_grad.into_shape(grouped_shape.as_slice()).expect("grouped shape should have the same number of elements as input shape");
+
+			let n = chunk_shape.iter().product::<usize>() as f32;
+			let iter = input_grad.exact_chunks_mut(chunk_shape.as_slice()).into_iter()
+				.zip(normalised.exact_chunks(chunk_shape.as_slice()))
+				.zip(normalised_grad.exact_chunks(chunk_shape.as_slice()))
+				.zip(inv_stds);
+
+			// dx = inv_std * (dn - mean(dn) - n * mean(dn * n)), where n is the normalised input, and dn its gradient
+			for (((mut in_grad_chunk, norm_chunk), norm_grad_chunk), inv_std) in iter {
+				let mean_grad = norm_grad_chunk.scalar_sum()/n;
+				let mut sum_grad_norm = 0.0;
+				Zip::from(&norm_grad_chunk).and(&norm_chunk).apply(|&g, &x|{
+					sum_grad_norm += g * x;
+				});
+				let mean_grad_norm = sum_grad_norm/n;
+				Zip::from(&mut in_grad_chunk)
+					.and(&norm_chunk)
+					.and(&norm_grad_chunk)
+					.apply(|input_grad, &x, &g|{
+						*input_grad += inv_std * (g - mean_grad - x * mean_grad_norm);
+					});
+			}
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_normalise_backprop(){
+	_normalise_backprop().unwrap();
+}
+
+fn _normalise_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use ops::norm::layer_norm::LayerNorm;
+	use ops::norm::instance_norm::InstanceNorm;
+	use ops::norm::group_norm::GroupNorm;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 5, 4, 6], "input", tag![])?;
+	let node2 = g.new_node(shape![3, 5, 4, 6], "output", tag![])?;
+	let node3 = g.new_node(shape![3, 5, 4, 6], "target", tag![])?;
+
+	let _o1 = g.new_op(LayerNorm::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(LayerNorm::new(&node1, &node2).axes(&[-1]).affine(false), tag![])?;
+	let _o3 = g.new_op(InstanceNorm::new(&node1, &node2), tag![])?;
+	let _o4 = g.new_op(InstanceNorm::new(&node1, &node2).axes(&[-2]).affine(false), tag![])?;
+	let _o5 = g.new_op(GroupNorm::new(&node1, &node2, 3), tag![])?;
+	let _o6 = g.new_op(GroupNorm::new(&node1, &node2, 2).axes(&[1]).affine(false), tag![])?;
+	let _o7 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_normalise_invalid_axes(){
+	_normalise_invalid_axes().unwrap();
+}
+
+fn _normalise_invalid_axes() -> Result<()>{
+	use graph::GraphDef;
+	use ops::norm::layer_norm::LayerNorm;
+	use ops::norm::instance_norm::InstanceNorm;
+	use ops::norm::group_norm::GroupNorm;
+	use shape::NodeShape;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 5, 6], "input", tag![])?;
+	let node2 = g.new_node(shape![3, 5, 6], "output", tag![])?;
+	let node3 = g.new_node(NodeShape::from(Vec::<usize>::new()), "scalar_input", tag![])?;
+	let node4 = g.new_node(NodeShape::from(Vec::<usize>::new()), "scalar_output", tag![])?;
+
+	assert!(g.new_op(LayerNorm::new(&node1, &node2).axes(&[3]), tag![]).is_err());
+	assert!(g.new_op(InstanceNorm::new(&node1, &node2).axes(&[-4]), tag![]).is_err());
+	assert!(g.new_op(GroupNorm::new(&node3, &node4, 1), tag![]).is_err());
+
+	Ok(())
+}
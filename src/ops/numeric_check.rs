@@ -1,6 +1,7 @@
 use graph::{GraphDef, Result, Dependencies};
 use id::{NodeID, DataID, NodeTag};
 use ndarray::ArrayD;
+use dtype::{DType, TypedArrayD};
 use rand::thread_rng;
 use rand::distributions::{Normal, Distribution};
 use indexmap::IndexMap;
@@ -91,18 +92,29 @@ pub fn numeric_test(iters: usize, failures: usize, tolerance: f32, graph: &Graph
 pub fn numeric_error(graph: &GraphDef, step_size: f32, default_variance: f32, override_distributions: &mut IndexMap<NodeID, Box<FnMut()->f64>>) -> Result<(f32, f32)> {
 	let dependencies = Dependencies::new(&graph);
 
-	let input_ids: Vec<NodeID> = graph.get_nodes().iter().filter(|node_id| dependencies.data_inputs(&node_id.value_id()).len() == 0 && !node_id.tags().contains(&NodeTag::Parameter)).cloned().collect();
+	let input_ids: Vec<NodeID> = graph.get_nodes().iter().filter(|node_id| dependencies.data_inputs(&node_id.value_id()).len() == 0 && !node_id.tags().contains(&NodeTag::Parameter) && node_id.dtype() == DType::F32).cloned().collect();
+	// non-F32 inputs (labels, indices, masks) are held fixed and not checked
+	let typed_input_ids: Vec<NodeID> = graph.get_nodes().iter().filter(|node_id| dependencies.data_inputs(&node_id.value_id()).len() == 0 && node_id.dtype() != DType::F32).cloned().collect();
 	let parameter_ids: Vec<NodeID> = graph.get_nodes().iter().filter(|node_id| dependencies.data_inputs(&node_id.value_id()).len() == 0 && node_id.tags().contains(&NodeTag::Parameter)).cloned().collect();
 
 	let inputs_0 = generate_input_data(&input_ids, default_variance, override_distributions)?;
 	let params_0 = generate_input_data(&parameter_ids, default_variance, override_distributions)?;
+	let typed_inputs_0: Vec<TypedArrayD> = generate_input_data(&typed_input_ids, default_variance, override_distributions)?.into_iter().zip(&typed_input_ids)
+		.map(|(data, node_id)| TypedArrayD::from(data).cast(node_id.dtype())).collect();
 
 	let mut subgraph = graph.subgraph(
-		&input_ids.iter().chain(&parameter_ids).map(|node_id| node_id.value_id()).collect::<Vec<_>>(),
+		&input_ids.iter().chain(&typed_input_ids).chain(&parameter_ids).map(|node_id| node_id.value_id()).collect::<Vec<_>>(),
 		&input_ids.iter().chain(&parameter_ids).map(|node_id| node_id.gradient_id()).collect::<Vec<_>>())?;
 
-	let data_0 = inputs_0.iter().chain(&params_0).cloned().collect();
-	let output_0 = subgraph.execute(data_0)?.into_map();
+	let collect_data = |inputs: &[ArrayD<f32>], params: &[ArrayD<f32>]| -> Vec<TypedArrayD> {
+		inputs.iter().cloned().map(TypedArrayD::from)
+			.chain(typed_inputs_0.iter().cloned())
+			.chain(params.iter().cloned().map(TypedArrayD::from))
+			.collect()
+	};
+
+	let data_0 = collect_data(&inputs_0, &params_0);
+	let output_0 = subgraph.execute_typed(data_0)?.into_map();
 	
 
 
@@ -111,11 +123,11 @@ pub fn numeric_error(graph: &GraphDef, step_size: f32, default_variance: f32, ov
 	if parameter_ids.len() > 0 {
 		let (params_1, params_2, grad_norm) = step(step_size, &parameter_ids, &params_0, &output_0);
 
-		let data_1 = inputs_0.iter().chain(&params_1).cloned().collect();
-		let loss_1 = subgraph.execute(data_1)?.loss();
+		let data_1 = collect_data(&inputs_0, &params_1);
+		let loss_1 = subgraph.execute_typed(data_1)?.loss();
 
-		let data_2 = inputs_0.iter().chain(&params_2).cloned().collect();
-		let loss_2 = subgraph.execute(data_2)?.loss();
+		let data_2 = collect_data(&inputs_0, &params_2);
+		let loss_2 = subgraph.execute_typed(data_2)?.loss();
 
 		let expected_diff = 2.0*step_size*grad_norm;
 		let diff = loss_2 - loss_1;
@@ -128,11 +140,11 @@ pub fn numeric_error(graph: &GraphDef, step_size: f32, default_variance: f32, ov
 	if input_ids.len() > 0 {
 		let (inputs_1, inputs_2, grad_norm) = step(step_size, &input_ids, &inputs_0, &output_0);
 
-		let data_1 = inputs_1.iter().chain(&params_0).cloned().collect();
-		let loss_1 = subgraph.execute(data_1)?.loss();
+		let data_1 = collect_data(&inputs_1, &params_0);
+		let loss_1 = subgraph.execute_typed(data_1)?.loss();
 
-		let data_2 = inputs_2.iter().chain(&params_0).cloned().collect();
-		let loss_2 = subgraph.execute(data_2)?.loss();
+		let data_2 = collect_data(&inputs_2, &params_0);
+		let loss_2 = subgraph.execute_typed(data_2)?.loss();
 
 		let expected_diff = 2.0*step_size*grad_norm;
 		let diff = loss_2 - loss_1;
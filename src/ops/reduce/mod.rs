@@ -1,2 +1,7 @@
 pub mod reduce_sum;
-pub mod reduce_mean;
\ No newline at end of file
+pub mod reduce_mean;
+pub mod reduction;
+pub mod reduce_max;
+pub mod reduce_prod;
+pub mod reduce_logsumexp;
+pub mod reduce_variance;
\ No newline at end of file
@@ -0,0 +1,151 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::reduce::reduction::{ReduceFunc, ReductionInstance, reduction_build};
+use smallvec::SmallVec;
+
+#[derive(Clone, Debug)]
+pub struct ReduceLogSumExpFunc{}
+
+impl ReduceFunc for ReduceLogSumExpFunc {
+	fn value(&self, input: &[f32]) -> f32 {
+		let max = input.iter().fold(::std::f32::NEG_INFINITY, |max, &x| x.max(max));
+		if !max.is_finite() {
+			return max;
+		}
+		max + input.iter().fold(0.0, |sum, &x| sum + (x - max).exp()).ln()
+	}
+
+	fn gradient(&self, input: &[f32], output: f32, output_grad: f32, input_grad: &mut [f32]) {
+		// The gradient is the softmax of the inputs
+		if !output.is_finite() {
+			return;
+		}
+		for (g, &x) in input_grad.iter_mut().zip(input) {
+			*g += output_grad*(x - output).exp();
+		}
+	}
+}
+
+
+/// ReduceLogSumExp
+///
+/// Calculates `ln(sum(exp(x)))` over the reduced axes, subtracting the maximum before exponentiation to avoid overflow.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct ReduceLogSumExp {
+	name: Option<String>,
+	input_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+}
+
+impl ReduceLogSumExp {
+
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self{
+		ReduceLogSumExp {
+			name: None,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+			axes: SmallVec::new(),
+			keep_dims: false,
+		}
+	}
+
+	/// Supply which axes are to be reduced across.
+	///
+	/// If axes is empty, all axes are reduced.
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	///
+	/// Default: empty
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the reduced axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+}
+
+impl Op for ReduceLogSumExp {
+	type InstanceType = ReductionInstance<ReduceLogSumExpFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"ReduceLogSumExp"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		reduction_build(graph, &self, &self.name, &self.input_id, &self.output_id, &self.axes, self.keep_dims, ReduceLogSumExpFunc{})
+	}
+}
+
+
+#[test]
+fn test_reduce_logsumexp_backprop(){
+	_reduce_logsumexp_backprop().unwrap();
+}
+
+fn _reduce_logsumexp_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 11, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 11, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceLogSumExp::new(&node1, &node2).axes(&[-2, 1]), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_reduce_logsumexp_keep_dims_backprop(){
+	_reduce_logsumexp_keep_dims_backprop().unwrap();
+}
+
+fn _reduce_logsumexp_keep_dims_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 1, 11, 1, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 1, 11, 1, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceLogSumExp::new(&node1, &node2).axes(&[-2, 1]).keep_dims(true), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
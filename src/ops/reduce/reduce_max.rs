@@ -0,0 +1,321 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::reduce::reduction::{ReduceFunc, ReductionInstance, reduction_build};
+use smallvec::SmallVec;
+
+/// Adds the output gradient to the inputs equal to the output, split evenly between ties
+fn extremum_gradient(input: &[f32], output: f32, output_grad: f32, input_grad: &mut [f32]) {
+	let ties = input.iter().filter(|&&x| x == output).count();
+	if ties == 0 {
+		return;
+	}
+	let grad = output_grad/ties as f32;
+	for (g, &x) in input_grad.iter_mut().zip(input) {
+		if x == output {
+			*g += grad;
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct ReduceMaxFunc{}
+
+impl ReduceFunc for ReduceMaxFunc {
+	fn value(&self, input: &[f32]) -> f32 {
+		input.iter().fold(::std::f32::NEG_INFINITY, |max, &x| x.max(max))
+	}
+
+	fn gradient(&self, input: &[f32], output: f32, output_grad: f32, input_grad: &mut [f32]) {
+		extremum_gradient(input, output, output_grad, input_grad);
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct ReduceMinFunc{}
+
+impl ReduceFunc for ReduceMinFunc {
+	fn value(&self, input: &[f32]) -> f32 {
+		input.iter().fold(::std::f32::INFINITY, |min, &x| x.min(min))
+	}
+
+	fn gradient(&self, input: &[f32], output: f32, output_grad: f32, input_grad: &mut [f32]) {
+		extremum_gradient(input, output, output_grad, input_grad);
+	}
+}
+
+
+/// ReduceMax
+///
+/// Where multiple inputs are equal to the maximum, the gradient is split evenly between them.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct ReduceMax {
+	name: Option<String>,
+	input_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+}
+
+impl ReduceMax {
+
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self{
+		ReduceMax {
+			name: None,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+			axes: SmallVec::new(),
+			keep_dims: false,
+		}
+	}
+
+	/// Supply which axes are to be reduced across.
+	///
+	/// If axes is empty, all axes are reduced.
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	///
+	/// Default: empty
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the reduced axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+}
+
+impl Op for ReduceMax {
+	type InstanceType = ReductionInstance<ReduceMaxFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"ReduceMax"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		reduction_build(graph, &self, &self.name, &self.input_id, &self.output_id, &self.axes, self.keep_dims, ReduceMaxFunc{})
+	}
+}
+
+
+/// ReduceMin
+///
+/// Where multiple inputs are equal to the minimum, the gradient is split evenly between them.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct ReduceMin {
+	name: Option<String>,
+	input_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+}
+
+impl ReduceMin {
+
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self{
+		ReduceMin {
+			name: None,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+			axes: SmallVec::new(),
+			keep_dims: false,
+		}
+	}
+
+	/// Supply which axes are to be reduced across.
+	///
+	/// If axes is empty, all axes are reduced.
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	///
+	/// Default: empty
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the reduced axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+}
+
+impl Op for ReduceMin {
+	type InstanceType = ReductionInstance<ReduceMinFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"ReduceMin"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		reduction_build(graph, &self, &self.name, &self.input_id, &self.output_id, &self.axes, self.keep_dims, ReduceMinFunc{})
+	}
+}
+
+
+#[test]
+fn test_reduce_max_backprop(){
+	_reduce_max_backprop().unwrap();
+}
+
+fn _reduce_max_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 11, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 11, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceMax::new(&node1, &node2).axes(&[-2, 1]), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_reduce_max_keep_dims_backprop(){
+	_reduce_max_keep_dims_backprop().unwrap();
+}
+
+fn _reduce_max_keep_dims_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 1, 11, 1, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 1, 11, 1, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceMax::new(&node1, &node2).axes(&[-2, 1]).keep_dims(true), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_reduce_min_backprop(){
+	_reduce_min_backprop().unwrap();
+}
+
+fn _reduce_min_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 11, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 11, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceMin::new(&node1, &node2).axes(&[-2, 1]), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_reduce_min_keep_dims_backprop(){
+	_reduce_min_keep_dims_backprop().unwrap();
+}
+
+fn _reduce_min_keep_dims_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 1, 11, 1, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 1, 11, 1, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceMin::new(&node1, &node2).axes(&[-2, 1]).keep_dims(true), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_reduce_max_ties(){
+	_reduce_max_ties().unwrap();
+}
+
+fn _reduce_max_ties() -> Result<()>{
+	use graph::GraphDef;
+	use ndarray::{ArrayD, IxDyn};
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 4], "input", tag![])?;
+	let node2 = g.new_node(shape![2], "output", tag![])?;
+
+	let _o1 = g.new_op(ReduceMax::new(&node1, &node2).axes(&[1]), tag![])?;
+
+	let mut subgraph = g.subgraph(&[node1.value_id(), node2.gradient_id()], &[node2.value_id(), node1.gradient_id()])?;
+	let input = ArrayD::from_shape_vec(IxDyn(&[2, 4]), vec![1.0, 3.0, 3.0, -2.0, 0.0, 5.0, 1.0, 2.0]).unwrap();
+	let output_grad = ArrayD::from_shape_vec(IxDyn(&[2]), vec![1.0, 2.0]).unwrap();
+	let storage = subgraph.execute(vec![input, output_grad])?;
+
+	let output = storage.get::<f32>(&node2.value_id())?;
+	assert_eq!(output.iter().cloned().collect::<Vec<_>>(), vec![3.0, 5.0]);
+	let input_grad = storage.get::<f32>(&node1.gradient_id())?;
+	assert_eq!(input_grad.iter().cloned().collect::<Vec<_>>(), vec![0.0, 0.5, 0.5, 0.0, 0.0, 2.0, 0.0, 0.0]);
+
+	Ok(())
+}
@@ -0,0 +1,151 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::reduce::reduction::{ReduceFunc, ReductionInstance, reduction_build};
+use smallvec::SmallVec;
+
+#[derive(Clone, Debug)]
+pub struct ReduceProdFunc{}
+
+impl ReduceFunc for ReduceProdFunc {
+	fn value(&self, input: &[f32]) -> f32 {
+		input.iter().product()
+	}
+
+	fn gradient(&self, input: &[f32], _output: f32, output_grad: f32, input_grad: &mut [f32]) {
+		// The gradient of each input is the product of all other inputs,
+		// calculated from prefix and suffix products rather than division so that zeros are handled correctly
+		let mut prefixes = Vec::with_capacity(input.len());
+		let mut prefix = output_grad;
+		for &x in input {
+			prefixes.push(prefix);
+			prefix *= x;
+		}
+		let mut suffix = 1.0;
+		for ((g, &x), prefix) in input_grad.iter_mut().zip(input).zip(prefixes).rev() {
+			*g += prefix*suffix;
+			suffix *= x;
+		}
+	}
+}
+
+
+/// ReduceProd
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct ReduceProd {
+	name: Option<String>,
+	input_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+}
+
+impl ReduceProd {
+
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self{
+		ReduceProd {
+			name: None,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+			axes: SmallVec::new(),
+			keep_dims: false,
+		}
+	}
+
+	/// Supply which axes are to be reduced across.
+	///
+	/// If axes is empty, all axes are reduced.
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	///
+	/// Default: empty
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the reduced axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+}
+
+impl Op for ReduceProd {
+	type InstanceType = ReductionInstance<ReduceProdFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"ReduceProd"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		reduction_build(graph, &self, &self.name, &self.input_id, &self.output_id, &self.axes, self.keep_dims, ReduceProdFunc{})
+	}
+}
+
+
+#[test]
+fn test_reduce_prod_backprop(){
+	_reduce_prod_backprop().unwrap();
+}
+
+fn _reduce_prod_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 11, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 11, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceProd::new(&node1, &node2).axes(&[-2, 1]), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_reduce_prod_keep_dims_backprop(){
+	_reduce_prod_keep_dims_backprop().unwrap();
+}
+
+fn _reduce_prod_keep_dims_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 1, 11, 1, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 1, 11, 1, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceProd::new(&node1, &node2).axes(&[-2, 1]).keep_dims(true), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
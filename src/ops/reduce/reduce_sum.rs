@@ -190,8 +190,8 @@ impl Pass for ReduceSumForward {
 	}
 
 	fn run(&self, data: &Storage) -> Result<Box<Any>> {
-		let input = data.get(&self.input_id.value_id())?;
-		let output = data.get_mut(&self.output_id.value_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		let input_shape: SmallVec<[usize; 6]> = input.shape().iter().cloned().collect();
 		let output_shape: SmallVec<[usize; 6]> = output.shape().iter().cloned().collect();
@@ -239,8 +239,8 @@ impl Pass for ReduceSumBackward {
 	}
 
 	fn run(&self, data: &Storage) -> Result<Box<Any>> {
-		let mut input_grad = data.get_mut(&self.input_id.gradient_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 
 		let input_shape: SmallVec<[usize; 6]> = input_grad.shape().iter().cloned().collect();
 		let output_shape: SmallVec<[usize; 6]> = output_grad.shape().iter().cloned().collect();
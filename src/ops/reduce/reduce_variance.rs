@@ -0,0 +1,326 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::reduce::reduction::{ReduceFunc, ReductionInstance, reduction_build};
+use smallvec::SmallVec;
+
+/// Returns the mean of the inputs, and the divisor of the sum of squared deviations
+fn moments(input: &[f32], unbiased: bool) -> (f32, f32) {
+	let n = input.len() as f32;
+	let mean = input.iter().sum::<f32>()/n;
+	let divisor = if unbiased {(n - 1.0).max(1.0)} else {n};
+	(mean, divisor)
+}
+
+/// Calculates the variance using two passes, which avoids the cancellation of the `E[x^2] - E[x]^2` formulation
+fn variance(input: &[f32], unbiased: bool) -> f32 {
+	let (mean, divisor) = moments(input, unbiased);
+	input.iter().fold(0.0, |sum, &x| sum + (x - mean)*(x - mean))/divisor
+}
+
+#[derive(Clone, Debug)]
+pub struct ReduceVarianceFunc{
+	unbiased: bool,
+}
+
+impl ReduceFunc for ReduceVarianceFunc {
+	fn value(&self, input: &[f32]) -> f32 {
+		variance(input, self.unbiased)
+	}
+
+	fn gradient(&self, input: &[f32], _output: f32, output_grad: f32, input_grad: &mut [f32]) {
+		let (mean, divisor) = moments(input, self.unbiased);
+		let scale = 2.0*output_grad/divisor;
+		for (g, &x) in input_grad.iter_mut().zip(input) {
+			*g += scale*(x - mean);
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct ReduceStdFunc{
+	unbiased: bool,
+}
+
+impl ReduceFunc for ReduceStdFunc {
+	fn value(&self, input: &[f32]) -> f32 {
+		variance(input, self.unbiased).sqrt()
+	}
+
+	fn gradient(&self, input: &[f32], output: f32, output_grad: f32, input_grad: &mut [f32]) {
+		// The gradient is undefined when all inputs are equal, in which case no gradient is propagated
+		if output <= 0.0 {
+			return;
+		}
+		let (mean, divisor) = moments(input, self.unbiased);
+		let scale = output_grad/(divisor*output);
+		for (g, &x) in input_grad.iter_mut().zip(input) {
+			*g += scale*(x - mean);
+		}
+	}
+}
+
+
+/// ReduceVariance
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct ReduceVariance {
+	name: Option<String>,
+	input_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	unbiased: bool,
+}
+
+impl ReduceVariance {
+
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self{
+		ReduceVariance {
+			name: None,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+			axes: SmallVec::new(),
+			keep_dims: false,
+			unbiased: false,
+		}
+	}
+
+	/// Supply which axes are to be reduced across.
+	///
+	/// If axes is empty, all axes are reduced.
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	///
+	/// Default: empty
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the reduced axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+
+	/// If `true` the sum of squared deviations is divided by `n - 1` rather than `n` (Bessel's correction).
+	///
+	/// Default: `false`
+	pub fn unbiased(mut self, unbiased: bool) -> Self {
+		self.unbiased = unbiased;
+		self
+	}
+}
+
+impl Op for ReduceVariance {
+	type InstanceType = ReductionInstance<ReduceVarianceFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"ReduceVariance"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		reduction_build(graph, &self, &self.name, &self.input_id, &self.output_id, &self.axes, self.keep_dims, ReduceVarianceFunc{unbiased: self.unbiased})
+	}
+}
+
+
+
+/// ReduceStd
+///
+/// Calculates the standard deviation over the reduced axes. Where all inputs in a group are equal, the gradient is zero.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct ReduceStd {
+	name: Option<String>,
+	input_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	unbiased: bool,
+}
+
+impl ReduceStd {
+
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self{
+		ReduceStd {
+			name: None,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+			axes: SmallVec::new(),
+			keep_dims: false,
+			unbiased: false,
+		}
+	}
+
+	/// Supply which axes are to be reduced across.
+	///
+	/// If axes is empty, all axes are reduced.
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	///
+	/// Default: empty
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the reduced axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+
+	/// If `true` the sum of squared deviations is divided by `n - 1` rather than `n` (Bessel's correction).
+	///
+	/// Default: `false`
+	pub fn unbiased(mut self, unbiased: bool) -> Self {
+		self.unbiased = unbiased;
+		self
+	}
+}
+
+impl Op for ReduceStd {
+	type InstanceType = ReductionInstance<ReduceStdFunc>;
+
+	fn type_name(&self) -> &'static str {
+		"ReduceStd"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		reduction_build(graph, &self, &self.name, &self.input_id, &self.output_id, &self.axes, self.keep_dims, ReduceStdFunc{unbiased: self.unbiased})
+	}
+}
+
+
+#[test]
+fn test_reduce_variance_backprop(){
+	_reduce_variance_backprop().unwrap();
+}
+
+fn _reduce_variance_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 11, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 11, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceVariance::new(&node1, &node2).axes(&[-2, 1]), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_reduce_variance_keep_dims_backprop(){
+	_reduce_variance_keep_dims_backprop().unwrap();
+}
+
+fn _reduce_variance_keep_dims_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 1, 11, 1, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 1, 11, 1, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceVariance::new(&node1, &node2).axes(&[-2, 1]).keep_dims(true), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_reduce_std_backprop(){
+	_reduce_std_backprop().unwrap();
+}
+
+fn _reduce_std_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 11, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 11, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceStd::new(&node1, &node2).axes(&[-2, 1]).unbiased(true), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_reduce_std_keep_dims_backprop(){
+	_reduce_std_keep_dims_backprop().unwrap();
+}
+
+fn _reduce_std_keep_dims_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 2, 11, 3, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 1, 11, 1, 5], "output", tag![])?;
+	let node3 = g.new_node(shape![7, 1, 11, 1, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(ReduceStd::new(&node1, &node2).axes(&[-2, 1]).keep_dims(true).unbiased(true), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
@@ -0,0 +1,255 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use shape::NodeShape;
+use ndarray::{ArrayD, ArrayViewD, Dimension, IxDyn, Zip};
+use smallvec::SmallVec;
+use std::any::Any;
+use std::fmt::Debug;
+use rayon::prelude::*;
+
+
+pub fn reduction_build<O: Op, F: ReduceFunc>(graph: &mut GraphDef, op: &O, name: &Option<String>, input: &NodeID, output: &NodeID, axes: &[isize], keep_dims: bool, func: F) -> Result<ReductionInstance<F>> {
+	let name = standard_op_name(op, name, graph, &[input.clone()], &[output.clone()]);
+	let axes: SmallVec<[isize; 6]> = axes.iter().cloned().collect();
+
+	Ok(ReductionInstance{
+		name: name,
+		input_id: input.clone(),
+		output_id: output.clone(),
+		axes: axes.clone(),
+		keep_dims: keep_dims,
+		func: func.clone(),
+		forward_id: graph.add_pass(ReductionForward::new(
+				input.clone(),
+				output.clone(),
+				axes.clone(),
+				keep_dims,
+				func.clone())),
+		backward_id: graph.add_pass(ReductionBackward::new(
+				input.clone(),
+				output.clone(),
+				axes.clone(),
+				keep_dims,
+				func.clone())),
+	})
+}
+
+
+/// Used to define a reduction which is not linear in the input, and so requires all of the values being reduced at once.
+pub trait ReduceFunc: Send + Sync + Clone + Debug + 'static {
+	/// For a given set of inputs, what is the reduced output
+	fn value(&self, input: &[f32]) -> f32;
+
+	/// For a given set of inputs, the reduced output, and the gradient of the output, add the gradients to each input
+	fn gradient(&self, input: &[f32], output: f32, output_grad: f32, input_grad: &mut [f32]);
+}
+
+#[derive(Clone, Debug)]
+pub struct ReductionInstance<F: ReduceFunc> {
+	name: String,
+	input_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	func: F,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl<F: ReduceFunc> OpInstance for ReductionInstance<F> {
+
+	fn name(&self) -> &str{&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){(vec![self.input_id.clone()], vec![self.output_id.clone()])}
+
+	fn inner_passes(&self) -> Vec<PassID>{vec![self.forward_id.clone(), self.backward_id.clone()]}
+
+	fn inner_ops(&self) -> Vec<OpID>{vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID>{vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+		let output_shape: NodeShape = calc_output_shape(input_shape.slice(), &self.axes, self.keep_dims).into();
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+fn calc_output_shape(input_shape: &[usize], axes: &[isize], keep_dims: bool) -> SmallVec<[usize; 6]> {
+	let reduce_mask = reduction_mask(input_shape.len(), &axes);
+	if keep_dims {
+		input_shape.iter().zip(&reduce_mask).map(|(&dim, &reduce)| {
+				if reduce {1} else {dim}
+			}).collect()
+	} else {
+		input_shape.iter().zip(&reduce_mask).filter_map(|(&dim, &reduce)| {
+				if reduce {None} else {Some(dim)}
+			}).collect()
+	}
+}
+
+/// Returns a mask indicating whether an axis should be reduced based on the axes list
+/// If axes is empty this returns all true,
+/// else only the axis provided are marked true.
+fn reduction_mask(len: usize, axes: &[isize]) -> SmallVec<[bool; 6]> {
+	let mut reduce = SmallVec::with_capacity(len);
+	if axes.len() == 0 {
+		for _ in 0..len {
+			reduce.push(true);
+		}
+	} else {
+		for _ in 0..len {
+			reduce.push(false);
+		}
+		for axis in axes {
+			reduce[(axis + len as isize) as usize % len] = true;
+		}
+	}
+	reduce
+}
+
+/// Returns an axis order which places the reduced axes innermost, and the size of the reduced group
+fn reduction_order(input_shape: &[usize], axes: &[isize]) -> (Vec<usize>, usize) {
+	let mask = reduction_mask(input_shape.len(), axes);
+	let order = (0..input_shape.len()).filter(|&i| !mask[i])
+		.chain((0..input_shape.len()).filter(|&i| mask[i]))
+		.collect();
+	let group_size = input_shape.iter().zip(&mask).filter_map(|(&dim, &reduce)| if reduce {Some(dim)} else {None}).product();
+	(order, group_size)
+}
+
+/// Copies the input into a contiguous buffer where each group of reduced values is contiguous, in output order
+fn grouped_values(input: &ArrayViewD<f32>, order: &[usize]) -> Vec<f32> {
+	input.view().permuted_axes(IxDyn(order)).iter().cloned().collect()
+}
+
+/// Checks the output shape and returns the permutation of input axes and the reduced group size
+fn check_shapes(pass_name: String, input_shape: &[usize], output_shape: &[usize], axes: &[isize], keep_dims: bool) -> Result<(Vec<usize>, usize)> {
+	let output_shape_actual = calc_output_shape(input_shape, axes, keep_dims);
+	ensure!(
+		output_shape_actual.as_slice() == output_shape,
+		ErrorKind::PassError(pass_name, format!("Output shape {:?} does not match reduced input shape {:?}", output_shape, output_shape_actual.as_slice()))
+	);
+	Ok(reduction_order(input_shape, axes))
+}
+
+
+#[derive(Clone, Debug)]
+pub struct ReductionForward<F: ReduceFunc> {
+	input_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	func: F,
+}
+
+impl<F: ReduceFunc> ReductionForward<F> {
+	pub fn new(input_id: NodeID, output_id: NodeID, axes: SmallVec<[isize; 6]>, keep_dims: bool, func: F) -> Self {
+		ReductionForward {
+			input_id,
+			output_id,
+			axes,
+			keep_dims,
+			func,
+		}
+	}
+}
+
+impl<F: ReduceFunc> Pass for ReductionForward<F> {
+	fn type_name(&self) -> &'static str {"ReductionForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.input_id.value_id()],
+			vec![self.output_id.value_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		let (order, group_size) = check_shapes(self.name(), input.shape(), output.shape(), &self.axes, self.keep_dims)?;
+		let values = grouped_values(&input, &order);
+
+		let mut results = vec![0.0; output.len()];
+		if group_size > 0 {
+			results.par_iter_mut().zip(values.par_chunks(group_size)).for_each(|(result, group)| {
+				*result = self.func.value(group);
+			});
+		}
+
+		for (output, result) in output.iter_mut().zip(results) {
+			*output += result;
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[derive(Clone, Debug)]
+pub struct ReductionBackward<F: ReduceFunc> {
+	input_id: NodeID,
+	output_id: NodeID,
+	axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	func: F,
+}
+
+impl<F: ReduceFunc> ReductionBackward<F> {
+	pub fn new(input_id: NodeID, output_id: NodeID, axes: SmallVec<[isize; 6]>, keep_dims: bool, func: F) -> Self {
+		ReductionBackward {
+			input_id,
+			output_id,
+			axes,
+			keep_dims,
+			func,
+		}
+	}
+}
+
+impl<F: ReduceFunc> Pass for ReductionBackward<F> {
+	fn type_name(&self) -> &'static str {"ReductionBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.input_id.value_id(), self.output_id.gradient_id()],
+			vec![self.input_id.gradient_id()]
+		)
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+
+		let (order, group_size) = check_shapes(self.name(), input.shape(), output_grad.shape(), &self.axes, self.keep_dims)?;
+		if group_size == 0 {
+			return Ok(Box::new(()));
+		}
+
+		let values = grouped_values(&input, &order);
+		let mut grads = vec![0.0; values.len()];
+
+		grads.par_chunks_mut(group_size)
+			.zip(values.par_chunks(group_size))
+			.zip(output_grad.as_slice().unwrap().par_iter())
+			.for_each(|((grad, group), &output_grad)| {
+				let output = self.func.value(group);
+				self.func.gradient(group, output, output_grad, grad);
+			});
+
+		let permuted_shape: Vec<usize> = order.iter().map(|&i| input.shape()[i]).collect();
+		let grads = ArrayD::from_shape_vec(IxDyn(&permuted_shape), grads).expect("Buffer size should match the input size");
+		Zip::from(input_grad.permuted_axes(IxDyn(&order)))
+			.and(&grads)
+			.apply(|input_grad, &grad| {
+				*input_grad += grad;
+			});
+
+		Ok(Box::new(()))
+	}
+}
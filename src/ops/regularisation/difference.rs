@@ -0,0 +1,454 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::loss::LossType;
+use shape::NodeShape;
+use smallvec::SmallVec;
+use ndarray::{ArrayD, ArrayViewD, Axis, Dimension, Slice, Zip};
+use std::any::Any;
+
+
+/// How the finite differences along each axis are combined into the loss of an element
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DifferenceNorm {
+	/// The sum of the absolute differences
+	Anisotropic,
+	/// The euclidean norm of the differences, smoothed as `sqrt(sum(d^2) + epsilon^2) - epsilon` so that it is differentiable at zero
+	Isotropic,
+	/// The sum of the squared differences
+	Squared,
+}
+
+pub fn difference_build<O: Op>(graph: &mut GraphDef, op: &O, name: &Option<String>, input1_id: &NodeID, input2_id: &Option<NodeID>, output: &Option<NodeID>,
+		norm: DifferenceNorm, epsilon: f32, axes: &[isize], multiplier: f32, mean_axes: &[isize], keep_dims: bool) -> Result<DifferenceInstance> {
+	let inputs: Vec<NodeID> = Some(input1_id.clone()).into_iter().chain(input2_id.clone()).collect();
+	let name = if let Some(ref output_id) = *output {
+		standard_op_name(op, name, graph, &inputs, &[output_id.clone()])
+	} else {
+		standard_op_name(op, name, graph, &inputs, &[])
+	};
+
+	// by default use all axes except the batch and channel axes
+	let ndim = input1_id.shape().ndim();
+	let axes: SmallVec<[usize; 6]> = if axes.is_empty() {
+		(1..ndim.saturating_sub(1)).collect()
+	} else {
+		axes.iter().map(|&axis| (axis + ndim as isize) as usize % ndim).collect()
+	};
+	ensure!(!axes.is_empty(), ErrorKind::ShapePropagationError(name.clone(), format!("no axes were supplied, and input shape {:?} has no spatial axes between the batch and channel axes", input1_id.shape())));
+	ensure!(norm != DifferenceNorm::Isotropic || epsilon > 0.0, ErrorKind::ShapePropagationError(name.clone(), format!("epsilon ({}) must be greater than zero for the isotropic norm", epsilon)));
+
+	let settings = DifferenceSettings {
+		norm: norm,
+		epsilon: epsilon,
+		axes: axes,
+		multiplier: multiplier,
+		mean_axes: mean_axes.iter().cloned().collect(),
+	};
+
+	let loss_type = if let Some(ref output_id) = *output {
+		LossType::Output{
+			output_id: output_id.clone(),
+			forward_id: graph.add_pass(DifferenceForward::new(
+				settings.clone(),
+				input1_id.clone(),
+				input2_id.clone(),
+				output_id.clone(),
+				keep_dims)),
+			backward_id: graph.add_pass(DifferenceBackward::new(
+				settings.clone(),
+				input1_id.clone(),
+				input2_id.clone(),
+				output_id.clone(),
+				keep_dims)),
+		}
+	} else {
+		LossType::Joint{
+			pass_id: graph.add_pass(DifferenceJointPass::new(
+				settings.clone(),
+				input1_id.clone(),
+				input2_id.clone()))
+		}
+	};
+
+	Ok(DifferenceInstance{
+		name: name,
+		input_ids: inputs,
+		loss_type: loss_type,
+		mean_axes: settings.mean_axes,
+		keep_dims: keep_dims,
+	})
+}
+
+
+#[derive(Clone, Debug)]
+pub struct DifferenceInstance {
+	name: String,
+	input_ids: Vec<NodeID>,
+	loss_type: LossType,
+	mean_axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+}
+
+impl OpInstance for DifferenceInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		match &self.loss_type {
+			&LossType::Joint{..} => (self.input_ids.clone(), vec![]),
+			&LossType::Output{ref output_id, ..} => (self.input_ids.clone(), vec![output_id.clone()]),
+		}
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		match &self.loss_type {
+			&LossType::Joint{ref pass_id} => vec![pass_id.clone()],
+			&LossType::Output{ref forward_id, ref backward_id, ..} => vec![forward_id.clone(), backward_id.clone()],
+		}
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {
+		vec![]
+	}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {
+		vec![]
+	}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		if let &LossType::Output{ref output_id, ..} = &self.loss_type {
+			let input_shape = shapes.get_shape(&self.input_ids[0]).to_data_shape()?;
+			for input_id in &self.input_ids[1..] {
+				ensure!(shapes.get_shape(input_id).to_data_shape()? == input_shape, "Shape of input2 did not match shape of input1");
+			}
+			let output_shape: NodeShape = calc_output_shape(input_shape.slice(), &self.mean_axes, self.keep_dims).into();
+			shapes.merge_with(output_id, &output_shape)
+		} else {
+			Ok(())
+		}
+	}
+
+}
+
+fn calc_output_shape(input_shape: &[usize], axes: &[isize], keep_dims: bool) -> SmallVec<[usize; 6]> {
+	let reduce_mask = reduction_mask(input_shape.len(), &axes);
+	if keep_dims {
+		input_shape.iter().zip(&reduce_mask).map(|(&dim, &reduce)| {
+				if reduce {1} else {dim}
+			}).collect()
+	} else {
+		input_shape.iter().zip(&reduce_mask).filter_map(|(&dim, &reduce)| {
+				if reduce {None} else {Some(dim)}
+			}).collect()
+	}
+}
+
+/// Returns a mask indicating whether an axis should be reduced based on the axes list
+fn reduction_mask(len: usize, axes: &[isize]) -> SmallVec<[bool; 6]> {
+	let mut reduce = SmallVec::with_capacity(len);
+	for _ in 0..len {
+		reduce.push(false);
+	}
+	for axis in axes {
+		reduce[(axis + len as isize) as usize % len] = true;
+	}
+	reduce
+}
+
+/// Returns the difference between each element and the next element along the axis, with zeros for the last element
+fn forward_difference(input: ArrayViewD<f32>, axis: usize) -> ArrayD<f32> {
+	let len = input.shape()[axis];
+	let mut output = ArrayD::zeros(input.shape());
+	if len > 1 {
+		let mut inner = output.slice_axis_mut(Axis(axis), Slice::from(0..len - 1));
+		inner += &input.slice_axis(Axis(axis), Slice::from(1..));
+		inner -= &input.slice_axis(Axis(axis), Slice::from(0..len - 1));
+	}
+	output
+}
+
+/// The transpose of `forward_difference()`, accumulating into the input gradient
+fn forward_difference_transpose(output_grad: ArrayViewD<f32>, axis: usize, input_grad: &mut ArrayD<f32>) {
+	let len = output_grad.shape()[axis];
+	if len > 1 {
+		let inner_grad = output_grad.slice_axis(Axis(axis), Slice::from(0..len - 1));
+		input_grad.slice_axis_mut(Axis(axis), Slice::from(1..)).scaled_add(1.0, &inner_grad);
+		input_grad.slice_axis_mut(Axis(axis), Slice::from(0..len - 1)).scaled_add(-1.0, &inner_grad);
+	}
+}
+
+/// The settings shared by all passes
+#[derive(Clone, Debug)]
+struct DifferenceSettings {
+	norm: DifferenceNorm,
+	epsilon: f32,
+	axes: SmallVec<[usize; 6]>,
+	multiplier: f32,
+	mean_axes: SmallVec<[isize; 6]>,
+}
+
+impl DifferenceSettings {
+	/// Checks the input shapes, returning the multiplier divided by the number of elements in each mean, and the keep_dims output shape
+	fn check(&self, pass_name: String, input1: &ArrayViewD<f32>, input2: Option<&ArrayViewD<f32>>) -> Result<(f32, SmallVec<[usize; 6]>)> {
+		if let Some(input2) = input2 {
+			ensure!(
+				input1.shape() == input2.shape(),
+				ErrorKind::PassError(pass_name.clone(), format!("input1 shape: {:?} did not match input2 shape: {:?}", input1.shape(), input2.shape()))
+			);
+		}
+		ensure!(
+			self.axes.iter().all(|&axis| axis < input1.ndim()),
+			ErrorKind::PassError(pass_name, format!("axes: {:?} are not all valid for input shape: {:?}", self.axes, input1.shape()))
+		);
+
+		let input_shape = input1.shape();
+		let divisor: usize = input_shape.iter().zip(reduction_mask(input_shape.len(), &self.mean_axes)).filter_map(|(dim, reduce)| if reduce{Some(dim)} else {None}).product();
+		Ok((self.multiplier/divisor as f32, calc_output_shape(input_shape, &self.mean_axes, true)))
+	}
+
+	/// Returns the forward differences of input1 along each axis, minus those of input2 if supplied
+	fn differences(&self, input1: &ArrayViewD<f32>, input2: Option<&ArrayViewD<f32>>) -> Vec<ArrayD<f32>> {
+		self.axes.iter().map(|&axis| {
+			let mut difference = forward_difference(input1.view(), axis);
+			if let Some(input2) = input2 {
+				difference -= &forward_difference(input2.view(), axis);
+			}
+			difference
+		}).collect()
+	}
+
+	/// Returns the euclidean norm of the differences at each element, smoothed by epsilon
+	fn smoothed_norm(&self, differences: &[ArrayD<f32>]) -> ArrayD<f32> {
+		let mut norm = ArrayD::from_elem(differences[0].shape(), self.epsilon * self.epsilon);
+		for difference in differences {
+			Zip::from(&mut norm).and(difference).apply(|norm, &d| *norm += d * d);
+		}
+		norm.mapv_into(f32::sqrt)
+	}
+
+	/// Returns the loss of each element, before reduction and the multiplier
+	fn loss(&self, differences: &[ArrayD<f32>]) -> ArrayD<f32> {
+		match self.norm {
+			DifferenceNorm::Isotropic => self.smoothed_norm(differences) - self.epsilon,
+			DifferenceNorm::Anisotropic | DifferenceNorm::Squared => {
+				let mut loss = ArrayD::zeros(differences[0].shape());
+				for difference in differences {
+					if self.norm == DifferenceNorm::Anisotropic {
+						Zip::from(&mut loss).and(difference).apply(|loss, &d| *loss += d.abs());
+					} else {
+						Zip::from(&mut loss).and(difference).apply(|loss, &d| *loss += d * d);
+					}
+				}
+				loss
+			},
+		}
+	}
+
+	/// Returns the gradient of input1, given the gradient of the loss of each element. The gradient of input2 is its negative.
+	fn gradient(&self, differences: &[ArrayD<f32>], loss_grad: &ArrayD<f32>) -> ArrayD<f32> {
+		let norm = if self.norm == DifferenceNorm::Isotropic {Some(self.smoothed_norm(differences))} else {None};
+
+		let mut input_grad = ArrayD::zeros(loss_grad.shape());
+		for (&axis, difference) in self.axes.iter().zip(differences) {
+			let mut difference_grad = loss_grad.clone();
+			match self.norm {
+				DifferenceNorm::Anisotropic => {
+					Zip::from(&mut difference_grad).and(difference).apply(|grad, &d| {
+						*grad *= if d > 0.0 {1.0} else if d < 0.0 {-1.0} else {0.0};
+					});
+				},
+				DifferenceNorm::Isotropic => {
+					Zip::from(&mut difference_grad).and(difference).and(norm.as_ref().unwrap()).apply(|grad, &d, &norm| *grad *= d/norm);
+				},
+				DifferenceNorm::Squared => {
+					Zip::from(&mut difference_grad).and(difference).apply(|grad, &d| *grad *= 2.0 * d);
+				},
+			}
+			forward_difference_transpose(difference_grad.view(), axis, &mut input_grad);
+		}
+		input_grad
+	}
+}
+
+#[derive(Clone, Debug)]
+struct DifferenceJointPass {
+	settings: DifferenceSettings,
+	input1_id: NodeID,
+	input2_id: Option<NodeID>,
+}
+
+impl DifferenceJointPass {
+	pub fn new(settings: DifferenceSettings, input1_id: NodeID, input2_id: Option<NodeID>) -> Self {
+		DifferenceJointPass {
+			settings,
+			input1_id,
+			input2_id,
+		}
+	}
+}
+
+impl Pass for DifferenceJointPass {
+	fn type_name(&self) -> &'static str {"DifferenceJointPass"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(Some(&self.input1_id).into_iter().chain(&self.input2_id).map(|id| id.value_id()).collect(),
+		Some(&self.input1_id).into_iter().chain(&self.input2_id).map(|id| id.gradient_id()).collect())
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = match self.input2_id {Some(ref id) => Some(data.get::<f32>(&id.value_id())?), None => None};
+		let (multiplier, _) = self.settings.check(self.name(), &input1, input2.as_ref())?;
+
+		let differences = self.settings.differences(&input1, input2.as_ref());
+		let error = self.settings.loss(&differences).scalar_sum() * multiplier;
+		data.loss_add(error);
+
+		let input1_required = data.is_required(&self.input1_id.gradient_id());
+		let input2_required = self.input2_id.as_ref().map(|id| data.is_required(&id.gradient_id())).unwrap_or(false);
+		if input1_required || input2_required {
+			let loss_grad = ArrayD::from_elem(input1.shape(), multiplier);
+			let input_grad = self.settings.gradient(&differences, &loss_grad);
+			if input1_required {
+				let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
+				input1_grad += &input_grad;
+			}
+			if input2_required {
+				let mut input2_grad = data.get_mut::<f32>(&self.input2_id.as_ref().unwrap().gradient_id())?;
+				input2_grad -= &input_grad;
+			}
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[derive(Clone, Debug)]
+struct DifferenceForward {
+	settings: DifferenceSettings,
+	input1_id: NodeID,
+	input2_id: Option<NodeID>,
+	output_id: NodeID,
+	keep_dims: bool,
+}
+
+impl DifferenceForward {
+	pub fn new(settings: DifferenceSettings, input1_id: NodeID, input2_id: Option<NodeID>, output_id: NodeID, keep_dims: bool) -> Self {
+		DifferenceForward {
+			settings,
+			input1_id,
+			input2_id,
+			output_id,
+			keep_dims,
+		}
+	}
+}
+
+impl Pass for DifferenceForward {
+	fn type_name(&self) -> &'static str {"DifferenceForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(Some(&self.input1_id).into_iter().chain(&self.input2_id).map(|id| id.value_id()).collect(),
+		vec![self.output_id.value_id()])
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = match self.input2_id {Some(ref id) => Some(data.get::<f32>(&id.value_id())?), None => None};
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
+		let (multiplier, output_shape_keep_dims) = self.settings.check(self.name(), &input1, input2.as_ref())?;
+
+		let output_shape_actual = calc_output_shape(input1.shape(), &self.settings.mean_axes, self.keep_dims);
+		ensure!(output_shape_actual.as_slice() == output.shape(), "Output shape {:?} does not match reduced input shape {:?}", output.shape(), output_shape_actual.as_slice());
+
+		let mut output = output.into_shape(&output_shape_keep_dims[..]).expect("This should have been caught by the ensure above");
+		let loss = self.settings.loss(&self.settings.differences(&input1, input2.as_ref()));
+
+		for loss_chunk in loss.exact_chunks(output_shape_keep_dims.as_slice()) {
+			output.scaled_add(multiplier, &loss_chunk);
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Clone, Debug)]
+struct DifferenceBackward {
+	settings: DifferenceSettings,
+	input1_id: NodeID,
+	input2_id: Option<NodeID>,
+	output_id: NodeID,
+	keep_dims: bool,
+}
+
+impl DifferenceBackward {
+	pub fn new(settings: DifferenceSettings, input1_id: NodeID, input2_id: Option<NodeID>, output_id: NodeID, keep_dims: bool) -> Self {
+		DifferenceBackward {
+			settings,
+			input1_id,
+			input2_id,
+			output_id,
+			keep_dims,
+		}
+	}
+}
+
+impl Pass for DifferenceBackward {
+	fn type_name(&self) -> &'static str {"DifferenceBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(Some(&self.input1_id).into_iter().chain(&self.input2_id).map(|id| id.value_id()).chain(Some(self.output_id.gradient_id())).collect(),
+		Some(&self.input1_id).into_iter().chain(&self.input2_id).map(|id| id.gradient_id()).collect())
+	}
+
+	fn run (&self, data: &Storage) -> Result<Box<Any>>{
+		let input1 = data.get::<f32>(&self.input1_id.value_id())?;
+		let input2 = match self.input2_id {Some(ref id) => Some(data.get::<f32>(&id.value_id())?), None => None};
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let (multiplier, output_shape_keep_dims) = self.settings.check(self.name(), &input1, input2.as_ref())?;
+
+		let output_shape_actual = calc_output_shape(input1.shape(), &self.settings.mean_axes, self.keep_dims);
+		ensure!(output_shape_actual.as_slice() == output_grad.shape(), "Output shape {:?} does not match reduced input shape {:?}", output_grad.shape(), output_shape_actual.as_slice());
+		let output_grad = output_grad.into_shape(&output_shape_keep_dims[..]).expect("This should have been caught by the ensure above");
+
+		let input1_required = data.is_required(&self.input1_id.gradient_id());
+		let input2_required = self.input2_id.as_ref().map(|id| data.is_required(&id.gradient_id())).unwrap_or(false);
+		if input1_required || input2_required {
+			let loss_grad = output_grad.broadcast(input1.shape()).unwrap().mapv(|grad| grad * multiplier);
+			let input_grad = self.settings.gradient(&self.settings.differences(&input1, input2.as_ref()), &loss_grad);
+			if input1_required {
+				let mut input1_grad = data.get_mut::<f32>(&self.input1_id.gradient_id())?;
+				input1_grad += &input_grad;
+			}
+			if input2_required {
+				let mut input2_grad = data.get_mut::<f32>(&self.input2_id.as_ref().unwrap().gradient_id())?;
+				input2_grad -= &input_grad;
+			}
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_forward_difference(){
+	use ndarray::arr2;
+
+	let input = arr2(&[[1.0, 2.0, 4.0], [0.0, -1.0, 3.0]]).into_dyn();
+	assert_eq!(forward_difference(input.view(), 0), arr2(&[[-1.0, -3.0, -1.0], [0.0, 0.0, 0.0]]).into_dyn());
+	assert_eq!(forward_difference(input.view(), 1), arr2(&[[1.0, 2.0, 0.0], [-1.0, 4.0, 0.0]]).into_dyn());
+
+	// the transpose satisfies <D x, g> = <x, D^T g>
+	let grad = arr2(&[[0.5, -2.0, 1.0], [3.0, 1.5, -1.0]]).into_dyn();
+	for axis in 0..2 {
+		let mut input_grad = ArrayD::zeros(input.shape());
+		forward_difference_transpose(grad.view(), axis, &mut input_grad);
+		let lhs = (forward_difference(input.view(), axis) * &grad).scalar_sum();
+		let rhs = (&input * &input_grad).scalar_sum();
+		assert!((lhs - rhs).abs() < 1e-6);
+	}
+}
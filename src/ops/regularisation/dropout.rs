@@ -0,0 +1,394 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ndarray::{ArrayD, Zip};
+use rand::{thread_rng, Isaac64Rng, Rng, SeedableRng};
+use smallvec::SmallVec;
+use std::any::Any;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Clone, Debug)]
+enum MaskSharing {
+	Axes(SmallVec<[isize; 6]>),
+	Spatial,
+	Channel,
+}
+
+/// Dropout
+///
+/// During training each element of the input is set to zero with probability `drop_rate`,
+/// and the remaining elements are scaled by `1/(1 - drop_rate)` so that the expected value is unchanged.
+/// During inference this `Op` is the identity.
+/// The mode is determined by the executing `Subgraph`, see `Subgraph::training()`,
+/// so evaluation subgraphs which only output values do not apply dropout.
+///
+/// A new mask is generated at each training step from the seed and a step counter,
+/// so that training runs are reproducible when a seed is supplied.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Dropout {
+	name: Option<String>,
+	input_id: NodeID,
+	output_id: NodeID,
+	drop_rate: f32,
+	sharing: MaskSharing,
+	seed: Option<u64>,
+}
+
+impl Dropout {
+	pub fn new(input_id: &NodeID, output_id: &NodeID, drop_rate: f32) -> Self {
+		Dropout {
+			name: None,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+			drop_rate: drop_rate,
+			sharing: MaskSharing::Axes(SmallVec::new()),
+			seed: None,
+		}
+	}
+
+	/// Spatial Dropout
+	///
+	/// The mask is shared across all axes except the outermost (batch) and innermost (channel) axes,
+	/// so entire channels are dropped from each example.
+	pub fn spatial(input_id: &NodeID, output_id: &NodeID, drop_rate: f32) -> Self {
+		Dropout {
+			sharing: MaskSharing::Spatial,
+			..Dropout::new(input_id, output_id, drop_rate)
+		}
+	}
+
+	/// Channel Dropout
+	///
+	/// The mask is shared across the innermost (channel) axis, so all channels of a spaxel are dropped together.
+	pub fn channel(input_id: &NodeID, output_id: &NodeID, drop_rate: f32) -> Self {
+		Dropout {
+			sharing: MaskSharing::Channel,
+			..Dropout::new(input_id, output_id, drop_rate)
+		}
+	}
+
+	/// Supply axes which the mask should be shared across.
+	///
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	/// This overrides the sharing set by `spatial()` or `channel()`.
+	///
+	/// Default: empty
+	pub fn shared_axes(mut self, shared_axes: &[isize]) -> Self {
+		self.sharing = MaskSharing::Axes(shared_axes.iter().cloned().collect());
+		self
+	}
+
+	/// Supply a seed for the mask generation.
+	///
+	/// If left as `None` a random seed is chosen when the `Op` is built.
+	///
+	/// Default: `None`
+	pub fn seed(mut self, seed: u64) -> Self {
+		self.seed = Some(seed);
+		self
+	}
+}
+
+impl Op for Dropout {
+	type InstanceType = DropoutInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Dropout"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		ensure!(self.drop_rate >= 0.0 && self.drop_rate < 1.0, "Dropout drop_rate must be in the range [0, 1), not {}", self.drop_rate);
+
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone()], &[self.output_id.clone()]);
+
+		let ndim = self.input_id.shape().ndim();
+		let shared_mask: SmallVec<[bool; 6]> = match self.sharing {
+			MaskSharing::Axes(ref axes) => {
+				let mut mask: SmallVec<[bool; 6]> = (0..ndim).map(|_| false).collect();
+				for &axis in axes {
+					ensure!(axis < ndim as isize && axis >= -(ndim as isize), ErrorKind::ShapePropagationError(name.clone(), format!("shared axis ({}) is out of range for input shape: {:?}", axis, self.input_id.shape())));
+					mask[(axis + ndim as isize) as usize % ndim] = true;
+				}
+				mask
+			},
+			MaskSharing::Spatial => (0..ndim).map(|axis| axis > 0 && axis + 1 < ndim).collect(),
+			MaskSharing::Channel => (0..ndim).map(|axis| axis + 1 == ndim).collect(),
+		};
+
+		let seed = self.seed.unwrap_or_else(|| thread_rng().gen());
+		let step = Arc::new(AtomicUsize::new(0));
+
+		let forward_id = graph.add_pass(DropoutForward::new(
+			self.input_id.clone(),
+			self.output_id.clone(),
+			self.drop_rate,
+			shared_mask,
+			seed,
+			step.clone(),
+		));
+
+		let backward_id = graph.add_pass(DropoutBackward::new(
+			self.input_id.clone(),
+			self.output_id.clone(),
+			forward_id.clone(),
+		));
+
+		Ok(DropoutInstance{
+			name: name,
+			input_id: self.input_id.clone(),
+			output_id: self.output_id.clone(),
+			step: step,
+			forward_id: forward_id,
+			backward_id: backward_id,
+		})
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct DropoutInstance {
+	name: String,
+	input_id: NodeID,
+	output_id: NodeID,
+	step: Arc<AtomicUsize>,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl DropoutInstance {
+	/// Returns the number of training steps for which masks have been generated.
+	pub fn step(&self) -> usize {
+		self.step.load(Ordering::SeqCst)
+	}
+
+	/// Sets the step counter used with the seed to generate the next mask, e.g. when resuming training from a checkpoint.
+	pub fn set_step(&self, step: usize) {
+		self.step.store(step, Ordering::SeqCst);
+	}
+}
+
+impl OpInstance for DropoutInstance {
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).clone();
+		shapes.merge_with(&self.output_id, &input_shape)
+	}
+}
+
+
+#[derive(Debug, Clone)]
+pub struct DropoutForward {
+	input_id: NodeID,
+	output_id: NodeID,
+	drop_rate: f32,
+	shared_mask: SmallVec<[bool; 6]>,
+	seed: u64,
+	step: Arc<AtomicUsize>,
+}
+
+impl DropoutForward {
+	pub fn new(input_id: NodeID, output_id: NodeID, drop_rate: f32, shared_mask: SmallVec<[bool; 6]>, seed: u64, step: Arc<AtomicUsize>) -> Self{
+		DropoutForward {
+			input_id,
+			output_id,
+			drop_rate,
+			shared_mask,
+			seed,
+			step,
+		}
+	}
+}
+
+impl Pass for DropoutForward {
+	fn type_name(&self) -> &'static str {"DropoutForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id()],
+		vec![self.output_id.value_id()])
+	}
+
+	/// In training mode the mask, with values of either `0` or `1/(1 - drop_rate)`, is returned for use by the backward pass.
+	fn run(&self, data: &Storage) -> Result<Box<Any>> {
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		ensure!(
+			input.shape() == output.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input.shape(), output.shape()))
+		);
+		ensure!(
+			input.ndim() == self.shared_mask.len(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not have the expected number of dimensions: {}", input.shape(), self.shared_mask.len()))
+		);
+
+		if !data.is_training() {
+			output += &input;
+			return Ok(Box::new(()));
+		}
+
+		let step = self.step.fetch_add(1, Ordering::SeqCst) as u64;
+		let mut rng = Isaac64Rng::seed_from_u64(self.seed ^ step.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+
+		let mask_shape: SmallVec<[usize; 6]> = input.shape().iter().zip(&self.shared_mask).map(|(&dim, &shared)| if shared {1} else {dim}).collect();
+		let keep_rate = 1.0 - self.drop_rate;
+		let mut mask = ArrayD::zeros(mask_shape.as_slice());
+		for e in mask.iter_mut() {
+			if rng.gen::<f32>() < keep_rate {
+				*e = 1.0/keep_rate;
+			}
+		}
+
+		Zip::from(&mut output)
+			.and(&input)
+			.and_broadcast(&mask)
+			.apply(|output, &input, &mask|{
+				*output += input * mask;
+			});
+
+		Ok(Box::new(mask))
+	}
+}
+
+
+#[derive(Debug, Clone)]
+pub struct DropoutBackward {
+	input_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+}
+
+impl DropoutBackward {
+	pub fn new(input_id: NodeID, output_id: NodeID, forward_id: PassID) -> Self{
+		DropoutBackward {
+			input_id,
+			output_id,
+			forward_id,
+		}
+	}
+}
+
+impl Pass for DropoutBackward {
+	fn type_name(&self) -> &'static str {"DropoutBackward"}
+
+	// The output value is listed as an input to ensure that the forward pass, which generates the mask, runs first.
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.output_id.value_id(), self.output_id.gradient_id()],
+		vec![self.input_id.gradient_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>> {
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+
+		ensure!(
+			input_grad.shape() == output_grad.shape(),
+			ErrorKind::PassError(self.name(), format!("input shape: {:?} did not match output shape: {:?}", input_grad.shape(), output_grad.shape()))
+		);
+
+		if !data.is_training() {
+			input_grad += &output_grad;
+			return Ok(Box::new(()));
+		}
+
+		let mask = data.get_pass_data(&self.forward_id)
+			.and_then(|pass_data| pass_data.downcast_ref::<ArrayD<f32>>());
+		let mask = if let Some(mask) = mask {
+			mask
+		} else {
+			bail!(ErrorKind::PassError(self.name(), format!("the mask from the forward pass was not available")));
+		};
+
+		Zip::from(&mut input_grad)
+			.and(&output_grad)
+			.and_broadcast(mask)
+			.apply(|input_grad, &output_grad, &mask|{
+				*input_grad += output_grad * mask;
+			});
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_dropout_mask(){
+	_dropout_mask().unwrap();
+}
+
+fn _dropout_mask() -> Result<()>{
+	use graph::GraphDef;
+	use ops::loss::proportional::Proportional;
+	use ops::numeric_check::generate_input_data;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![7, 5, 16], "input", tag![])?;
+	let node2 = g.new_node(shape![7, 5, 16], "output", tag![])?;
+
+	let o1 = g.new_op(Dropout::spatial(&node1, &node2, 0.5).seed(42), tag![])?;
+	let _o2 = g.new_op(Proportional::new(&node2), tag![])?;
+
+	let input = generate_input_data(&[node1.clone()], 1.0, &mut indexmap![])?;
+
+	let mut subgraph = g.subgraph(&[node1.value_id()], &[node2.value_id(), node1.gradient_id()])?;
+	assert!(subgraph.is_training());
+	let results = subgraph.execute(input.clone())?.into_map();
+	let output = results.get(&node2.value_id()).unwrap();
+	let input_grad = results.get(&node1.gradient_id()).unwrap();
+
+	// output is either dropped or scaled, and the gradient uses the same mask
+	// Proportional loss is averaged over all elements
+	let n = input[0].len() as f32;
+	for ((&x, &y), &dx) in input[0].iter().zip(output.iter()).zip(input_grad.iter()) {
+		assert!(dx == 0.0 || (dx * n - 2.0).abs() < 1e-5);
+		assert!((x * dx * n - y).abs() < 1e-5);
+	}
+
+	// the mask is shared across the spatial axis
+	for example in input_grad.outer_iter() {
+		for spaxel in example.outer_iter() {
+			assert_eq!(spaxel, example.subview(::ndarray::Axis(0), 0));
+		}
+	}
+
+	// the same seed and step produces the same mask
+	let instance = o1.instance().as_any().downcast_ref::<DropoutInstance>().unwrap();
+	assert_eq!(instance.step(), 1);
+	instance.set_step(0);
+	let results2 = subgraph.execute(input.clone())?.into_map();
+	assert_eq!(results2.get(&node2.value_id()).unwrap(), output);
+
+	// dropout is the identity in inference mode
+	subgraph.training(false);
+	let results3 = subgraph.execute(input.clone())?.into_map();
+	assert_eq!(results3.get(&node2.value_id()).unwrap(), &input[0]);
+
+	// shared axes must be in range
+	assert!(g.new_op(Dropout::new(&node1, &node2, 0.5).shared_axes(&[3]), tag![]).is_err());
+	assert!(g.new_op(Dropout::new(&node1, &node2, 0.5).shared_axes(&[-4]), tag![]).is_err());
+
+	Ok(())
+}
@@ -0,0 +1,180 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::regularisation::difference::{DifferenceInstance, DifferenceNorm, difference_build};
+use smallvec::SmallVec;
+
+/// An `Op` which implements an image gradient matching loss
+///
+/// The forward differences of both inputs are taken along each of the selected axes, and the loss of each element is the norm of their difference.
+/// This encourages edges and fine detail in the first input to match the second, and is commonly combined with a pixel loss for super-resolution.
+///
+/// By default this `Op` has no output and will generate loss and gradients.
+///
+/// If `output()` is set, the loss will be written to that Node,
+/// and instead of generating gradients this loss function will backprop gradients from the output node.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct GradientMatching {
+	input1_id: NodeID,
+	input2_id: NodeID,
+	output: Option<NodeID>,
+	axes: SmallVec<[isize; 6]>,
+	norm: DifferenceNorm,
+	epsilon: f32,
+	mean_axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	multiplier: f32,
+	name: Option<String>,
+}
+
+impl GradientMatching {
+	pub fn new(input1: &NodeID, input2: &NodeID) -> Self {
+		GradientMatching {
+			input1_id: input1.clone(),
+			input2_id: input2.clone(),
+			output: None,
+			axes: SmallVec::new(),
+			norm: DifferenceNorm::Squared,
+			epsilon: 1e-3,
+			mean_axes: SmallVec::new(),
+			keep_dims: false,
+			multiplier: 1.0,
+			name: None,
+		}
+	}
+
+	/// If set this `Op` will output to the supplied node, any rely no other use ops to generate loss and gradients
+	/// The output node must have the same size as the input node unless reductions are applied using `.mean_axes()`.
+	///
+	/// Default: None.
+	pub fn output(mut self, output: &NodeID) -> Self {
+		self.output = Some(output.clone());
+		self
+	}
+
+	/// The axes along which differences are taken.
+	///
+	/// `axes` can be in the range [-input.ndims(), input.ndims());
+	/// If no axes are supplied then all axes except the first and last are used, matching the [batch, spatial.., channels] layout.
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// How the mismatches along each axis are combined.
+	///
+	/// Default: `DifferenceNorm::Squared`
+	pub fn norm(mut self, norm: DifferenceNorm) -> Self {
+		self.norm = norm;
+		self
+	}
+
+	/// Smooths the isotropic norm near zero. Has no effect on the other norms.
+	///
+	/// Default: 1e-3
+	pub fn epsilon(mut self, epsilon: f32) -> Self {
+		self.epsilon = epsilon;
+		self
+	}
+
+	/// The axes supplied will be grouped when finding the mean,
+	/// with the operation repeated across the axes not supplied.
+	///
+	/// `axes` can be in the range [-input.ndims(), input.ndims());
+	/// If no axes are supplied then no mean operation is applied.
+	pub fn mean_axes(mut self, mean_axes: &[isize]) -> Self {
+		self.mean_axes = mean_axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the reduced axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+
+	/// Applies a multiplier to the output or to the loss generated.
+	pub fn multiplier(mut self, multiplier: f32) -> Self {
+		self.multiplier = multiplier;
+		self
+	}
+}
+
+impl Op for GradientMatching {
+	type InstanceType = DifferenceInstance;
+
+	fn type_name(&self) -> &'static str {
+		"GradientMatching"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		difference_build(graph, &self, &self.name, &self.input1_id, &Some(self.input2_id.clone()), &self.output,
+			self.norm, self.epsilon, &self.axes, self.multiplier, &self.mean_axes, self.keep_dims)
+	}
+}
+
+
+#[test]
+fn test_gradient_matching_backprop(){
+	_gradient_matching_backprop().unwrap();
+}
+
+fn _gradient_matching_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 5, 4, 2], "input1", tag![])?;
+	let node2 = g.new_node(shape![3, 5, 4, 2], "input2", tag![])?;
+
+	let _o1 = g.new_op(GradientMatching::new(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(GradientMatching::new(&node1, &node2).norm(DifferenceNorm::Anisotropic).axes(&[1]), tag![])?;
+	let _o3 = g.new_op(GradientMatching::new(&node1, &node2).norm(DifferenceNorm::Isotropic).epsilon(0.1).multiplier(2.0), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_gradient_matching_output_backprop(){
+	_gradient_matching_output_backprop().unwrap();
+}
+
+fn _gradient_matching_output_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::proportional::Proportional;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 5, 4, 2], "input1", tag![])?;
+	let node2 = g.new_node(shape![3, 5, 4, 2], "input2", tag![])?;
+	let node3 = g.new_node(shape![3, 1, 1, 2], "output", tag![])?;
+
+	let _o1 = g.new_op(GradientMatching::new(&node1, &node2).mean_axes(&[1, 2]).keep_dims(true).output(&node3), tag![])?;
+	let _o2 = g.new_op(Proportional::new(&node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
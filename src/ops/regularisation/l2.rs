@@ -228,7 +228,7 @@ impl Pass for L2JointPass {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input = data.get(&self.input_id.value_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
 
 		let input_shape: SmallVec<[usize; 6]> = input.shape().iter().cloned().collect();
 
@@ -239,7 +239,7 @@ impl Pass for L2JointPass {
 
 		let mut error = 0.0;
 
-		let mut input_grad = data.get_mut(&self.input_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
 
 		let iter1 = input.exact_chunks(output_shape_keep_dims.as_slice()).into_iter();
 		let iter2 = input_grad.exact_chunks_mut(output_shape_keep_dims.as_slice()).into_iter();
@@ -290,8 +290,8 @@ impl Pass for L2Forward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input = data.get(&self.input_id.value_id())?;
-		let output = data.get_mut(&self.output_id.value_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		let input_shape: SmallVec<[usize; 6]> = input.shape().iter().cloned().collect();
 		let output_shape: SmallVec<[usize; 6]> = output.shape().iter().cloned().collect();
@@ -350,8 +350,8 @@ impl Pass for L2Backward {
 	}
 
 	fn run (&self, data: &Storage) -> Result<Box<Any>>{
-		let input = data.get(&self.input_id.value_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 
 		let input_shape: SmallVec<[usize; 6]> = input.shape().iter().cloned().collect();
 		let output_shape: SmallVec<[usize; 6]> = output_grad.shape().iter().cloned().collect();
@@ -367,7 +367,7 @@ impl Pass for L2Backward {
 		let output_grad = output_grad.into_shape(&output_shape_keep_dims[..]).expect("This should have been caught by the ensure above");;
 
 
-		let mut input_grad = data.get_mut(&self.input_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
 
 		let iter1 = input.exact_chunks(output_shape_keep_dims.as_slice()).into_iter();
 		let iter2 = input_grad.exact_chunks_mut(output_shape_keep_dims.as_slice()).into_iter();
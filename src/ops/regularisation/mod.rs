@@ -1,2 +1,6 @@
 pub mod l1;
-pub mod l2;
\ No newline at end of file
+pub mod l2;
+pub mod dropout;
+pub mod difference;
+pub mod total_variation;
+pub mod gradient_matching;
\ No newline at end of file
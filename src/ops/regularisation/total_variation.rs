@@ -0,0 +1,208 @@
+use graph::{GraphDef, Result};
+use id::NodeID;
+use ops::Op;
+use ops::regularisation::difference::{DifferenceInstance, DifferenceNorm, difference_build};
+use smallvec::SmallVec;
+
+/// An `Op` which implements a Total Variation regulariser
+///
+/// The loss of each element is the norm of the forward differences to the next element along each of the selected axes,
+/// with the differences past the end of an axis treated as zero.
+/// This penalises noise and high frequency detail, and is commonly used for denoising and to suppress artifacts.
+///
+/// By default this `Op` has no output and will generate loss and gradients.
+///
+/// If `output()` is set, the loss will be written to that Node,
+/// and instead of generating gradients this loss function will backprop gradients from the output node.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct TotalVariation {
+	input_id: NodeID,
+	output: Option<NodeID>,
+	axes: SmallVec<[isize; 6]>,
+	norm: DifferenceNorm,
+	epsilon: f32,
+	mean_axes: SmallVec<[isize; 6]>,
+	keep_dims: bool,
+	multiplier: f32,
+	name: Option<String>,
+}
+
+impl TotalVariation {
+	pub fn new(input: &NodeID) -> Self {
+		TotalVariation {
+			input_id: input.clone(),
+			output: None,
+			axes: SmallVec::new(),
+			norm: DifferenceNorm::Anisotropic,
+			epsilon: 1e-3,
+			mean_axes: SmallVec::new(),
+			keep_dims: false,
+			multiplier: 1.0,
+			name: None,
+		}
+	}
+
+	/// If set this `Op` will output to the supplied node, any rely no other use ops to generate loss and gradients
+	/// The output node must have the same size as the input node unless reductions are applied using `.mean_axes()`.
+	///
+	/// Default: None.
+	pub fn output(mut self, output: &NodeID) -> Self {
+		self.output = Some(output.clone());
+		self
+	}
+
+	/// The axes along which differences are taken.
+	///
+	/// `axes` can be in the range [-input.ndims(), input.ndims());
+	/// If no axes are supplied then all axes except the first and last are used, matching the [batch, spatial.., channels] layout.
+	pub fn axes(mut self, axes: &[isize]) -> Self {
+		self.axes = axes.iter().cloned().collect();
+		self
+	}
+
+	/// How the differences along each axis are combined.
+	///
+	/// Default: `DifferenceNorm::Anisotropic`
+	pub fn norm(mut self, norm: DifferenceNorm) -> Self {
+		self.norm = norm;
+		self
+	}
+
+	/// Smooths the isotropic norm near zero. Has no effect on the other norms.
+	///
+	/// Default: 1e-3
+	pub fn epsilon(mut self, epsilon: f32) -> Self {
+		self.epsilon = epsilon;
+		self
+	}
+
+	/// The axes supplied will be grouped when finding the mean,
+	/// with the operation repeated across the axes not supplied.
+	///
+	/// `axes` can be in the range [-input.ndims(), input.ndims());
+	/// If no axes are supplied then no mean operation is applied.
+	pub fn mean_axes(mut self, mean_axes: &[isize]) -> Self {
+		self.mean_axes = mean_axes.iter().cloned().collect();
+		self
+	}
+
+	/// If `true` the reduced axes still appear in the output with size 1, otherwise they are removed.
+	///
+	/// Default: `false`
+	pub fn keep_dims(mut self, keep_dims: bool) -> Self {
+		self.keep_dims = keep_dims;
+		self
+	}
+
+	/// Applies a multiplier to the output or to the loss generated.
+	pub fn multiplier(mut self, multiplier: f32) -> Self {
+		self.multiplier = multiplier;
+		self
+	}
+}
+
+impl Op for TotalVariation {
+	type InstanceType = DifferenceInstance;
+
+	fn type_name(&self) -> &'static str {
+		"TotalVariation"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		difference_build(graph, &self, &self.name, &self.input_id, &None, &self.output,
+			self.norm, self.epsilon, &self.axes, self.multiplier, &self.mean_axes, self.keep_dims)
+	}
+}
+
+
+#[test]
+fn test_total_variation_values(){
+	_total_variation_values().unwrap();
+}
+
+fn _total_variation_values() -> Result<()>{
+	use graph::GraphDef;
+	use ndarray::{arr2, ArrayD};
+
+	let mut g = GraphDef::new();
+
+	let input = g.new_node(shape![1, 2, 3, 1], "input", tag![])?;
+	let output1 = g.new_node(shape![1], "output1", tag![])?;
+	let output2 = g.new_node(shape![1], "output2", tag![])?;
+
+	let _o1 = g.new_op(TotalVariation::new(&input).output(&output1).mean_axes(&[1, 2, 3]), tag![])?;
+	let _o2 = g.new_op(TotalVariation::new(&input).output(&output2).mean_axes(&[1, 2, 3]).norm(DifferenceNorm::Isotropic).epsilon(1e-6), tag![])?;
+
+	let mut subgraph = g.subgraph(&[input.value_id()], &[output1.value_id(), output2.value_id()])?;
+
+	let input_data: ArrayD<f32> = arr2(&[[0.0, 3.0, 3.0], [4.0, 3.0, 1.0]]).into_shape(vec![1, 2, 3, 1]).unwrap();
+	let storage = subgraph.execute(vec![input_data])?;
+
+	// anisotropic: (|4| + |3|) + (|0| + |0|) + |-2| + |-1| + |-2| = 12
+	// isotropic: 5 + 0 + 2 + 1 + 2 = 10
+	assert!((storage.get::<f32>(&output1.value_id())?[0] - 12.0/6.0).abs() < 1e-5);
+	assert!((storage.get::<f32>(&output2.value_id())?[0] - 10.0/6.0).abs() < 1e-5);
+
+	Ok(())
+}
+
+#[test]
+fn test_total_variation_backprop(){
+	_total_variation_backprop().unwrap();
+}
+
+fn _total_variation_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 5, 4, 2], "input1", tag![])?;
+
+	let _o1 = g.new_op(TotalVariation::new(&node1), tag![])?;
+	let _o2 = g.new_op(TotalVariation::new(&node1).axes(&[0, -1]).norm(DifferenceNorm::Squared), tag![])?;
+	let _o3 = g.new_op(TotalVariation::new(&node1).norm(DifferenceNorm::Isotropic).epsilon(0.1).multiplier(2.0), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_total_variation_output_backprop(){
+	_total_variation_output_backprop().unwrap();
+}
+
+fn _total_variation_output_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::proportional::Proportional;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 5, 4, 2], "input1", tag![])?;
+	let node2 = g.new_node(shape![4], "output", tag![])?;
+
+	let _o1 = g.new_op(TotalVariation::new(&node1).norm(DifferenceNorm::Isotropic).epsilon(0.1).mean_axes(&[0, 1, -1]).output(&node2), tag![])?;
+	let _o2 = g.new_op(Proportional::new(&node2), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-3;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
@@ -136,8 +136,8 @@ impl Pass for AvgPoolForward {
 	}
 
 	fn run(&self, data: &Storage) -> Result<Box<Any>> {
-		let input = data.get(&self.input_id.value_id())?;
-		let mut output = data.get_mut(&self.output_id.value_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		let input_shape = input.shape();
 		let output_shape = output.shape().to_vec();
@@ -202,8 +202,8 @@ impl Pass for AvgPoolBackward {
 	}
 
 	fn run(&self, data: &Storage) -> Result<Box<Any>> {
-		let mut input_grad = data.get_mut(&self.input_id.gradient_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 
 		let input_shape = input_grad.shape().to_vec();
 		let output_shape = output_grad.shape();
@@ -0,0 +1,426 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::shape::normalise_axis;
+use shape::{NodeShape, NodeDim};
+use ndarray::{Axis, Slice, Dimension};
+use std::any::Any;
+
+/// Joins the inputs along an axis.
+///
+/// All inputs must have the same number of dimensions, and the same size in all dimensions other than the concatenation axis.
+/// The output size along the axis is the sum of the input sizes.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Concat {
+	name: Option<String>,
+	axis: isize,
+	input_ids: Vec<NodeID>,
+	output_id: NodeID,
+}
+
+impl Concat {
+	/// The axis can be in the range [-input.ndims(), input.ndims())
+	pub fn new(input_ids: &[NodeID], output_id: &NodeID, axis: isize) -> Self{
+		Concat {
+			name: None,
+			axis,
+			input_ids: input_ids.to_vec(),
+			output_id: output_id.clone(),
+		}
+	}
+}
+
+impl Op for Concat {
+	type InstanceType = ConcatInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Concat"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		ensure!(self.input_ids.len() > 0, "Concat requires at least one input");
+		let name = standard_op_name(&self, &self.name, graph, &self.input_ids, &[self.output_id.clone()]);
+
+		Ok(ConcatInstance{
+			name: name,
+			axis: self.axis,
+			input_ids: self.input_ids.clone(),
+			output_id: self.output_id.clone(),
+			forward_id:graph.add_pass(ConcatPass::new(
+				self.input_ids.iter().map(|id| id.value_id()).collect(),
+				self.output_id.value_id(),
+				self.axis,
+			)),
+			backward_id:graph.add_pass(SplitPass::new(
+				self.output_id.gradient_id(),
+				self.input_ids.iter().map(|id| id.gradient_id()).collect(),
+				self.axis,
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ConcatInstance {
+	name: String,
+	axis: isize,
+	input_ids: Vec<NodeID>,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for ConcatInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			self.input_ids.clone(),
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let first_shape = shapes.get_shape(&self.input_ids[0]).to_data_shape()?;
+		let axis = normalise_axis(self.axis, first_shape.ndim())?;
+
+		let mut axis_size = 0;
+		for input_id in &self.input_ids {
+			let input_shape = shapes.get_shape(input_id).to_data_shape()?;
+			ensure!(
+				input_shape.ndim() == first_shape.ndim() && (0..first_shape.ndim()).all(|i| i == axis || input_shape[i] == first_shape[i]),
+				ErrorKind::ShapePropagationError(self.name.clone(), format!("input shapes {:?} and {:?} cannot be concatenated along axis {}", first_shape.slice(), input_shape.slice(), self.axis))
+			);
+			axis_size += input_shape[axis];
+		}
+
+		let output_shape: NodeShape = first_shape.slice().iter().enumerate().map(|(i, &dim)| {
+			if i == axis {axis_size} else {dim}
+		}).into();
+
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+
+/// Separates the input into sections along an axis.
+///
+/// Each output receives one section, and all outputs have the same size as the input in all dimensions other than the split axis.
+/// If the section sizes are not supplied they are taken from the `Known` sizes of the outputs along the split axis,
+/// with the remaining size divided evenly between outputs which are not `Known` along the split axis.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Split {
+	name: Option<String>,
+	axis: isize,
+	sections: Option<Vec<usize>>,
+	input_id: NodeID,
+	output_ids: Vec<NodeID>,
+}
+
+impl Split {
+	/// The axis can be in the range [-input.ndims(), input.ndims())
+	pub fn new(input_id: &NodeID, output_ids: &[NodeID], axis: isize) -> Self{
+		Split {
+			name: None,
+			axis,
+			sections: None,
+			input_id: input_id.clone(),
+			output_ids: output_ids.to_vec(),
+		}
+	}
+
+	/// The size of each section along the split axis, one per output.
+	///
+	/// The sections must sum to the size of the input along the split axis.
+	///
+	/// Default: `None`
+	pub fn sections(mut self, sections: &[usize]) -> Self {
+		self.sections = Some(sections.to_vec());
+		self
+	}
+}
+
+impl Op for Split {
+	type InstanceType = SplitInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Split"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		ensure!(self.output_ids.len() > 0, "Split requires at least one output");
+		if let Some(ref sections) = self.sections {
+			ensure!(sections.len() == self.output_ids.len(), "The number of sections ({}) must match the number of outputs ({})", sections.len(), self.output_ids.len());
+		}
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone()], &self.output_ids);
+
+		Ok(SplitInstance{
+			name: name,
+			axis: self.axis,
+			sections: self.sections.clone(),
+			input_id: self.input_id.clone(),
+			output_ids: self.output_ids.clone(),
+			forward_id:graph.add_pass(SplitPass::new(
+				self.input_id.value_id(),
+				self.output_ids.iter().map(|id| id.value_id()).collect(),
+				self.axis,
+			)),
+			backward_id:graph.add_pass(ConcatPass::new(
+				self.output_ids.iter().map(|id| id.gradient_id()).collect(),
+				self.input_id.gradient_id(),
+				self.axis,
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct SplitInstance {
+	name: String,
+	axis: isize,
+	sections: Option<Vec<usize>>,
+	input_id: NodeID,
+	output_ids: Vec<NodeID>,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for SplitInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone()],
+			self.output_ids.clone()
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+		let axis = normalise_axis(self.axis, input_shape.ndim())?;
+		let axis_size = input_shape[axis];
+
+		let sections = if let Some(ref sections) = self.sections {
+			sections.clone()
+		} else {
+			let known: Vec<Option<usize>> = self.output_ids.iter().map(|output_id| {
+				match shapes.get_output_shape(output_id).dimensions().get(axis) {
+					Some(&NodeDim::Known(size)) => Some(size),
+					_ => None,
+				}
+			}).collect();
+			let known_size: usize = known.iter().filter_map(|&size| size).sum();
+			let unknown_count = known.iter().filter(|size| size.is_none()).count();
+			ensure!(
+				known_size <= axis_size && (unknown_count > 0 || known_size == axis_size) && (unknown_count == 0 || (axis_size - known_size)%unknown_count == 0),
+				ErrorKind::ShapePropagationError(self.name.clone(), format!("input size {} along axis {} cannot be divided between outputs with sizes: {:?}", axis_size, self.axis, known))
+			);
+			known.iter().map(|&size| size.unwrap_or_else(|| (axis_size - known_size)/unknown_count)).collect()
+		};
+
+		ensure!(
+			sections.iter().sum::<usize>() == axis_size,
+			ErrorKind::ShapePropagationError(self.name.clone(), format!("sections {:?} do not sum to the input size {} along axis {}", sections, axis_size, self.axis))
+		);
+
+		for (output_id, &section) in self.output_ids.iter().zip(&sections) {
+			let output_shape: NodeShape = input_shape.slice().iter().enumerate().map(|(i, &dim)| {
+				if i == axis {section} else {dim}
+			}).into();
+			shapes.merge_with(output_id, &output_shape)?;
+		}
+		Ok(())
+	}
+}
+
+
+/// Adds each input to consecutive sections of the output along an axis
+#[derive(Debug, Clone)]
+pub struct ConcatPass {
+	input_ids: Vec<DataID>,
+	output_id: DataID,
+	axis: isize,
+}
+
+impl ConcatPass {
+	pub fn new(input_ids: Vec<DataID>, output_id: DataID, axis: isize) -> Self{
+		ConcatPass {
+			input_ids,
+			output_id,
+			axis,
+		}
+	}
+}
+
+impl Pass for ConcatPass {
+	fn type_name(&self) -> &'static str {"ConcatPass"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(self.input_ids.clone(), vec![self.output_id.clone()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let mut output = data.get_mut::<f32>(&self.output_id)?;
+		let axis = normalise_axis(self.axis, output.ndim())?;
+
+		let mut offset = 0;
+		for input_id in &self.input_ids {
+			let input = data.get::<f32>(input_id)?;
+			let size = input.shape()[axis];
+			ensure!(offset + size <= output.shape()[axis], ErrorKind::PassError(self.name(), format!("input shapes do not sum to output shape: {:?} along axis {}", output.shape(), self.axis)));
+
+			let mut output_section = output.slice_axis_mut(Axis(axis), Slice::from(offset..offset + size));
+			output_section += &input;
+			offset += size;
+		}
+		ensure!(offset == output.shape()[axis], ErrorKind::PassError(self.name(), format!("input shapes do not sum to output shape: {:?} along axis {}", output.shape(), self.axis)));
+
+		Ok(Box::new(()))
+	}
+}
+
+/// Adds consecutive sections of the input along an axis to each output
+#[derive(Debug, Clone)]
+pub struct SplitPass {
+	input_id: DataID,
+	output_ids: Vec<DataID>,
+	axis: isize,
+}
+
+impl SplitPass {
+	pub fn new(input_id: DataID, output_ids: Vec<DataID>, axis: isize) -> Self{
+		SplitPass {
+			input_id,
+			output_ids,
+			axis,
+		}
+	}
+}
+
+impl Pass for SplitPass {
+	fn type_name(&self) -> &'static str {"SplitPass"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.clone()], self.output_ids.clone())
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id)?;
+		let axis = normalise_axis(self.axis, input.ndim())?;
+
+		let mut offset = 0;
+		for output_id in &self.output_ids {
+			// outputs which aren't required must still be skipped over, so the size is taken from the node shape
+			let size = data.shape(&output_id.node_id())?[axis];
+			ensure!(offset + size <= input.shape()[axis], ErrorKind::PassError(self.name(), format!("output shapes do not sum to input shape: {:?} along axis {}", input.shape(), self.axis)));
+
+			if data.is_required(output_id) {
+				let mut output = data.get_mut::<f32>(output_id)?;
+				output += &input.slice_axis(Axis(axis), Slice::from(offset..offset + size));
+			}
+			offset += size;
+		}
+		ensure!(offset == input.shape()[axis], ErrorKind::PassError(self.name(), format!("output shapes do not sum to input shape: {:?} along axis {}", input.shape(), self.axis)));
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_concat_backprop(){
+	_concat_backprop().unwrap();
+}
+
+fn _concat_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 2, 5], "input1", tag![])?;
+	let node2 = g.new_node(shape![3, 4, 5], "input2", tag![])?;
+	let node3 = g.new_node(shape![3, 1, 5], "input3", tag![])?;
+	let node4 = g.new_node(shape![3, Unknown, 5], "output", tag![])?;
+	let node5 = g.new_node(shape![3, 7, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(Concat::new(&[node1, node2, node3], &node4, -2), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node4, &node5), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_split_backprop(){
+	_split_backprop().unwrap();
+}
+
+fn _split_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 8, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![3, 2, 5], "output1", tag![])?;
+	let node3 = g.new_node(shape![3, Unknown, 5], "output2", tag![])?;
+	let node4 = g.new_node(shape![3, Unknown, 5], "output3", tag![])?;
+	let node5 = g.new_node(shape![3, 2, 5], "target1", tag![])?;
+	let node6 = g.new_node(shape![3, 3, 5], "target2", tag![])?;
+	let node7 = g.new_node(shape![3, 3, 5], "target3", tag![])?;
+
+	// the sizes of output2 and output3 are inferred as 3 each
+	let _o1 = g.new_op(Split::new(&node1, &[node2.clone(), node3.clone(), node4.clone()], 1), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node5), tag![])?;
+	let _o3 = g.new_op(Mse::new(&node3, &node6), tag![])?;
+	let _o4 = g.new_op(Mse::new(&node4, &node7), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
@@ -0,0 +1,564 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use smallvec::SmallVec;
+use std::any::Any;
+use rayon::prelude::*;
+
+/// How the input is sampled at each coordinate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleMode {
+	/// Use the value of the nearest input element. No gradient is propagated to the coordinates.
+	Nearest,
+	/// Linear interpolation between the neighbouring input elements along each spatial axis, i.e. bilinear for images.
+	Linear,
+}
+
+/// How coordinates outside of the input are handled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BorderMode {
+	/// Elements outside of the input are treated as zero.
+	Zeros,
+	/// Coordinates are clamped to the edge of the input.
+	Border,
+	/// Coordinates are reflected about the edge of the input.
+	Reflection,
+}
+
+/// Settings shared by the forward and backward grid sample passes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridSampleSettings {
+	sample_mode: SampleMode,
+	border_mode: BorderMode,
+	align_corners: bool,
+	normalised: bool,
+}
+
+/// An input element which contributes to a sampled value
+struct Tap {
+	/// Index of the element within the spatial dimensions of the input
+	index: usize,
+	weight: f32,
+	/// Derivative of the weight with respect to each coordinate
+	coord_grads: SmallVec<[f32; 4]>,
+}
+
+impl GridSampleSettings {
+	/// Converts a coordinate to the position in elements along an axis, returning the position and its derivative
+	fn position(&self, coord: f32, len: usize) -> (f32, f32) {
+		let (pos, scale) = if !self.normalised {
+			(coord, 1.0)
+		} else if self.align_corners {
+			(coord * (len - 1) as f32, (len - 1) as f32)
+		} else {
+			(coord * len as f32 - 0.5, len as f32)
+		};
+
+		let (pos, grad) = match self.border_mode {
+			BorderMode::Zeros => (pos, 1.0),
+			BorderMode::Border => clip(pos, len),
+			BorderMode::Reflection => {
+				let (lower, upper) = if self.align_corners {(0.0, (len - 1) as f32)} else {(-0.5, len as f32 - 0.5)};
+				let span = upper - lower;
+				if span <= 0.0 {
+					(0.0, 0.0)
+				} else {
+					let period = 2.0 * span;
+					let m = ((pos - lower) % period + period) % period;
+					let (pos, grad) = if m <= span {(lower + m, 1.0)} else {(upper - (m - span), -1.0)};
+					let (pos, clip_grad) = clip(pos, len);
+					(pos, grad * clip_grad)
+				}
+			},
+		};
+		(pos, grad * scale)
+	}
+
+	/// Returns the input elements, weights and weight derivatives for sampling at a single point
+	fn taps(&self, coords: &[f32], spatial_shape: &[usize]) -> Vec<Tap> {
+		let n = spatial_shape.len();
+
+		// for each axis, a list of (index, weight, derivative of weight wrt coord)
+		let mut axis_taps: SmallVec<[SmallVec<[(usize, f32, f32); 2]>; 4]> = SmallVec::new();
+		for (&coord, &len) in coords.iter().zip(spatial_shape) {
+			let (pos, pos_grad) = self.position(coord, len);
+			let mut taps = SmallVec::new();
+			match self.sample_mode {
+				SampleMode::Nearest => {
+					let i = pos.round();
+					if i >= 0.0 && i < len as f32 {
+						taps.push((i as usize, 1.0, 0.0));
+					}
+				},
+				SampleMode::Linear => {
+					let i0 = pos.floor();
+					let t = pos - i0;
+					for &(i, w, dw) in &[(i0, 1.0 - t, -pos_grad), (i0 + 1.0, t, pos_grad)] {
+						if i >= 0.0 && i < len as f32 {
+							taps.push((i as usize, w, dw));
+						}
+					}
+				},
+			}
+			if taps.is_empty() {
+				return vec![];
+			}
+			axis_taps.push(taps);
+		}
+
+		let mut result = vec![Tap{index: 0, weight: 1.0, coord_grads: (0..n).map(|_| 1.0).collect()}];
+		for (axis, taps) in axis_taps.iter().enumerate() {
+			result = result.iter().flat_map(|tap| {
+				taps.iter().map(move |&(i, w, dw)| {
+					Tap {
+						index: tap.index * spatial_shape[axis] + i,
+						weight: tap.weight * w,
+						coord_grads: tap.coord_grads.iter().enumerate().map(|(k, &g)| if k == axis {g * dw} else {g * w}).collect(),
+					}
+				})
+			}).collect();
+		}
+		result
+	}
+}
+
+/// Clamps a position to lie within an axis, returning the position and its derivative
+fn clip(pos: f32, len: usize) -> (f32, f32) {
+	let upper = (len - 1) as f32;
+	if pos < 0.0 {
+		(0.0, 0.0)
+	} else if pos > upper {
+		(upper, 0.0)
+	} else {
+		(pos, 1.0)
+	}
+}
+
+/// GridSample Op
+///
+/// Samples the input at the coordinates supplied by the grid, as used for spatial transformer networks and warping by optical flow.
+///
+/// The input has shape [batch, spatial dims.., channels], and the grid has shape [batch, output spatial dims.., n],
+/// where the innermost axis of the grid holds one coordinate for each of the n spatial dimensions of the input, in the same order.
+/// The output has shape [batch, output spatial dims.., channels].
+///
+/// By default coordinates are normalised so that 0 and 1 are the centres of the first and last elements of each spatial axis,
+/// matching the increasing channels produced by `ops::fill::coord::Coord`.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct GridSample {
+	name: Option<String>,
+	settings: GridSampleSettings,
+	input_id: NodeID,
+	grid_id: NodeID,
+	output_id: NodeID,
+}
+
+impl GridSample {
+	pub fn new(input_id: &NodeID, grid_id: &NodeID, output_id: &NodeID) -> Self{
+		GridSample {
+			name: None,
+			settings: GridSampleSettings {
+				sample_mode: SampleMode::Linear,
+				border_mode: BorderMode::Zeros,
+				align_corners: true,
+				normalised: true,
+			},
+			input_id: input_id.clone(),
+			grid_id: grid_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+
+	/// Default: `SampleMode::Linear`
+	pub fn sample_mode(mut self, sample_mode: SampleMode) -> Self {
+		self.settings.sample_mode = sample_mode;
+		self
+	}
+
+	/// Default: `BorderMode::Zeros`
+	pub fn border_mode(mut self, border_mode: BorderMode) -> Self {
+		self.settings.border_mode = border_mode;
+		self
+	}
+
+	/// If true, normalised coordinates of 0 and 1 are the centres of the first and last elements.
+	/// Otherwise they are the outer edges of the first and last elements.
+	///
+	/// Default: true
+	pub fn align_corners(mut self, align_corners: bool) -> Self {
+		self.settings.align_corners = align_corners;
+		self
+	}
+
+	/// If false, coordinates are element indices rather than normalised to the range [0, 1], which is convenient for optical flow.
+	///
+	/// Default: true
+	pub fn normalised(mut self, normalised: bool) -> Self {
+		self.settings.normalised = normalised;
+		self
+	}
+}
+
+impl Op for GridSample {
+	type InstanceType = GridSampleInstance;
+
+	fn type_name(&self) -> &'static str {
+		"GridSample"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone(), self.grid_id.clone()], &[self.output_id.clone()]);
+
+		Ok(GridSampleInstance{
+			name: name,
+			input_id: self.input_id.clone(),
+			grid_id: self.grid_id.clone(),
+			output_id: self.output_id.clone(),
+			forward_id: graph.add_pass(GridSampleForward::new(
+				self.input_id.clone(),
+				self.grid_id.clone(),
+				self.output_id.clone(),
+				self.settings,
+			)),
+			backward_id: graph.add_pass(GridSampleBackward::new(
+				self.input_id.clone(),
+				self.grid_id.clone(),
+				self.output_id.clone(),
+				self.settings,
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct GridSampleInstance {
+	name: String,
+	input_id: NodeID,
+	grid_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for GridSampleInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone(), self.grid_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).clone();
+		let mut output_shape = shapes.get_shape(&self.grid_id).clone();
+		ensure!(input_shape.ndim() >= 3, ErrorKind::ShapePropagationError(self.name.clone(), format!("input shape {:?} must have a batch axis, at least one spatial axis, and a channel axis", input_shape.dimensions())));
+		ensure!(output_shape.ndim() >= 2, ErrorKind::ShapePropagationError(self.name.clone(), format!("grid shape {:?} must have a batch axis and a coordinate axis", output_shape.dimensions())));
+
+		let channel_axis = output_shape.ndim() - 1;
+		output_shape.dimensions_mut()[channel_axis] = input_shape.dimensions()[input_shape.ndim() - 1].clone();
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+/// Dimensions of the input and grid, shared by the forward and backward passes
+struct GridSampleDims {
+	batch: usize,
+	spatial_shape: SmallVec<[usize; 4]>,
+	channels: usize,
+	/// The number of sample points in each batch
+	points: usize,
+}
+
+fn check_shapes(pass_name: String, input_shape: &[usize], grid_shape: &[usize], output_shape: &[usize]) -> Result<GridSampleDims> {
+	ensure!(input_shape.len() >= 3 && grid_shape.len() >= 2, ErrorKind::PassError(pass_name.clone(), format!("input shape: {:?} must have at least 3 dimensions and grid shape: {:?} at least 2", input_shape, grid_shape)));
+	let n = input_shape.len() - 2;
+	ensure!(grid_shape[grid_shape.len() - 1] == n, ErrorKind::PassError(pass_name.clone(), format!("innermost dimension of grid shape: {:?} must equal the number of spatial dimensions of input shape: {:?}", grid_shape, input_shape)));
+	ensure!(grid_shape[0] == input_shape[0], ErrorKind::PassError(pass_name.clone(), format!("batch size of grid shape: {:?} does not match input shape: {:?}", grid_shape, input_shape)));
+	ensure!(
+		output_shape.len() == grid_shape.len() && output_shape[..grid_shape.len() - 1] == grid_shape[..grid_shape.len() - 1] && output_shape[output_shape.len() - 1] == input_shape[input_shape.len() - 1],
+		ErrorKind::PassError(pass_name.clone(), format!("output shape: {:?} does not match grid shape: {:?} and input shape: {:?}", output_shape, grid_shape, input_shape))
+	);
+	ensure!(input_shape[1..input_shape.len() - 1].iter().all(|&len| len > 0), ErrorKind::PassError(pass_name, format!("input shape: {:?} must not have an empty spatial axis to sample from", input_shape)));
+
+	Ok(GridSampleDims {
+		batch: input_shape[0],
+		spatial_shape: input_shape[1..input_shape.len() - 1].iter().cloned().collect(),
+		channels: input_shape[input_shape.len() - 1],
+		points: grid_shape[1..grid_shape.len() - 1].iter().product(),
+	})
+}
+
+#[derive(Debug, Clone)]
+pub struct GridSampleForward {
+	input_id: NodeID,
+	grid_id: NodeID,
+	output_id: NodeID,
+	settings: GridSampleSettings,
+}
+
+impl GridSampleForward {
+	pub fn new(input_id: NodeID, grid_id: NodeID, output_id: NodeID, settings: GridSampleSettings) -> Self{
+		GridSampleForward {
+			input_id,
+			grid_id,
+			output_id,
+			settings,
+		}
+	}
+}
+
+impl Pass for GridSampleForward {
+	fn type_name(&self) -> &'static str {"GridSampleForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id(), self.grid_id.value_id()], vec![self.output_id.value_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let grid = data.get::<f32>(&self.grid_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+		let dims = check_shapes(self.name(), input.shape(), grid.shape(), output.shape())?;
+
+		let input = input.as_slice().unwrap();
+		let grid = grid.as_slice().unwrap();
+		let output = output.as_slice_mut().unwrap();
+
+		let n = dims.spatial_shape.len();
+		let channels = dims.channels;
+		let in_size = input.len()/dims.batch;
+		let points = dims.points;
+
+		if channels == 0 {
+			return Ok(Box::new(()));
+		}
+
+		output.par_chunks_mut(channels).zip(grid.par_chunks(n)).enumerate().for_each(|(i, (out_point, coords))| {
+			let in_batch = &input[(i/points)*in_size..][..in_size];
+			for tap in self.settings.taps(coords, &dims.spatial_shape) {
+				let in_element = &in_batch[tap.index*channels..][..channels];
+				for (o, x) in out_point.iter_mut().zip(in_element) {
+					*o += tap.weight * x;
+				}
+			}
+		});
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct GridSampleBackward {
+	input_id: NodeID,
+	grid_id: NodeID,
+	output_id: NodeID,
+	settings: GridSampleSettings,
+}
+
+impl GridSampleBackward {
+	pub fn new(input_id: NodeID, grid_id: NodeID, output_id: NodeID, settings: GridSampleSettings) -> Self{
+		GridSampleBackward {
+			input_id,
+			grid_id,
+			output_id,
+			settings,
+		}
+	}
+}
+
+impl Pass for GridSampleBackward {
+	fn type_name(&self) -> &'static str {"GridSampleBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(
+			vec![self.input_id.value_id(), self.grid_id.value_id(), self.output_id.gradient_id()],
+			vec![self.input_id.gradient_id(), self.grid_id.gradient_id()]
+		)
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let grid = data.get::<f32>(&self.grid_id.value_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let dims = check_shapes(self.name(), input.shape(), grid.shape(), output_grad.shape())?;
+
+		let input = input.as_slice().unwrap();
+		let grid = grid.as_slice().unwrap();
+		let output_grad = output_grad.as_slice().unwrap();
+
+		let n = dims.spatial_shape.len();
+		let channels = dims.channels;
+		let in_size = input.len()/dims.batch;
+		let points = dims.points;
+
+		if channels == 0 {
+			return Ok(Box::new(()));
+		}
+
+		if data.is_required(&self.grid_id.gradient_id()) {
+			let mut grid_grad = data.get_mut::<f32>(&self.grid_id.gradient_id())?;
+			let grid_grad = grid_grad.as_slice_mut().unwrap();
+
+			grid_grad.par_chunks_mut(n).zip(grid.par_chunks(n)).zip(output_grad.par_chunks(channels)).enumerate().for_each(|(i, ((coord_grads, coords), out_grad))| {
+				let in_batch = &input[(i/points)*in_size..][..in_size];
+				for tap in self.settings.taps(coords, &dims.spatial_shape) {
+					let in_element = &in_batch[tap.index*channels..][..channels];
+					let dot: f32 = out_grad.iter().zip(in_element).map(|(g, x)| g * x).sum();
+					for (coord_grad, tap_grad) in coord_grads.iter_mut().zip(&tap.coord_grads) {
+						*coord_grad += dot * tap_grad;
+					}
+				}
+			});
+		}
+
+		if data.is_required(&self.input_id.gradient_id()) {
+			let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+			let input_grad = input_grad.as_slice_mut().unwrap();
+
+			// sample points may share input elements, so parallelise over the batch only
+			input_grad.par_chunks_mut(in_size).zip(grid.par_chunks(points * n)).zip(output_grad.par_chunks(points * channels)).for_each(|((in_grad_batch, grid_batch), out_grad_batch)| {
+				for (coords, out_grad) in grid_batch.chunks(n).zip(out_grad_batch.chunks(channels)) {
+					for tap in self.settings.taps(coords, &dims.spatial_shape) {
+						let in_grad_element = &mut in_grad_batch[tap.index*channels..][..channels];
+						for (ig, g) in in_grad_element.iter_mut().zip(out_grad) {
+							*ig += tap.weight * g;
+						}
+					}
+				}
+			});
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_grid_sample_values(){
+	_grid_sample_values().unwrap();
+}
+
+fn _grid_sample_values() -> Result<()>{
+	use graph::GraphDef;
+	use ndarray::{ArrayD, IxDyn};
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![1, 2, 2, 1], "input", tag![])?;
+	let node2 = g.new_node(shape![1, 4, 2], "grid", tag![])?;
+	let node3 = g.new_node(shape![Unknown, Unknown, Unknown], "linear_output", tag![])?;
+	let node4 = g.new_node(shape![Unknown, Unknown, Unknown], "border_output", tag![])?;
+
+	let _o1 = g.new_op(GridSample::new(&node1, &node2, &node3), tag![])?;
+	let _o2 = g.new_op(GridSample::new(&node1, &node2, &node4).border_mode(BorderMode::Border).sample_mode(SampleMode::Nearest), tag![])?;
+
+	let mut subgraph = g.subgraph(&[node1.value_id(), node2.value_id()], &[node3.value_id(), node4.value_id()])?;
+	let input = ArrayD::from_shape_vec(IxDyn(&[1, 2, 2, 1]), vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+	let grid = ArrayD::from_shape_vec(IxDyn(&[1, 4, 2]), vec![0.5, 0.5, 0.0, 1.0, 1.0, 0.25, -1.0, 0.0]).unwrap();
+	let storage = subgraph.execute(vec![input, grid])?;
+
+	let linear = storage.get::<f32>(&node3.value_id())?;
+	assert_eq!(linear.shape(), &[1, 4, 1]);
+	assert_eq!(linear.iter().cloned().collect::<Vec<_>>(), vec![2.5, 2.0, 3.25, 0.0]);
+	let border = storage.get::<f32>(&node4.value_id())?;
+	assert_eq!(border.iter().cloned().collect::<Vec<_>>(), vec![4.0, 2.0, 3.0, 1.0]);
+
+	Ok(())
+}
+
+#[test]
+fn test_grid_sample_empty_axis(){
+	_grid_sample_empty_axis().unwrap();
+}
+
+fn _grid_sample_empty_axis() -> Result<()>{
+	use graph::GraphDef;
+	use ndarray::ArrayD;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![1, Unknown, 2, 1], "input", tag![])?;
+	let node2 = g.new_node(shape![1, 4, 2], "grid", tag![])?;
+	let node3 = g.new_node(shape![Unknown, Unknown, Unknown], "output", tag![])?;
+
+	let _o1 = g.new_op(GridSample::new(&node1, &node2, &node3).border_mode(BorderMode::Border), tag![])?;
+
+	let mut subgraph = g.subgraph(&[node1.value_id(), node2.value_id()], &[node3.value_id()])?;
+	assert!(subgraph.execute(vec![ArrayD::zeros(vec![1, 0, 2, 1]), ArrayD::zeros(vec![1, 4, 2])]).is_err());
+
+	Ok(())
+}
+
+#[test]
+fn test_grid_sample_backprop(){
+	_grid_sample_backprop().unwrap();
+}
+
+fn _grid_sample_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+	use rand::{thread_rng, Rng};
+	use indexmap::IndexMap;
+
+	let size = 5;
+	for &sample_mode in &[SampleMode::Nearest, SampleMode::Linear] {
+		for &border_mode in &[BorderMode::Zeros, BorderMode::Border, BorderMode::Reflection] {
+			for &(align_corners, normalised) in &[(true, true), (false, true), (true, false)] {
+				let mut g = GraphDef::new();
+
+				let node1 = g.new_node(shape![3, size, size, 4], "input", tag![])?;
+				let node2 = g.new_node(shape![3, 6, 7, 2], "grid", tag![])?;
+				let node3 = g.new_node(shape![Unknown, Unknown, Unknown, Unknown], "output", tag![])?;
+				let node4 = g.new_node(shape![3, 6, 7, 4], "target", tag![])?;
+
+				let _o1 = g.new_op(GridSample::new(&node1, &node2, &node3)
+					.sample_mode(sample_mode)
+					.border_mode(border_mode)
+					.align_corners(align_corners)
+					.normalised(normalised), tag![])?;
+				let _o2 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+				let iters = 100;
+				let failures = 1;
+				let tolerance = 0.002;
+				let step_size = 1E-2;
+				let default_variance = 1.0;
+				// place each coordinate away from the positions where the interpolation weights are not differentiable,
+				// at integer element positions for linear sampling or half way between elements for nearest sampling
+				let mut override_dist: IndexMap<NodeID, Box<FnMut()->f64>> = indexmap![
+					node2.clone() => Box::new(move || {
+						let offset = if sample_mode == SampleMode::Linear {0.5} else {0.0};
+						let pos = thread_rng().gen_range(-3, size as isize + 2) as f64 + offset + thread_rng().gen_range(-0.3, 0.3);
+						match (align_corners, normalised) {
+							(_, false) => pos,
+							(true, true) => pos/(size - 1) as f64,
+							(false, true) => (pos + 0.5)/size as f64,
+						}
+					}) as Box<FnMut()->f64>,
+				];
+				numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut override_dist)?;
+			}
+		}
+	}
+
+	Ok(())
+}
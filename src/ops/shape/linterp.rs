@@ -146,8 +146,8 @@ impl Pass for LinterpForward {
 	}
 
 	fn run(&self, data: &Storage) -> Result<Box<Any>> {
-		let input = data.get(&self.input_id.value_id())?;
-		let mut output = data.get_mut(&self.output_id.value_id())?;
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
 
 		let input_shape = input.shape();
 		let output_shape = output.shape().to_vec();
@@ -247,8 +247,8 @@ impl Pass for LinterpBackward {
 	}
 
 	fn run(&self, data: &Storage) -> Result<Box<Any>> {
-		let mut input_grad = data.get_mut(&self.input_id.gradient_id())?;
-		let output_grad = data.get(&self.output_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
 
 		let input_shape = input_grad.shape().to_vec();
 		let output_shape = output_grad.shape();
@@ -631,7 +631,7 @@ fn _test_linterp() -> Result<()>{
 
 		let storage = subgraph.execute(vec![data_in])?;
 
-		let out = storage.get_mut(&output.value_id())?;
+		let out = storage.get_mut::<f32>(&output.value_id())?;
 		let out_slice = out.as_slice().unwrap();
 
 		let expected = vec![
@@ -663,7 +663,7 @@ fn _test_linterp() -> Result<()>{
 
 		let storage = subgraph.execute(vec![data_in])?;
 
-		let out = storage.get_mut(&output.value_id())?;
+		let out = storage.get_mut::<f32>(&output.value_id())?;
 		let out_slice = out.as_slice().unwrap();
 
 		let expected = vec![
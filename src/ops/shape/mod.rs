@@ -1,4 +1,19 @@
 pub mod avg_pool;
 pub mod shape_constraint;
 pub mod linterp;
-pub mod pixel_shuffle;
\ No newline at end of file
+pub mod pixel_shuffle;
+pub mod concat;
+pub mod reshape;
+pub mod permute;
+pub mod slice;
+pub mod pad;
+pub mod resize;
+pub mod grid_sample;
+
+use graph::Result;
+
+/// Returns the axis in the range [0, ndim) if `axis` is in the range [-ndim, ndim)
+pub fn normalise_axis(axis: isize, ndim: usize) -> Result<usize> {
+	ensure!(axis < ndim as isize && axis >= -(ndim as isize), format!("axis {} is out of range for a shape with {} dimensions", axis, ndim));
+	Ok((axis + ndim as isize) as usize % ndim)
+}
@@ -0,0 +1,358 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use shape::NodeShape;
+use ndarray::{ArrayD, ArrayViewD, Axis, Dimension};
+use std::any::Any;
+
+/// How the values of the padded region are determined
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PadMode {
+	/// Fill with a constant value, which does not receive a gradient.
+	Constant(f32),
+	/// Mirror the input about the edge element, without repeating it, e.g. `[c, b, | a, b, c, d, | c, b]`.
+	Reflect,
+	/// Repeat the edge element, e.g. `[a, a, | a, b, c, d, | d, d]`.
+	Replicate,
+	/// Wrap around to the other end of the axis, e.g. `[c, d, | a, b, c, d, | a, b]`.
+	Circular,
+}
+
+impl PadMode {
+	/// Returns the index of the input element for position `i` of an output axis, or `None` if a constant is used.
+	///
+	/// `i` is relative to the start of the input, i.e. the padding before the input has negative indices.
+	fn source(&self, i: isize, len: usize) -> Option<usize> {
+		let len = len as isize;
+		if i >= 0 && i < len {
+			return Some(i as usize);
+		}
+		match *self {
+			PadMode::Constant(_) => None,
+			PadMode::Reflect => {
+				if len == 1 {
+					Some(0)
+				} else {
+					let period = 2*(len - 1);
+					let m = ((i % period) + period) % period;
+					Some(if m < len {m} else {period - m} as usize)
+				}
+			},
+			PadMode::Replicate => Some(i.max(0).min(len - 1) as usize),
+			PadMode::Circular => Some((((i % len) + len) % len) as usize),
+		}
+	}
+}
+
+/// Pads each axis of the input.
+///
+/// The output size along each axis is the input size plus the padding before and after.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Pad {
+	name: Option<String>,
+	padding: Vec<(usize, usize)>,
+	mode: PadMode,
+	input_id: NodeID,
+	output_id: NodeID,
+}
+
+impl Pad {
+	/// Padding is supplied as (before, after) for each axis of the input.
+	pub fn new(input_id: &NodeID, output_id: &NodeID, padding: &[(usize, usize)]) -> Self{
+		Pad {
+			name: None,
+			padding: padding.to_vec(),
+			mode: PadMode::Constant(0.0),
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+
+	/// Default: `PadMode::Constant(0.0)`
+	pub fn mode(mut self, mode: PadMode) -> Self {
+		self.mode = mode;
+		self
+	}
+}
+
+impl Op for Pad {
+	type InstanceType = PadInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Pad"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone()], &[self.output_id.clone()]);
+
+		Ok(PadInstance{
+			name: name,
+			padding: self.padding.clone(),
+			input_id: self.input_id.clone(),
+			output_id: self.output_id.clone(),
+			forward_id: graph.add_pass(PadForward::new(
+				self.input_id.clone(),
+				self.output_id.clone(),
+				self.padding.clone(),
+				self.mode,
+			)),
+			backward_id: graph.add_pass(PadBackward::new(
+				self.input_id.clone(),
+				self.output_id.clone(),
+				self.padding.clone(),
+				self.mode,
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct PadInstance {
+	name: String,
+	padding: Vec<(usize, usize)>,
+	input_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for PadInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+		ensure!(input_shape.ndim() == self.padding.len(), ErrorKind::ShapePropagationError(self.name.clone(), format!("input shape {:?} does not match the number of padding entries: {:?}", input_shape.slice(), self.padding)));
+
+		let output_shape: NodeShape = input_shape.slice().iter().zip(&self.padding).map(|(&dim, &(before, after))| dim + before + after).collect::<Vec<_>>().into();
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+/// Ensures that every padded axis has input elements to copy from, unless the padding is constant
+fn check_padded_axes(pass_name: String, input_shape: &[usize], padding: &[(usize, usize)], mode: PadMode) -> Result<()> {
+	if let PadMode::Constant(_) = mode {
+		return Ok(());
+	}
+	for (axis, (&len, &(before, after))) in input_shape.iter().zip(padding).enumerate() {
+		ensure!(len > 0 || (before == 0 && after == 0), ErrorKind::PassError(pass_name.clone(), format!("axis {} of input shape: {:?} is empty, so it can only be padded with PadMode::Constant, not {:?}", axis, input_shape, mode)));
+	}
+	Ok(())
+}
+
+/// Pads a single axis, returning a new array
+fn pad_axis(input: ArrayViewD<f32>, axis: usize, before: usize, after: usize, mode: PadMode) -> ArrayD<f32> {
+	let len = input.shape()[axis];
+	let mut shape = input.shape().to_vec();
+	shape[axis] += before + after;
+
+	let mut output = ArrayD::zeros(shape);
+	for (i, mut out_subview) in output.axis_iter_mut(Axis(axis)).enumerate() {
+		match mode.source(i as isize - before as isize, len) {
+			Some(j) => out_subview.assign(&input.subview(Axis(axis), j)),
+			None => if let PadMode::Constant(val) = mode {out_subview.fill(val)},
+		}
+	}
+	output
+}
+
+/// The transpose of `pad_axis()`, accumulating the gradient of each padded element into its source element
+fn unpad_axis(output_grad: ArrayViewD<f32>, axis: usize, before: usize, len: usize, mode: PadMode) -> ArrayD<f32> {
+	let mut shape = output_grad.shape().to_vec();
+	shape[axis] = len;
+
+	let mut input_grad = ArrayD::zeros(shape);
+	for (i, out_subview) in output_grad.axis_iter(Axis(axis)).enumerate() {
+		if let Some(j) = mode.source(i as isize - before as isize, len) {
+			let mut in_subview = input_grad.subview_mut(Axis(axis), j);
+			in_subview += &out_subview;
+		}
+	}
+	input_grad
+}
+
+#[derive(Debug, Clone)]
+pub struct PadForward {
+	input_id: NodeID,
+	output_id: NodeID,
+	padding: Vec<(usize, usize)>,
+	mode: PadMode,
+}
+
+impl PadForward {
+	pub fn new(input_id: NodeID, output_id: NodeID, padding: Vec<(usize, usize)>, mode: PadMode) -> Self{
+		PadForward {
+			input_id,
+			output_id,
+			padding,
+			mode,
+		}
+	}
+}
+
+impl Pass for PadForward {
+	fn type_name(&self) -> &'static str {"PadForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id()], vec![self.output_id.value_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+		ensure!(input.ndim() == self.padding.len(), ErrorKind::PassError(self.name(), format!("input shape: {:?} does not match the number of padding entries: {:?}", input.shape(), self.padding)));
+		check_padded_axes(self.name(), input.shape(), &self.padding, self.mode)?;
+
+		// pad one axis at a time, which also fills the corner regions appropriately
+		let mut padded = input.to_owned();
+		for (axis, &(before, after)) in self.padding.iter().enumerate() {
+			if before > 0 || after > 0 {
+				padded = pad_axis(padded.view(), axis, before, after, self.mode);
+			}
+		}
+		ensure!(padded.shape() == output.shape(), ErrorKind::PassError(self.name(), format!("padded input shape: {:?} does not match output shape: {:?}", padded.shape(), output.shape())));
+
+		output += &padded;
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct PadBackward {
+	input_id: NodeID,
+	output_id: NodeID,
+	padding: Vec<(usize, usize)>,
+	mode: PadMode,
+}
+
+impl PadBackward {
+	pub fn new(input_id: NodeID, output_id: NodeID, padding: Vec<(usize, usize)>, mode: PadMode) -> Self{
+		PadBackward {
+			input_id,
+			output_id,
+			padding,
+			mode,
+		}
+	}
+}
+
+impl Pass for PadBackward {
+	fn type_name(&self) -> &'static str {"PadBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.output_id.gradient_id()], vec![self.input_id.gradient_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+		ensure!(input_grad.ndim() == self.padding.len(), ErrorKind::PassError(self.name(), format!("input shape: {:?} does not match the number of padding entries: {:?}", input_grad.shape(), self.padding)));
+		check_padded_axes(self.name(), input_grad.shape(), &self.padding, self.mode)?;
+
+		// undo the padding of each axis in reverse order
+		let mut grad = output_grad.to_owned();
+		for (axis, &(before, after)) in self.padding.iter().enumerate().rev() {
+			if before > 0 || after > 0 {
+				grad = unpad_axis(grad.view(), axis, before, input_grad.shape()[axis], self.mode);
+			}
+		}
+		ensure!(grad.shape() == input_grad.shape(), ErrorKind::PassError(self.name(), format!("output gradient shape: {:?} does not match input shape: {:?} after removing padding", output_grad.shape(), input_grad.shape())));
+
+		input_grad += &grad;
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_pad_source(){
+	let len = 4;
+	let indices = |mode: PadMode| (-3..7).map(|i| mode.source(i, len)).collect::<Vec<_>>();
+	assert_eq!(indices(PadMode::Constant(0.0)), vec![None, None, None, Some(0), Some(1), Some(2), Some(3), None, None, None]);
+	assert_eq!(indices(PadMode::Reflect), vec![Some(3), Some(2), Some(1), Some(0), Some(1), Some(2), Some(3), Some(2), Some(1), Some(0)]);
+	assert_eq!(indices(PadMode::Replicate), vec![Some(0), Some(0), Some(0), Some(0), Some(1), Some(2), Some(3), Some(3), Some(3), Some(3)]);
+	assert_eq!(indices(PadMode::Circular), vec![Some(1), Some(2), Some(3), Some(0), Some(1), Some(2), Some(3), Some(0), Some(1), Some(2)]);
+}
+
+#[test]
+fn test_pad_empty_axis(){
+	_pad_empty_axis().unwrap();
+}
+
+fn _pad_empty_axis() -> Result<()>{
+	use graph::GraphDef;
+
+	for &mode in &[PadMode::Constant(0.5), PadMode::Reflect, PadMode::Replicate, PadMode::Circular] {
+		let mut g = GraphDef::new();
+
+		let node1 = g.new_node(shape![2, Unknown], "input", tag![])?;
+		let node2 = g.new_node(shape![2, Unknown], "output", tag![])?;
+
+		let _o1 = g.new_op(Pad::new(&node1, &node2, &[(0, 0), (1, 2)]).mode(mode), tag![])?;
+
+		let mut subgraph = g.subgraph(&[node1.value_id()], &[node2.value_id()])?;
+		let result = subgraph.execute(vec![ArrayD::zeros(vec![2, 0])]);
+		if let PadMode::Constant(_) = mode {
+			assert_eq!(result?.get::<f32>(&node2.value_id())?.shape(), &[2, 3]);
+		} else {
+			assert!(result.is_err());
+		}
+	}
+
+	Ok(())
+}
+
+#[test]
+fn test_pad_backprop(){
+	_pad_backprop().unwrap();
+}
+
+fn _pad_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	for &mode in &[PadMode::Constant(0.5), PadMode::Reflect, PadMode::Replicate, PadMode::Circular] {
+		let mut g = GraphDef::new();
+
+		let node1 = g.new_node(shape![3, 4, 5], "input", tag![])?;
+		let node2 = g.new_node(shape![Unknown, Unknown, Unknown], "output", tag![])?;
+		let node3 = g.new_node(shape![3, 9, 6], "target", tag![])?;
+
+		let _o1 = g.new_op(Pad::new(&node1, &node2, &[(0, 0), (2, 3), (1, 0)]).mode(mode), tag![])?;
+		let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+		let iters = 100;
+		let failures = 1;
+		let tolerance = 0.002;
+		let step_size = 1E-2;
+		let default_variance = 1.0;
+		numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+	}
+
+	Ok(())
+}
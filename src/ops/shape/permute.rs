@@ -0,0 +1,232 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::shape::normalise_axis;
+use shape::NodeShape;
+use ndarray::Dimension;
+use std::any::Any;
+
+/// Reorders the axes of the input.
+///
+/// Output axis `i` corresponds to input axis `axes[i]`, e.g. `&[0, 2, 1]` swaps the two innermost axes of a 3D input.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Permute {
+	name: Option<String>,
+	axes: Vec<usize>,
+	swap: Option<(isize, isize)>,
+	input_id: NodeID,
+	output_id: NodeID,
+}
+
+impl Permute {
+	pub fn new(input_id: &NodeID, output_id: &NodeID, axes: &[usize]) -> Self{
+		Permute {
+			name: None,
+			axes: axes.to_vec(),
+			swap: None,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+
+	/// Swaps two axes, leaving all others in place.
+	///
+	/// Each axis can be in the range [-input.ndims(), input.ndims()), and is checked when the Op is built.
+	pub fn transpose(input_id: &NodeID, output_id: &NodeID, axis1: isize, axis2: isize) -> Self{
+		Permute {
+			name: None,
+			axes: vec![],
+			swap: Some((axis1, axis2)),
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+}
+
+impl Op for Permute {
+	type InstanceType = PermuteInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Permute"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let axes = if let Some((axis1, axis2)) = self.swap {
+			let ndim = self.input_id.shape().ndims();
+			let mut axes: Vec<usize> = (0..ndim).collect();
+			axes.swap(normalise_axis(axis1, ndim)?, normalise_axis(axis2, ndim)?);
+			axes
+		} else {
+			self.axes.clone()
+		};
+
+		let mut sorted = axes.clone();
+		sorted.sort();
+		ensure!(sorted.iter().enumerate().all(|(i, &axis)| i == axis), "Permute axes must contain each axis exactly once: {:?}", axes);
+		ensure!(axes.len() == self.input_id.shape().ndims(), "Permute axes {:?} must have the same length as the input shape: {:?}", axes, self.input_id.shape());
+
+		let mut inverse = vec![0; axes.len()];
+		for (i, &axis) in axes.iter().enumerate() {
+			inverse[axis] = i;
+		}
+
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone()], &[self.output_id.clone()]);
+
+		Ok(PermuteInstance{
+			name: name,
+			axes: axes.clone(),
+			input_id: self.input_id.clone(),
+			output_id: self.output_id.clone(),
+			forward_id: graph.add_pass(PermutePass::new(
+				self.input_id.value_id(),
+				self.output_id.value_id(),
+				axes,
+			)),
+			backward_id: graph.add_pass(PermutePass::new(
+				self.output_id.gradient_id(),
+				self.input_id.gradient_id(),
+				inverse,
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct PermuteInstance {
+	name: String,
+	axes: Vec<usize>,
+	input_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for PermuteInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+		ensure!(input_shape.ndim() == self.axes.len(), ErrorKind::ShapePropagationError(self.name.clone(), format!("input shape {:?} does not match the number of permute axes: {:?}", input_shape.slice(), self.axes)));
+
+		let output_shape: NodeShape = self.axes.iter().map(|&axis| input_shape[axis]).collect::<Vec<_>>().into();
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+
+/// Adds the input, with permuted axes, to the output.
+///
+/// The permutation is applied to a view of the input, without copying.
+#[derive(Debug, Clone)]
+pub struct PermutePass {
+	input_id: DataID,
+	output_id: DataID,
+	axes: Vec<usize>,
+}
+
+impl PermutePass {
+	pub fn new(input_id: DataID, output_id: DataID, axes: Vec<usize>) -> Self{
+		PermutePass {
+			input_id,
+			output_id,
+			axes,
+		}
+	}
+}
+
+impl Pass for PermutePass {
+	fn type_name(&self) -> &'static str {"PermutePass"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.clone()], vec![self.output_id.clone()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id)?;
+		let mut output = data.get_mut::<f32>(&self.output_id)?;
+		ensure!(input.ndim() == self.axes.len(), ErrorKind::PassError(self.name(), format!("input shape: {:?} does not match the number of permute axes: {:?}", input.shape(), self.axes)));
+
+		let input = input.permuted_axes(&self.axes[..]);
+		ensure!(input.shape() == output.shape(), ErrorKind::PassError(self.name(), format!("permuted input shape: {:?} does not match output shape: {:?}", input.shape(), output.shape())));
+		output += &input;
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_permute_backprop(){
+	_permute_backprop().unwrap();
+}
+
+fn _permute_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 3, 4, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![Unknown, Unknown, Unknown, Unknown], "output", tag![])?;
+	let node3 = g.new_node(shape![4, 2, 5, 3], "target", tag![])?;
+
+	let _o1 = g.new_op(Permute::new(&node1, &node2, &[2, 0, 3, 1]), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+
+#[test]
+fn test_permute_transpose(){
+	_permute_transpose().unwrap();
+}
+
+fn _permute_transpose() -> Result<()>{
+	use graph::GraphDef;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![2, 3, 4], "input", tag![])?;
+	let node2 = g.new_node(shape![Unknown, Unknown, Unknown], "output", tag![])?;
+
+	let o1 = g.new_op(Permute::transpose(&node1, &node2, 0, -1), tag![])?;
+	assert_eq!(o1.instance().as_any().downcast_ref::<PermuteInstance>().unwrap().axes, vec![2, 1, 0]);
+
+	// out of range axes are an error rather than a panic
+	assert!(g.new_op(Permute::transpose(&node1, &node2, 0, 3), tag![]).is_err());
+	assert!(g.new_op(Permute::transpose(&node1, &node2, -4, 1), tag![]).is_err());
+
+	Ok(())
+}
@@ -243,8 +243,8 @@ impl Pass for CollapsePass {
 	}
 
 	fn run(&self, data: &Storage) -> Result<Box<Any>> {
-		let input = data.get(&self.input_id)?;
-		let mut output = data.get_mut(&self.output_id)?;
+		let input = data.get::<f32>(&self.input_id)?;
+		let mut output = data.get_mut::<f32>(&self.output_id)?;
 
 		let input_shape = input.shape();
 		let output_shape = output.shape().to_vec();
@@ -312,8 +312,8 @@ impl Pass for ExpandPass {
 	}
 
 	fn run(&self, data: &Storage) -> Result<Box<Any>> {
-		let input = data.get(&self.input_id)?;
-		let mut output = data.get_mut(&self.output_id)?;
+		let input = data.get::<f32>(&self.input_id)?;
+		let mut output = data.get_mut::<f32>(&self.output_id)?;
 
 		let input_shape = input.shape();
 		let output_shape = output.shape().to_vec();
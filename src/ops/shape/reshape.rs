@@ -0,0 +1,418 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::shape::normalise_axis;
+use shape::NodeShape;
+use ndarray::Dimension;
+use std::any::Any;
+
+/// Changes the shape of the input without changing the order of elements.
+///
+/// The output shape may contain one `-1` entry, the size of which is inferred from the number of elements in the input.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Reshape {
+	name: Option<String>,
+	shape: Vec<isize>,
+	input_id: NodeID,
+	output_id: NodeID,
+}
+
+impl Reshape {
+	pub fn new(input_id: &NodeID, output_id: &NodeID, shape: &[isize]) -> Self{
+		Reshape {
+			name: None,
+			shape: shape.to_vec(),
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+}
+
+impl Op for Reshape {
+	type InstanceType = ReshapeInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Reshape"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		ensure!(self.shape.iter().all(|&dim| dim >= -1), "Reshape dimensions must be non-negative or -1: {:?}", self.shape);
+		ensure!(self.shape.iter().filter(|&&dim| dim == -1).count() <= 1, "Reshape can infer at most one dimension (-1): {:?}", self.shape);
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone()], &[self.output_id.clone()]);
+
+		Ok(ReshapeInstance{
+			name: name,
+			shape: self.shape.clone(),
+			input_id: self.input_id.clone(),
+			output_id: self.output_id.clone(),
+			forward_id: graph.add_pass(ReshapePass::new(
+				self.input_id.value_id(),
+				self.output_id.value_id(),
+			)),
+			backward_id: graph.add_pass(ReshapePass::new(
+				self.output_id.gradient_id(),
+				self.input_id.gradient_id(),
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ReshapeInstance {
+	name: String,
+	shape: Vec<isize>,
+	input_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for ReshapeInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_size = shapes.get_shape(&self.input_id).to_data_shape()?.size();
+		let known_size: usize = self.shape.iter().filter(|&&dim| dim != -1).map(|&dim| dim as usize).product();
+
+		let output_shape: NodeShape = if self.shape.contains(&-1) {
+			ensure!(known_size > 0 && input_size % known_size == 0, ErrorKind::ShapePropagationError(self.name.clone(), format!("input size {} cannot be reshaped to {:?}", input_size, self.shape)));
+			self.shape.iter().map(|&dim| if dim == -1 {input_size/known_size} else {dim as usize}).into()
+		} else {
+			ensure!(input_size == known_size, ErrorKind::ShapePropagationError(self.name.clone(), format!("input size {} cannot be reshaped to {:?}", input_size, self.shape)));
+			self.shape.iter().map(|&dim| dim as usize).into()
+		};
+
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+
+/// Removes axes of size 1.
+///
+/// If no axes are supplied, all axes of size 1 are removed.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Squeeze {
+	name: Option<String>,
+	axes: Vec<isize>,
+	input_id: NodeID,
+	output_id: NodeID,
+}
+
+impl Squeeze {
+	/// Each element of `axes` can be in the range [-input.ndims(), input.ndims()).
+	pub fn new(input_id: &NodeID, output_id: &NodeID, axes: &[isize]) -> Self{
+		Squeeze {
+			name: None,
+			axes: axes.to_vec(),
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+}
+
+impl Op for Squeeze {
+	type InstanceType = SqueezeInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Squeeze"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone()], &[self.output_id.clone()]);
+
+		Ok(SqueezeInstance{
+			name: name,
+			axes: self.axes.clone(),
+			input_id: self.input_id.clone(),
+			output_id: self.output_id.clone(),
+			forward_id: graph.add_pass(ReshapePass::new(
+				self.input_id.value_id(),
+				self.output_id.value_id(),
+			)),
+			backward_id: graph.add_pass(ReshapePass::new(
+				self.output_id.gradient_id(),
+				self.input_id.gradient_id(),
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct SqueezeInstance {
+	name: String,
+	axes: Vec<isize>,
+	input_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for SqueezeInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+
+		let mut remove = vec![self.axes.is_empty(); input_shape.ndim()];
+		for &axis in &self.axes {
+			let axis = normalise_axis(axis, input_shape.ndim())?;
+			ensure!(input_shape[axis] == 1, ErrorKind::ShapePropagationError(self.name.clone(), format!("cannot squeeze axis {} of input shape {:?} as it does not have size 1", axis, input_shape.slice())));
+			remove[axis] = true;
+		}
+
+		let output_shape: NodeShape = input_shape.slice().iter().zip(&remove).filter(|&(&dim, &remove)| !(remove && dim == 1)).map(|(&dim, _)| dim).into();
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+
+/// Inserts axes of size 1.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Unsqueeze {
+	name: Option<String>,
+	axes: Vec<isize>,
+	input_id: NodeID,
+	output_id: NodeID,
+}
+
+impl Unsqueeze {
+	/// The position of the new axes in the output.
+	/// Each element of `axes` can be in the range [-output.ndims(), output.ndims()), where output.ndims() is input.ndims() + axes.len().
+	pub fn new(input_id: &NodeID, output_id: &NodeID, axes: &[isize]) -> Self{
+		Unsqueeze {
+			name: None,
+			axes: axes.to_vec(),
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+}
+
+impl Op for Unsqueeze {
+	type InstanceType = UnsqueezeInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Unsqueeze"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone()], &[self.output_id.clone()]);
+
+		Ok(UnsqueezeInstance{
+			name: name,
+			axes: self.axes.clone(),
+			input_id: self.input_id.clone(),
+			output_id: self.output_id.clone(),
+			forward_id: graph.add_pass(ReshapePass::new(
+				self.input_id.value_id(),
+				self.output_id.value_id(),
+			)),
+			backward_id: graph.add_pass(ReshapePass::new(
+				self.output_id.gradient_id(),
+				self.input_id.gradient_id(),
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct UnsqueezeInstance {
+	name: String,
+	axes: Vec<isize>,
+	input_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for UnsqueezeInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+		let output_ndim = input_shape.ndim() + self.axes.len();
+
+		let mut inserted = vec![false; output_ndim];
+		for &axis in &self.axes {
+			let axis = normalise_axis(axis, output_ndim)?;
+			ensure!(!inserted[axis], ErrorKind::ShapePropagationError(self.name.clone(), format!("axis {} was repeated", axis)));
+			inserted[axis] = true;
+		}
+
+		let mut input_dims = input_shape.slice().iter();
+		let output_shape: NodeShape = inserted.iter().map(|&inserted| {
+			if inserted {1} else {*input_dims.next().unwrap()}
+		}).collect::<Vec<_>>().into();
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+
+/// Adds the input to the output, reinterpreting the input with the shape of the output.
+///
+/// The input is viewed in the output shape without copying, so long as it has a standard layout.
+#[derive(Debug, Clone)]
+pub struct ReshapePass {
+	input_id: DataID,
+	output_id: DataID,
+}
+
+impl ReshapePass {
+	pub fn new(input_id: DataID, output_id: DataID) -> Self{
+		ReshapePass {
+			input_id,
+			output_id,
+		}
+	}
+}
+
+impl Pass for ReshapePass {
+	fn type_name(&self) -> &'static str {"ReshapePass"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.clone()], vec![self.output_id.clone()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id)?;
+		let mut output = data.get_mut::<f32>(&self.output_id)?;
+		ensure!(input.len() == output.len(), ErrorKind::PassError(self.name(), format!("input shape: {:?} has a different number of elements to output shape: {:?}", input.shape(), output.shape())));
+
+		let output_shape = output.raw_dim();
+		if input.is_standard_layout() {
+			output += &input.into_shape(output_shape).expect("size and layout were checked");
+		} else {
+			output += &input.to_owned().into_shape(output_shape).expect("size was checked");
+		}
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_reshape_backprop(){
+	_reshape_backprop().unwrap();
+}
+
+fn _reshape_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 4, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![Unknown, 10], "output", tag![])?;
+	let node3 = g.new_node(shape![6, 10], "target", tag![])?;
+
+	let _o1 = g.new_op(Reshape::new(&node1, &node2, &[-1, 10]), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_squeeze_unsqueeze_backprop(){
+	_squeeze_unsqueeze_backprop().unwrap();
+}
+
+fn _squeeze_unsqueeze_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 1, 5, 1], "input", tag![])?;
+	let node2 = g.new_node(shape![Unknown, Unknown], "squeezed", tag![])?;
+	let node3 = g.new_node(shape![Unknown, Unknown, Unknown], "unsqueezed", tag![])?;
+	let node4 = g.new_node(shape![3, 5, 1], "target", tag![])?;
+
+	let _o1 = g.new_op(Squeeze::new(&node1, &node2, &[]), tag![])?;
+	let _o2 = g.new_op(Unsqueeze::new(&node2, &node3, &[-1]), tag![])?;
+	let _o3 = g.new_op(Mse::new(&node3, &node4), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
@@ -0,0 +1,478 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use shape::{NodeShape, NodeDim};
+use ndarray::{ArrayD, ArrayViewD, Axis, Dimension};
+use std::any::Any;
+
+/// The kernel used to interpolate between input elements
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+	/// Use the value of the nearest input element.
+	Nearest,
+	/// Linear interpolation along each resized axis, i.e. bilinear for images.
+	Linear,
+	/// Cubic convolution along each resized axis (Keys, a = -0.5), i.e. bicubic for images.
+	Cubic,
+}
+
+impl Interpolation {
+	/// The distance from the centre at which the kernel becomes zero
+	fn radius(&self) -> f64 {
+		match *self {
+			Interpolation::Nearest => 0.5,
+			Interpolation::Linear => 1.0,
+			Interpolation::Cubic => 2.0,
+		}
+	}
+
+	fn kernel(&self, x: f64) -> f64 {
+		let x = x.abs();
+		match *self {
+			Interpolation::Nearest => if x < 0.5 {1.0} else {0.0},
+			Interpolation::Linear => (1.0 - x).max(0.0),
+			Interpolation::Cubic => {
+				let a = -0.5;
+				if x <= 1.0 {
+					((a + 2.0)*x - (a + 3.0))*x*x + 1.0
+				} else if x < 2.0 {
+					((a*x - 5.0*a)*x + 8.0*a)*x - 4.0*a
+				} else {
+					0.0
+				}
+			},
+		}
+	}
+}
+
+/// Settings shared by the forward and backward resize passes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResizeSettings {
+	interpolation: Interpolation,
+	align_corners: bool,
+	antialias: bool,
+}
+
+impl ResizeSettings {
+	/// Returns the input elements and weights which contribute to each element along an output axis
+	fn axis_weights(&self, in_len: usize, out_len: usize) -> Vec<Vec<(usize, f32)>> {
+		let scale = in_len as f64/out_len as f64;
+		(0..out_len).map(|o| {
+			if self.interpolation == Interpolation::Nearest {
+				let i = if self.align_corners {
+					if out_len > 1 {(o as f64 * (in_len - 1) as f64/(out_len - 1) as f64).round() as usize} else {0}
+				} else {
+					((o as f64 + 0.5) * scale).floor() as usize
+				};
+				return vec![(i.min(in_len - 1), 1.0)];
+			}
+
+			let centre = if self.align_corners {
+				if out_len > 1 {o as f64 * (in_len - 1) as f64/(out_len - 1) as f64} else {0.0}
+			} else {
+				(o as f64 + 0.5) * scale - 0.5
+			};
+
+			// when downsampling with antialiasing the kernel is stretched to cover all input elements
+			let support_scale = if self.antialias && out_len < in_len {scale} else {1.0};
+			let radius = self.interpolation.radius() * support_scale;
+			let start = (centre - radius).floor() as isize;
+			let end = (centre + radius).ceil() as isize;
+
+			let mut weights: Vec<(usize, f64)> = vec![];
+			for i in start..end + 1 {
+				let w = self.interpolation.kernel((i as f64 - centre)/support_scale);
+				if w == 0.0 {
+					continue;
+				}
+				// elements beyond the edge take the value of the edge element
+				let i = i.max(0).min(in_len as isize - 1) as usize;
+				match weights.iter_mut().find(|&&mut (j, _)| j == i) {
+					Some(entry) => entry.1 += w,
+					None => weights.push((i, w)),
+				}
+			}
+
+			let sum: f64 = weights.iter().map(|&(_, w)| w).sum();
+			weights.iter().map(|&(i, w)| (i, (w/sum) as f32)).collect()
+		}).collect()
+	}
+}
+
+/// Resize Op
+///
+/// Resamples the input to a new size along each axis, which can be larger or smaller than the input size, and need not be an integer multiple.
+///
+/// The output size of each axis is determined by, in order of precedence: `sizes()`, `scales()`, the output node shape if it is known, or else the input size.
+/// Axes which do not change size are not interpolated, so batch and channel axes can be left alone.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Resize {
+	name: Option<String>,
+	sizes: Option<Vec<Option<usize>>>,
+	scales: Option<Vec<f32>>,
+	settings: ResizeSettings,
+	input_id: NodeID,
+	output_id: NodeID,
+}
+
+impl Resize {
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self{
+		Resize {
+			name: None,
+			sizes: None,
+			scales: None,
+			settings: ResizeSettings {
+				interpolation: Interpolation::Linear,
+				align_corners: false,
+				antialias: false,
+			},
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+
+	/// The output size for each axis of the input, where `None` leaves the size to be determined by the other settings.
+	///
+	/// Default: None
+	pub fn sizes(mut self, sizes: &[Option<usize>]) -> Self {
+		self.sizes = Some(sizes.to_vec());
+		self
+	}
+
+	/// A scale factor for each axis of the input. The output size is the input size times the scale, rounded to the nearest integer.
+	///
+	/// Default: None
+	pub fn scales(mut self, scales: &[f32]) -> Self {
+		self.scales = Some(scales.to_vec());
+		self
+	}
+
+	/// Default: `Interpolation::Linear`
+	pub fn interpolation(mut self, interpolation: Interpolation) -> Self {
+		self.settings.interpolation = interpolation;
+		self
+	}
+
+	/// If true, the centres of the first and last elements of the input and output are aligned.
+	/// Otherwise the outer edges of the first and last elements are aligned (half pixel centres).
+	///
+	/// Default: false
+	pub fn align_corners(mut self, align_corners: bool) -> Self {
+		self.settings.align_corners = align_corners;
+		self
+	}
+
+	/// If true, when an axis is downsampled the linear or cubic kernel is widened by the downsampling factor to prevent aliasing.
+	///
+	/// Has no effect on `Interpolation::Nearest`.
+	///
+	/// Default: false
+	pub fn antialias(mut self, antialias: bool) -> Self {
+		self.settings.antialias = antialias;
+		self
+	}
+}
+
+impl Op for Resize {
+	type InstanceType = ResizeInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Resize"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone()], &[self.output_id.clone()]);
+
+		Ok(ResizeInstance{
+			name: name,
+			sizes: self.sizes.clone(),
+			scales: self.scales.clone(),
+			input_id: self.input_id.clone(),
+			output_id: self.output_id.clone(),
+			forward_id: graph.add_pass(ResizeForward::new(
+				self.input_id.clone(),
+				self.output_id.clone(),
+				self.settings,
+			)),
+			backward_id: graph.add_pass(ResizeBackward::new(
+				self.input_id.clone(),
+				self.output_id.clone(),
+				self.settings,
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ResizeInstance {
+	name: String,
+	sizes: Option<Vec<Option<usize>>>,
+	scales: Option<Vec<f32>>,
+	input_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for ResizeInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+		let output_shape = shapes.get_output_shape(&self.output_id).clone();
+		let ndim = input_shape.ndim();
+
+		if let Some(ref sizes) = self.sizes {
+			ensure!(sizes.len() == ndim, ErrorKind::ShapePropagationError(self.name.clone(), format!("input shape {:?} does not match the number of sizes: {:?}", input_shape.slice(), sizes)));
+		}
+		if let Some(ref scales) = self.scales {
+			ensure!(scales.len() == ndim, ErrorKind::ShapePropagationError(self.name.clone(), format!("input shape {:?} does not match the number of scales: {:?}", input_shape.slice(), scales)));
+		}
+
+		let dims: Vec<NodeDim> = input_shape.slice().iter().enumerate().map(|(axis, &input_dim)| {
+			let size = self.sizes.as_ref().and_then(|sizes| sizes[axis]);
+			let scale = self.scales.as_ref().map(|scales| scales[axis]);
+			match (size, scale) {
+				(Some(size), _) => NodeDim::Known(size),
+				(None, Some(scale)) => NodeDim::Known(((input_dim as f32 * scale).round() as usize).max(1)),
+				(None, None) => match output_shape.dimensions().get(axis) {
+					Some(&NodeDim::Known(x)) => NodeDim::Known(x),
+					_ => NodeDim::Known(input_dim),
+				},
+			}
+		}).collect();
+
+		shapes.merge_with(&self.output_id, &NodeShape::from(dims))
+	}
+}
+
+/// Resamples a single axis, returning a new array
+pub(crate) fn resize_axis(input: ArrayViewD<f32>, axis: usize, weights: &[Vec<(usize, f32)>]) -> ArrayD<f32> {
+	let mut shape = input.shape().to_vec();
+	shape[axis] = weights.len();
+
+	let mut output = ArrayD::zeros(shape);
+	for (out_subview, weights) in output.axis_iter_mut(Axis(axis)).zip(weights) {
+		let mut out_subview = out_subview;
+		for &(i, w) in weights {
+			out_subview.scaled_add(w, &input.subview(Axis(axis), i));
+		}
+	}
+	output
+}
+
+/// The transpose of `resize_axis()`, accumulating the gradient of each output element into the input elements it was interpolated from
+pub(crate) fn resize_axis_transpose(output_grad: ArrayViewD<f32>, axis: usize, weights: &[Vec<(usize, f32)>], len: usize) -> ArrayD<f32> {
+	let mut shape = output_grad.shape().to_vec();
+	shape[axis] = len;
+
+	let mut input_grad = ArrayD::zeros(shape);
+	for (out_subview, weights) in output_grad.axis_iter(Axis(axis)).zip(weights) {
+		for &(i, w) in weights {
+			input_grad.subview_mut(Axis(axis), i).scaled_add(w, &out_subview);
+		}
+	}
+	input_grad
+}
+
+/// Checks that the input and output are compatible, returning the axes which change size
+fn resized_axes(pass_name: String, input_shape: &[usize], output_shape: &[usize]) -> Result<Vec<usize>> {
+	ensure!(input_shape.len() == output_shape.len(), ErrorKind::PassError(pass_name.clone(), format!("input shape: {:?} does not have the same number of dimensions as output shape: {:?}", input_shape, output_shape)));
+	ensure!(input_shape.iter().zip(output_shape).all(|(&i, &o)| i > 0 || o == 0), ErrorKind::PassError(pass_name, format!("input shape: {:?} can not be resized to output shape: {:?}", input_shape, output_shape)));
+	Ok((0..input_shape.len()).filter(|&axis| input_shape[axis] != output_shape[axis]).collect())
+}
+
+#[derive(Debug, Clone)]
+pub struct ResizeForward {
+	input_id: NodeID,
+	output_id: NodeID,
+	settings: ResizeSettings,
+}
+
+impl ResizeForward {
+	pub fn new(input_id: NodeID, output_id: NodeID, settings: ResizeSettings) -> Self{
+		ResizeForward {
+			input_id,
+			output_id,
+			settings,
+		}
+	}
+}
+
+impl Pass for ResizeForward {
+	fn type_name(&self) -> &'static str {"ResizeForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id()], vec![self.output_id.value_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+		let axes = resized_axes(self.name(), input.shape(), output.shape())?;
+
+		// resize one axis at a time, as each kernel is separable
+		let mut resized = input.to_owned();
+		for axis in axes {
+			let weights = self.settings.axis_weights(input.shape()[axis], output.shape()[axis]);
+			resized = resize_axis(resized.view(), axis, &weights);
+		}
+
+		output += &resized;
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ResizeBackward {
+	input_id: NodeID,
+	output_id: NodeID,
+	settings: ResizeSettings,
+}
+
+impl ResizeBackward {
+	pub fn new(input_id: NodeID, output_id: NodeID, settings: ResizeSettings) -> Self{
+		ResizeBackward {
+			input_id,
+			output_id,
+			settings,
+		}
+	}
+}
+
+impl Pass for ResizeBackward {
+	fn type_name(&self) -> &'static str {"ResizeBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.output_id.gradient_id()], vec![self.input_id.gradient_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let mut input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+		let axes = resized_axes(self.name(), input_grad.shape(), output_grad.shape())?;
+
+		// undo the resizing of each axis in reverse order
+		let mut grad = output_grad.to_owned();
+		for axis in axes.into_iter().rev() {
+			let weights = self.settings.axis_weights(input_grad.shape()[axis], output_grad.shape()[axis]);
+			grad = resize_axis_transpose(grad.view(), axis, &weights, input_grad.shape()[axis]);
+		}
+
+		input_grad += &grad;
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_resize_weights(){
+	let settings = |interpolation, align_corners, antialias| ResizeSettings{interpolation, align_corners, antialias};
+	let values = |settings: ResizeSettings, input: &[f32], out_len: usize| -> Vec<f32> {
+		settings.axis_weights(input.len(), out_len).iter().map(|weights| weights.iter().map(|&(i, w)| input[i] * w).sum()).collect()
+	};
+
+	assert_eq!(values(settings(Interpolation::Linear, true, false), &[0.0, 1.0, 2.0], 5), vec![0.0, 0.5, 1.0, 1.5, 2.0]);
+	assert_eq!(values(settings(Interpolation::Linear, false, false), &[0.0, 1.0], 4), vec![0.0, 0.25, 0.75, 1.0]);
+	assert_eq!(values(settings(Interpolation::Nearest, false, false), &[0.0, 1.0, 2.0], 2), vec![0.0, 2.0]);
+	assert_eq!(values(settings(Interpolation::Nearest, false, false), &[0.0, 1.0], 5), vec![0.0, 0.0, 1.0, 1.0, 1.0]);
+	assert_eq!(values(settings(Interpolation::Linear, false, true), &[0.0, 1.0, 2.0, 3.0], 2), vec![0.625, 2.375]);
+	assert_eq!(values(settings(Interpolation::Cubic, false, false), &[1.0, 1.0, 1.0], 7), vec![1.0; 7]);
+
+	// cubic interpolation is exact for linear functions away from the edges
+	let cubic = values(settings(Interpolation::Cubic, true, false), &[0.0, 1.0, 2.0, 3.0, 4.0], 9);
+	for (i, &x) in cubic[2..7].iter().enumerate() {
+		assert!((x - (i + 2) as f32 * 0.5).abs() < 1e-6);
+	}
+}
+
+#[test]
+fn test_resize_backprop(){
+	_resize_backprop().unwrap();
+}
+
+fn _resize_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	for &interpolation in &[Interpolation::Nearest, Interpolation::Linear, Interpolation::Cubic] {
+		for &(align_corners, antialias) in &[(false, false), (true, false), (false, true)] {
+			let mut g = GraphDef::new();
+
+			let node1 = g.new_node(shape![3, 5, 7, 2], "input", tag![])?;
+			let node2 = g.new_node(shape![Unknown, Unknown, Unknown, 2], "output", tag![])?;
+			let node3 = g.new_node(shape![3, 8, 4, 2], "target", tag![])?;
+
+			let _o1 = g.new_op(Resize::new(&node1, &node2)
+				.scales(&[1.0, 1.6, 0.6, 1.0])
+				.interpolation(interpolation)
+				.align_corners(align_corners)
+				.antialias(antialias), tag![])?;
+			let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+			let iters = 100;
+			let failures = 1;
+			let tolerance = 0.002;
+			let step_size = 1E-2;
+			let default_variance = 1.0;
+			numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+		}
+	}
+
+	Ok(())
+}
+
+#[test]
+fn test_resize_sizes(){
+	_resize_sizes().unwrap();
+}
+
+fn _resize_sizes() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 6, 5, 2], "input", tag![])?;
+	let node2 = g.new_node(shape![Unknown, 4, Unknown, Unknown], "output", tag![])?;
+	let node3 = g.new_node(shape![3, 4, 11, 2], "target", tag![])?;
+
+	let _o1 = g.new_op(Resize::new(&node1, &node2).sizes(&[None, None, Some(11), None]).interpolation(Interpolation::Cubic).antialias(true), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
@@ -0,0 +1,343 @@
+use graph::{GraphDef, GraphShapes, ErrorKind, Result};
+use id::{NodeID, DataID, OpID, PassID};
+use storage::Storage;
+use ops::{standard_op_name, Op, OpInstance, Pass};
+use ops::shape::normalise_axis;
+use shape::{NodeShape, NodeDim};
+use ndarray::{ArrayViewD, ArrayViewMutD, Axis, Dimension};
+use ndarray::Slice as AxisSlice;
+use std::any::Any;
+
+#[derive(Clone, Debug)]
+enum SliceSpec {
+	/// (axis, slice) pairs, unlisted axes are taken in full
+	Explicit(Vec<(isize, AxisSlice)>),
+	/// Crop each axis symmetrically to the size of the output
+	Centre,
+}
+
+/// Selects a (possibly strided) region of the input.
+///
+/// Each axis can be sliced with an `ndarray::Slice`, where negative `start` and `end` values count back from the end of the axis,
+/// and a negative `step` reverses the order of elements. Axes without a slice are taken in full.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct Slice {
+	name: Option<String>,
+	spec: SliceSpec,
+	input_id: NodeID,
+	output_id: NodeID,
+}
+
+impl Slice {
+	pub fn new(input_id: &NodeID, output_id: &NodeID) -> Self{
+		Slice {
+			name: None,
+			spec: SliceSpec::Explicit(vec![]),
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+
+	/// Crops each axis symmetrically to the size of the output.
+	///
+	/// Axes where the output size is not `Known` are not cropped.
+	/// If the difference in size is odd, the extra element is removed from the end of the axis.
+	/// Useful for matching outputs of `Padding::Valid` convolutions to their targets.
+	pub fn centre_crop(input_id: &NodeID, output_id: &NodeID) -> Self{
+		Slice {
+			name: None,
+			spec: SliceSpec::Centre,
+			input_id: input_id.clone(),
+			output_id: output_id.clone(),
+		}
+	}
+
+	/// Set the slice for an axis.
+	///
+	/// The axis can be in the range [-input.ndims(), input.ndims()).
+	/// Has no effect on a `centre_crop()` op.
+	pub fn axis<S: Into<AxisSlice>>(mut self, axis: isize, slice: S) -> Self {
+		if let SliceSpec::Explicit(ref mut slices) = self.spec {
+			slices.push((axis, slice.into()));
+		}
+		self
+	}
+}
+
+impl Op for Slice {
+	type InstanceType = SliceInstance;
+
+	fn type_name(&self) -> &'static str {
+		"Slice"
+	}
+
+	fn name<T: Into<String>>(mut self, name: T) -> Self{
+		self.name = Some(name.into());
+		self
+	}
+
+	fn build(self, graph: &mut GraphDef) -> Result<Self::InstanceType> {
+		if let SliceSpec::Explicit(ref slices) = self.spec {
+			ensure!(slices.iter().all(|&(_, slice)| slice.step != 0), "Slice step must not be zero");
+		}
+		let name = standard_op_name(&self, &self.name, graph, &[self.input_id.clone()], &[self.output_id.clone()]);
+
+		Ok(SliceInstance{
+			name: name,
+			spec: self.spec.clone(),
+			input_id: self.input_id.clone(),
+			output_id: self.output_id.clone(),
+			forward_id: graph.add_pass(SliceForward::new(
+				self.input_id.clone(),
+				self.output_id.clone(),
+				self.spec.clone(),
+			)),
+			backward_id: graph.add_pass(SliceBackward::new(
+				self.input_id.clone(),
+				self.output_id.clone(),
+				self.spec.clone(),
+			)),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct SliceInstance {
+	name: String,
+	spec: SliceSpec,
+	input_id: NodeID,
+	output_id: NodeID,
+	forward_id: PassID,
+	backward_id: PassID,
+}
+
+impl OpInstance for SliceInstance {
+
+	fn name(&self) -> &str {&self.name}
+
+	fn dependencies(&self) -> (Vec<NodeID>, Vec<NodeID>){
+		(
+			vec![self.input_id.clone()],
+			vec![self.output_id.clone()]
+		)
+	}
+
+	fn inner_passes(&self) -> Vec<PassID> {
+		vec![self.forward_id.clone(), self.backward_id.clone()]
+	}
+
+	fn inner_ops(&self) -> Vec<OpID> {vec![]}
+
+	fn inner_nodes(&self) -> Vec<NodeID> {vec![]}
+
+	fn propagate_shape_constraints(&self, shapes: &mut GraphShapes) -> Result<()>{
+		let input_shape = shapes.get_shape(&self.input_id).to_data_shape()?;
+
+		let output_shape: NodeShape = match self.spec {
+			SliceSpec::Explicit(_) => {
+				let slices = resolve_slices(&self.spec, input_shape.slice(), &[])?;
+				input_shape.slice().iter().zip(&slices).map(|(&dim, &slice)| slice_len(dim, slice)).collect::<Vec<_>>().into()
+			},
+			SliceSpec::Centre => {
+				let output_shape = shapes.get_output_shape(&self.output_id).clone();
+				ensure!(output_shape.ndims() == input_shape.ndim(), ErrorKind::ShapePropagationError(self.name.clone(), format!("output shape {:?} must have the same number of dimensions as input shape {:?}", output_shape, input_shape.slice())));
+				input_shape.slice().iter().zip(output_shape.dimensions()).map(|(&input_dim, output_dim)| {
+					match output_dim {
+						&NodeDim::Known(dim) => dim,
+						_ => input_dim,
+					}
+				}).collect::<Vec<_>>().into()
+			},
+		};
+
+		shapes.merge_with(&self.output_id, &output_shape)
+	}
+}
+
+/// Returns the number of elements selected by the slice from an axis of length `len`
+fn slice_len(len: usize, slice: AxisSlice) -> usize {
+	let start = if slice.start < 0 {slice.start + len as isize} else {slice.start} as usize;
+	let end = slice.end.map(|end| if end < 0 {end + len as isize} else {end}).unwrap_or(len as isize) as usize;
+	let step = slice.step.abs() as usize;
+	(end - start + step - 1)/step
+}
+
+/// Returns one slice for each axis of the input, checking that each is within the bounds of the axis.
+///
+/// For centre cropping the output shape must be supplied.
+fn resolve_slices(spec: &SliceSpec, input_shape: &[usize], output_shape: &[usize]) -> Result<Vec<AxisSlice>> {
+	let mut slices = vec![AxisSlice::from(..); input_shape.len()];
+	match *spec {
+		SliceSpec::Explicit(ref axis_slices) => {
+			for &(axis, slice) in axis_slices {
+				slices[normalise_axis(axis, input_shape.len())?] = slice;
+			}
+		},
+		SliceSpec::Centre => {
+			ensure!(output_shape.len() == input_shape.len(), format!("output shape {:?} must have the same number of dimensions as input shape {:?}", output_shape, input_shape));
+			for (i, (&input_dim, &output_dim)) in input_shape.iter().zip(output_shape).enumerate() {
+				ensure!(output_dim <= input_dim, format!("output shape {:?} cannot be larger than input shape {:?}", output_shape, input_shape));
+				let start = (input_dim - output_dim)/2;
+				slices[i] = AxisSlice::from(start..start + output_dim);
+			}
+		},
+	}
+
+	for (&len, slice) in input_shape.iter().zip(&slices) {
+		let start = if slice.start < 0 {slice.start + len as isize} else {slice.start};
+		let end = slice.end.map(|end| if end < 0 {end + len as isize} else {end}).unwrap_or(len as isize);
+		ensure!(0 <= start && start <= end && end <= len as isize, format!("slice {:?} is out of bounds for an axis of length {}", slice, len));
+	}
+
+	Ok(slices)
+}
+
+fn slice_view<'a>(mut arr: ArrayViewD<'a, f32>, slices: &[AxisSlice]) -> ArrayViewD<'a, f32> {
+	for (i, &slice) in slices.iter().enumerate() {
+		arr.slice_axis_inplace(Axis(i), slice);
+	}
+	arr
+}
+
+fn slice_view_mut<'a>(mut arr: ArrayViewMutD<'a, f32>, slices: &[AxisSlice]) -> ArrayViewMutD<'a, f32> {
+	for (i, &slice) in slices.iter().enumerate() {
+		arr.slice_axis_inplace(Axis(i), slice);
+	}
+	arr
+}
+
+#[derive(Debug, Clone)]
+pub struct SliceForward {
+	input_id: NodeID,
+	output_id: NodeID,
+	spec: SliceSpec,
+}
+
+impl SliceForward {
+	fn new(input_id: NodeID, output_id: NodeID, spec: SliceSpec) -> Self{
+		SliceForward {
+			input_id,
+			output_id,
+			spec,
+		}
+	}
+}
+
+impl Pass for SliceForward {
+	fn type_name(&self) -> &'static str {"SliceForward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.input_id.value_id()], vec![self.output_id.value_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let input = data.get::<f32>(&self.input_id.value_id())?;
+		let mut output = data.get_mut::<f32>(&self.output_id.value_id())?;
+
+		let slices = resolve_slices(&self.spec, input.shape(), output.shape()).map_err(|e| ErrorKind::PassError(self.name(), e.to_string()))?;
+		let input = slice_view(input, &slices);
+		ensure!(input.shape() == output.shape(), ErrorKind::PassError(self.name(), format!("sliced input shape: {:?} does not match output shape: {:?}", input.shape(), output.shape())));
+
+		output += &input;
+
+		Ok(Box::new(()))
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct SliceBackward {
+	input_id: NodeID,
+	output_id: NodeID,
+	spec: SliceSpec,
+}
+
+impl SliceBackward {
+	fn new(input_id: NodeID, output_id: NodeID, spec: SliceSpec) -> Self{
+		SliceBackward {
+			input_id,
+			output_id,
+			spec,
+		}
+	}
+}
+
+impl Pass for SliceBackward {
+	fn type_name(&self) -> &'static str {"SliceBackward"}
+
+	fn dependencies(&self) -> (Vec<DataID>, Vec<DataID>){
+		(vec![self.output_id.gradient_id()], vec![self.input_id.gradient_id()])
+	}
+
+	fn run(&self, data: &Storage) -> Result<Box<Any>>{
+		let output_grad = data.get::<f32>(&self.output_id.gradient_id())?;
+		let input_grad = data.get_mut::<f32>(&self.input_id.gradient_id())?;
+
+		let slices = resolve_slices(&self.spec, input_grad.shape(), output_grad.shape()).map_err(|e| ErrorKind::PassError(self.name(), e.to_string()))?;
+		let mut input_grad = slice_view_mut(input_grad, &slices);
+		ensure!(input_grad.shape() == output_grad.shape(), ErrorKind::PassError(self.name(), format!("sliced input shape: {:?} does not match output shape: {:?}", input_grad.shape(), output_grad.shape())));
+
+		input_grad += &output_grad;
+
+		Ok(Box::new(()))
+	}
+}
+
+
+#[test]
+fn test_slice_backprop(){
+	_slice_backprop().unwrap();
+}
+
+fn _slice_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![4, 9, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![Unknown, Unknown, Unknown], "output", tag![])?;
+	let node3 = g.new_node(shape![4, 4, 3], "target", tag![])?;
+
+	let _o1 = g.new_op(Slice::new(&node1, &node2).axis(1, AxisSlice::new(1, Some(-1), 2)).axis(-1, AxisSlice::new(0, Some(3), -1)), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
+
+#[test]
+fn test_centre_crop_backprop(){
+	_centre_crop_backprop().unwrap();
+}
+
+fn _centre_crop_backprop() -> Result<()>{
+	use graph::GraphDef;
+	use ops::numeric_check::numeric_test;
+	use ops::loss::mse::Mse;
+
+	let mut g = GraphDef::new();
+
+	let node1 = g.new_node(shape![3, 9, 8, 5], "input", tag![])?;
+	let node2 = g.new_node(shape![Unknown, 5, 5, Unknown], "output", tag![])?;
+	let node3 = g.new_node(shape![3, 5, 5, 5], "target", tag![])?;
+
+	let _o1 = g.new_op(Slice::centre_crop(&node1, &node2), tag![])?;
+	let _o2 = g.new_op(Mse::new(&node2, &node3), tag![])?;
+
+	let iters = 100;
+	let failures = 1;
+	let tolerance = 0.002;
+	let step_size = 1E-2;
+	let default_variance = 1.0;
+	numeric_test(iters, failures, tolerance, &g, step_size, default_variance, &mut indexmap![])?;
+
+	Ok(())
+}
@@ -1,7 +1,8 @@
 use graph::{GraphDef, Subgraph, Result};
 use id::{NodeTag, NodeID, DataID};
-use opt::{Opt, CallbackData, CallbackSignal};
-use ndarray::{ArrayD, Zip};
+use opt::{Opt, CallbackData, CallbackSignal, cast_inputs};
+use dtype::TypedArrayD;
+use ndarray::{ArrayD, ArrayViewD, ArrayViewMutD, Axis, Zip};
 use std::num::FpCategory;
 use rayon::prelude::*;
 
@@ -141,16 +142,18 @@ impl Opt for Adam {
 		&self.parameters
 	}
 
-	fn step(&mut self, mut inputs: Vec<ArrayD<f32>>, mut parameters: Vec<ArrayD<f32>>) -> Result<(f32, usize, f32, Vec<ArrayD<f32>>)> {
+	fn step(&mut self, mut inputs: Vec<TypedArrayD>, parameters: Vec<ArrayD<f32>>) -> Result<(f32, usize, f32, Vec<ArrayD<f32>>)> {
 		assert_eq!(inputs.len(), self.inputs().len(), "Incorrect number of inputs supplied to optimiser.step()");
 		assert_eq!(parameters.len(), self.parameters().len(), "Incorrect number of prameters supplied to optimiser.step()");
 
-		inputs.append(&mut parameters);
+		inputs.extend(parameters.into_iter().map(TypedArrayD::from));
 		
 		assert_eq!(self.subgraph.inputs().len(), inputs.len());
 
-		let storage = self.subgraph.execute(inputs)?;
+		let inputs = cast_inputs(inputs, self.subgraph.inputs())?;
+		let storage = self.subgraph.execute_typed(inputs)?;
 		let loss = storage.loss();
+		let sparse_rows: Vec<_> = self.parameters.iter().map(|p| storage.sparse_rows(&p.gradient_id())).collect();
 		let mut map = storage.into_map();
 
 		let mut params: Vec<_> = self.parameters.iter().map(|p| map.remove(&p.value_id()).expect("Subgraph must have parameter values as outputs.")).collect();
@@ -177,13 +180,13 @@ impl Opt for Adam {
 		
 		//for (i, param_grad) in self.parameters.iter().map(|p| map.remove(&p.gradient_id()).expect("Subgraph must have parameter gradients as outputs.")).enumerate() {
 		let param_grads: Vec<_> = self.parameters.iter().map(|p| map.remove(&p.gradient_id()).expect("Subgraph must have parameter gradients as outputs.")).collect();
-		let change_sqr: f32 = param_grads.par_iter().zip(self.momentum_vec.par_iter_mut()).zip(self.curvature_vec.par_iter_mut()).zip(params.par_iter_mut()).with_max_len(1).map(|(((param_grad_outer, momentum_outer), curvature_outer), params_outer)| {
+		let update = |param_grad: ArrayViewD<f32>, momentum_arr: ArrayViewMutD<f32>, curvature_arr: ArrayViewMutD<f32>, param_arr: ArrayViewMutD<f32>| {
 			let mut change_sqr = 0.0;
 			if bias_correct {
-				Zip::from(params_outer)
-					.and(momentum_outer)
-					.and(curvature_outer)
-					.and(param_grad_outer)
+				Zip::from(param_arr)
+					.and(momentum_arr)
+					.and(curvature_arr)
+					.and(param_grad)
 					.apply(|param, momentum, curv, param_grad| {
 						*momentum = *momentum * beta1 + (1.0-beta1)*param_grad;
 						*curv = *curv * beta2 + (1.0-beta1)*param_grad*param_grad;
@@ -195,10 +198,10 @@ impl Opt for Adam {
 						}
 					});
 			} else {
-				Zip::from(params_outer)
-					.and(momentum_outer)
-					.and(curvature_outer)
-					.and(param_grad_outer)
+				Zip::from(param_arr)
+					.and(momentum_arr)
+					.and(curvature_arr)
+					.and(param_grad)
 					.apply(|param, momentum, curv, param_grad| {
 						*momentum = *momentum * beta1 + (1.0-beta1)*param_grad;
 						*curv = *curv * beta2 + (1.0-beta1)*param_grad*param_grad;
@@ -211,6 +214,15 @@ impl Opt for Adam {
 					});
 			}
 			change_sqr
+		};
+
+		let change_sqr: f32 = param_grads.par_iter().zip(self.momentum_vec.par_iter_mut()).zip(self.curvature_vec.par_iter_mut()).zip(params.par_iter_mut()).zip(sparse_rows.par_iter()).with_max_len(1).map(|((((param_grad_outer, momentum_outer), curvature_outer), params_outer), rows)| {
+			// sparse gradients only update the moments and parameters of the rows written to
+			if let Some(ref rows) = *rows {
+				rows.iter().map(|&row| update(param_grad_outer.subview(Axis(0), row), momentum_outer.subview_mut(Axis(0), row), curvature_outer.subview_mut(Axis(0), row), params_outer.subview_mut(Axis(0), row))).sum()
+			} else {
+				update(param_grad_outer.view(), momentum_outer.view_mut(), curvature_outer.view_mut(), params_outer.view_mut())
+			}
 		}).sum();
 
 		self.step_count += 1;
@@ -1,10 +1,11 @@
 pub mod sgd;
 pub mod adam;
 
-use graph::{GraphDef, Subgraph, Result};
+use graph::{GraphDef, Subgraph, ErrorKind, Result};
 use id::{NodeID, DataID};
 use data::DataStream;
 use ndarray::ArrayD;
+use dtype::{DType, TypedArrayD};
 
 pub enum CallbackSignal{
 	Stop,
@@ -32,7 +33,9 @@ pub trait Opt {
 	fn parameters(&self) -> &[NodeID];
 
 	/// Returns the error, step number, l2 norm of param change, and the new parameters
-	fn step(&mut self, inputs: Vec<ArrayD<f32>>, parameters: Vec<ArrayD<f32>>) -> Result<(f32, usize, f32, Vec<ArrayD<f32>>)>;
+	///
+	/// The inputs are converted to the element type of each subgraph input by `cast_inputs()`.
+	fn step(&mut self, inputs: Vec<TypedArrayD>, parameters: Vec<ArrayD<f32>>) -> Result<(f32, usize, f32, Vec<ArrayD<f32>>)>;
 
 	fn callbacks(&mut self) -> &mut [Box<FnMut(&CallbackData)->CallbackSignal>];
 
@@ -66,6 +69,30 @@ pub trait UnboxedCallbacks: Opt {
 
 impl<O: Opt> UnboxedCallbacks for O {}
 
+/// Converts the inputs supplied by a `DataStream` to the element type of each subgraph input.
+///
+/// Inputs which already have the element type of the subgraph input are passed through unchanged,
+/// so integer and boolean nodes (e.g. labels, indices and masks) of any size are best fed by `DataSet`s producing that element type.
+/// Other inputs are converted by `TypedArrayD::cast()`, however `F32` values for `I64` and `U32` nodes must be integers
+/// smaller in magnitude than 2^24, below which `f32` values can't have been rounded from another integer,
+/// otherwise an `InexactInputConversion` error is returned.
+pub fn cast_inputs(inputs: Vec<TypedArrayD>, ids: &[DataID]) -> Result<Vec<TypedArrayD>> {
+	const EXACT_INTEGER_LIMIT: f32 = 16777216.0;
+	inputs.into_iter().zip(ids).map(|(input, id)| {
+		if let Some(input) = input.as_array::<f32>() {
+			let inexact = match id.dtype() {
+				DType::I64 => input.iter().find(|&&x| !(x.fract() == 0.0 && x.abs() < EXACT_INTEGER_LIMIT)),
+				DType::U32 => input.iter().find(|&&x| !(x.fract() == 0.0 && x >= 0.0 && x < EXACT_INTEGER_LIMIT)),
+				DType::F32 | DType::Bool => None,
+			};
+			if let Some(&x) = inexact {
+				bail!(ErrorKind::InexactInputConversion(id.name(), id.dtype(), x));
+			}
+		}
+		Ok(input.cast(id.dtype()))
+	}).collect()
+}
+
 pub fn print_step_data() -> Box<FnMut(&CallbackData)->CallbackSignal>{
 	let mut step = 0;
 	Box::new(move |data|{
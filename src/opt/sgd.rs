@@ -1,7 +1,8 @@
 use graph::{GraphDef, Subgraph, Result};
 use id::{NodeTag, NodeID, DataID};
-use opt::{Opt, CallbackData, CallbackSignal};
-use ndarray::{ArrayD, Zip};
+use opt::{Opt, CallbackData, CallbackSignal, cast_inputs};
+use dtype::TypedArrayD;
+use ndarray::{ArrayD, ArrayViewD, ArrayViewMutD, Axis, Zip};
 use std::num::FpCategory;
 use rayon::prelude::*;
 
@@ -103,16 +104,18 @@ impl Opt for Sgd {
 		&self.parameters
 	}
 
-	fn step(&mut self, mut inputs: Vec<ArrayD<f32>>, mut parameters: Vec<ArrayD<f32>>) -> Result<(f32, usize, f32, Vec<ArrayD<f32>>)>{
+	fn step(&mut self, mut inputs: Vec<TypedArrayD>, parameters: Vec<ArrayD<f32>>) -> Result<(f32, usize, f32, Vec<ArrayD<f32>>)>{
 		assert_eq!(inputs.len(), self.inputs().len(), "Incorrect number of inputs supplied to optimiser.step()");
 		assert_eq!(parameters.len(), self.parameters().len(), "Incorrect number of prameters supplied to optimiser.step()");
 
-		inputs.append(&mut parameters);
+		inputs.extend(parameters.into_iter().map(TypedArrayD::from));
 		
 		assert_eq!(self.subgraph.inputs().len(), inputs.len());
 
-		let storage = self.subgraph.execute(inputs)?;
+		let inputs = cast_inputs(inputs, self.subgraph.inputs())?;
+		let storage = self.subgraph.execute_typed(inputs)?;
 		let loss = storage.loss();
+		let sparse_rows: Vec<_> = self.parameters.iter().map(|p| storage.sparse_rows(&p.gradient_id())).collect();
 		let mut map = storage.into_map();
 
 		let mut params: Vec<_> = self.parameters.iter().map(|p| map.remove(&p.value_id()).expect("Subgraph must have parameter values as outputs.")).collect();
@@ -129,11 +132,11 @@ impl Opt for Sgd {
 				// self.momentum_vec[i] *= momentum;
 				// self.momentum_vec[i] += &grad;
 				// params[i].scaled_add(-self.rate, &self.momentum_vec[i]);
-			change_sqr = param_grads.par_iter().zip(params.par_iter_mut()).zip(self.momentum_vec.par_iter_mut()).with_max_len(1).map(|((param_grad_outer, params_outer), momentum_outer)| {
+			let update = |param_grad: ArrayViewD<f32>, momentum_arr: ArrayViewMutD<f32>, param_arr: ArrayViewMutD<f32>| {
 				let mut change_sqr = 0.0;
-				Zip::from(param_grad_outer)
-					.and(momentum_outer)
-					.and(params_outer)
+				Zip::from(param_grad)
+					.and(momentum_arr)
+					.and(param_arr)
 					.apply(|grad, grad_momentum, param| {
 						*grad_momentum = (*grad_momentum) * momentum + grad;
 						let change = -rate * (*grad_momentum);
@@ -144,14 +147,23 @@ impl Opt for Sgd {
 						}
 					});
 				change_sqr
+			};
+
+			change_sqr = param_grads.par_iter().zip(params.par_iter_mut()).zip(self.momentum_vec.par_iter_mut()).zip(sparse_rows.par_iter()).with_max_len(1).map(|(((param_grad_outer, params_outer), momentum_outer), rows)| {
+				// sparse gradients only update the momentum and parameters of the rows written to
+				if let Some(ref rows) = *rows {
+					rows.iter().map(|&row| update(param_grad_outer.subview(Axis(0), row), momentum_outer.subview_mut(Axis(0), row), params_outer.subview_mut(Axis(0), row))).sum()
+				} else {
+					update(param_grad_outer.view(), momentum_outer.view_mut(), params_outer.view_mut())
+				}
 			}).sum();
 
 		} else {
 			//for (i, grad) in self.parameters.iter().map(|p| map.remove(&p.gradient_id()).expect("Subgraph must have parameter gradients as outputs.")).enumerate() {
-			change_sqr = param_grads.par_iter().zip(params.par_iter_mut()).with_max_len(1).map(|(param_grad_outer, params_outer)| {
+			let update = |param_grad: ArrayViewD<f32>, param_arr: ArrayViewMutD<f32>| {
 				let mut change_sqr = 0.0;
-				Zip::from(param_grad_outer)
-					.and(params_outer)
+				Zip::from(param_grad)
+					.and(param_arr)
 					.apply(|grad, param| {
 						let change = -rate * (*grad);
 						change_sqr += change * change;
@@ -161,6 +173,14 @@ impl Opt for Sgd {
 						}
 					});
 				change_sqr
+			};
+
+			change_sqr = param_grads.par_iter().zip(params.par_iter_mut()).zip(sparse_rows.par_iter()).with_max_len(1).map(|((param_grad_outer, params_outer), rows)| {
+				if let Some(ref rows) = *rows {
+					rows.iter().map(|&row| update(param_grad_outer.subview(Axis(0), row), params_outer.subview_mut(Axis(0), row))).sum()
+				} else {
+					update(param_grad_outer.view(), params_outer.view_mut())
+				}
 			}).sum();
 		};
 
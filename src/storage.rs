@@ -1,11 +1,12 @@
 use ndarray::ArrayD;
 use ndarray::prelude::*;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::mem;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use std::any::Any;
 
 use id::*;
+use dtype::{Element, TypedArrayD};
 use graph::{Dependencies, DataStatus, ErrorKind, Result};
 
 enum DataState<T>{
@@ -22,12 +23,15 @@ enum DataState<T>{
 /// Each element can only be borrowed either once mutably or many times immutably, however, borrows are not reset until the end of the Pass
 pub struct Storage<'a> {
 	shapes: &'a IndexMap<NodeID, IxDyn>,
-	static_inputs: &'a IndexMap<DataID, ArrayD<f32>>,
+	static_inputs: &'a IndexMap<DataID, TypedArrayD>,
 	dependencies: &'a Dependencies,
 
 	loss: Cell<f32>,
-	data: IndexMap<DataID, DataState<ArrayD<f32>>>,
+	training: bool,
+	data: IndexMap<DataID, DataState<TypedArrayD>>,
 	borrow_flags: IndexMap<DataID, Cell<usize>>,
+	/// Rows written via `get_mut_sparse_rows()`, or `None` if the data has also been borrowed by `get_mut()`
+	sparse_rows: RefCell<IndexMap<DataID, Option<IndexSet<usize>>>>,
 	current_pass: Option<PassID>,
 	pass_data: IndexMap<PassID, Box<Any>>,
 }
@@ -36,7 +40,7 @@ const UNUSED: usize = 0;
 const WRITING: usize = !0;
 impl<'a> Storage<'a> {
 
-	pub (crate) fn new(included_data: &IndexMap<DataID, DataStatus>, dependencies: &'a Dependencies, static_inputs: &'a IndexMap<DataID, ArrayD<f32>>, input_data: IndexMap<DataID, ArrayD<f32>>, shapes: &'a IndexMap<NodeID, IxDyn>) -> Storage<'a> { //, graph: &'a GraphDef
+	pub (crate) fn new(included_data: &IndexMap<DataID, DataStatus>, dependencies: &'a Dependencies, static_inputs: &'a IndexMap<DataID, TypedArrayD>, input_data: IndexMap<DataID, TypedArrayD>, shapes: &'a IndexMap<NodeID, IxDyn>, training: bool) -> Storage<'a> { //, graph: &'a GraphDef
 
 		// let num_nodes = dependencies.node_inputs().len();
 		// let num_data = dependencies.data_inputs().len();
@@ -45,7 +49,7 @@ impl<'a> Storage<'a> {
 		//debug_assert_eq!(num_nodes, shapes.len());
 		//debug_assert_eq!(num_data, included_data.len());
 
-		let mut data: IndexMap<DataID, DataState<ArrayD<f32>>> = included_data.iter().map(|(id, _state)| (id.clone(), DataState::Unallocated)).collect();
+		let mut data: IndexMap<DataID, DataState<TypedArrayD>> = included_data.iter().map(|(id, _state)| (id.clone(), DataState::Unallocated)).collect();
 		let borrow_flags = included_data.iter().map(|(id, _state)| (id.clone(), Cell::new(UNUSED))).collect();
 
 		for (data_id, input_data) in input_data.into_iter() {
@@ -64,8 +68,10 @@ impl<'a> Storage<'a> {
 			dependencies,
 
 			loss: Cell::new(0.0),
+			training: training,
 			data: data,
 			borrow_flags: borrow_flags,
+			sparse_rows: RefCell::new(indexmap![]),
 			current_pass: None,
 			pass_data: indexmap![],
 		}
@@ -104,7 +110,7 @@ impl<'a> Storage<'a> {
 	}	
 		
 	/// Should never be called if a &mut borrow could possibly already exist.
-	unsafe fn get_or_init(&self, id: &DataID) -> Result<*mut ArrayD<f32>>{
+	unsafe fn get_or_init(&self, id: &DataID) -> Result<*mut TypedArrayD>{
 		
 		let ptr = if let Some(reference) = self.data.get(id) {
 			reference as *const _ as *mut _
@@ -115,7 +121,7 @@ impl<'a> Storage<'a> {
 		match *ptr {
 			DataState::Deallocated => bail!(ErrorKind::StorageDataDeallocated),
 			DataState::Unallocated => {
-				*ptr = DataState::Allocated(ArrayD::zeros(self.shapes.get(&id.node_id()).unwrap().clone()));
+				*ptr = DataState::Allocated(TypedArrayD::zeros(id.dtype(), self.shapes.get(&id.node_id()).unwrap().clone()));
 			},
 			// DataState::UnallocatedInput(ind) =>{
 			// 	*ptr = DataState::Allocated(self.input_data[ind].clone())
@@ -123,8 +129,8 @@ impl<'a> Storage<'a> {
 			DataState::UnallocatedStaticInput => {
 				let shape = self.shapes.get(&id.node_id()).unwrap().clone();
 				if let Some(ref static_data) = self.static_inputs.get(id){
-					if let Some(broadcasted_view) = static_data.broadcast(shape){
-						*ptr = DataState::Allocated(broadcasted_view.to_owned())
+					if let Some(broadcasted) = static_data.broadcast_to_owned(shape){
+						*ptr = DataState::Allocated(broadcasted)
 					} else {
 						bail!(ErrorKind::StaticInputBroadcastFailure(id.node_id(), static_data.shape().to_owned(), self.shapes.get(&id.node_id()).unwrap().slice().to_owned()))
 					}
@@ -137,12 +143,29 @@ impl<'a> Storage<'a> {
 
 		// return pointer to allocated data
 		if let DataState::Allocated(ref mut data) = *ptr{
-			Ok(data as *mut ArrayD<f32>)
+			Ok(data as *mut TypedArrayD)
 		} else {
 			unreachable!()
 		}
 	}
 
+	/// Returns the shape of a node included in the subgraph.
+	///
+	/// Unlike `get()` this does not require the data to be a dependency of the current pass, or to be allocated.
+	pub fn shape(&self, node_id: &NodeID) -> Result<&[usize]> {
+		match self.shapes.get(node_id) {
+			Some(shape) => Ok(shape.slice()),
+			None => bail!(ErrorKind::StorageDataMarkedNotRequired),
+		}
+	}
+
+	/// Returns true if the subgraph is being executed in training mode.
+	///
+	/// Passes with distinct training and inference behaviour should check this rather than storing their own mode.
+	pub fn is_training(&self) -> bool {
+		self.training
+	}
+
 	/// Access the loss variable.
 	pub fn loss(&self) -> f32 {
 		self.loss.get()
@@ -154,12 +177,14 @@ impl<'a> Storage<'a> {
 		unsafe{*self.loss.as_ptr() += additional_loss;}
 	}
 
-	/// Immutably borrows data element associated with the given ID.
+	/// Immutably borrows data element associated with the given ID, with the element type `T`.
 	/// 
 	/// A Pass may only borrow data which is listed as a input or output dependency.
 	/// Will panic if data element is already mutably borrowed.
 	/// The borrow will stick until `clear_borrow_flags()` is called.
-	pub fn get<'b>(&'b self, data_id: &DataID) -> Result<ArrayViewD<f32>> {
+	///
+	/// Returns an error if `T` does not match the element type of the data, see `DataID::dtype()`.
+	pub fn get<'b, T: Element>(&'b self, data_id: &DataID) -> Result<ArrayViewD<'b, T>> {
 		if let Some(ref pass_id) = self.current_pass {
 			ensure!(self.dependencies.pass_inputs(pass_id).contains(data_id)||self.dependencies.pass_outputs(pass_id).contains(data_id), ErrorKind::StorageImmutableBorrowError(pass_id.name(), data_id.name()));
 		}
@@ -169,20 +194,53 @@ impl<'a> Storage<'a> {
 		} else {
 			bail!(ErrorKind::StorageDataMarkedNotRequired)
 		};
+		ensure!(data_id.dtype() == T::dtype(), ErrorKind::DataTypeMismatch(data_id.name(), data_id.dtype(), T::dtype()));
 		if flag.get() != WRITING {
 				let ptr = unsafe{self.get_or_init(data_id)?};
 				flag.set(flag.get() + 1);
-				let array: &'b ArrayD<f32> = unsafe{&*ptr};
-				Ok(array.view())
+				let array: &'b TypedArrayD = unsafe{&*ptr};
+				Ok(array.as_array::<T>().expect("dtype was checked").view())
 		} else {
 			bail!(ErrorKind::StorageDataAlreadyMutablyBorrowed)
 		}
 	}
 
-	/// Mutably borrows data element associated with the given ID.
+	/// Mutably borrows data element associated with the given ID, with the element type `T`.
 	/// Will panic if data element is already mutably or immutably borrowed.
 	/// The borrow will stick until `clear_borrow_flags()` is called.
-	pub fn get_mut<'b>(&'b self, data_id: &DataID) -> Result<ArrayViewMutD<f32>> {
+	///
+	/// Returns an error if `T` does not match the element type of the data, see `DataID::dtype()`.
+	pub fn get_mut<'b, T: Element>(&'b self, data_id: &DataID) -> Result<ArrayViewMutD<'b, T>> {
+		let array = self.borrow_mut::<T>(data_id)?;
+		self.sparse_rows.borrow_mut().insert(data_id.clone(), None);
+		Ok(array)
+	}
+
+	/// Mutably borrows data element associated with the given ID, declaring that only the listed rows (indices of the outermost axis) will be written.
+	///
+	/// If all mutable borrows of a data element are made this way, `sparse_rows()` will return the union of the rows,
+	/// allowing e.g. optimisers to skip untouched rows of large parameter gradients. Otherwise identical to `get_mut()`.
+	pub fn get_mut_sparse_rows<'b>(&'b self, data_id: &DataID, rows: &[usize]) -> Result<ArrayViewMutD<'b, f32>> {
+		let array = self.borrow_mut::<f32>(data_id)?;
+		let mut sparse_rows = self.sparse_rows.borrow_mut();
+		if let Some(set) = sparse_rows.entry(data_id.clone()).or_insert_with(|| Some(IndexSet::new())).as_mut() {
+			set.extend(rows.iter().cloned());
+		}
+		Ok(array)
+	}
+
+	/// Returns the sorted rows written to a data element, if it has only been mutably borrowed via `get_mut_sparse_rows()`.
+	///
+	/// Returns `None` if the data was borrowed by `get_mut()`, or never mutably borrowed, in which case all rows should be treated as written.
+	pub fn sparse_rows(&self, data_id: &DataID) -> Option<Vec<usize>> {
+		self.sparse_rows.borrow().get(data_id).and_then(|rows| rows.as_ref()).map(|rows| {
+			let mut rows: Vec<usize> = rows.iter().cloned().collect();
+			rows.sort();
+			rows
+		})
+	}
+
+	fn borrow_mut<'b, T: Element>(&'b self, data_id: &DataID) -> Result<ArrayViewMutD<'b, T>> {
 		if let Some(ref pass_id) = self.current_pass {
 			ensure!(self.dependencies.pass_outputs(pass_id).contains(data_id), ErrorKind::StorageMutableBorrowError(pass_id.name(), data_id.name()));
 		}
@@ -192,12 +250,13 @@ impl<'a> Storage<'a> {
 		} else {
 			bail!(ErrorKind::StorageDataMarkedNotRequired)
 		};
+		ensure!(data_id.dtype() == T::dtype(), ErrorKind::DataTypeMismatch(data_id.name(), data_id.dtype(), T::dtype()));
 		match flag.get() {
 			UNUSED => {
 				let ptr = unsafe{self.get_or_init(data_id)?};
 				flag.set(WRITING);
-				let array: &'b mut ArrayD<f32> = unsafe{&mut *ptr};
-				Ok(array.view_mut())
+				let array: &'b mut TypedArrayD = unsafe{&mut *ptr};
+				Ok(array.as_array_mut::<T>().expect("dtype was checked").view_mut())
 			},
 			WRITING => bail!(ErrorKind::StorageDataAlreadyMutablyBorrowed),
 			_ => bail!(ErrorKind::StorageDataAlreadyBorrowed),
@@ -216,7 +275,17 @@ impl<'a> Storage<'a> {
 	/// Consume the Storage and converts it into a IndexMap.
 	///
 	/// Intended for use after storage is returned from `execute()`.
+	/// Only `F32` data is included, use `into_typed_map()` to retrieve data of all element types.
 	pub fn into_map(self) -> IndexMap<DataID, ArrayD<f32>> {
+		self.into_typed_map().into_iter().filter_map(|(id, arr)|{
+			arr.into_array::<f32>().ok().map(|arr| (id, arr))
+		}).collect()
+	}
+
+	/// Consume the Storage and converts it into a IndexMap of data of any element type.
+	///
+	/// Intended for use after storage is returned from `execute()`.
+	pub fn into_typed_map(self) -> IndexMap<DataID, TypedArrayD> {
 		self.data.into_iter().filter_map(|(id, entry)|{
 			match entry {
 				DataState::Allocated(arr) => Some((id, arr)),